@@ -0,0 +1,224 @@
+//! Audit trail of every command and component interaction, appended to a
+//! file and/or posted to a channel when `[audit]` is set.
+
+use discord_assist_plugin_api::{PluginError, no_mentions};
+use discord_assist_util::{iso_date, redact_secrets};
+use serenity::builder::CreateMessage;
+use serenity::http::Http;
+use serenity::model::application::{CommandDataOption, CommandDataOptionValue};
+use serenity::model::id::ChannelId;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Option values longer than this are cut off in audit lines.
+const MAX_VALUE_CHARS: usize = 200;
+
+/// Option names whose values are never written out.
+const SECRET_OPTION_NAMES: &[&str] = &["token", "key", "password", "secret"];
+
+/// How an interaction ended, as recorded in the audit trail.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Handled,
+    Denied,
+    RateLimited,
+    Failed(String),
+    Unhandled,
+}
+
+/// How a plugin that claimed the interaction got on.
+impl From<Result<(), PluginError>> for Outcome {
+    fn from(result: Result<(), PluginError>) -> Self {
+        match result {
+            Ok(()) => Outcome::Handled,
+            Err(e) => Outcome::Failed(e.to_string()),
+        }
+    }
+}
+
+impl Outcome {
+    fn describe(&self) -> String {
+        match self {
+            Outcome::Handled => "ok".into(),
+            Outcome::Denied => "denied".into(),
+            Outcome::RateLimited => "rate-limited".into(),
+            Outcome::Failed(e) => format!("error: {}", redact_secrets(e)),
+            Outcome::Unhandled => "unhandled".into(),
+        }
+    }
+}
+
+/// Queues audit lines so recording never waits on disk or Discord. Lines
+/// recorded before [`AuditLog::start`] are kept and written once it runs.
+pub struct AuditLog {
+    tx: mpsc::UnboundedSender<String>,
+    writer: Mutex<Option<AuditWriter>>,
+}
+
+struct AuditWriter {
+    rx: mpsc::UnboundedReceiver<String>,
+    path: Option<PathBuf>,
+    channel_id: Option<ChannelId>,
+}
+
+impl AuditLog {
+    pub fn new(path: Option<PathBuf>, channel_id: Option<u64>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            tx,
+            writer: Mutex::new(Some(AuditWriter {
+                rx,
+                path,
+                channel_id: channel_id.map(ChannelId::new),
+            })),
+        }
+    }
+
+    pub fn record(&self, line: String) {
+        let _ = self.tx.send(line);
+    }
+
+    /// Spawn the writer. Needs `http` for channel posts, so it's called once
+    /// the bot is connected; later calls do nothing.
+    pub fn start(&self, http: Arc<Http>) {
+        let Some(mut writer) = self.writer.lock().ok().and_then(|mut w| w.take()) else {
+            return;
+        };
+        tokio::spawn(async move {
+            while let Some(line) = writer.rx.recv().await {
+                writer.write(&http, &line).await;
+            }
+        });
+    }
+}
+
+impl AuditWriter {
+    async fn write(&self, http: &Http, line: &str) {
+        if let Some(path) = &self.path {
+            let appended = async {
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(format!("{line}\n").as_bytes()).await
+            };
+            if let Err(e) = appended.await {
+                warn!("Failed to append to audit log {}: {e}", path.display());
+            }
+        }
+        if let Some(channel_id) = self.channel_id {
+            let message = CreateMessage::new()
+                .content(format!("`{}`", line.replace('`', "'")))
+                .allowed_mentions(no_mentions());
+            if let Err(e) = channel_id.send_message(http, message).await {
+                warn!("Failed to post audit line to channel {channel_id}: {e}");
+            }
+        }
+    }
+}
+
+/// One audit line, e.g.
+/// `2024-01-02 03:04:05 UTC alice (42) /unraid docker start name="plex" -> ok`.
+/// `action` is the command path or component id.
+pub fn audit_line(
+    unix_secs: u64,
+    user_name: &str,
+    user_id: u64,
+    action: &str,
+    options: &[(String, String)],
+    outcome: &Outcome,
+) -> String {
+    let secs_of_day = unix_secs % 86_400;
+    let mut line = format!(
+        "{} {:02}:{:02}:{:02} UTC {user_name} ({user_id}) {action}",
+        iso_date(unix_secs),
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    );
+    for (name, value) in options {
+        line.push_str(&format!(" {name}={value}"));
+    }
+    line.push_str(&format!(" -> {}", outcome.describe()));
+    line
+}
+
+/// `name=value` pairs for the options the user filled in, with secret-looking
+/// names and values redacted and long values cut short.
+pub fn audit_options(options: &[CommandDataOption]) -> Vec<(String, String)> {
+    options
+        .iter()
+        .map(|option| {
+            let name_lower = option.name.to_ascii_lowercase();
+            let value = if SECRET_OPTION_NAMES.iter().any(|s| name_lower.contains(s)) {
+                "[redacted]".to_string()
+            } else {
+                let value = redact_secrets(&option_value(&option.value));
+                match value.char_indices().nth(MAX_VALUE_CHARS) {
+                    Some((cut, _)) => format!("{}…", &value[..cut]),
+                    None => value,
+                }
+            };
+            (option.name.clone(), value)
+        })
+        .collect()
+}
+
+fn option_value(value: &CommandDataOptionValue) -> String {
+    match value {
+        CommandDataOptionValue::String(s) => format!("{s:?}"),
+        CommandDataOptionValue::Integer(i) => i.to_string(),
+        CommandDataOptionValue::Number(n) => n.to_string(),
+        CommandDataOptionValue::Boolean(b) => b.to_string(),
+        CommandDataOptionValue::User(id) => format!("user:{id}"),
+        CommandDataOptionValue::Channel(id) => format!("channel:{id}"),
+        CommandDataOptionValue::Role(id) => format!("role:{id}"),
+        CommandDataOptionValue::Mentionable(id) => format!("mentionable:{id}"),
+        CommandDataOptionValue::Attachment(id) => format!("attachment:{id}"),
+        _ => "?".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_line_redacts_secrets() {
+        let options: Vec<CommandDataOption> = serde_json::from_value(serde_json::json!([
+            {"name": "name", "type": 3, "value": "plex"},
+            {"name": "api_token", "type": 3, "value": "hunter2"},
+            {"name": "url", "type": 3, "value": "http://x/?apikey=abc123&page=2"},
+            {"name": "count", "type": 4, "value": 5},
+            {"name": "force", "type": 5, "value": true},
+        ]))
+        .unwrap();
+        let line = audit_line(
+            86_400 + 3_723,
+            "alice",
+            42,
+            "/unraid docker start",
+            &audit_options(&options),
+            &Outcome::Failed("HTTP 401 for ?X-Api-Key=sekrit".into()),
+        );
+        assert_eq!(
+            line,
+            "1970-01-02 01:02:03 UTC alice (42) /unraid docker start name=\"plex\" \
+             api_token=[redacted] url=\"http://x/?apikey=[redacted]&page=2\" count=5 force=true \
+             -> error: HTTP 401 for ?X-Api-Key=[redacted]"
+        );
+        assert!(!line.contains("hunter2") && !line.contains("abc123") && !line.contains("sekrit"));
+
+        let long: Vec<CommandDataOption> = serde_json::from_value(serde_json::json!([
+            {"name": "prompt", "type": 3, "value": "é".repeat(500)},
+        ]))
+        .unwrap();
+        let (_, value) = &audit_options(&long)[0];
+        assert_eq!(value.chars().count(), MAX_VALUE_CHARS + 1);
+        assert!(audit_line(0, "bob", 7, "button request:1", &[], &Outcome::Handled).ends_with("bob (7) button request:1 -> ok"));
+    }
+}
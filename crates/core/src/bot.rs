@@ -1,19 +1,51 @@
-use crate::notifications::NotificationStarter;
-use discord_assist_plugin_api::Plugin;
+use crate::audit::{AuditLog, Outcome, audit_line, audit_options};
+use crate::config::CommandScope;
+use crate::metrics::METRICS;
+use crate::notifications::{NotificationCategory, NotificationStarter, TestNotifier};
+use discord_assist_plugin_api::{
+    Plugin, PluginError, ack, edit_message, inline_or_attachment, response_message,
+};
+use discord_assist_util::now_secs;
+use futures::FutureExt;
 use serenity::async_trait;
-use serenity::builder::{CreateInteractionResponse, CreateInteractionResponseMessage};
-use serenity::model::application::Interaction;
+use serenity::builder::{CreateCommand, CreateCommandOption, CreateInteractionResponse};
+use serenity::http::Http;
+use serenity::model::application::{
+    Command, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    ComponentInteraction, Interaction, ResolvedValue,
+};
 use serenity::model::gateway::Ready;
 use serenity::model::id::GuildId;
+use serenity::model::user::User;
 use serenity::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::panic::AssertUnwindSafe;
 use std::sync::Mutex;
-use tracing::{error, info, warn};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{Instrument, Span, error, info, info_span, warn};
+
+/// A command still unanswered after this long is deferred by the bot, so
+/// Discord's 3 second window doesn't run out while a plugin works.
+const DEFER_AFTER: Duration = Duration::from_millis(2500);
 
 pub struct Bot {
     plugins: Vec<Box<dyn Plugin>>,
     owner_id: u64,
+    /// Users who may run read-only commands; see [`authorize`].
+    guest_ids: Vec<u64>,
+    /// Per-command allowlists keyed by command name; see [`authorize`].
+    permissions: HashMap<String, Vec<u64>>,
     guild_id: Option<u64>,
+    /// Per-plugin registration scope overrides, keyed by plugin name.
+    scopes: HashMap<String, CommandScope>,
+    cooldowns: Cooldowns,
     notification_starter: Mutex<Option<NotificationStarter>>,
+    /// Backs `/notify test`; `None` when notifications aren't configured.
+    test_notifier: Option<TestNotifier>,
+    /// Records every interaction when `[audit]` is set.
+    audit: Option<AuditLog>,
+    shutdown: watch::Receiver<bool>,
 }
 
 impl Bot {
@@ -21,18 +53,322 @@ impl Bot {
         plugins: Vec<Box<dyn Plugin>>,
         owner_id: u64,
         guild_id: Option<u64>,
+        scopes: HashMap<String, CommandScope>,
+        cooldown: Duration,
         notification_starter: Option<NotificationStarter>,
+        shutdown: watch::Receiver<bool>,
     ) -> Self {
         Self {
             plugins,
             owner_id,
+            guest_ids: Vec::new(),
+            permissions: HashMap::new(),
             guild_id,
+            scopes,
+            cooldowns: Cooldowns::new(cooldown),
+            test_notifier: notification_starter.as_ref().map(NotificationStarter::test_notifier),
             notification_starter: Mutex::new(notification_starter),
+            audit: None,
+            shutdown,
         }
     }
 
-    fn is_owner(&self, user_id: u64) -> bool {
-        user_id == self.owner_id
+    pub fn with_guest_ids(mut self, guest_ids: Vec<u64>) -> Self {
+        self.guest_ids = guest_ids;
+        self
+    }
+
+    pub fn with_permissions(mut self, permissions: HashMap<String, Vec<u64>>) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    fn audit(&self, user: &User, action: &str, options: &[(String, String)], outcome: &Outcome) {
+        if let Some(audit) = &self.audit {
+            audit.record(audit_line(now_secs(), &user.name, user.id.get(), action, options, outcome));
+        }
+    }
+
+    /// Whether the command at `path` only reads state, so guests may run it.
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        path == ["help"] || self.plugins.iter().any(|p| p.is_read_only(path))
+    }
+
+    async fn dispatch_command(&self, ctx: &Context, command: &CommandInteraction) {
+        let (path, options) = command_path(&command.data.name, &command.data.options);
+        let action = format!("/{}", path.join(" "));
+        ack::track(command.id, replies_ephemeral(&path, options));
+        let defer_if_slow = async {
+            match ack::defer_if_pending(ctx, command).await {
+                Ok(true) => info!("Deferred {action}: no response after {DEFER_AFTER:?}"),
+                Ok(false) => {}
+                Err(e) => warn!("Failed to defer {action}: {e}"),
+            }
+        };
+        let outcome =
+            with_watchdog(self.run_command(ctx, command, &path), DEFER_AFTER, defer_if_slow).await;
+        ack::forget(command.id);
+        self.audit(&command.user, &action, &audit_options(options), &outcome);
+    }
+
+    async fn run_command(&self, ctx: &Context, command: &CommandInteraction, path: &[&str]) -> Outcome {
+        if let Err(refusal) = authorize(
+            self.owner_id,
+            &self.guest_ids,
+            self.permissions.get(path[0]).map(Vec::as_slice),
+            command.user.id.get(),
+            self.is_read_only(path),
+        ) {
+            warn!(
+                "Unauthorized command attempt by {} ({}): /{}",
+                command.user.name,
+                command.user.id,
+                path.join(" ")
+            );
+            let data = response_message()
+                .content(refusal)
+                .ephemeral(true);
+            let _ = ack::respond(ctx, command, data).await;
+            return Outcome::Denied;
+        }
+
+        let command_name = command.data.name.clone();
+        match command_name.as_str() {
+            "help" => self.handle_help(ctx, command).await,
+            "admin" => self.handle_admin(ctx, command).await,
+            "notify" => self.handle_notify(ctx, command).await,
+            _ => return self.run_plugin_command(ctx, command, &command_name).await,
+        }
+        Outcome::Handled
+    }
+
+    /// Cooldown, then the first plugin that claims the command.
+    async fn run_plugin_command(&self, ctx: &Context, command: &CommandInteraction, command_name: &str) -> Outcome {
+
+        if let Err(remaining) =
+            self.cooldowns
+                .check(command.user.id.get(), command_name, Instant::now())
+        {
+            let data = response_message()
+                .content(format!(
+                    "Slow down, try again in {}s.",
+                    remaining.as_secs_f64().ceil() as u64
+                ))
+                .ephemeral(true);
+            let _ = ack::respond(ctx, command, data).await;
+            return Outcome::RateLimited;
+        }
+
+        // Collected up front: holding the lazy iterator across the awaits
+        // trips the compiler's `Send` inference. Nothing runs until polled.
+        let calls: Vec<_> = self.plugins.iter().map(|p| (p.name(), p.handle_command(ctx, command))).collect();
+        let Some((plugin, result)) = offer(calls).await else {
+            warn!("No plugin handled command: {command_name}");
+            let data = response_message()
+                .content("Unknown command.")
+                .ephemeral(true);
+            let _ = ack::respond(ctx, command, data).await;
+            return Outcome::Unhandled;
+        };
+
+        Span::current().record("plugin", plugin);
+        METRICS.command_handled(plugin);
+        if let Err(e) = &result {
+            METRICS.plugin_error(plugin);
+            log_plugin_error(plugin, command_name, e);
+            let data = response_message()
+                .content(e.user_message())
+                .ephemeral(true);
+            // The plugin may have deferred before failing; then only
+            // an edit of the deferred response reaches the user.
+            if ack::respond(ctx, command, data).await.is_err() {
+                let edit = edit_message().content(e.user_message());
+                let _ = command.edit_response(&ctx.http, edit).await;
+            }
+        }
+        Outcome::from(result)
+    }
+
+    async fn dispatch_component(&self, ctx: &Context, component: &ComponentInteraction) {
+        let outcome = self.run_component(ctx, component).await;
+        let action = format!("component {}", component.data.custom_id);
+        self.audit(&component.user, &action, &[], &outcome);
+    }
+
+    /// Components act on behalf of the command that sent them, so they're
+    /// open to that command's allowlist. They always count as mutating.
+    fn authorize_component(&self, custom_id: &str, user_id: u64) -> Result<(), &'static str> {
+        let command = self.plugins.iter().find_map(|p| p.component_command(custom_id));
+        let allowlist = command.and_then(|c| self.permissions.get(c)).map(Vec::as_slice);
+        authorize(self.owner_id, &self.guest_ids, allowlist, user_id, false)
+    }
+
+    async fn run_component(&self, ctx: &Context, component: &ComponentInteraction) -> Outcome {
+        let custom_id = component.data.custom_id.clone();
+        if let Err(refusal) = self.authorize_component(&custom_id, component.user.id.get()) {
+            warn!(
+                "Unauthorized component use by {} ({}): {custom_id}",
+                component.user.name, component.user.id
+            );
+            let data = response_message()
+                .content(refusal)
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            let _ = component.create_response(&ctx.http, builder).await;
+            return Outcome::Denied;
+        }
+
+        let calls: Vec<_> = self.plugins.iter().map(|p| (p.name(), p.handle_component(ctx, component))).collect();
+        let Some((plugin, result)) = offer(calls).await else {
+            return Outcome::Unhandled;
+        };
+
+        Span::current().record("plugin", plugin);
+        if let Err(e) = &result {
+            METRICS.plugin_error(plugin);
+            log_plugin_error(plugin, &format!("component {custom_id}"), e);
+            let data = response_message()
+                .content(e.user_message())
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            let _ = component.create_response(&ctx.http, builder).await;
+        }
+        Outcome::from(result)
+    }
+
+    /// Core `/help` command listing every plugin's commands.
+    fn help_command() -> CreateCommand {
+        CreateCommand::new("help").description("List available commands")
+    }
+
+    async fn handle_help(&self, ctx: &Context, command: &CommandInteraction) {
+        let mut groups = vec![(
+            "core".to_string(),
+            vec![
+                ("/help".to_string(), "List available commands".to_string()),
+                (
+                    "/admin clear".to_string(),
+                    "Clear in-memory plugin state".to_string(),
+                ),
+            ],
+        )];
+        if self.test_notifier.is_some() {
+            groups[0].1.push((
+                "/notify test <category>".to_string(),
+                "Send a sample notification".to_string(),
+            ));
+        }
+        groups.extend(
+            self.plugins
+                .iter()
+                .map(|p| (p.name().to_string(), p.describe())),
+        );
+        let reply = inline_or_attachment("**Commands**", &format_help(&groups), "help.md")
+            .ephemeral(true);
+        if let Err(e) = ack::respond_reply(ctx, command, reply).await {
+            error!("Failed to respond to /help: {e}");
+        }
+    }
+
+    /// Core `/admin` command, handled here rather than by a plugin.
+    fn admin_command() -> CreateCommand {
+        CreateCommand::new("admin")
+            .description("Bot administration")
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "clear",
+                "Clear in-memory plugin state (pending requests, conversations)",
+            ))
+    }
+
+    /// `/admin clear`: drop every plugin's in-memory state and summarise.
+    async fn clear_plugin_state(&self) -> String {
+        let mut total = 0;
+        let mut lines = Vec::new();
+        for plugin in &self.plugins {
+            let cleared = guarded(plugin.name(), async { Ok(plugin.clear_state().await) }).await;
+            let removed = cleared.unwrap_or(0);
+            if removed > 0 {
+                lines.push(format!("- {}: {removed}", plugin.name()));
+            }
+            total += removed;
+        }
+        info!("Cleared {total} in-memory entries across plugins");
+        if total == 0 {
+            "Nothing to clear.".to_string()
+        } else {
+            format!("Cleared {total} entries:\n{}", lines.join("\n"))
+        }
+    }
+
+    async fn handle_admin(&self, ctx: &Context, command: &CommandInteraction) {
+        let options = command.data.options();
+        let content = match options.first().map(|o| o.name) {
+            Some("clear") => self.clear_plugin_state().await,
+            _ => "Unknown admin command.".to_string(),
+        };
+        let data = response_message()
+            .content(content)
+            .ephemeral(true);
+        if let Err(e) = ack::respond(ctx, command, data).await {
+            error!("Failed to respond to /admin: {e}");
+        }
+    }
+
+    /// Core `/notify` command, registered only when notifications are configured.
+    fn notify_command() -> CreateCommand {
+        let category = NotificationCategory::ALL.into_iter().fold(
+            CreateCommandOption::new(CommandOptionType::String, "category", "Notification category")
+                .required(true),
+            |option, c| option.add_string_choice(c.label(), c.label()),
+        );
+        CreateCommand::new("notify")
+            .description("Notification tools")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "test",
+                    "Send a sample notification to check where it lands",
+                )
+                .add_sub_option(category),
+            )
+    }
+
+    async fn handle_notify(&self, ctx: &Context, command: &CommandInteraction) {
+        let options = command.data.options();
+        let category = match options.first().map(|o| (o.name, &o.value)) {
+            Some(("test", ResolvedValue::SubCommand(opts))) => opts.iter().find_map(|o| match o.value {
+                ResolvedValue::String(s) if o.name == "category" => {
+                    NotificationCategory::from_label(s)
+                }
+                _ => None,
+            }),
+            _ => None,
+        };
+        let content = match (&self.test_notifier, category) {
+            (None, _) => "Notifications are not configured.".to_string(),
+            (Some(_), None) => "Unknown notify command.".to_string(),
+            (Some(notifier), Some(category)) => match notifier.send(&ctx.http, category).await {
+                Ok(channel) => {
+                    format!("Sent a test {} notification to <#{channel}>.", category.label())
+                }
+                Err(e) => {
+                    error!("Failed to send test notification: {e}");
+                    format!("Couldn't send to <#{}>: {e}", notifier.channel_for(category))
+                }
+            },
+        };
+        let data = response_message()
+            .content(content)
+            .ephemeral(true);
+        if let Err(e) = ack::respond(ctx, command, data).await {
+            error!("Failed to respond to /notify: {e}");
+        }
     }
 }
 
@@ -41,107 +377,614 @@ impl EventHandler for Bot {
     async fn ready(&self, ctx: Context, ready: Ready) {
         info!("{} is connected!", ready.user.name);
 
-        let mut commands = Vec::new();
+        let default_scope = self.guild_id.map_or(CommandScope::Global, CommandScope::Guild);
+        for name in self.scopes.keys() {
+            if !self.plugins.iter().any(|p| p.name() == name) {
+                warn!("[discord.scopes] names unknown or disabled plugin '{name}'");
+            }
+        }
+
+        let mut core_commands = vec![Self::help_command(), Self::admin_command()];
+        if self.test_notifier.is_some() {
+            core_commands.push(Self::notify_command());
+        }
+        let mut scoped = vec![(default_scope, core_commands)];
         for plugin in &self.plugins {
             let plugin_commands = plugin.register_commands();
+            let scope = self.scopes.get(plugin.name()).copied().unwrap_or(default_scope);
             info!(
-                "Registering {} commands from plugin '{}'",
+                "Registering {} commands from plugin '{}' ({scope:?})",
                 plugin_commands.len(),
                 plugin.name()
             );
-            commands.extend(plugin_commands);
+            scoped.push((scope, plugin_commands));
         }
 
-        if let Some(gid) = self.guild_id {
-            let guild_id = GuildId::new(gid);
-            match guild_id.set_commands(&ctx.http, commands).await {
-                Ok(cmds) => info!("Registered {} guild commands", cmds.len()),
-                Err(e) => error!("Failed to register guild commands: {e}"),
-            }
-        } else {
-            for command in commands {
-                match serenity::model::application::Command::create_global_command(&ctx.http, command).await {
-                    Ok(cmd) => info!("Registered global command: {}", cmd.name),
-                    Err(e) => error!("Failed to register global command: {e}"),
-                }
-            }
+        for (scope, commands) in group_by_scope(scoped) {
+            register_scope(&ctx.http, scope, commands).await;
         }
 
         // Start notification polling if configured (take once)
+        if let Some(audit) = &self.audit {
+            audit.start(ctx.http.clone());
+        }
         if let Some(starter) = self.notification_starter.lock().ok().and_then(|mut g| g.take()) {
-            starter.start(ctx.http.clone());
+            starter.start(ctx.http.clone(), self.shutdown.clone());
         }
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         match interaction {
             Interaction::Command(command) => {
-                if !self.is_owner(command.user.id.get()) {
-                    warn!(
-                        "Unauthorized command attempt by {} ({})",
-                        command.user.name,
-                        command.user.id
-                    );
-                    let data = CreateInteractionResponseMessage::new()
-                        .content("You are not authorized to use this bot.")
-                        .ephemeral(true);
-                    let builder = CreateInteractionResponse::Message(data);
-                    let _ = command.create_response(&ctx.http, builder).await;
-                    return;
-                }
+                let span = interaction_span(
+                    "command",
+                    command.id.get(),
+                    command.user.id.get(),
+                    &command.data.name,
+                );
+                self.dispatch_command(&ctx, &command).instrument(span).await;
+            }
+            Interaction::Component(component) => {
+                let span = interaction_span(
+                    "component",
+                    component.id.get(),
+                    component.user.id.get(),
+                    &component.data.custom_id,
+                );
+                self.dispatch_component(&ctx, &component)
+                    .instrument(span)
+                    .await;
+            }
+            _ => {}
+        }
+    }
+}
 
-                let command_name = command.data.name.clone();
-                for plugin in &self.plugins {
-                    match plugin.handle_command(&ctx, &command).await {
-                        Ok(true) => return,
-                        Ok(false) => continue,
-                        Err(e) => {
-                            error!("Plugin '{}' error handling '{}': {e}", plugin.name(), command_name);
-                            let data = CreateInteractionResponseMessage::new()
-                                .content(e.user_message())
-                                .ephemeral(true);
-                            let builder = CreateInteractionResponse::Message(data);
-                            let _ = command.create_response(&ctx.http, builder).await;
-                            return;
-                        }
-                    }
-                }
+/// Await `dispatch`. If it's still running after `after`, run `on_timeout`
+/// alongside it and keep waiting.
+async fn with_watchdog<T>(
+    dispatch: impl Future<Output = T>,
+    after: Duration,
+    on_timeout: impl Future<Output = ()>,
+) -> T {
+    tokio::pin!(dispatch);
+    tokio::select! {
+        output = &mut dispatch => output,
+        _ = tokio::time::sleep(after) => tokio::join!(dispatch, on_timeout).0,
+    }
+}
+
+/// Offer an interaction to each plugin in turn, as `(name, handler call)`
+/// pairs, until one claims it. Returns that plugin's name and how handling
+/// went (a panic counts as an error), or `None` if no plugin claimed it.
+async fn offer<'a, F>(calls: impl IntoIterator<Item = (&'a str, F)>) -> Option<(&'a str, Result<(), PluginError>)>
+where
+    F: Future<Output = Result<bool, PluginError>>,
+{
+    for (plugin, call) in calls {
+        match guarded(plugin, call).await {
+            Ok(true) => return Some((plugin, Ok(()))),
+            Ok(false) => continue,
+            Err(e) => return Some((plugin, Err(e))),
+        }
+    }
+    None
+}
+
+/// Await a plugin call, turning a panic into an error so one buggy plugin
+/// can't take down the interaction handler.
+async fn guarded<T>(
+    plugin: &str,
+    call: impl Future<Output = Result<T, PluginError>>,
+) -> Result<T, PluginError> {
+    match AssertUnwindSafe(call).catch_unwind().await {
+        Ok(result) => result,
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "non-string panic payload".into());
+            error!("Plugin '{plugin}' panicked: {message}");
+            Err(PluginError::Other(format!("plugin '{plugin}' panicked: {message}")))
+        }
+    }
+}
+
+/// Auth failures won't go away on retry, so they're logged as a credentials
+/// problem for the operator rather than as a generic plugin error.
+fn log_plugin_error(plugin: &str, what: &str, e: &PluginError) {
+    match e {
+        PluginError::AuthError(_) => {
+            warn!("Plugin '{plugin}' could not authenticate handling '{what}': {e} (check its credentials in config)");
+        }
+        _ => error!("Plugin '{plugin}' error handling '{what}': {e}"),
+    }
+}
 
-                warn!("No plugin handled command: {command_name}");
-                let data = CreateInteractionResponseMessage::new()
-                    .content("Unknown command.")
-                    .ephemeral(true);
-                let builder = CreateInteractionResponse::Message(data);
-                let _ = command.create_response(&ctx.http, builder).await;
+/// Per-user, per-command cooldown so repeated commands can't hammer backends.
+/// Core commands (`/help`, `/admin`, `/notify`) are not subject to it.
+struct Cooldowns {
+    period: Duration,
+    last_used: Mutex<HashMap<(u64, String), Instant>>,
+}
+
+impl Cooldowns {
+    fn new(period: Duration) -> Self {
+        Self {
+            period,
+            last_used: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a use of `command` by `user_id` at `now`, or return how long
+    /// they still have to wait if the last use was within the period.
+    fn check(&self, user_id: u64, command: &str, now: Instant) -> Result<(), Duration> {
+        if self.period.is_zero() {
+            return Ok(());
+        }
+        let mut last_used = self.last_used.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (user_id, command.to_string());
+        if let Some(&last) = last_used.get(&key) {
+            let elapsed = now.saturating_duration_since(last);
+            if elapsed < self.period {
+                return Err(self.period - elapsed);
             }
-            Interaction::Component(component) => {
-                if !self.is_owner(component.user.id.get()) {
-                    let data = CreateInteractionResponseMessage::new()
-                        .content("You are not authorized to use this bot.")
-                        .ephemeral(true);
-                    let builder = CreateInteractionResponse::Message(data);
-                    let _ = component.create_response(&ctx.http, builder).await;
-                    return;
-                }
+        }
+        // Drop expired entries so the map only holds users still on cooldown
+        last_used.retain(|_, &mut t| now.saturating_duration_since(t) < self.period);
+        last_used.insert(key, now);
+        Ok(())
+    }
+}
 
-                let custom_id = component.data.custom_id.clone();
-                for plugin in &self.plugins {
-                    match plugin.handle_component(&ctx, &component).await {
-                        Ok(true) => return,
-                        Ok(false) => continue,
-                        Err(e) => {
-                            error!("Plugin '{}' error handling component '{}': {e}", plugin.name(), custom_id);
-                            let data = CreateInteractionResponseMessage::new()
-                                .content(e.user_message())
-                                .ephemeral(true);
-                            let builder = CreateInteractionResponse::Message(data);
-                            let _ = component.create_response(&ctx.http, builder).await;
-                            return;
-                        }
-                    }
-                }
+/// Whether `user_id` may run a command. The owner always can. A command
+/// with an `allowlist` is open to exactly those users; otherwise guests may
+/// run it when it's read-only and anyone else never. `Err` is the refusal
+/// to show.
+fn authorize(
+    owner_id: u64,
+    guest_ids: &[u64],
+    allowlist: Option<&[u64]>,
+    user_id: u64,
+    read_only: bool,
+) -> Result<(), &'static str> {
+    if let Some(allowed) = allowlist
+        && user_id != owner_id
+    {
+        return if allowed.contains(&user_id) {
+            Ok(())
+        } else {
+            Err("You are not allowed to use this command.")
+        };
+    }
+    let guest = guest_ids.contains(&user_id);
+    if user_id == owner_id || (guest && read_only) {
+        Ok(())
+    } else if guest {
+        Err("Guests can only use read-only commands.")
+    } else {
+        Err("You are not authorized to use this bot.")
+    }
+}
+
+/// Whether the reply to the command at `path` with `options` will be
+/// ephemeral, so a watchdog defer has to be too: core commands always are,
+/// and so is anything run with `private:true`.
+fn replies_ephemeral(path: &[&str], options: &[CommandDataOption]) -> bool {
+    matches!(path.first(), Some(&("help" | "admin" | "notify")))
+        || options
+            .iter()
+            .any(|o| o.name == "private" && matches!(o.value, CommandDataOptionValue::Boolean(true)))
+}
+
+/// The command name followed by any subcommand group and subcommand, e.g.
+/// `["unraid", "docker", "list"]`, and the options given to that subcommand.
+fn command_path<'a>(
+    name: &'a str,
+    mut options: &'a [CommandDataOption],
+) -> (Vec<&'a str>, &'a [CommandDataOption]) {
+    let mut path = vec![name];
+    while let Some(option) = options.first() {
+        match &option.value {
+            CommandDataOptionValue::SubCommandGroup(nested)
+            | CommandDataOptionValue::SubCommand(nested) => {
+                path.push(&option.name);
+                options = nested;
             }
-            _ => {}
+            _ => break,
+        }
+    }
+    (path, options)
+}
+
+/// Span wrapping one interaction's dispatch, so every log line emitted while a
+/// plugin handles it (including client warnings) carries the same ids.
+/// `plugin` is filled in once a plugin claims the interaction.
+fn interaction_span(kind: &str, interaction_id: u64, user_id: u64, action: &str) -> Span {
+    info_span!(
+        "interaction",
+        kind,
+        interaction_id,
+        user_id,
+        action,
+        plugin = tracing::field::Empty,
+    )
+}
+
+/// One bold heading per plugin followed by its `usage — description` lines.
+fn format_help(groups: &[(String, Vec<(String, String)>)]) -> String {
+    let mut msg = String::new();
+    for (plugin, commands) in groups {
+        msg.push_str(&format!("**{plugin}**\n"));
+        for (usage, description) in commands {
+            if description.is_empty() {
+                msg.push_str(&format!("- `{usage}`\n"));
+            } else {
+                msg.push_str(&format!("- `{usage}` — {description}\n"));
+            }
+        }
+    }
+    msg
+}
+
+/// Register `commands` in `scope` with one bulk call. Discord rejects the
+/// whole batch if any command is invalid, so on failure retry them one at a
+/// time to keep the valid ones working. Global commands can take a while to
+/// show up in clients; that's expected.
+async fn register_scope(http: &Http, scope: CommandScope, commands: Vec<CreateCommand>) {
+    let bulk = match scope {
+        CommandScope::Guild(gid) => GuildId::new(gid).set_commands(http, commands.clone()).await,
+        CommandScope::Global => Command::set_global_commands(http, commands.clone()).await,
+    };
+    let err = match bulk {
+        Ok(cmds) => {
+            info!("Registered {} commands ({scope:?})", cmds.len());
+            return;
+        }
+        Err(e) => e,
+    };
+
+    error!("Bulk command registration failed ({scope:?}), retrying one at a time: {err}");
+    let mut registered = 0;
+    for command in commands {
+        let result = match scope {
+            CommandScope::Guild(gid) => GuildId::new(gid).create_command(http, command).await,
+            CommandScope::Global => Command::create_global_command(http, command).await,
+        };
+        match result {
+            Ok(_) => registered += 1,
+            Err(e) => error!("Failed to register command ({scope:?}): {e}"),
+        }
+    }
+    info!("Registered {registered} commands ({scope:?})");
+}
+
+/// Merge per-plugin command lists into one list per scope. Each scope gets a
+/// single bulk registration, which replaces whatever was registered there
+/// before, so commands moved to another scope disappear from the old one.
+fn group_by_scope<T>(
+    scoped: impl IntoIterator<Item = (CommandScope, Vec<T>)>,
+) -> BTreeMap<CommandScope, Vec<T>> {
+    let mut groups: BTreeMap<CommandScope, Vec<T>> = BTreeMap::new();
+    for (scope, items) in scoped {
+        groups.entry(scope).or_default().extend(items);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interaction_span_fields() {
+        let lines = crate::logging::capture_json_logs(|| {
+            let span = interaction_span("command", 42, 7, "sonarr");
+            let _guard = span.enter();
+            Span::current().record("plugin", "sonarr");
+            warn!("downstream warning");
+        });
+
+        assert_eq!(lines.len(), 1);
+        let span = &lines[0]["spans"][0];
+        assert_eq!(span["name"], "interaction");
+        assert_eq!(span["kind"], "command");
+        assert_eq!(span["interaction_id"], 42);
+        assert_eq!(span["user_id"], 7);
+        assert_eq!(span["action"], "sonarr");
+        assert_eq!(span["plugin"], "sonarr");
+    }
+
+    #[derive(Default)]
+    struct FakePlugin {
+        name: &'static str,
+        panics: bool,
+        cleared: usize,
+        /// How many commands it was offered.
+        offered: std::sync::atomic::AtomicUsize,
+    }
+
+    impl FakePlugin {
+        /// `handle_command` without the `Context`, which can't be built
+        /// outside serenity: claims commands named after the plugin, and
+        /// panics doing so if `panics` is set.
+        async fn handle(&self, command_name: &str) -> Result<bool, PluginError> {
+            self.offered.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if command_name != self.name {
+                return Ok(false);
+            }
+            if self.panics {
+                panic!("called `Option::unwrap()` on a `None` value");
+            }
+            Ok(true)
+        }
+    }
+
+    #[async_trait]
+    impl Plugin for FakePlugin {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn register_commands(&self) -> Vec<CreateCommand> {
+            Vec::new()
+        }
+
+        fn component_command(&self, custom_id: &str) -> Option<&str> {
+            custom_id.starts_with(&format!("{}:", self.name)).then_some(self.name)
+        }
+
+        async fn handle_command(
+            &self,
+            _ctx: &Context,
+            command: &CommandInteraction,
+        ) -> Result<bool, PluginError> {
+            self.handle(&command.data.name).await
+        }
+
+        async fn clear_state(&self) -> usize {
+            if self.panics {
+                panic!("state lock poisoned");
+            }
+            self.cleared
         }
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_watchdog_fires_only_for_slow_dispatch() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        // A slow plugin: the watchdog fires, and the result still arrives.
+        let fired = AtomicBool::new(false);
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Outcome::Handled
+        };
+        let outcome = with_watchdog(slow, DEFER_AFTER, async { fired.store(true, Ordering::SeqCst) }).await;
+        assert_eq!(outcome, Outcome::Handled);
+        assert!(fired.load(Ordering::SeqCst));
+
+        let fired = AtomicBool::new(false);
+        let fast = async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Outcome::Unhandled
+        };
+        let outcome = with_watchdog(fast, DEFER_AFTER, async { fired.store(true, Ordering::SeqCst) }).await;
+        assert_eq!(outcome, Outcome::Unhandled);
+        assert!(!fired.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_guarded_turns_panics_into_errors() {
+        let err = guarded::<bool>("fake", async { panic!("boom") }).await.unwrap_err();
+        assert_eq!(err.to_string(), "plugin 'fake' panicked: boom");
+        assert_eq!(err.user_message(), "Something went wrong. Check bot logs for details.");
+
+        let id = 7;
+        let err = guarded::<bool>("fake", async move { panic!("bad id {id}") }).await.unwrap_err();
+        assert_eq!(err.to_string(), "plugin 'fake' panicked: bad id 7");
+
+        assert!(guarded("fake", async { Ok(true) }).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handler_fails_the_command() {
+        let plugins = [
+            FakePlugin { name: "fine", ..Default::default() },
+            FakePlugin { name: "broken", panics: true, ..Default::default() },
+        ];
+        let dispatch = |command: &'static str| {
+            offer(plugins.iter().map(move |p| (p.name, p.handle(command))))
+        };
+
+        // "fine" passes, then "broken" claims the command and panics.
+        let (plugin, result) = dispatch("broken").await.unwrap();
+        assert_eq!(plugin, "broken");
+        assert_eq!(
+            Outcome::from(result),
+            Outcome::Failed("plugin 'broken' panicked: called `Option::unwrap()` on a `None` value".into())
+        );
+        // The loop carries on serving commands afterwards.
+        let (plugin, result) = dispatch("fine").await.unwrap();
+        assert_eq!((plugin, Outcome::from(result)), ("fine", Outcome::Handled));
+        assert!(dispatch("missing").await.is_none());
+        let offered = |i: usize| plugins[i].offered.load(std::sync::atomic::Ordering::SeqCst);
+        assert_eq!((offered(0), offered(1)), (3, 2));
+    }
+
+    #[tokio::test]
+    async fn test_panicking_plugin_does_not_stop_the_rest() {
+        let plugins: Vec<Box<dyn Plugin>> = vec![
+            Box::new(FakePlugin { name: "broken", panics: true, ..Default::default() }),
+            Box::new(FakePlugin { name: "fine", cleared: 3, ..Default::default() }),
+        ];
+        let (_tx, rx) = watch::channel(false);
+        let bot = Bot::new(plugins, 1, None, HashMap::new(), Duration::ZERO, None, rx);
+        assert_eq!(bot.clear_plugin_state().await, "Cleared 3 entries:\n- fine: 3");
+        // Still answering after the panic.
+        assert_eq!(bot.clear_plugin_state().await, "Cleared 3 entries:\n- fine: 3");
+    }
+
+    #[test]
+    fn test_authorize_matrix() {
+        let (owner, guest, stranger) = (1, 2, 3);
+        let guests = [guest];
+        let allowed = |user, read_only| authorize(owner, &guests, None, user, read_only).is_ok();
+        assert!(allowed(owner, true));
+        assert!(allowed(owner, false));
+        assert!(allowed(guest, true));
+        assert!(!allowed(guest, false));
+        assert!(!allowed(stranger, true));
+        assert!(!allowed(stranger, false));
+        assert_eq!(
+            authorize(owner, &guests, None, guest, false),
+            Err("Guests can only use read-only commands.")
+        );
+        assert_eq!(
+            authorize(owner, &[], None, guest, true),
+            Err("You are not authorized to use this bot.")
+        );
+    }
+
+    #[test]
+    fn test_authorize_command_allowlist() {
+        let (owner, guest, listed, stranger) = (1, 2, 3, 4);
+        let guests = [guest];
+        let allowlist = [listed];
+        let allowed = |user, read_only| {
+            authorize(owner, &guests, Some(&allowlist), user, read_only).is_ok()
+        };
+        // Listed users get the whole command, mutations included.
+        assert!(allowed(listed, true) && allowed(listed, false));
+        assert!(allowed(owner, true) && allowed(owner, false));
+        // Guests and strangers are shut out even of read-only subcommands.
+        assert!(!allowed(guest, true) && !allowed(stranger, true));
+        assert_eq!(
+            authorize(owner, &guests, Some(&[]), guest, true),
+            Err("You are not allowed to use this command.")
+        );
+        assert!(authorize(owner, &guests, Some(&[]), owner, false).is_ok());
+    }
+
+    #[test]
+    fn test_components_follow_their_command_allowlist() {
+        let (owner, guest, listed, stranger) = (1, 2, 3, 4);
+        let plugins: Vec<Box<dyn Plugin>> = vec![
+            Box::new(FakePlugin { name: "unraid", ..Default::default() }),
+            Box::new(FakePlugin { name: "qbit", ..Default::default() }),
+        ];
+        let (_tx, rx) = watch::channel(false);
+        let bot = Bot::new(plugins, owner, None, HashMap::new(), Duration::ZERO, None, rx)
+            .with_guest_ids(vec![guest])
+            .with_permissions(HashMap::from([("unraid".to_string(), vec![listed])]));
+
+        // Listed under [permissions] unraid, so they can confirm `/unraid array stop`.
+        assert!(bot.authorize_component("unraid:stop", listed).is_ok());
+        assert!(bot.authorize_component("unraid:stop", owner).is_ok());
+        assert!(bot.authorize_component("unraid:stop", stranger).is_err());
+        // Without an allowlist, or for an unknown component, only the owner.
+        assert!(bot.authorize_component("qbit:pause", listed).is_err());
+        assert!(bot.authorize_component("qbit:pause", owner).is_ok());
+        assert!(bot.authorize_component("other:x", listed).is_err());
+        // Components mutate, so guests are refused.
+        assert_eq!(
+            bot.authorize_component("qbit:pause", guest),
+            Err("Guests can only use read-only commands.")
+        );
+    }
+
+    #[test]
+    fn test_command_path() {
+        let options: Vec<CommandDataOption> = serde_json::from_value(serde_json::json!([{
+            "name": "docker",
+            "type": 2,
+            "options": [{"name": "list", "type": 1, "options": [{"name": "all", "type": 5, "value": true}]}],
+        }]))
+        .unwrap();
+        let (path, leaf) = command_path("unraid", &options);
+        assert_eq!(path, ["unraid", "docker", "list"]);
+        assert_eq!(leaf.len(), 1);
+        assert_eq!(leaf[0].name, "all");
+        assert_eq!(command_path("ping", &[]).0, ["ping"]);
+    }
+
+    #[test]
+    fn test_replies_ephemeral() {
+        let options = |json| serde_json::from_value::<Vec<CommandDataOption>>(json).unwrap();
+        let private = options(serde_json::json!([
+            {"name": "name", "type": 3, "value": "plan"},
+            {"name": "private", "type": 5, "value": true},
+        ]));
+        assert!(replies_ephemeral(&["notes", "read"], &private));
+        let public = options(serde_json::json!([{"name": "private", "type": 5, "value": false}]));
+        assert!(!replies_ephemeral(&["notes", "read"], &public));
+        assert!(!replies_ephemeral(&["sonarr", "search"], &[]));
+        assert!(replies_ephemeral(&["admin", "clear"], &[]));
+        assert!(replies_ephemeral(&["help"], &[]));
+    }
+
+    #[test]
+    fn test_cooldown_bookkeeping() {
+        let cooldowns = Cooldowns::new(Duration::from_secs(3));
+        let start = Instant::now();
+        assert!(cooldowns.check(1, "prowlarr", start).is_ok());
+
+        // Same user and command within the period is rejected with the remainder
+        let remaining = cooldowns
+            .check(1, "prowlarr", start + Duration::from_secs(1))
+            .unwrap_err();
+        assert_eq!(remaining, Duration::from_secs(2));
+
+        // Other commands and other users are tracked separately
+        assert!(cooldowns.check(1, "sonarr", start).is_ok());
+        assert!(cooldowns.check(2, "prowlarr", start).is_ok());
+
+        // A rejected attempt doesn't extend the cooldown
+        assert!(cooldowns.check(1, "prowlarr", start + Duration::from_secs(3)).is_ok());
+        assert_eq!(cooldowns.last_used.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_cooldown_disabled() {
+        let cooldowns = Cooldowns::new(Duration::ZERO);
+        let now = Instant::now();
+        assert!(cooldowns.check(1, "prowlarr", now).is_ok());
+        assert!(cooldowns.check(1, "prowlarr", now).is_ok());
+    }
+
+    #[test]
+    fn test_group_by_scope() {
+        let groups = group_by_scope(vec![
+            (CommandScope::Guild(1), vec!["help", "admin"]),
+            (CommandScope::Global, vec!["notes"]),
+            (CommandScope::Guild(1), vec!["sonarr"]),
+            (CommandScope::Guild(2), vec!["unraid"]),
+            (CommandScope::Global, vec![]),
+        ]);
+        let groups: Vec<_> = groups.into_iter().collect();
+        assert_eq!(
+            groups,
+            vec![
+                (CommandScope::Global, vec!["notes"]),
+                (CommandScope::Guild(1), vec!["help", "admin", "sonarr"]),
+                (CommandScope::Guild(2), vec!["unraid"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_help() {
+        let groups = vec![
+            (
+                "sonarr".to_string(),
+                vec![("/sonarr search".to_string(), "Search for a TV show".to_string())],
+            ),
+            ("custom".to_string(), vec![("/custom".to_string(), String::new())]),
+        ];
+        assert_eq!(
+            format_help(&groups),
+            "**sonarr**\n- `/sonarr search` — Search for a TV show\n**custom**\n- `/custom`\n"
+        );
+    }
 }
@@ -1,4 +1,6 @@
+use discord_assist_util::size::Unit;
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fmt;
 
@@ -6,7 +8,26 @@ const REDACTED: &str = "[redacted]";
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
+    /// Skip mutating API calls (container/VM actions, request adds, indexer
+    /// toggles, torrent pause/resume) and reply with what would have happened
+    /// instead.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Skip TLS certificate verification for Unraid, qBittorrent, Plex, and
+    /// health checks unless a section says otherwise. Off by default; only
+    /// needed for self-signed internal services.
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+    /// Whether sizes and speeds in replies use `binary` (1 KB = 1024 B, the
+    /// default) or `decimal` (1 KB = 1000 B) units.
+    #[serde(default)]
+    pub size_units: Unit,
     pub discord: DiscordConfig,
+    /// Per-command allowlists keyed by command name (e.g. `unraid = [123]`).
+    /// Only the owner and the listed users may run that command; commands
+    /// without an entry use the owner/guest rules.
+    #[serde(default)]
+    pub permissions: HashMap<String, Vec<u64>>,
     #[serde(default)]
     pub unraid: Option<UnraidConfig>,
     #[serde(default)]
@@ -29,14 +50,58 @@ pub struct Config {
     pub notifications: Option<NotificationsConfig>,
     #[serde(default)]
     pub notes: Option<NotesConfig>,
+    #[serde(default)]
+    pub metrics: Option<MetricsConfig>,
+    #[serde(default)]
+    pub audit: Option<AuditConfig>,
 }
 
 #[derive(Deserialize)]
 pub struct DiscordConfig {
     pub token: String,
     pub owner_id: u64,
+    /// Users allowed to run read-only commands (searches, status, lists)
+    /// but nothing that changes state.
+    #[serde(default)]
+    pub guest_ids: Vec<u64>,
     #[serde(default)]
     pub guild_id: Option<u64>,
+    /// Minimum seconds between uses of the same command by one user. 0 disables.
+    #[serde(default = "default_command_cooldown_secs")]
+    pub command_cooldown_secs: u64,
+    /// Where each plugin's commands are registered, keyed by plugin name
+    /// (e.g. `unraid = "guild:123"`). Unlisted plugins use `guild_id` if set,
+    /// otherwise global.
+    #[serde(default)]
+    pub scopes: HashMap<String, CommandScope>,
+}
+
+fn default_command_cooldown_secs() -> u64 {
+    3
+}
+
+/// Where slash commands are registered: `"global"` or `"guild:<id>"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum CommandScope {
+    Global,
+    Guild(u64),
+}
+
+impl TryFrom<String> for CommandScope {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value == "global" {
+            return Ok(Self::Global);
+        }
+        value
+            .strip_prefix("guild:")
+            .and_then(|id| id.trim().parse().ok())
+            .filter(|id| *id != 0)
+            .map(Self::Guild)
+            .ok_or_else(|| format!("invalid scope \"{value}\", expected \"global\" or \"guild:<id>\""))
+    }
 }
 
 impl fmt::Debug for DiscordConfig {
@@ -44,7 +109,10 @@ impl fmt::Debug for DiscordConfig {
         f.debug_struct("DiscordConfig")
             .field("token", &REDACTED)
             .field("owner_id", &self.owner_id)
+            .field("guest_ids", &self.guest_ids)
             .field("guild_id", &self.guild_id)
+            .field("command_cooldown_secs", &self.command_cooldown_secs)
+            .field("scopes", &self.scopes)
             .finish()
     }
 }
@@ -53,6 +121,13 @@ impl fmt::Debug for DiscordConfig {
 pub struct UnraidConfig {
     pub api_url: String,
     pub api_key: String,
+    /// Skip TLS certificate verification (self-signed certs). Defaults to the
+    /// top-level `accept_invalid_certs`.
+    #[serde(default)]
+    pub accept_invalid_certs: Option<bool>,
+    /// Set to false to keep the section but not load the plugin.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
 }
 
 impl fmt::Debug for UnraidConfig {
@@ -60,6 +135,8 @@ impl fmt::Debug for UnraidConfig {
         f.debug_struct("UnraidConfig")
             .field("api_url", &self.api_url)
             .field("api_key", &REDACTED)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("enabled", &self.enabled)
             .finish()
     }
 }
@@ -69,6 +146,61 @@ pub struct ClaudeConfig {
     pub api_url: String,
     #[serde(default)]
     pub api_key: Option<String>,
+    /// If set, conversations are saved to this JSON file and restored on startup.
+    #[serde(default)]
+    pub persist_path: Option<String>,
+    #[serde(default)]
+    pub backend: LlmBackendKind,
+    /// Model to chat with; required by the `ollama` and `openai` backends.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// How long to wait for an answer before telling the user the model took
+    /// too long.
+    #[serde(default = "default_claude_timeout")]
+    pub timeout_secs: u64,
+    /// Largest image `/claude ask` accepts, in megabytes.
+    #[serde(default = "default_max_image_mb")]
+    pub max_image_mb: u64,
+    /// Set to false to keep the section but not load the plugin.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// Long conversations can take minutes on local models.
+fn default_claude_timeout() -> u64 {
+    300
+}
+
+fn default_max_image_mb() -> u64 {
+    5
+}
+
+/// Which API `[claude]` speaks.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmBackendKind {
+    /// Anthropic-style `POST /v1/messages`.
+    #[default]
+    Http,
+    /// Ollama's `POST /api/chat`.
+    Ollama,
+    /// OpenAI-compatible `POST /v1/chat/completions`.
+    OpenAi,
+}
+
+impl LlmBackendKind {
+    /// The config value for this backend.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Http => "http",
+            Self::Ollama => "ollama",
+            Self::OpenAi => "openai",
+        }
+    }
+
+    fn needs_model(self) -> bool {
+        matches!(self, Self::Ollama | Self::OpenAi)
+    }
 }
 
 impl fmt::Debug for ClaudeConfig {
@@ -76,6 +208,12 @@ impl fmt::Debug for ClaudeConfig {
         f.debug_struct("ClaudeConfig")
             .field("api_url", &self.api_url)
             .field("api_key", &self.api_key.as_ref().map(|_| REDACTED))
+            .field("persist_path", &self.persist_path)
+            .field("backend", &self.backend)
+            .field("model", &self.model)
+            .field("timeout_secs", &self.timeout_secs)
+            .field("max_image_mb", &self.max_image_mb)
+            .field("enabled", &self.enabled)
             .finish()
     }
 }
@@ -84,6 +222,9 @@ impl fmt::Debug for ClaudeConfig {
 pub struct SonarrConfig {
     pub api_url: String,
     pub api_key: String,
+    /// Set to false to keep the section but not load the plugin.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
 }
 
 impl fmt::Debug for SonarrConfig {
@@ -91,6 +232,7 @@ impl fmt::Debug for SonarrConfig {
         f.debug_struct("SonarrConfig")
             .field("api_url", &self.api_url)
             .field("api_key", &REDACTED)
+            .field("enabled", &self.enabled)
             .finish()
     }
 }
@@ -99,6 +241,9 @@ impl fmt::Debug for SonarrConfig {
 pub struct RadarrConfig {
     pub api_url: String,
     pub api_key: String,
+    /// Set to false to keep the section but not load the plugin.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
 }
 
 impl fmt::Debug for RadarrConfig {
@@ -106,6 +251,7 @@ impl fmt::Debug for RadarrConfig {
         f.debug_struct("RadarrConfig")
             .field("api_url", &self.api_url)
             .field("api_key", &REDACTED)
+            .field("enabled", &self.enabled)
             .finish()
     }
 }
@@ -114,6 +260,9 @@ impl fmt::Debug for RadarrConfig {
 pub struct ProwlarrConfig {
     pub api_url: String,
     pub api_key: String,
+    /// Set to false to keep the section but not load the plugin.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
 }
 
 impl fmt::Debug for ProwlarrConfig {
@@ -121,6 +270,7 @@ impl fmt::Debug for ProwlarrConfig {
         f.debug_struct("ProwlarrConfig")
             .field("api_url", &self.api_url)
             .field("api_key", &REDACTED)
+            .field("enabled", &self.enabled)
             .finish()
     }
 }
@@ -129,6 +279,13 @@ impl fmt::Debug for ProwlarrConfig {
 pub struct HealthConfig {
     #[serde(default)]
     pub services: Vec<ServiceConfig>,
+    /// If true, check services on the notification interval and post UP/DOWN
+    /// transitions to the notifications channel. Requires [notifications].
+    #[serde(default)]
+    pub alerts: bool,
+    /// Set to false to keep the section but not load the plugin.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
 }
 
 #[derive(Deserialize, Clone)]
@@ -139,6 +296,25 @@ pub struct ServiceConfig {
     pub api_key: Option<String>,
     #[serde(default)]
     pub key_header: Option<String>,
+    /// Report the service as SLOW when it responds successfully but takes longer than this.
+    #[serde(default)]
+    pub warn_ms: Option<u64>,
+    /// Require this exact HTTP status instead of any 2xx.
+    #[serde(default)]
+    pub expect_status: Option<u16>,
+    /// Require the response body to contain this substring.
+    #[serde(default)]
+    pub expect_body_contains: Option<String>,
+    /// HTTP method for the check: GET (default), HEAD, or POST.
+    #[serde(default)]
+    pub method: Option<String>,
+    /// Optional form-encoded request body, e.g. login credentials for POST checks.
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Skip TLS certificate verification (self-signed certs). Defaults to the
+    /// top-level `accept_invalid_certs`.
+    #[serde(default)]
+    pub accept_invalid_certs: Option<bool>,
 }
 
 impl fmt::Debug for ServiceConfig {
@@ -148,6 +324,12 @@ impl fmt::Debug for ServiceConfig {
             .field("url", &self.url)
             .field("api_key", &self.api_key.as_ref().map(|_| REDACTED))
             .field("key_header", &self.key_header)
+            .field("warn_ms", &self.warn_ms)
+            .field("expect_status", &self.expect_status)
+            .field("expect_body_contains", &self.expect_body_contains)
+            .field("method", &self.method)
+            .field("body", &self.body.as_ref().map(|_| REDACTED))
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
             .finish()
     }
 }
@@ -157,6 +339,13 @@ pub struct QbitConfig {
     pub api_url: String,
     pub username: String,
     pub password: String,
+    /// Skip TLS certificate verification (self-signed certs). Defaults to the
+    /// top-level `accept_invalid_certs`.
+    #[serde(default)]
+    pub accept_invalid_certs: Option<bool>,
+    /// Set to false to keep the section but not load the plugin.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
 }
 
 impl fmt::Debug for QbitConfig {
@@ -165,6 +354,8 @@ impl fmt::Debug for QbitConfig {
             .field("api_url", &self.api_url)
             .field("username", &self.username)
             .field("password", &REDACTED)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("enabled", &self.enabled)
             .finish()
     }
 }
@@ -173,6 +364,13 @@ impl fmt::Debug for QbitConfig {
 pub struct PlexConfig {
     pub api_url: String,
     pub api_key: String,
+    /// Skip TLS certificate verification (self-signed certs). Defaults to the
+    /// top-level `accept_invalid_certs`.
+    #[serde(default)]
+    pub accept_invalid_certs: Option<bool>,
+    /// Set to false to keep the section but not load the plugin.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
 }
 
 impl fmt::Debug for PlexConfig {
@@ -180,6 +378,8 @@ impl fmt::Debug for PlexConfig {
         f.debug_struct("PlexConfig")
             .field("api_url", &self.api_url)
             .field("api_key", &REDACTED)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("enabled", &self.enabled)
             .finish()
     }
 }
@@ -190,6 +390,10 @@ pub struct RequestConfig {
     /// Requires [prowlarr] to be configured. Optionally uses [sonarr] and [radarr].
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// Search Sonarr/Radarr lookups (clean titles with year and network)
+    /// instead of raw Prowlarr release names.
+    #[serde(default)]
+    pub clean_titles: bool,
 }
 
 fn default_true() -> bool {
@@ -201,10 +405,57 @@ pub struct NotificationsConfig {
     pub channel_id: u64,
     #[serde(default = "default_poll_interval")]
     pub poll_interval_secs: u64,
+    /// Per-poller overrides of `poll_interval_secs`, keyed by one of
+    /// [`POLLER_NAMES`].
+    #[serde(default)]
+    pub poll_intervals: BTreeMap<String, u64>,
     #[serde(default = "default_temp_threshold")]
     pub temp_threshold: f64,
+    /// Alert when a data disk's free space drops below this percentage.
+    #[serde(default = "default_free_space_threshold_pct")]
+    pub free_space_threshold_pct: f64,
+    /// Alert when a UPS battery charge drops below this percentage.
+    #[serde(default = "default_ups_battery_threshold_pct")]
+    pub ups_battery_threshold_pct: f64,
+    /// Ping someone on critical alerts (overheating disks, UPS on battery,
+    /// failed downloads): `alert_role_id` if set, otherwise the owner.
+    #[serde(default)]
+    pub mention_on_critical: bool,
+    #[serde(default)]
+    pub alert_role_id: Option<u64>,
+    /// If set, seen history ids and Unraid state are saved to this JSON file
+    /// so a restart doesn't repeat or miss notifications.
+    #[serde(default)]
+    pub persist_path: Option<String>,
+    /// Custom embed wording per category. Unset categories keep the defaults.
+    #[serde(default)]
+    pub templates: NotificationTemplates,
+}
+
+/// `[notifications.templates.<category>]` tables.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct NotificationTemplates {
+    #[serde(default)]
+    pub media_grab: Option<MessageTemplate>,
+    #[serde(default)]
+    pub media_import: Option<MessageTemplate>,
+    #[serde(default)]
+    pub server_alert: Option<MessageTemplate>,
 }
 
+/// Embed title and description with `{title}`, `{body}`, and `{service}`
+/// placeholders. A missing field keeps that part of the default message.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct MessageTemplate {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+/// Pollers that can be given their own interval in `[notifications.poll_intervals]`.
+pub const POLLER_NAMES: [&str; 6] = ["sonarr", "radarr", "unraid", "qbit", "plex", "health"];
+
 fn default_poll_interval() -> u64 {
     60
 }
@@ -213,9 +464,198 @@ fn default_temp_threshold() -> f64 {
     50.0
 }
 
+fn default_free_space_threshold_pct() -> f64 {
+    5.0
+}
+
+fn default_ups_battery_threshold_pct() -> f64 {
+    50.0
+}
+
+fn default_snippet_radius() -> u64 {
+    30
+}
+
+fn default_notes_max_depth() -> u64 {
+    16
+}
+
+fn default_daily_folder() -> String {
+    "Daily".into()
+}
+
+/// Ten results of this much context either side still read as a list.
+const MAX_SNIPPET_RADIUS: u64 = 500;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct NotesConfig {
     pub vault_path: String,
+    /// Characters of context shown on each side of a search match.
+    #[serde(default = "default_snippet_radius")]
+    pub snippet_radius: u64,
+    /// Glob patterns for vault files and folders to leave out of the plugin.
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Folders nested deeper than this below the vault root aren't searched.
+    #[serde(default = "default_notes_max_depth")]
+    pub max_depth: u64,
+    /// Folder inside the vault for `/notes daily` journal notes.
+    #[serde(default = "default_daily_folder")]
+    pub daily_folder: String,
+    /// Set to false to keep the section but not load the plugin.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// Prometheus `/metrics` endpoint.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MetricsConfig {
+    /// Address to listen on. The default accepts scrapes from other containers.
+    #[serde(default = "default_metrics_bind")]
+    pub bind: String,
+    #[serde(default = "default_metrics_port")]
+    pub port: u16,
+    /// Set to false to keep the section but not serve metrics.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// Audit trail of every command and button press; see `audit.rs`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuditConfig {
+    /// File each line is appended to.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Channel each line is posted to.
+    #[serde(default)]
+    pub channel_id: Option<u64>,
+}
+
+fn default_metrics_bind() -> String {
+    "0.0.0.0".into()
+}
+
+fn default_metrics_port() -> u16 {
+    9184
+}
+
+/// Apply `field: "ENV_VAR"` overrides to each present section.
+macro_rules! env_overrides {
+    ($($section:expr => { $($field:ident: $var:literal),* $(,)? })*) => {
+        $(
+            if let Some(section) = $section {
+                $(
+                    if let Some(val) = env_value($var) {
+                        tracing::debug!("Overriding {} from {}", stringify!($field), $var);
+                        FromEnv::set(&mut section.$field, $var, val);
+                    }
+                )*
+            }
+        )*
+    };
+}
+
+/// The value of `var`, treating unset and empty the same.
+fn env_value(var: &str) -> Option<String> {
+    env::var(var).ok().filter(|v| !v.is_empty())
+}
+
+/// `My Service` -> `MY_SERVICE`, for building env var names from config values.
+fn env_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
+
+/// Config field types that can be set from an env var string.
+trait FromEnv: Sized {
+    fn parse_env(val: String) -> Option<Self>;
+
+    /// Replace `field`, or warn and keep it if `val` doesn't parse.
+    fn set(field: &mut Self, var: &str, val: String) {
+        match Self::parse_env(val) {
+            Some(parsed) => *field = parsed,
+            None => tracing::warn!("Ignoring {var}: not a valid value"),
+        }
+    }
+}
+
+impl FromEnv for String {
+    fn parse_env(val: String) -> Option<Self> {
+        Some(val)
+    }
+}
+
+impl FromEnv for Option<String> {
+    fn parse_env(val: String) -> Option<Self> {
+        Some(Some(val))
+    }
+}
+
+impl FromEnv for u64 {
+    fn parse_env(val: String) -> Option<Self> {
+        val.trim().parse().ok()
+    }
+}
+
+impl FromEnv for u16 {
+    fn parse_env(val: String) -> Option<Self> {
+        val.trim().parse().ok()
+    }
+}
+
+impl FromEnv for Option<u64> {
+    fn parse_env(val: String) -> Option<Self> {
+        u64::parse_env(val).map(Some)
+    }
+}
+
+/// Comma-separated, e.g. `DISCORD_GUEST_IDS=1,2`.
+impl FromEnv for Vec<u64> {
+    fn parse_env(val: String) -> Option<Self> {
+        val.split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| id.parse().ok())
+            .collect()
+    }
+}
+
+impl FromEnv for f64 {
+    fn parse_env(val: String) -> Option<Self> {
+        val.trim().parse().ok()
+    }
+}
+
+impl FromEnv for bool {
+    fn parse_env(val: String) -> Option<Self> {
+        match val.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Some(true),
+            "0" | "false" | "no" | "off" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl FromEnv for Unit {
+    fn parse_env(val: String) -> Option<Self> {
+        match val.trim().to_ascii_lowercase().as_str() {
+            "binary" => Some(Unit::Binary),
+            "decimal" => Some(Unit::Decimal),
+            _ => None,
+        }
+    }
+}
+
+impl FromEnv for LlmBackendKind {
+    fn parse_env(val: String) -> Option<Self> {
+        match val.trim().to_ascii_lowercase().as_str() {
+            "http" => Some(Self::Http),
+            "ollama" => Some(Self::Ollama),
+            "openai" => Some(Self::OpenAi),
+            _ => None,
+        }
+    }
 }
 
 impl Config {
@@ -223,75 +663,292 @@ impl Config {
         let content = std::fs::read_to_string(path)?;
         let mut config: Config = toml::from_str(&content)?;
         config.apply_env_overrides();
+        config.drop_disabled();
+        config.normalize_urls();
         Ok(config)
     }
 
-    fn apply_env_overrides(&mut self) {
-        if let Ok(val) = env::var("DISCORD_TOKEN")
-            && !val.is_empty()
-        {
-            tracing::debug!("Overriding discord.token from env");
-            self.discord.token = val;
+    /// Whether a client should skip TLS verification: the section's own
+    /// `accept_invalid_certs` if set, otherwise the top-level one.
+    pub fn accept_invalid_certs(&self, section: Option<bool>) -> bool {
+        section.unwrap_or(self.accept_invalid_certs)
+    }
+
+    /// Treat sections with `enabled = false` as absent, so nothing (plugins,
+    /// pollers, validation) uses them.
+    fn drop_disabled(&mut self) {
+        keep_enabled(&mut self.unraid, |c| c.enabled);
+        keep_enabled(&mut self.claude, |c| c.enabled);
+        keep_enabled(&mut self.sonarr, |c| c.enabled);
+        keep_enabled(&mut self.radarr, |c| c.enabled);
+        keep_enabled(&mut self.prowlarr, |c| c.enabled);
+        keep_enabled(&mut self.health, |c| c.enabled);
+        keep_enabled(&mut self.qbit, |c| c.enabled);
+        keep_enabled(&mut self.plex, |c| c.enabled);
+        keep_enabled(&mut self.request, |c| c.enabled);
+        keep_enabled(&mut self.notes, |c| c.enabled);
+        keep_enabled(&mut self.metrics, |c| c.enabled);
+    }
+
+    /// Trim whitespace and trailing slashes from every base URL. Invalid URLs
+    /// are left as-is for `validate` to report.
+    fn normalize_urls(&mut self) {
+        let urls = [
+            self.unraid.as_mut().map(|c| &mut c.api_url),
+            self.claude.as_mut().map(|c| &mut c.api_url),
+            self.sonarr.as_mut().map(|c| &mut c.api_url),
+            self.radarr.as_mut().map(|c| &mut c.api_url),
+            self.prowlarr.as_mut().map(|c| &mut c.api_url),
+            self.qbit.as_mut().map(|c| &mut c.api_url),
+            self.plex.as_mut().map(|c| &mut c.api_url),
+        ];
+        for url in urls.into_iter().flatten() {
+            if let Ok(normalized) = normalize_base_url(url) {
+                *url = normalized;
+            }
         }
-        if let Some(ref mut unraid) = self.unraid
-            && let Ok(val) = env::var("UNRAID_API_KEY")
-            && !val.is_empty()
-        {
-            tracing::debug!("Overriding unraid.api_key from env");
-            unraid.api_key = val;
+    }
+
+    /// Check semantics that TOML parsing can't: empty URLs, sections that depend
+    /// on other sections, and half-configured auth. Returns every problem found.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        if self.discord.token.trim().is_empty() {
+            problems.push("discord.token is empty (set it in config.toml or DISCORD_TOKEN)".into());
         }
-        if let Some(ref mut claude) = self.claude
-            && let Ok(val) = env::var("CLAUDE_API_KEY")
-            && !val.is_empty()
-        {
-            tracing::debug!("Overriding claude.api_key from env");
-            claude.api_key = Some(val);
+        if self.discord.owner_id == 0 {
+            problems.push("discord.owner_id must be your Discord user id".into());
         }
-        if let Some(ref mut sonarr) = self.sonarr
-            && let Ok(val) = env::var("SONARR_API_KEY")
-            && !val.is_empty()
-        {
-            tracing::debug!("Overriding sonarr.api_key from env");
-            sonarr.api_key = val;
+        if self.discord.guest_ids.contains(&0) {
+            problems.push("discord.guest_ids must be Discord user ids, not 0".into());
         }
-        if let Some(ref mut radarr) = self.radarr
-            && let Ok(val) = env::var("RADARR_API_KEY")
-            && !val.is_empty()
+        if let Some(audit) = &self.audit
+            && audit.path.as_deref().is_none_or(|p| p.trim().is_empty())
+            && audit.channel_id.is_none_or(|id| id == 0)
         {
-            tracing::debug!("Overriding radarr.api_key from env");
-            radarr.api_key = val;
+            problems.push("audit needs a path or a channel_id".into());
+        }
+        let mut commands: Vec<_> = self.permissions.keys().collect();
+        commands.sort();
+        for command in commands {
+            if command.is_empty() || command.contains(['/', ' ']) {
+                problems.push(format!(
+                    "permissions key \"{command}\" must be a bare command name like \"unraid\""
+                ));
+            }
+            if self.permissions[command].contains(&0) {
+                problems.push(format!("permissions.{command} must be Discord user ids, not 0"));
+            }
+        }
+
+        let urls = [
+            ("unraid", self.unraid.as_ref().map(|c| &c.api_url)),
+            ("claude", self.claude.as_ref().map(|c| &c.api_url)),
+            ("sonarr", self.sonarr.as_ref().map(|c| &c.api_url)),
+            ("radarr", self.radarr.as_ref().map(|c| &c.api_url)),
+            ("prowlarr", self.prowlarr.as_ref().map(|c| &c.api_url)),
+            ("qbit", self.qbit.as_ref().map(|c| &c.api_url)),
+            ("plex", self.plex.as_ref().map(|c| &c.api_url)),
+        ];
+        for (section, url) in urls {
+            if let Some(Err(e)) = url.map(|u| normalize_base_url(u)) {
+                problems.push(format!("{section}.api_url {e}"));
+            }
+        }
+
+        if self.claude.as_ref().is_some_and(|c| c.timeout_secs == 0) {
+            problems.push("claude.timeout_secs must be greater than 0".into());
         }
-        if let Some(ref mut prowlarr) = self.prowlarr
-            && let Ok(val) = env::var("PROWLARR_API_KEY")
-            && !val.is_empty()
+        if let Some(ref claude) = self.claude
+            && claude.backend.needs_model()
+            && claude.model.as_deref().is_none_or(|m| m.trim().is_empty())
         {
-            tracing::debug!("Overriding prowlarr.api_key from env");
-            prowlarr.api_key = val;
+            problems.push(format!(
+                "claude.model is required with backend = \"{}\"",
+                claude.backend.name()
+            ));
         }
-        if let Some(ref mut qbit) = self.qbit {
-            if let Ok(val) = env::var("QBIT_USERNAME")
-                && !val.is_empty()
-            {
-                tracing::debug!("Overriding qbit.username from env");
-                qbit.username = val;
+
+        if let Some(ref health) = self.health {
+            for svc in &health.services {
+                if let Err(e) = normalize_base_url(&svc.url) {
+                    problems.push(format!("health service '{}' url {e}", svc.name));
+                }
+                match (&svc.api_key, &svc.key_header) {
+                    (Some(_), None) => problems.push(format!(
+                        "health service '{}' sets api_key but no key_header to send it in",
+                        svc.name
+                    )),
+                    (None, Some(_)) => problems.push(format!(
+                        "health service '{}' sets key_header but no api_key",
+                        svc.name
+                    )),
+                    _ => {}
+                }
             }
-            if let Ok(val) = env::var("QBIT_PASSWORD")
-                && !val.is_empty()
-            {
-                tracing::debug!("Overriding qbit.password from env");
-                qbit.password = val;
+            if health.alerts && self.notifications.is_none() {
+                problems.push("health.alerts is on but [notifications] is not configured".into());
             }
         }
-        if let Some(ref mut plex) = self.plex
-            && let Ok(val) = env::var("PLEX_API_KEY")
-            && !val.is_empty()
+
+        if self.request.as_ref().is_some_and(|r| r.enabled) && self.prowlarr.is_none() {
+            problems.push("[request] is enabled but requires [prowlarr]".into());
+        }
+        if self.request.as_ref().is_some_and(|r| r.enabled && r.clean_titles)
+            && self.sonarr.is_none()
+            && self.radarr.is_none()
         {
-            tracing::debug!("Overriding plex.api_key from env");
-            plex.api_key = val;
+            problems.push("request.clean_titles needs [sonarr] or [radarr] to search".into());
+        }
+
+        if let Some(ref notif) = self.notifications {
+            if notif.channel_id == 0 {
+                problems.push("notifications.channel_id must be a Discord channel id".into());
+            }
+            if notif.poll_interval_secs == 0 {
+                problems.push("notifications.poll_interval_secs must be greater than 0".into());
+            }
+            for (name, secs) in &notif.poll_intervals {
+                if !POLLER_NAMES.contains(&name.as_str()) {
+                    problems.push(format!(
+                        "notifications.poll_intervals.{name} is not a poller (expected one of: {})",
+                        POLLER_NAMES.join(", ")
+                    ));
+                } else if *secs == 0 {
+                    problems.push(format!("notifications.poll_intervals.{name} must be greater than 0"));
+                }
+            }
+            if notif.alert_role_id == Some(0) {
+                problems.push("notifications.alert_role_id must be a Discord role id".into());
+            }
+        }
+
+        if let Some(ref notes) = self.notes {
+            if notes.vault_path.trim().is_empty() {
+                problems.push("notes.vault_path is empty".into());
+            }
+            if notes.ignore.iter().any(|glob| glob.trim_matches('/').is_empty()) {
+                problems.push("notes.ignore contains an empty pattern".into());
+            }
+            let daily = notes.daily_folder.trim();
+            if daily.is_empty() || daily.starts_with(['/', '\\']) || daily.contains("..") {
+                problems.push("notes.daily_folder must be a folder inside the vault".into());
+            }
+            if notes.max_depth == 0 {
+                problems.push("notes.max_depth must be greater than 0".into());
+            }
+            if notes.snippet_radius > MAX_SNIPPET_RADIUS {
+                problems.push(format!("notes.snippet_radius must be at most {MAX_SNIPPET_RADIUS}"));
+            }
+        }
+
+        if let Some(ref metrics) = self.metrics {
+            if metrics.bind.trim().is_empty() {
+                problems.push("metrics.bind is empty".into());
+            }
+            if metrics.port == 0 {
+                problems.push("metrics.port must be greater than 0".into());
+            }
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+
+    /// Override config values from `SECTION_FIELD` env vars (e.g. `PLEX_API_URL`,
+    /// `NOTES_VAULT_PATH`). Only sections present in the file are touched, and
+    /// empty values are ignored. Health service keys use `HEALTH_<NAME>_API_KEY`,
+    /// and top-level fields use their upper-cased name (`DRY_RUN`, `SIZE_UNITS`).
+    fn apply_env_overrides(&mut self) {
+        env_overrides! {
+            Some(&mut *self) => {
+                dry_run: "DRY_RUN",
+                accept_invalid_certs: "ACCEPT_INVALID_CERTS",
+                size_units: "SIZE_UNITS",
+            }
+            Some(&mut self.discord) => {
+                token: "DISCORD_TOKEN",
+                owner_id: "DISCORD_OWNER_ID",
+                guest_ids: "DISCORD_GUEST_IDS",
+                guild_id: "DISCORD_GUILD_ID",
+                command_cooldown_secs: "DISCORD_COMMAND_COOLDOWN_SECS",
+            }
+            self.unraid.as_mut() => { api_url: "UNRAID_API_URL", api_key: "UNRAID_API_KEY" }
+            self.claude.as_mut() => {
+                api_url: "CLAUDE_API_URL",
+                api_key: "CLAUDE_API_KEY",
+                persist_path: "CLAUDE_PERSIST_PATH",
+                backend: "CLAUDE_BACKEND",
+                model: "CLAUDE_MODEL",
+                timeout_secs: "CLAUDE_TIMEOUT_SECS",
+                max_image_mb: "CLAUDE_MAX_IMAGE_MB",
+            }
+            self.sonarr.as_mut() => { api_url: "SONARR_API_URL", api_key: "SONARR_API_KEY" }
+            self.radarr.as_mut() => { api_url: "RADARR_API_URL", api_key: "RADARR_API_KEY" }
+            self.prowlarr.as_mut() => { api_url: "PROWLARR_API_URL", api_key: "PROWLARR_API_KEY" }
+            self.qbit.as_mut() => {
+                api_url: "QBIT_API_URL",
+                username: "QBIT_USERNAME",
+                password: "QBIT_PASSWORD",
+            }
+            self.plex.as_mut() => { api_url: "PLEX_API_URL", api_key: "PLEX_API_KEY" }
+            self.notifications.as_mut() => {
+                channel_id: "NOTIFICATIONS_CHANNEL_ID",
+                poll_interval_secs: "NOTIFICATIONS_POLL_INTERVAL_SECS",
+                temp_threshold: "NOTIFICATIONS_TEMP_THRESHOLD",
+                free_space_threshold_pct: "NOTIFICATIONS_FREE_SPACE_THRESHOLD_PCT",
+                ups_battery_threshold_pct: "NOTIFICATIONS_UPS_BATTERY_THRESHOLD_PCT",
+                mention_on_critical: "NOTIFICATIONS_MENTION_ON_CRITICAL",
+                alert_role_id: "NOTIFICATIONS_ALERT_ROLE_ID",
+                persist_path: "NOTIFICATIONS_PERSIST_PATH",
+            }
+            self.notes.as_mut() => {
+                vault_path: "NOTES_VAULT_PATH",
+                snippet_radius: "NOTES_SNIPPET_RADIUS",
+                max_depth: "NOTES_MAX_DEPTH",
+                daily_folder: "NOTES_DAILY_FOLDER",
+            }
+            self.metrics.as_mut() => { bind: "METRICS_BIND", port: "METRICS_PORT" }
+            self.audit.as_mut() => { path: "AUDIT_PATH", channel_id: "AUDIT_CHANNEL_ID" }
+        }
+
+        if let Some(ref mut health) = self.health {
+            for svc in &mut health.services {
+                let var = format!("HEALTH_{}_API_KEY", env_name(&svc.name));
+                if let Some(val) = env_value(&var) {
+                    tracing::debug!("Overriding health service '{}' api_key from {var}", svc.name);
+                    svc.api_key = Some(val);
+                }
+            }
         }
     }
 }
 
+fn keep_enabled<T>(section: &mut Option<T>, enabled: impl Fn(&T) -> bool) {
+    if section.as_ref().is_some_and(|s| !enabled(s)) {
+        *section = None;
+    }
+}
+
+/// Require an `http://` or `https://` scheme and strip trailing slashes, so
+/// clients can append paths with a single `/`. The error completes a sentence
+/// starting with the field name.
+pub fn normalize_base_url(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err("is empty".into());
+    }
+    let lower = url.to_ascii_lowercase();
+    let rest = lower
+        .strip_prefix("https://")
+        .or_else(|| lower.strip_prefix("http://"))
+        .ok_or_else(|| format!("must start with http:// or https:// (got \"{url}\")"))?;
+    if rest.trim_start_matches('/').is_empty() {
+        return Err(format!("has no host (got \"{url}\")"));
+    }
+    Ok(url.trim_end_matches('/').to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -304,8 +961,10 @@ mod tests {
             owner_id = 123456789
         "#;
         let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(!config.dry_run);
         assert_eq!(config.discord.token, "test-token");
         assert_eq!(config.discord.owner_id, 123456789);
+        assert_eq!(config.discord.command_cooldown_secs, 3);
         assert!(config.unraid.is_none());
         assert!(config.sonarr.is_none());
     }
@@ -384,6 +1043,7 @@ mod tests {
         let config: Config = toml::from_str(toml_str).unwrap();
         let health = config.health.unwrap();
         assert_eq!(health.services.len(), 1);
+        assert!(!health.alerts);
         assert_eq!(health.services[0].name, "Sonarr");
 
         let qbit = config.qbit.unwrap();
@@ -394,14 +1054,369 @@ mod tests {
 
         let request = config.request.unwrap();
         assert!(request.enabled);
+        assert!(!request.clean_titles);
 
         let notif = config.notifications.unwrap();
         assert_eq!(notif.channel_id, 1234567890);
         assert_eq!(notif.poll_interval_secs, 60);
         assert_eq!(notif.temp_threshold, 50.0);
+        assert_eq!(notif.free_space_threshold_pct, 5.0);
+        assert_eq!(notif.ups_battery_threshold_pct, 50.0);
+        assert_eq!(notif.templates, NotificationTemplates::default());
+        assert!(!notif.mention_on_critical);
+        assert_eq!(notif.alert_role_id, None);
 
         let notes = config.notes.unwrap();
         assert_eq!(notes.vault_path, "/vault");
+        assert_eq!(notes.snippet_radius, 30);
+        assert_eq!(notes.max_depth, 16);
+        assert_eq!(notes.daily_folder, "Daily");
+    }
+
+    #[test]
+    fn validate_notes_settings() {
+        let base = "[discord]\ntoken = \"t\"\nowner_id = 1\n[notes]\nvault_path = \"/vault\"\n";
+        let config: Config = toml::from_str(&format!("{base}snippet_radius = 120\n")).unwrap();
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(&format!("{base}snippet_radius = 501\n")).unwrap();
+        assert_eq!(config.validate().unwrap_err(), vec!["notes.snippet_radius must be at most 500"]);
+
+        let config: Config = toml::from_str(&format!("{base}ignore = [\"Templates\", \"/\"]\n")).unwrap();
+        assert_eq!(config.notes.as_ref().unwrap().ignore[0], "Templates");
+        assert_eq!(config.validate().unwrap_err(), vec!["notes.ignore contains an empty pattern"]);
+
+        let config: Config = toml::from_str(&format!("{base}max_depth = 0\n")).unwrap();
+        assert_eq!(config.validate().unwrap_err(), vec!["notes.max_depth must be greater than 0"]);
+
+        let config: Config = toml::from_str(&format!("{base}daily_folder = \"../Journal\"\n")).unwrap();
+        assert_eq!(
+            config.validate().unwrap_err(),
+            vec!["notes.daily_folder must be a folder inside the vault"]
+        );
+    }
+
+    #[test]
+    fn normalize_base_url_valid() {
+        assert_eq!(normalize_base_url("http://sonarr:8989").unwrap(), "http://sonarr:8989");
+        assert_eq!(
+            normalize_base_url(" HTTPS://unraid.local/graphql ").unwrap(),
+            "HTTPS://unraid.local/graphql"
+        );
+    }
+
+    #[test]
+    fn normalize_base_url_trailing_slash() {
+        assert_eq!(normalize_base_url("http://plex:32400/").unwrap(), "http://plex:32400");
+        assert_eq!(normalize_base_url("http://plex:32400//").unwrap(), "http://plex:32400");
+    }
+
+    #[test]
+    fn normalize_base_url_missing_scheme() {
+        let err = normalize_base_url("unraid.local/graphql").unwrap_err();
+        assert!(err.contains("http:// or https://"), "{err}");
+        assert!(normalize_base_url("ftp://host").is_err());
+        assert!(normalize_base_url("http://").is_err());
+        assert_eq!(normalize_base_url("  ").unwrap_err(), "is empty");
+    }
+
+    #[test]
+    fn normalize_urls_on_load() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [sonarr]
+            api_url = "http://sonarr:8989/"
+            api_key = "key"
+
+            [plex]
+            api_url = "plex:32400"
+            api_key = "key"
+        "#,
+        )
+        .unwrap();
+        config.normalize_urls();
+        assert_eq!(config.sonarr.as_ref().unwrap().api_url, "http://sonarr:8989");
+        let problems = config.validate().unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("plex.api_url must start with"));
+    }
+
+    #[test]
+    fn accept_invalid_certs_defaults_and_overrides() {
+        let toml_str = r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [plex]
+            api_url = "https://plex:32400"
+            api_key = "key"
+            accept_invalid_certs = true
+
+            [qbit]
+            api_url = "https://qbit:8080"
+            username = "admin"
+            password = "pass"
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        let plex = config.plex.as_ref().unwrap().accept_invalid_certs;
+        let qbit = config.qbit.as_ref().unwrap().accept_invalid_certs;
+        // Verification stays on unless a section opts out
+        assert!(!config.accept_invalid_certs);
+        assert!(config.accept_invalid_certs(plex));
+        assert!(!config.accept_invalid_certs(qbit));
+
+        // The global flag covers sections that don't say otherwise
+        config.accept_invalid_certs = true;
+        assert!(config.accept_invalid_certs(qbit));
+        assert!(!config.accept_invalid_certs(Some(false)));
+    }
+
+    #[test]
+    fn disabled_section_is_not_loaded() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [sonarr]
+            api_url = "http://sonarr:8989"
+            api_key = "key"
+            enabled = false
+
+            [radarr]
+            api_url = "http://radarr:7878"
+            api_key = "key"
+
+            [request]
+            enabled = false
+        "#,
+        )
+        .unwrap();
+        assert!(!config.sonarr.as_ref().unwrap().enabled);
+        assert!(config.radarr.as_ref().unwrap().enabled);
+
+        config.drop_disabled();
+        assert!(config.sonarr.is_none());
+        assert!(config.radarr.is_some());
+        assert!(config.request.is_none());
+    }
+
+    #[test]
+    fn validate_accepts_minimal_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+        "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_collects_all_problems() {
+        let toml_str = r#"
+            [discord]
+            token = ""
+            owner_id = 1
+
+            [sonarr]
+            api_url = " "
+            api_key = "key"
+
+            [health]
+            alerts = true
+            [[health.services]]
+            name = "Sonarr"
+            url = "http://sonarr:8989"
+            api_key = "key"
+
+            [request]
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let problems = config.validate().unwrap_err();
+        assert_eq!(problems.len(), 5, "{problems:?}");
+        assert!(problems.iter().any(|p| p.contains("discord.token")));
+        assert!(problems.iter().any(|p| p.contains("sonarr.api_url")));
+        assert!(problems.iter().any(|p| p.contains("no key_header")));
+        assert!(problems.iter().any(|p| p.contains("health.alerts")));
+        assert!(problems.iter().any(|p| p.contains("requires [prowlarr]")));
+    }
+
+    #[test]
+    fn command_scopes() {
+        let toml_str = r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [discord.scopes]
+            notes = "global"
+            unraid = "guild:42"
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.discord.scopes["notes"], CommandScope::Global);
+        assert_eq!(config.discord.scopes["unraid"], CommandScope::Guild(42));
+
+        for bad in ["guild:", "guild:abc", "guild:0", "server:1"] {
+            let toml_str = format!("[discord]\ntoken = \"t\"\nowner_id = 1\nscopes = {{ notes = \"{bad}\" }}\n");
+            let err = toml::from_str::<Config>(&toml_str).unwrap_err();
+            assert!(err.to_string().contains("invalid scope"), "{bad}: {err}");
+        }
+    }
+
+    #[test]
+    fn validate_request_clean_titles_needs_a_service() {
+        let toml_str = r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [prowlarr]
+            api_url = "http://prowlarr:9696"
+            api_key = "key"
+
+            [request]
+            clean_titles = true
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let problems = config.validate().unwrap_err();
+        assert_eq!(problems, vec!["request.clean_titles needs [sonarr] or [radarr] to search"]);
+    }
+
+    #[test]
+    fn parse_notification_templates() {
+        let toml_str = r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [notifications]
+            channel_id = 10
+
+            [notifications.templates.media_grab]
+            title = "⬇️ {title}"
+
+            [notifications.templates.server_alert]
+            title = "🚨 {service}"
+            body = "{title}: {body}"
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let templates = config.notifications.unwrap().templates;
+        let grab = templates.media_grab.unwrap();
+        assert_eq!(grab.title.as_deref(), Some("⬇️ {title}"));
+        assert!(grab.body.is_none());
+        assert!(templates.media_import.is_none());
+        assert_eq!(templates.server_alert.unwrap().body.as_deref(), Some("{title}: {body}"));
+    }
+
+    #[test]
+    fn validate_notifications_channel() {
+        let toml_str = r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [notifications]
+            channel_id = 0
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let problems = config.validate().unwrap_err();
+        assert_eq!(problems, vec!["notifications.channel_id must be a Discord channel id"]);
+    }
+
+    #[test]
+    fn claude_backend_defaults_to_http_and_ollama_needs_model() {
+        let base = "[discord]\ntoken = \"t\"\nowner_id = 1\n[claude]\napi_url = \"http://ollama:11434\"\n";
+        let config: Config = toml::from_str(base).unwrap();
+        assert_eq!(config.claude.as_ref().unwrap().backend, LlmBackendKind::Http);
+        assert_eq!(config.claude.as_ref().unwrap().timeout_secs, 300);
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(&format!("{base}timeout_secs = 0\n")).unwrap();
+        assert_eq!(config.validate().unwrap_err(), vec!["claude.timeout_secs must be greater than 0"]);
+
+        let config: Config = toml::from_str(&format!("{base}backend = \"ollama\"\n")).unwrap();
+        assert_eq!(
+            config.validate().unwrap_err(),
+            vec!["claude.model is required with backend = \"ollama\""]
+        );
+
+        let config: Config =
+            toml::from_str(&format!("{base}backend = \"ollama\"\nmodel = \"llama3.1\"\n")).unwrap();
+        let claude = config.claude.as_ref().unwrap();
+        assert_eq!(claude.backend, LlmBackendKind::Ollama);
+        assert_eq!(claude.model.as_deref(), Some("llama3.1"));
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            toml::from_str(&format!("{base}backend = \"openai\"\nmodel = \"gpt-4o-mini\"\n")).unwrap();
+        assert_eq!(config.claude.as_ref().unwrap().backend, LlmBackendKind::OpenAi);
+        assert!(config.validate().is_ok());
+
+        assert!(toml::from_str::<Config>(&format!("{base}backend = \"gpt\"\n")).is_err());
+    }
+
+    #[test]
+    fn validate_notifications_poll_intervals() {
+        let toml_str = r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [notifications]
+            channel_id = 1
+            poll_intervals = { sonarr = 30, unraid = 0, lidarr = 60 }
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let problems = config.validate().unwrap_err();
+        assert_eq!(
+            problems,
+            vec![
+                "notifications.poll_intervals.lidarr is not a poller (expected one of: sonarr, radarr, unraid, qbit, plex, health)",
+                "notifications.poll_intervals.unraid must be greater than 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_metrics_defaults_and_validate_port() {
+        let toml_str = r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [metrics]
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let metrics = config.metrics.as_ref().unwrap();
+        assert_eq!(metrics.bind, "0.0.0.0");
+        assert_eq!(metrics.port, 9184);
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(&format!("{toml_str}\nport = 0")).unwrap();
+        assert_eq!(config.validate().unwrap_err(), vec!["metrics.port must be greater than 0"]);
+    }
+
+    #[test]
+    fn validate_disabled_request_needs_no_prowlarr() {
+        let toml_str = r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [request]
+            enabled = false
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
     }
 
     #[test]
@@ -434,6 +1449,105 @@ mod tests {
         assert_eq!(config.discord.token, "env-token");
     }
 
+    #[test]
+    fn env_override_dry_run() {
+        let _lock = ENV_LOCK.lock().unwrap();
+        let toml_str = r#"
+            dry_run = false
+
+            [discord]
+            token = "t"
+            owner_id = 1
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        // SAFETY: test holds ENV_LOCK so no concurrent env mutation.
+        unsafe { env::set_var("DRY_RUN", "1") };
+        config.apply_env_overrides();
+        unsafe { env::remove_var("DRY_RUN") };
+        assert!(config.dry_run);
+    }
+
+    #[test]
+    fn size_units_from_file_and_env() {
+        let _lock = ENV_LOCK.lock().unwrap();
+        let toml_str = r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.size_units, Unit::Binary);
+
+        // SAFETY: test holds ENV_LOCK so no concurrent env mutation.
+        unsafe { env::set_var("SIZE_UNITS", "Decimal") };
+        config.apply_env_overrides();
+        unsafe { env::set_var("SIZE_UNITS", "metric") };
+        config.apply_env_overrides();
+        unsafe { env::remove_var("SIZE_UNITS") };
+        assert_eq!(config.size_units, Unit::Decimal);
+
+        let config: Config = toml::from_str(&format!("size_units = \"decimal\"\n{toml_str}")).unwrap();
+        assert_eq!(config.size_units, Unit::Decimal);
+        assert!(toml::from_str::<Config>(&format!("size_units = \"si\"\n{toml_str}")).is_err());
+    }
+
+    #[test]
+    fn guest_ids_from_file_and_env() {
+        let _lock = ENV_LOCK.lock().unwrap();
+        let toml_str = "[discord]\ntoken = \"t\"\nowner_id = 1\nguest_ids = [2, 3]\n";
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.discord.guest_ids, [2, 3]);
+
+        // SAFETY: test holds ENV_LOCK so no concurrent env mutation.
+        unsafe { env::set_var("DISCORD_GUEST_IDS", " 4, 5,") };
+        config.apply_env_overrides();
+        unsafe { env::set_var("DISCORD_GUEST_IDS", "4,bob") };
+        config.apply_env_overrides();
+        unsafe { env::remove_var("DISCORD_GUEST_IDS") };
+        assert_eq!(config.discord.guest_ids, [4, 5]);
+
+        config.discord.guest_ids.push(0);
+        let problems = config.validate().unwrap_err();
+        assert!(problems.iter().any(|p| p.contains("guest_ids")), "{problems:?}");
+    }
+
+    #[test]
+    fn permissions_parse_and_validate() {
+        let base = "[discord]\ntoken = \"t\"\nowner_id = 1\n";
+        let config: Config = toml::from_str(base).unwrap();
+        assert!(config.permissions.is_empty());
+
+        let config: Config =
+            toml::from_str(&format!("{base}[permissions]\nunraid = [2, 3]\nplex = []\n")).unwrap();
+        assert_eq!(config.permissions["unraid"], [2, 3]);
+        assert!(config.permissions["plex"].is_empty());
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            toml::from_str(&format!("{base}[permissions]\n\"/unraid\" = [2]\nqbit = [0]\n")).unwrap();
+        let problems = config.validate().unwrap_err();
+        assert!(problems.iter().any(|p| p.contains("\"/unraid\" must be a bare command name")), "{problems:?}");
+        assert!(problems.iter().any(|p| p.contains("permissions.qbit")), "{problems:?}");
+        assert!(toml::from_str::<Config>(&format!("{base}[permissions]\nunraid = \"2\"\n")).is_err());
+    }
+
+    #[test]
+    fn audit_section_needs_a_destination() {
+        let base = "[discord]\ntoken = \"t\"\nowner_id = 1\n";
+        let config: Config = toml::from_str(&format!("{base}[audit]\npath = \"/data/audit.log\"\n")).unwrap();
+        let audit = config.audit.as_ref().unwrap();
+        assert_eq!(audit.path.as_deref(), Some("/data/audit.log"));
+        assert_eq!(audit.channel_id, None);
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(&format!("{base}[audit]\nchannel_id = 5\n")).unwrap();
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(&format!("{base}[audit]\n")).unwrap();
+        let problems = config.validate().unwrap_err();
+        assert_eq!(problems, ["audit needs a path or a channel_id"]);
+    }
+
     #[test]
     fn env_override_empty_is_ignored() {
         let _lock = ENV_LOCK.lock().unwrap();
@@ -485,4 +1599,72 @@ mod tests {
         unsafe { env::remove_var("SONARR_API_KEY") };
         assert!(config.sonarr.is_none());
     }
+
+    #[test]
+    fn env_override_url_and_vault_path() {
+        let _lock = ENV_LOCK.lock().unwrap();
+        let toml_str = r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [plex]
+            api_url = "http://plex:32400"
+            api_key = "key"
+
+            [notes]
+            vault_path = "/vault"
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        // SAFETY: test holds ENV_LOCK so no concurrent env mutation.
+        unsafe {
+            env::set_var("PLEX_API_URL", "http://plex.lan:32400");
+            env::set_var("NOTES_VAULT_PATH", "");
+        }
+        config.apply_env_overrides();
+        unsafe {
+            env::remove_var("PLEX_API_URL");
+            env::remove_var("NOTES_VAULT_PATH");
+        }
+        assert_eq!(config.plex.unwrap().api_url, "http://plex.lan:32400");
+        assert_eq!(config.notes.unwrap().vault_path, "/vault");
+    }
+
+    #[test]
+    fn env_override_numbers_and_health_keys() {
+        let _lock = ENV_LOCK.lock().unwrap();
+        let toml_str = r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [health]
+            [[health.services]]
+            name = "Home Assistant"
+            url = "http://ha:8123"
+            key_header = "Authorization"
+
+            [notifications]
+            channel_id = 10
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        // SAFETY: test holds ENV_LOCK so no concurrent env mutation.
+        unsafe {
+            env::set_var("NOTIFICATIONS_CHANNEL_ID", "42");
+            env::set_var("NOTIFICATIONS_POLL_INTERVAL_SECS", "soon");
+            env::set_var("HEALTH_HOME_ASSISTANT_API_KEY", "Bearer abc");
+        }
+        config.apply_env_overrides();
+        unsafe {
+            env::remove_var("NOTIFICATIONS_CHANNEL_ID");
+            env::remove_var("NOTIFICATIONS_POLL_INTERVAL_SECS");
+            env::remove_var("HEALTH_HOME_ASSISTANT_API_KEY");
+        }
+        let notif = config.notifications.unwrap();
+        assert_eq!(notif.channel_id, 42);
+        // Unparseable values keep the file's setting
+        assert_eq!(notif.poll_interval_secs, 60);
+        let health = config.health.unwrap();
+        assert_eq!(health.services[0].api_key.as_deref(), Some("Bearer abc"));
+    }
 }
@@ -0,0 +1,95 @@
+//! Log output setup. `LOG_FORMAT=json` switches to one JSON object per line for
+//! Loki/ELK; anything else keeps the human-readable formatter. The JSON output
+//! needs tracing-subscriber's `json` feature.
+
+#[cfg(test)]
+use serde_json::Value;
+use tracing_subscriber::EnvFilter;
+
+/// Install the global subscriber. `RUST_LOG` filtering applies to both formats.
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "discord_assist=info".parse().unwrap());
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    if json_requested(std::env::var("LOG_FORMAT").ok().as_deref()) {
+        builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init();
+    } else {
+        builder.init();
+    }
+}
+
+fn json_requested(log_format: Option<&str>) -> bool {
+    log_format.is_some_and(|f| f.trim().eq_ignore_ascii_case("json"))
+}
+
+/// Run `f` under a JSON subscriber and return the parsed log lines.
+#[cfg(test)]
+pub(crate) fn capture_json_logs(f: impl FnOnce()) -> Vec<Value> {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let capture = Capture::default();
+    let writer = capture.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_writer(move || writer.clone())
+        .finish();
+    tracing::subscriber::with_default(subscriber, f);
+
+    let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+    output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_requested() {
+        assert!(json_requested(Some("json")));
+        assert!(json_requested(Some(" JSON ")));
+        assert!(!json_requested(Some("text")));
+        assert!(!json_requested(None));
+    }
+
+    #[test]
+    fn test_json_format_event() {
+        let lines = capture_json_logs(|| {
+            let span = tracing::info_span!("interaction", command = "sonarr");
+            let _guard = span.enter();
+            tracing::warn!(status = 503, "Sonarr is down");
+        });
+
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["fields"]["message"], "Sonarr is down");
+        assert_eq!(line["fields"]["status"], 503);
+        assert_eq!(line["span"]["name"], "interaction");
+        assert_eq!(line["spans"][0]["name"], "interaction");
+        assert_eq!(line["spans"][0]["command"], "sonarr");
+    }
+}
@@ -1,64 +1,96 @@
+mod audit;
 mod bot;
 mod config;
+mod logging;
+mod metrics;
 mod notifications;
+mod reload;
 
+use audit::AuditLog;
 use bot::Bot;
-use config::Config;
+use config::{Config, HealthConfig, LlmBackendKind};
+use discord_assist_arr_common::ArrClient;
 use discord_assist_plugin_api::Plugin;
-use notifications::NotificationStarter;
+use discord_assist_util::http::SharedClients;
+use notifications::{AlertMention, NotificationStarter};
+use reload::ReloadHandles;
 use serenity::prelude::*;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::watch;
 use tracing::info;
 
-fn build_plugins(config: &Config) -> Vec<Box<dyn Plugin>> {
+fn build_plugins(
+    config: &Config,
+    handles: &ReloadHandles,
+    clients: &SharedClients,
+) -> Vec<Box<dyn Plugin>> {
     let mut plugins: Vec<Box<dyn Plugin>> = Vec::new();
 
+    if config.dry_run {
+        tracing::warn!("Dry-run mode: mutating commands will reply without calling any API");
+    }
+
     if let Some(ref cfg) = config.unraid {
-        plugins.push(Box::new(
-            discord_assist_unraid::UnraidPlugin::new(&cfg.api_url, &cfg.api_key),
-        ));
+        let client = clients.get(config.accept_invalid_certs(cfg.accept_invalid_certs));
+        let api = discord_assist_unraid::api::UnraidApi::with_client(client, &cfg.api_url, &cfg.api_key);
+        plugins.push(Box::new(discord_assist_unraid::UnraidPlugin::with_api(
+            api,
+            config.dry_run,
+        )));
         info!("Loaded Unraid plugin");
     }
 
     if let Some(ref cfg) = config.claude {
+        use discord_assist_claude::backend::{
+            HttpLlmBackend, LlmBackend, OllamaBackend, OpenAiBackend,
+        };
+        let timeout = Duration::from_secs(cfg.timeout_secs);
+        let model = cfg.model.as_deref().unwrap_or_default();
+        let backend: Box<dyn LlmBackend> = match cfg.backend {
+            LlmBackendKind::Http => Box::new(
+                HttpLlmBackend::new(&cfg.api_url, cfg.api_key.clone()).with_timeout(timeout),
+            ),
+            LlmBackendKind::Ollama => Box::new(
+                OllamaBackend::new(&cfg.api_url, model, cfg.api_key.clone()).with_timeout(timeout),
+            ),
+            LlmBackendKind::OpenAi => Box::new(
+                OpenAiBackend::new(&cfg.api_url, model, cfg.api_key.clone()).with_timeout(timeout),
+            ),
+        };
         plugins.push(Box::new(
-            discord_assist_claude::ClaudePlugin::new(&cfg.api_url, cfg.api_key.clone()),
+            discord_assist_claude::ClaudePlugin::with_backend(backend, cfg.persist_path.as_deref())
+                .with_max_image_mb(cfg.max_image_mb),
         ));
-        info!("Loaded Claude plugin");
+        info!("Loaded Claude plugin ({} backend)", cfg.backend.name());
     }
 
     if let Some(ref cfg) = config.sonarr {
-        plugins.push(Box::new(
-            discord_assist_sonarr::SonarrPlugin::new(&cfg.api_url, &cfg.api_key),
-        ));
+        plugins.push(Box::new(discord_assist_sonarr::SonarrPlugin::with_client(
+            arr_client(clients, &cfg.api_url, &cfg.api_key, "v3"),
+        )));
         info!("Loaded Sonarr plugin");
     }
 
     if let Some(ref cfg) = config.radarr {
-        plugins.push(Box::new(
-            discord_assist_radarr::RadarrPlugin::new(&cfg.api_url, &cfg.api_key),
-        ));
+        plugins.push(Box::new(discord_assist_radarr::RadarrPlugin::with_client(
+            arr_client(clients, &cfg.api_url, &cfg.api_key, "v3"),
+        )));
         info!("Loaded Radarr plugin");
     }
 
     if let Some(ref cfg) = config.prowlarr {
-        plugins.push(Box::new(
-            discord_assist_prowlarr::ProwlarrPlugin::new(&cfg.api_url, &cfg.api_key),
-        ));
+        plugins.push(Box::new(discord_assist_prowlarr::ProwlarrPlugin::with_client(
+            arr_client(clients, &cfg.api_url, &cfg.api_key, "v1"),
+            config.dry_run,
+        )));
         info!("Loaded Prowlarr plugin");
     }
 
-    if let Some(ref cfg) = config.health {
-        let services = cfg
-            .services
-            .iter()
-            .map(|s| discord_assist_health::ServiceTarget {
-                name: s.name.clone(),
-                url: s.url.clone(),
-                api_key: s.api_key.clone(),
-                key_header: s.key_header.clone(),
-            })
-            .collect();
-        plugins.push(Box::new(discord_assist_health::HealthPlugin::new(services)));
+    if let Some(ref checker) = handles.health {
+        plugins.push(Box::new(discord_assist_health::HealthPlugin::with_checker(
+            checker.clone(),
+        )));
         info!("Loaded Health plugin");
     }
 
@@ -67,14 +99,17 @@ fn build_plugins(config: &Config) -> Vec<Box<dyn Plugin>> {
             &cfg.api_url,
             &cfg.username,
             &cfg.password,
+            config.accept_invalid_certs(cfg.accept_invalid_certs),
+            config.dry_run,
         )));
         info!("Loaded qBittorrent plugin");
     }
 
     if let Some(ref cfg) = config.plex {
-        plugins.push(Box::new(discord_assist_plex::PlexPlugin::new(
-            &cfg.api_url,
-            &cfg.api_key,
+        let client = clients.get(config.accept_invalid_certs(cfg.accept_invalid_certs));
+        plugins.push(Box::new(discord_assist_plex::PlexPlugin::with_client(
+            discord_assist_plex::PlexClient::with_client(client, &cfg.api_url, &cfg.api_key),
+            config.dry_run,
         )));
         info!("Loaded Plex plugin");
     }
@@ -86,16 +121,17 @@ fn build_plugins(config: &Config) -> Vec<Box<dyn Plugin>> {
             let sonarr = config
                 .sonarr
                 .as_ref()
-                .map(|c| (c.api_url.as_str(), c.api_key.as_str()));
+                .map(|c| arr_client(clients, &c.api_url, &c.api_key, "v3"));
             let radarr = config
                 .radarr
                 .as_ref()
-                .map(|c| (c.api_url.as_str(), c.api_key.as_str()));
-            plugins.push(Box::new(discord_assist_request::RequestPlugin::new(
-                &prowlarr.api_url,
-                &prowlarr.api_key,
+                .map(|c| arr_client(clients, &c.api_url, &c.api_key, "v3"));
+            plugins.push(Box::new(discord_assist_request::RequestPlugin::with_clients(
+                arr_client(clients, &prowlarr.api_url, &prowlarr.api_key, "v1"),
                 sonarr,
                 radarr,
+                req_cfg.clean_titles,
+                config.dry_run,
             )));
             info!("Loaded Request plugin");
         } else {
@@ -104,18 +140,137 @@ fn build_plugins(config: &Config) -> Vec<Box<dyn Plugin>> {
     }
 
     if let Some(ref cfg) = config.notes {
-        plugins.push(Box::new(discord_assist_notes::NotesPlugin::new(
-            &cfg.vault_path,
-        )));
+        plugins.push(Box::new(
+            discord_assist_notes::NotesPlugin::new(&cfg.vault_path)
+                .with_snippet_radius(cfg.snippet_radius as usize)
+                .with_ignore(cfg.ignore.clone())
+                .with_max_depth(cfg.max_depth as usize)
+                .with_daily_folder(&cfg.daily_folder),
+        ));
         info!("Loaded Notes plugin");
     }
 
+    let dashboard = discord_assist_dashboard::DashboardClients {
+        unraid: config.unraid.as_ref().map(|c| {
+            let client = clients.get(config.accept_invalid_certs(c.accept_invalid_certs));
+            discord_assist_unraid::api::UnraidApi::with_client(client, &c.api_url, &c.api_key)
+        }),
+        plex: config.plex.as_ref().map(|c| {
+            let client = clients.get(config.accept_invalid_certs(c.accept_invalid_certs));
+            discord_assist_plex::PlexClient::with_client(client, &c.api_url, &c.api_key)
+        }),
+        qbit: config.qbit.as_ref().map(|c| {
+            discord_assist_qbit::QbitClient::new(
+                &c.api_url,
+                &c.username,
+                &c.password,
+                config.accept_invalid_certs(c.accept_invalid_certs),
+            )
+        }),
+        sonarr: config
+            .sonarr
+            .as_ref()
+            .map(|c| arr_client(clients, &c.api_url, &c.api_key, "v3")),
+        radarr: config
+            .radarr
+            .as_ref()
+            .map(|c| arr_client(clients, &c.api_url, &c.api_key, "v3")),
+    };
+    if !dashboard.is_empty() {
+        plugins.push(Box::new(discord_assist_dashboard::DashboardPlugin::with_clients(
+            dashboard,
+        )));
+        info!("Loaded Dashboard plugin");
+    }
+
+    plugins.push(Box::new(discord_assist_ping::PingPlugin::with_checker(
+        discord_assist_health::HealthChecker::with_clients(ping_targets(config), clients.clone()),
+    )));
+    info!("Loaded Ping plugin");
+
     info!("Loaded {} plugins", plugins.len());
     plugins
 }
 
-fn build_notification_starter(config: &Config) -> Option<NotificationStarter> {
+/// An *arr client on the shared verified pool (*arr clients always verify certs).
+fn arr_client(clients: &SharedClients, url: &str, key: &str, api_version: &str) -> ArrClient {
+    ArrClient::with_client(clients.get(false), url, key, api_version)
+}
+
+/// Health check targets from `[health]`. Services without their own
+/// `accept_invalid_certs` use `default_accept_invalid_certs`.
+fn health_targets(
+    cfg: &HealthConfig,
+    default_accept_invalid_certs: bool,
+) -> Vec<discord_assist_health::ServiceTarget> {
+    cfg.services
+        .iter()
+        .map(|s| discord_assist_health::ServiceTarget {
+            name: s.name.clone(),
+            url: s.url.clone(),
+            api_key: s.api_key.clone(),
+            key_header: s.key_header.clone(),
+            warn_ms: s.warn_ms,
+            expect_status: s.expect_status,
+            expect_body_contains: s.expect_body_contains.clone(),
+            method: s.method.clone(),
+            body: s.body.clone(),
+            accept_invalid_certs: s.accept_invalid_certs.unwrap_or(default_accept_invalid_certs),
+        })
+        .collect()
+}
+
+/// Lightweight latency targets for `/ping`, one per configured service.
+fn ping_targets(config: &Config) -> Vec<discord_assist_health::ServiceTarget> {
+    let target = |name: &str, url: String, api_key: Option<&str>, accept_invalid_certs: bool| {
+        discord_assist_health::ServiceTarget {
+            name: name.to_string(),
+            url,
+            api_key: api_key.map(str::to_string),
+            key_header: api_key.map(|_| "X-Api-Key".to_string()),
+            warn_ms: None,
+            expect_status: None,
+            expect_body_contains: None,
+            method: None,
+            body: None,
+            accept_invalid_certs,
+        }
+    };
+    let arr_health = |url: &str, version: &str| {
+        format!("{}/api/{version}/health", url.trim_end_matches('/'))
+    };
+
+    let mut targets = Vec::new();
+    if let Some(ref c) = config.sonarr {
+        targets.push(target("Sonarr", arr_health(&c.api_url, "v3"), Some(&c.api_key), false));
+    }
+    if let Some(ref c) = config.radarr {
+        targets.push(target("Radarr", arr_health(&c.api_url, "v3"), Some(&c.api_key), false));
+    }
+    if let Some(ref c) = config.prowlarr {
+        targets.push(target("Prowlarr", arr_health(&c.api_url, "v1"), Some(&c.api_key), false));
+    }
+    if let Some(ref c) = config.qbit {
+        // The WebUI root answers without a session cookie.
+        let insecure = config.accept_invalid_certs(c.accept_invalid_certs);
+        targets.push(target("qBittorrent", c.api_url.clone(), None, insecure));
+    }
+    if let Some(ref c) = config.plex {
+        // `/identity` is unauthenticated and cheap.
+        let url = format!("{}/identity", c.api_url.trim_end_matches('/'));
+        let insecure = config.accept_invalid_certs(c.accept_invalid_certs);
+        targets.push(target("Plex", url, None, insecure));
+    }
+    targets
+}
+
+fn build_notification_starter(
+    config: &Config,
+    handles: &ReloadHandles,
+    clients: &SharedClients,
+) -> Option<NotificationStarter> {
     let notif = config.notifications.as_ref()?;
+    let settings = handles.notifications.clone()?;
 
     let sonarr = config
         .sonarr
@@ -125,48 +280,127 @@ fn build_notification_starter(config: &Config) -> Option<NotificationStarter> {
         .radarr
         .as_ref()
         .map(|c| (c.api_url.clone(), c.api_key.clone()));
-    let unraid = config
-        .unraid
+    let unraid = config.unraid.as_ref().map(|c| {
+        let insecure = config.accept_invalid_certs(c.accept_invalid_certs);
+        (c.api_url.clone(), c.api_key.clone(), insecure)
+    });
+    let qbit = config.qbit.as_ref().map(|c| {
+        let insecure = config.accept_invalid_certs(c.accept_invalid_certs);
+        (c.api_url.clone(), c.username.clone(), c.password.clone(), insecure)
+    });
+    let plex = config.plex.as_ref().map(|c| {
+        let insecure = config.accept_invalid_certs(c.accept_invalid_certs);
+        (c.api_url.clone(), c.api_key.clone(), insecure)
+    });
+    let health = config
+        .health
         .as_ref()
-        .map(|c| (c.api_url.clone(), c.api_key.clone()));
+        .filter(|c| c.alerts)
+        .and(handles.health.clone());
 
     Some(NotificationStarter {
         channel_id: notif.channel_id,
-        poll_interval_secs: notif.poll_interval_secs,
-        temp_threshold: notif.temp_threshold,
+        settings,
         sonarr,
         radarr,
         unraid,
+        qbit,
+        plex,
+        health,
+        clients: clients.clone(),
+        templates: notif.templates.clone(),
+        mention: notif.mention_on_critical.then_some(match notif.alert_role_id {
+            Some(role) => AlertMention::Role(role),
+            None => AlertMention::User(config.discord.owner_id),
+        }),
+        state_path: notif.persist_path.as_ref().map(PathBuf::from),
     })
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "discord_assist=info".parse().unwrap()),
-        )
-        .init();
+    logging::init();
 
     let config_path = std::env::var("CONFIG_PATH").unwrap_or_else(|_| "config.toml".into());
     let config = Config::load(&config_path)?;
+    if let Err(problems) = config.validate() {
+        anyhow::bail!(
+            "invalid config in {config_path}:\n  - {}",
+            problems.join("\n  - ")
+        );
+    }
 
-    let plugins = build_plugins(&config);
-    let notification_starter = build_notification_starter(&config);
-    let bot = Bot::new(
+    discord_assist_util::size::set_unit(config.size_units);
+    let clients = SharedClients::default();
+    let handles = ReloadHandles::new(&config, &clients);
+    let plugins = build_plugins(&config, &handles, &clients);
+    let notification_starter = build_notification_starter(&config, &handles, &clients);
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut bot = Bot::new(
         plugins,
         config.discord.owner_id,
         config.discord.guild_id,
+        config.discord.scopes.clone(),
+        Duration::from_secs(config.discord.command_cooldown_secs),
         notification_starter,
-    );
+        shutdown_rx.clone(),
+    )
+    .with_guest_ids(config.discord.guest_ids.clone())
+    .with_permissions(config.permissions.clone());
+    if let Some(ref cfg) = config.audit {
+        let path = cfg.path.as_ref().filter(|p| !p.trim().is_empty()).map(PathBuf::from);
+        bot = bot.with_audit(AuditLog::new(path, cfg.channel_id.filter(|id| *id != 0)));
+    }
 
     let mut client = Client::builder(&config.discord.token, GatewayIntents::empty())
         .event_handler(bot)
         .await?;
 
+    if let Some(ref cfg) = config.metrics {
+        let listener = tokio::net::TcpListener::bind((cfg.bind.as_str(), cfg.port)).await?;
+        info!("Serving metrics on http://{}/metrics", listener.local_addr()?);
+        tokio::spawn(metrics::serve(listener, shutdown_rx.clone()));
+    }
+
+    reload::spawn_sighup_reload(config_path, config, handles, shutdown_rx);
+
     info!("Starting DiscordAssist...");
-    client.start().await?;
+    let shard_manager = client.shard_manager.clone();
+    let result = tokio::select! {
+        result = client.start() => result,
+        _ = shutdown_signal() => {
+            info!("Shutdown signal received");
+            Ok(())
+        }
+    };
+
+    // Stop background tasks, then close the gateway connection
+    info!("Shutting down");
+    let _ = shutdown_tx.send(true);
+    shard_manager.shutdown_all().await;
+    result?;
 
     Ok(())
 }
+
+/// Resolves on Ctrl-C, or on SIGTERM (e.g. `docker stop`) on Unix.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => tracing::warn!("Failed to install SIGTERM handler: {e}"),
+        }
+    }
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::warn!("Failed to listen for Ctrl-C: {e}");
+        std::future::pending::<()>().await;
+    }
+}
@@ -0,0 +1,204 @@
+//! Prometheus counters for handled commands, plugin errors, and notifications,
+//! served in the text exposition format on `/metrics` when `[metrics]` is set.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+/// Process-wide counters; cheap enough to always count, served only if enabled.
+pub static METRICS: Metrics = Metrics::new();
+
+type Counter = Mutex<BTreeMap<String, u64>>;
+
+#[derive(Default)]
+pub struct Metrics {
+    /// Commands a plugin took on (including ones that failed), by plugin.
+    commands: Counter,
+    /// Commands and component interactions that returned an error, by plugin.
+    errors: Counter,
+    /// Notifications posted to the channel, by category.
+    notifications: Counter,
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Self {
+            commands: Mutex::new(BTreeMap::new()),
+            errors: Mutex::new(BTreeMap::new()),
+            notifications: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn command_handled(&self, plugin: &str) {
+        increment(&self.commands, plugin);
+    }
+
+    pub fn plugin_error(&self, plugin: &str) {
+        increment(&self.errors, plugin);
+    }
+
+    pub fn notification_sent(&self, category: &str) {
+        increment(&self.notifications, category);
+    }
+
+    /// All counters in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        write_counter(
+            &mut out,
+            "discord_assist_commands_total",
+            "Slash commands handled, by plugin.",
+            "plugin",
+            &self.commands,
+        );
+        write_counter(
+            &mut out,
+            "discord_assist_plugin_errors_total",
+            "Commands and interactions that failed, by plugin.",
+            "plugin",
+            &self.errors,
+        );
+        write_counter(
+            &mut out,
+            "discord_assist_notifications_sent_total",
+            "Notifications posted, by category.",
+            "category",
+            &self.notifications,
+        );
+        out
+    }
+}
+
+fn increment(counter: &Counter, key: &str) {
+    let mut counts = counter.lock().unwrap_or_else(|e| e.into_inner());
+    *counts.entry(key.to_string()).or_default() += 1;
+}
+
+fn write_counter(out: &mut String, name: &str, help: &str, label: &str, counter: &Counter) {
+    let counts = counter.lock().unwrap_or_else(|e| e.into_inner());
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} counter");
+    for (key, count) in counts.iter() {
+        let _ = writeln!(out, "{name}{{{label}=\"{}\"}} {count}", escape_label(key));
+    }
+}
+
+/// Label values escape backslash, double quote, and newline.
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Answer HTTP requests on `listener` until shutdown: `GET /metrics` gets the
+/// counters, anything else a 404.
+pub async fn serve(listener: TcpListener, mut shutdown: watch::Receiver<bool>) {
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(respond(stream));
+                }
+                Err(e) => warn!("Metrics listener failed to accept: {e}"),
+            },
+            changed = shutdown.changed() => {
+                if changed.is_err() || *shutdown.borrow() {
+                    info!("Metrics server shutting down");
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn respond(mut stream: TcpStream) {
+    // The request line is all we need; scrapers send small requests.
+    let mut buf = [0u8; 1024];
+    let n = match stream.read(&mut buf).await {
+        Ok(n) => n,
+        Err(e) => {
+            debug!("Metrics request read failed: {e}");
+            return;
+        }
+    };
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let (status, body) = route(request.lines().next().unwrap_or_default());
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        debug!("Metrics response write failed: {e}");
+    }
+}
+
+fn route(request_line: &str) -> (&'static str, String) {
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next();
+    let path = parts.next().map(|p| p.split('?').next().unwrap_or(p));
+    match (method, path) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", METRICS.render()),
+        _ => ("404 Not Found", "not found\n".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_counts_by_label() {
+        let metrics = Metrics::new();
+        metrics.command_handled("qbit");
+        metrics.command_handled("qbit");
+        metrics.command_handled("plex");
+        metrics.plugin_error("qbit");
+        metrics.notification_sent("media_import");
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE discord_assist_commands_total counter\n"));
+        assert!(text.contains("discord_assist_commands_total{plugin=\"qbit\"} 2\n"), "{text}");
+        assert!(text.contains("discord_assist_commands_total{plugin=\"plex\"} 1\n"));
+        assert!(text.contains("discord_assist_plugin_errors_total{plugin=\"qbit\"} 1\n"));
+        assert!(text.contains("discord_assist_notifications_sent_total{category=\"media_import\"} 1\n"));
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+        assert_eq!(escape_label("a\nb"), "a\\nb");
+    }
+
+    #[test]
+    fn only_get_metrics_is_served() {
+        assert_eq!(route("GET /metrics HTTP/1.1").0, "200 OK");
+        assert_eq!(route("GET /metrics?x=1 HTTP/1.1").0, "200 OK");
+        assert_eq!(route("GET / HTTP/1.1").0, "404 Not Found");
+        assert_eq!(route("POST /metrics HTTP/1.1").0, "404 Not Found");
+        assert_eq!(route("").0, "404 Not Found");
+    }
+
+    #[tokio::test]
+    async fn serves_metrics_over_http() {
+        METRICS.command_handled("metrics-test");
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let server = tokio::spawn(serve(listener, shutdown_rx));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.contains("discord_assist_commands_total{plugin=\"metrics-test\"} 1\n"));
+
+        shutdown_tx.send(true).unwrap();
+        server.await.unwrap();
+    }
+}
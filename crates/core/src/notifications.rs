@@ -1,32 +1,129 @@
-use discord_assist_arr_common::ArrClient;
+use crate::config::{MessageTemplate, NotificationTemplates};
+use crate::metrics::METRICS;
+use discord_assist_arr_common::{ArrClient, PagedResponse};
+use discord_assist_health::{HealthChecker, ServiceStatus};
+use discord_assist_plex::{PlexClient, RecentMetadata};
+use discord_assist_qbit::{QbitClient, TorrentInfo};
+use discord_assist_util::http::SharedClients;
+use discord_assist_util::size::{self, format_size};
 use reqwest::Client;
-use serde::Deserialize;
-use serenity::http::Http;
-use serenity::model::id::ChannelId;
-use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::watch;
-use tracing::{error, info, warn};
-
-/// Discord message length limit.
-const MAX_MESSAGE_LEN: usize = 2000;
+use serde::{Deserialize, Serialize};
+use serenity::async_trait;
+use serenity::builder::{CreateAllowedMentions, CreateEmbed, CreateMessage};
+use serenity::http::{Http, HttpError};
+use serenity::model::id::{ChannelId, RoleId, UserId};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{BuildHasher, RandomState};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, error, info, warn};
 
-pub struct NotificationStarter {
-    pub channel_id: u64,
+/// Discord embed description length limit.
+const MAX_DESCRIPTION_LEN: usize = 4096;
+
+const COLOR_GRAB: u32 = 0x3498db;
+const COLOR_IMPORT: u32 = 0x2ecc71;
+const COLOR_ALERT: u32 = 0xe67e22;
+const COLOR_ALERT_CRIT: u32 = 0xe74c3c;
+
+/// Minimum gap between repeated alerts for the same disk or container.
+const ALERT_COOLDOWN: Duration = Duration::from_secs(30 * 60);
+
+/// Tunables that a config reload can change while the pollers keep running.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationSettings {
     pub poll_interval_secs: u64,
+    /// Per-poller overrides of `poll_interval_secs`, keyed by poller name.
+    pub poll_intervals: BTreeMap<String, u64>,
     pub temp_threshold: f64,
+    pub free_space_threshold_pct: f64,
+    pub ups_battery_threshold_pct: f64,
+}
+
+impl NotificationSettings {
+    fn interval_for(&self, poller: &str) -> Duration {
+        let secs = self
+            .poll_intervals
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(poller))
+            .map_or(self.poll_interval_secs, |(_, secs)| *secs);
+        Duration::from_secs(secs)
+    }
+}
+
+pub type SharedSettings = Arc<RwLock<NotificationSettings>>;
+
+fn read_settings(settings: &SharedSettings) -> NotificationSettings {
+    settings.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Up to a tenth of `interval` extra, so pollers sharing an interval drift
+/// apart instead of hitting their backends at the same moment.
+fn jitter(interval: Duration) -> Duration {
+    let max = interval.as_millis() as u64 / 10;
+    if max == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(RandomState::new().hash_one(Instant::now()) % max)
+}
+
+/// Who critical alerts ping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertMention {
+    Role(u64),
+    User(u64),
+}
+
+impl AlertMention {
+    /// Message content and the matching `allowed_mentions`, so only this
+    /// role or user is pinged.
+    fn apply(self, message: CreateMessage) -> CreateMessage {
+        match self {
+            Self::Role(id) => message
+                .content(format!("<@&{id}>"))
+                .allowed_mentions(CreateAllowedMentions::new().roles([RoleId::new(id)])),
+            Self::User(id) => message
+                .content(format!("<@{id}>"))
+                .allowed_mentions(CreateAllowedMentions::new().users([UserId::new(id)])),
+        }
+    }
+}
+
+pub struct NotificationStarter {
+    pub channel_id: u64,
+    pub settings: SharedSettings,
     pub sonarr: Option<(String, String)>,
     pub radarr: Option<(String, String)>,
-    pub unraid: Option<(String, String)>,
+    /// Unraid URL, API key, and whether to accept invalid TLS certs.
+    pub unraid: Option<(String, String, bool)>,
+    /// qBittorrent URL, username, password, and whether to accept invalid TLS certs.
+    pub qbit: Option<(String, String, String, bool)>,
+    /// Plex URL, token, and whether to accept invalid TLS certs.
+    pub plex: Option<(String, String, bool)>,
+    pub health: Option<Arc<HealthChecker>>,
+    /// Pooled HTTP clients the pollers share with the plugins.
+    pub clients: SharedClients,
+    pub templates: NotificationTemplates,
+    /// Pinged on critical alerts; `None` keeps every alert silent.
+    pub mention: Option<AlertMention>,
+    /// Where poller state is saved between restarts, if anywhere.
+    pub state_path: Option<PathBuf>,
 }
 
 impl NotificationStarter {
-    pub fn start(self, http: Arc<Http>) {
-        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+    pub fn test_notifier(&self) -> TestNotifier {
+        TestNotifier {
+            channel_id: ChannelId::new(self.channel_id),
+            templates: self.templates.clone(),
+        }
+    }
 
+    /// Spawn the polling loop. It exits once `shutdown` flips to `true`.
+    pub fn start(self, http: Arc<Http>, shutdown: watch::Receiver<bool>) {
         tokio::spawn(async move {
-            let mut manager = NotificationManager::from_starter(self, http, shutdown_rx);
+            let mut manager = NotificationManager::from_starter(self, http, shutdown);
             manager.run().await;
         });
     }
@@ -35,22 +132,278 @@ impl NotificationStarter {
 struct NotificationManager {
     http: Arc<Http>,
     channel_id: ChannelId,
-    poll_interval: Duration,
+    settings: SharedSettings,
     pollers: Vec<Box<dyn Poller>>,
+    templates: NotificationTemplates,
+    mention: Option<AlertMention>,
+    state_path: Option<PathBuf>,
+    /// Every poller's latest state; each poller task updates its own entries.
+    state: Arc<Mutex<PersistedState>>,
+    /// The state last written to `state_path`, to skip unchanged writes.
+    saved_state: Option<String>,
+    /// Set after logging that the bot can't post in the channel, so the error
+    /// is logged once rather than for every event until it's fixed.
+    permission_reported: bool,
     shutdown: watch::Receiver<bool>,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+enum SendFailure {
+    /// The bot can't see or post in the channel; retrying won't help until
+    /// someone fixes its permissions.
+    Permission,
+    /// Anything else (network trouble, Discord outages, rate limits).
+    Transient,
+}
+
+/// Discord's "Missing Access" and "Missing Permissions" error codes.
+const MISSING_ACCESS: isize = 50001;
+const MISSING_PERMISSIONS: isize = 50013;
+
+fn classify_send_error(status: Option<u16>, code: Option<isize>) -> SendFailure {
+    match (status, code) {
+        (_, Some(MISSING_ACCESS | MISSING_PERMISSIONS)) | (Some(403), _) => SendFailure::Permission,
+        _ => SendFailure::Transient,
+    }
+}
+
+fn send_failure(e: &serenity::Error) -> SendFailure {
+    match e {
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(resp)) => {
+            classify_send_error(Some(resp.status_code.as_u16()), Some(resp.error.code))
+        }
+        _ => SendFailure::Transient,
+    }
+}
+
+#[async_trait]
 trait Poller: Send + Sync {
-    fn poll(
-        &mut self,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Vec<NotificationEvent>> + Send + '_>>;
+    /// Key for this poller in `poll_intervals`.
+    fn name(&self) -> &str;
+
+    async fn poll(&mut self) -> Vec<NotificationEvent>;
+
+    /// Copy whatever should survive a restart into `state`.
+    fn save(&self, _state: &mut PersistedState) {}
+
+    /// Pick up state saved by a previous run.
+    fn restore(&mut self, _state: &PersistedState) {}
+}
+
+/// Poller state kept in `state_path`, so a restart neither re-announces old
+/// events nor misses transitions that happened while the bot was down.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct PersistedState {
+    /// History record ids already seen, by service name.
+    #[serde(default)]
+    arr_seen_ids: BTreeMap<String, BTreeSet<u64>>,
+    #[serde(default)]
+    unraid: Option<UnraidState>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct UnraidState {
+    last_array_state: Option<String>,
+    #[serde(default)]
+    low_space_disks: BTreeSet<String>,
+    #[serde(default)]
+    ups_on_battery: BTreeMap<String, bool>,
+    #[serde(default)]
+    ups_low_battery: BTreeSet<String>,
+    #[serde(default)]
+    last_container_states: BTreeMap<String, String>,
+}
+
+/// Load saved poller state. A missing file is a fresh start; a corrupt one is
+/// logged and ignored.
+fn load_state(path: &Path) -> PersistedState {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            debug!("No saved notification state at {}: {e}", path.display());
+            return PersistedState::default();
+        }
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!("Ignoring corrupt notification state {}: {e}", path.display());
+        PersistedState::default()
+    })
+}
+
+/// Write `contents` to `<path>.tmp` and rename it over `path`, so a crash
+/// mid-write never leaves a truncated state file behind.
+async fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    tokio::fs::write(&tmp, contents).await?;
+    tokio::fs::rename(&tmp, path).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationCategory {
+    MediaGrab,
+    MediaImport,
+    ServerAlert,
+}
+
+impl NotificationCategory {
+    fn color(self) -> u32 {
+        match self {
+            Self::MediaGrab => COLOR_GRAB,
+            Self::MediaImport => COLOR_IMPORT,
+            Self::ServerAlert => COLOR_ALERT,
+        }
+    }
+
+    pub const ALL: [Self; 3] = [Self::MediaGrab, Self::MediaImport, Self::ServerAlert];
+
+    /// The `category` label on the notifications metric, also used as the
+    /// template table name and the `/notify test` choice.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::MediaGrab => "media_grab",
+            Self::MediaImport => "media_import",
+            Self::ServerAlert => "server_alert",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.label() == label)
+    }
+
+    /// A made-up event in this category for `/notify test`.
+    fn sample(self) -> NotificationEvent {
+        let (service, title, body) = match self {
+            Self::MediaGrab => ("Sonarr", "Grabbed", "Test Show S01E01 (WEBDL-1080p · 1.5 GB · Test)"),
+            Self::MediaImport => ("Radarr", "Imported", "Test Movie (2024)"),
+            Self::ServerAlert => ("Unraid", "Unraid Array", "This is a test alert."),
+        };
+        NotificationEvent {
+            category: self,
+            service: service.into(),
+            critical: false,
+            title: format!("{title} (test)"),
+            body: body.into(),
+        }
+    }
+}
+
+/// Sends `/notify test` samples where the real notifications go, rendered
+/// with the same templates. Test alerts never ping anyone.
+#[derive(Clone)]
+pub struct TestNotifier {
+    channel_id: ChannelId,
+    templates: NotificationTemplates,
+}
+
+impl TestNotifier {
+    /// The channel notifications of `category` are posted to. There is one
+    /// notifications channel, so every category resolves to it.
+    pub fn channel_for(&self, _category: NotificationCategory) -> ChannelId {
+        self.channel_id
+    }
+
+    /// Post a sample `category` notification, returning the channel it went to.
+    pub async fn send(
+        &self,
+        http: &Http,
+        category: NotificationCategory,
+    ) -> Result<ChannelId, serenity::Error> {
+        let channel_id = self.channel_for(category);
+        let message = category.sample().to_message(&self.templates, None);
+        channel_id.send_message(http, message).await?;
+        Ok(channel_id)
+    }
 }
 
 struct NotificationEvent {
+    category: NotificationCategory,
+    /// Where the event came from, e.g. `Sonarr` or `Unraid`.
+    service: String,
+    /// Needs immediate attention; rendered in the critical alert color.
+    critical: bool,
     title: String,
     body: String,
 }
 
+impl NotificationEvent {
+    fn color(&self) -> u32 {
+        if self.critical {
+            COLOR_ALERT_CRIT
+        } else {
+            self.category.color()
+        }
+    }
+
+    fn to_message(
+        &self,
+        templates: &NotificationTemplates,
+        mention: Option<AlertMention>,
+    ) -> CreateMessage {
+        let (title, body) = self.render(templates);
+        let embed = CreateEmbed::new()
+            .title(title)
+            .description(truncate_chars(&body, MAX_DESCRIPTION_LEN))
+            .color(self.color());
+        let message = CreateMessage::new().embed(embed);
+        match mention {
+            Some(mention) if self.critical => mention.apply(message),
+            _ => message,
+        }
+    }
+
+    /// Title and body after applying the category's template, if any.
+    fn render(&self, templates: &NotificationTemplates) -> (String, String) {
+        let template = match self.category {
+            NotificationCategory::MediaGrab => &templates.media_grab,
+            NotificationCategory::MediaImport => &templates.media_import,
+            NotificationCategory::ServerAlert => &templates.server_alert,
+        };
+        let Some(MessageTemplate { title, body }) = template else {
+            return (self.title.clone(), self.body.clone());
+        };
+        let values = HashMap::from([
+            ("title", self.title.as_str()),
+            ("body", self.body.as_str()),
+            ("service", self.service.as_str()),
+        ]);
+        (
+            title.as_deref().map_or_else(|| self.title.clone(), |t| render_template(t, &values)),
+            body.as_deref().map_or_else(|| self.body.clone(), |b| render_template(b, &values)),
+        )
+    }
+}
+
+/// Replace `{name}` with its value from `values`. Unknown placeholders and
+/// unmatched braces are left as written.
+fn render_template(template: &str, values: &HashMap<&str, &str>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}').and_then(|end| values.get(&after[..end]).map(|v| (end, v))) {
+            Some((end, value)) => {
+                out.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn truncate_chars(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => s[..end].to_string(),
+        None => s.to_string(),
+    }
+}
+
 impl NotificationManager {
     fn from_starter(
         starter: NotificationStarter,
@@ -60,59 +413,176 @@ impl NotificationManager {
         let mut pollers: Vec<Box<dyn Poller>> = Vec::new();
 
         if let Some((url, key)) = starter.sonarr {
-            pollers.push(Box::new(ArrHistoryPoller::new("Sonarr", &url, &key, "v3")));
+            let client = ArrClient::with_client(starter.clients.get(false), &url, &key, "v3");
+            pollers.push(Box::new(ArrHistoryPoller::new("Sonarr", client)));
             info!("Notifications: added Sonarr history poller");
         }
         if let Some((url, key)) = starter.radarr {
-            pollers.push(Box::new(ArrHistoryPoller::new("Radarr", &url, &key, "v3")));
+            let client = ArrClient::with_client(starter.clients.get(false), &url, &key, "v3");
+            pollers.push(Box::new(ArrHistoryPoller::new("Radarr", client)));
             info!("Notifications: added Radarr history poller");
         }
-        if let Some((url, key)) = starter.unraid {
+        if let Some((url, key, accept_invalid_certs)) = starter.unraid {
             pollers.push(Box::new(UnraidPoller::new(
                 &url,
                 &key,
-                starter.temp_threshold,
+                starter.clients.get(accept_invalid_certs),
+                starter.settings.clone(),
             )));
             info!("Notifications: added Unraid poller");
         }
+        if let Some((url, username, password, accept_invalid_certs)) = starter.qbit {
+            pollers.push(Box::new(QbitPoller::new(
+                &url,
+                &username,
+                &password,
+                accept_invalid_certs,
+            )));
+            info!("Notifications: added qBittorrent poller");
+        }
+        if let Some((url, token, accept_invalid_certs)) = starter.plex {
+            let client = starter.clients.get(accept_invalid_certs);
+            pollers.push(Box::new(PlexPoller::new(PlexClient::with_client(client, &url, &token))));
+            info!("Notifications: added Plex recently added poller");
+        }
+        if let Some(checker) = starter.health {
+            pollers.push(Box::new(HealthPoller::new(checker)));
+            info!("Notifications: added health alerts poller");
+        }
+
+        // Keep what was loaded, so a poller that can't reach its backend yet
+        // doesn't drop its saved state on the next write.
+        let state = match starter.state_path {
+            Some(ref path) => load_state(path),
+            None => PersistedState::default(),
+        };
+        for poller in &mut pollers {
+            poller.restore(&state);
+        }
 
         Self {
             http,
             channel_id: ChannelId::new(starter.channel_id),
-            poll_interval: Duration::from_secs(starter.poll_interval_secs),
+            settings: starter.settings,
             pollers,
+            templates: starter.templates,
+            mention: starter.mention,
+            state_path: starter.state_path,
+            state: Arc::new(Mutex::new(state)),
+            saved_state: None,
+            permission_reported: false,
             shutdown,
         }
     }
 
+    /// Write the pollers' state to `state_path` if it changed since the last write.
+    async fn save_state(&mut self) {
+        let Some(ref path) = self.state_path else { return };
+        let json = serde_json::to_string(&*self.state.lock().unwrap_or_else(|e| e.into_inner()));
+        let json = match json {
+            Ok(j) => j,
+            Err(e) => {
+                warn!("Failed to serialize notification state: {e}");
+                return;
+            }
+        };
+        if self.saved_state.as_ref() == Some(&json) {
+            return;
+        }
+        match write_atomic(path, &json).await {
+            Ok(()) => self.saved_state = Some(json),
+            Err(e) => warn!("Failed to save notification state to {}: {e}", path.display()),
+        }
+    }
+
+    /// Spawn a task per poller and post what they find until shutdown.
     async fn run(&mut self) {
         info!(
-            "Notification manager started, polling every {}s to channel {}",
-            self.poll_interval.as_secs(),
+            "Notification manager started, polling every {}s by default to channel {}",
+            read_settings(&self.settings).poll_interval_secs,
             self.channel_id
         );
 
+        let (tx, mut rx) = mpsc::channel(16);
+        for poller in std::mem::take(&mut self.pollers) {
+            tokio::spawn(run_poller(
+                poller,
+                self.settings.clone(),
+                self.state.clone(),
+                tx.clone(),
+                self.shutdown.clone(),
+            ));
+        }
+        drop(tx);
+
         loop {
-            for poller in &mut self.pollers {
-                let events = poller.poll().await;
-                for event in events {
-                    let mut msg = format!("**[{}]** {}", event.title, event.body);
-                    msg.truncate(MAX_MESSAGE_LEN);
-                    if let Err(e) = self
-                        .channel_id
-                        .say(&self.http, &msg)
-                        .await
-                    {
-                        error!("Failed to send notification: {e}");
+            tokio::select! {
+                events = rx.recv() => {
+                    // Every poller has stopped
+                    let Some(events) = events else { return };
+                    for event in events {
+                        self.send(event).await;
+                    }
+                    self.save_state().await;
+                }
+                changed = self.shutdown.changed() => {
+                    // A dropped sender also means the bot is going away
+                    if changed.is_err() || *self.shutdown.borrow() {
+                        info!("Notification manager shutting down");
+                        return;
                     }
                 }
             }
+        }
+    }
 
-            // Wait for poll interval or shutdown signal
-            tokio::select! {
-                _ = tokio::time::sleep(self.poll_interval) => {}
-                _ = self.shutdown.changed() => {
-                    info!("Notification manager shutting down");
+    async fn send(&mut self, event: NotificationEvent) {
+        let message = event.to_message(&self.templates, self.mention);
+        match self.channel_id.send_message(&self.http, message).await {
+            Ok(_) => {
+                self.permission_reported = false;
+                METRICS.notification_sent(event.category.label());
+            }
+            Err(e) => match send_failure(&e) {
+                SendFailure::Permission if self.permission_reported => {
+                    debug!("Notification dropped, still missing permissions: {e}");
+                }
+                SendFailure::Permission => {
+                    self.permission_reported = true;
+                    error!(
+                        "Can't post notifications in channel {}: {e}. Give the bot \
+                         View Channel, Send Messages and Embed Links there, or point \
+                         notifications.channel_id at a channel it can use",
+                        self.channel_id
+                    );
+                }
+                SendFailure::Transient => error!("Failed to send notification: {e}"),
+            },
+        }
+    }
+}
+
+/// Poll on this poller's own interval (re-read each cycle so a config reload
+/// applies), handing events to the manager, until shutdown.
+async fn run_poller(
+    mut poller: Box<dyn Poller>,
+    settings: SharedSettings,
+    state: Arc<Mutex<PersistedState>>,
+    events: mpsc::Sender<Vec<NotificationEvent>>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        let found = poller.poll().await;
+        poller.save(&mut state.lock().unwrap_or_else(|e| e.into_inner()));
+        if events.send(found).await.is_err() {
+            return;
+        }
+
+        let interval = read_settings(&settings).interval_for(poller.name());
+        tokio::select! {
+            _ = tokio::time::sleep(interval + jitter(interval)) => {}
+            changed = shutdown.changed() => {
+                if changed.is_err() || *shutdown.borrow() {
                     return;
                 }
             }
@@ -122,11 +592,6 @@ impl NotificationManager {
 
 // --- Arr History Poller (Sonarr/Radarr) ---
 
-#[derive(Debug, Deserialize)]
-struct HistoryResponse {
-    records: Vec<HistoryRecord>,
-}
-
 #[derive(Debug, Deserialize)]
 struct HistoryRecord {
     id: u64,
@@ -134,6 +599,61 @@ struct HistoryRecord {
     event_type: String,
     #[serde(rename = "sourceTitle")]
     source_title: Option<String>,
+    #[serde(default)]
+    quality: Option<QualityModel>,
+    #[serde(default)]
+    data: HistoryData,
+}
+
+#[derive(Debug, Deserialize)]
+struct QualityModel {
+    quality: QualityName,
+}
+
+#[derive(Debug, Deserialize)]
+struct QualityName {
+    name: String,
+}
+
+/// The free-form `data` map; the *arrs send every value as a string.
+#[derive(Debug, Default, Deserialize)]
+struct HistoryData {
+    /// Why a download or import failed.
+    message: Option<String>,
+    indexer: Option<String>,
+    /// Release size in bytes, e.g. `"1572864000"`.
+    size: Option<serde_json::Value>,
+}
+
+impl HistoryRecord {
+    /// e.g. `Bluray-1080p · 1.5 GB · Nyaa`, from whichever details are present.
+    fn grab_details(&self) -> Option<String> {
+        let size = match &self.data.size {
+            Some(serde_json::Value::String(s)) => s.parse().ok(),
+            Some(serde_json::Value::Number(n)) => n.as_u64(),
+            _ => None,
+        };
+        let details: Vec<String> = [
+            self.quality.as_ref().map(|q| q.quality.name.clone()),
+            size.filter(|&b| b > 0).map(|b| format_size(b, size::unit())),
+            self.data.indexer.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|d| !d.is_empty())
+        .collect();
+        (!details.is_empty()).then(|| details.join(" · "))
+    }
+}
+
+/// An entry from the *arr `/health` endpoint.
+#[derive(Debug, Deserialize)]
+struct HealthIssue {
+    source: Option<String>,
+    /// `ok`, `notice`, `warning`, or `error`.
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    message: Option<String>,
 }
 
 struct ArrHistoryPoller {
@@ -141,89 +661,163 @@ struct ArrHistoryPoller {
     client: ArrClient,
     seen_ids: HashSet<u64>,
     first_poll: bool,
+    /// Health issue messages from the last poll; `None` until the first one
+    /// succeeds, so issues present at startup aren't announced.
+    seen_issues: Option<HashSet<String>>,
 }
 
 impl ArrHistoryPoller {
-    fn new(service_name: &str, url: &str, key: &str, api_version: &str) -> Self {
+    fn new(service_name: &str, client: ArrClient) -> Self {
         Self {
             service_name: service_name.to_string(),
-            client: ArrClient::with_api_version(url, key, api_version),
+            client,
             seen_ids: HashSet::new(),
             first_poll: true,
+            seen_issues: None,
         }
     }
 }
 
+#[async_trait]
 impl Poller for ArrHistoryPoller {
-    fn poll(
-        &mut self,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Vec<NotificationEvent>> + Send + '_>>
-    {
-        Box::pin(async move {
-            let result: Result<HistoryResponse, _> = self
-                .client
-                .get_with_params(
-                    "history",
-                    &[("pageSize", "20"), ("sortDirection", "descending"), ("sortKey", "date")],
-                )
-                .await;
-
-            let history = match result {
-                Ok(h) => h,
-                Err(e) => {
-                    warn!("{} history poll failed: {e}", self.service_name);
-                    return Vec::new();
-                }
-            };
+    fn name(&self) -> &str {
+        &self.service_name
+    }
 
-            let mut events = Vec::new();
+    async fn poll(&mut self) -> Vec<NotificationEvent> {
+        let result: Result<PagedResponse<HistoryRecord>, _> = self
+            .client
+            .get_with_params(
+                "history",
+                &[("pageSize", "20"), ("sortDirection", "descending"), ("sortKey", "date")],
+            )
+            .await;
 
-            for record in &history.records {
-                if self.first_poll {
-                    self.seen_ids.insert(record.id);
-                    continue;
-                }
+        let history = match result {
+            Ok(h) => h,
+            Err(e) => {
+                warn!("{} history poll failed: {e}", self.service_name);
+                return Vec::new();
+            }
+        };
 
-                if self.seen_ids.contains(&record.id) {
-                    continue;
-                }
+        let mut events = Vec::new();
 
+        for record in &history.records {
+            if self.first_poll {
                 self.seen_ids.insert(record.id);
-
-                let title_str = record
-                    .source_title
-                    .as_deref()
-                    .unwrap_or("Unknown");
-
-                match record.event_type.as_str() {
-                    "grabbed" => {
-                        events.push(NotificationEvent {
-                            title: format!("{} Grab", self.service_name),
-                            body: format!("Grabbed: {title_str}"),
-                        });
-                    }
-                    "downloadFolderImported" => {
-                        events.push(NotificationEvent {
-                            title: format!("{} Import", self.service_name),
-                            body: format!("Imported: {title_str}"),
-                        });
-                    }
-                    _ => {}
-                }
+                continue;
             }
 
-            // Keep seen_ids from growing unbounded
-            if self.seen_ids.len() > 1000 {
-                let current_ids: HashSet<u64> = history.records.iter().map(|r| r.id).collect();
-                self.seen_ids.retain(|id| current_ids.contains(id));
+            if self.seen_ids.contains(&record.id) {
+                continue;
             }
 
+            self.seen_ids.insert(record.id);
+
+            events.extend(history_event(&self.service_name, record));
+        }
+
+        // Keep seen_ids from growing unbounded
+        if self.seen_ids.len() > 1000 {
+            let current_ids: HashSet<u64> = history.records.iter().map(|r| r.id).collect();
+            self.seen_ids.retain(|id| current_ids.contains(id));
+        }
+
+        self.first_poll = false;
+
+        match self.client.get::<Vec<HealthIssue>>("health").await {
+            Ok(issues) => events.extend(new_health_issues(
+                &mut self.seen_issues,
+                &self.service_name,
+                &issues,
+            )),
+            Err(e) => warn!("{} health poll failed: {e}", self.service_name),
+        }
+        events
+    }
+
+    fn save(&self, state: &mut PersistedState) {
+        if !self.first_poll {
+            state
+                .arr_seen_ids
+                .insert(self.service_name.clone(), self.seen_ids.iter().copied().collect());
+        }
+    }
+
+    fn restore(&mut self, state: &PersistedState) {
+        if let Some(ids) = state.arr_seen_ids.get(&self.service_name) {
+            self.seen_ids = ids.iter().copied().collect();
             self.first_poll = false;
-            events
-        })
+        }
     }
 }
 
+/// The notification for a history record, if its event type is one we announce.
+/// Failed grabs, downloads, and imports are critical alerts.
+fn history_event(service: &str, record: &HistoryRecord) -> Option<NotificationEvent> {
+    let title = record.source_title.as_deref().unwrap_or("Unknown");
+    let (category, critical, kind, body) = match record.event_type.as_str() {
+        "grabbed" => {
+            let body = match record.grab_details() {
+                Some(details) => format!("Grabbed: {title}\n{details}"),
+                None => format!("Grabbed: {title}"),
+            };
+            (NotificationCategory::MediaGrab, false, "Grab", body)
+        }
+        "downloadFolderImported" => {
+            (NotificationCategory::MediaImport, false, "Import", format!("Imported: {title}"))
+        }
+        failed @ ("grabFailed" | "downloadFailed" | "importFailed") => {
+            let kind = match failed {
+                "grabFailed" => "Grab Failed",
+                "downloadFailed" => "Download Failed",
+                _ => "Import Failed",
+            };
+            let mut body = format!("Failed: {title}");
+            if let Some(reason) = record.data.message.as_deref().filter(|m| !m.is_empty()) {
+                body.push_str(&format!("\n{reason}"));
+            }
+            (NotificationCategory::ServerAlert, true, kind, body)
+        }
+        _ => return None,
+    };
+    Some(NotificationEvent {
+        category,
+        service: service.to_string(),
+        critical,
+        title: format!("{service} {kind}"),
+        body,
+    })
+}
+
+/// Alerts for health issues not present on the previous poll. Resolved issues
+/// are forgotten, so one that comes back is announced again.
+fn new_health_issues(
+    seen: &mut Option<HashSet<String>>,
+    service: &str,
+    issues: &[HealthIssue],
+) -> Vec<NotificationEvent> {
+    let current: HashSet<String> = issues.iter().filter_map(|i| i.message.clone()).collect();
+    let Some(previous) = seen.replace(current) else {
+        return Vec::new();
+    };
+    issues
+        .iter()
+        .filter(|i| i.message.as_ref().is_some_and(|m| !previous.contains(m)))
+        .map(|i| {
+            let source = i.source.as_deref().unwrap_or("unknown");
+            NotificationEvent {
+                category: NotificationCategory::ServerAlert,
+                service: service.to_string(),
+                critical: i.kind.as_deref() == Some("error"),
+                title: format!("{service} Health Issue"),
+                body: format!("**{source}**: {}", i.message.as_deref().unwrap_or_default()),
+            }
+        })
+        .collect()
+}
+
 // --- Unraid Poller ---
 
 #[derive(Debug, Deserialize)]
@@ -231,16 +825,55 @@ struct UnraidGraphQLResponse<T> {
     data: Option<T>,
 }
 
-#[derive(Debug, Deserialize)]
+/// One poll's worth of Unraid data. Each section is queried separately and is
+/// `None` if that query failed, so one rejected field doesn't drop every alert.
+#[derive(Debug, Default)]
 struct UnraidPollData {
+    array: Option<UnraidArrayState>,
+    disks: Option<Vec<UnraidDiskInfo>>,
+    containers: Option<Vec<UnraidContainer>>,
+    ups: Vec<UnraidUps>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnraidArrayQuery {
     array: UnraidArrayState,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnraidDisksQuery {
     disks: Vec<UnraidDiskInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnraidDockerQuery {
     docker: UnraidDockerData,
 }
 
 #[derive(Debug, Deserialize)]
 struct UnraidArrayState {
     state: String,
+    #[serde(default)]
+    disks: Vec<UnraidArrayDisk>,
+}
+
+/// Array data disk. Filesystem sizes are null while the array is stopped.
+#[derive(Debug, Deserialize)]
+struct UnraidArrayDisk {
+    name: String,
+    #[serde(rename = "fsSize")]
+    fs_size: Option<f64>,
+    #[serde(rename = "fsFree")]
+    fs_free: Option<f64>,
+}
+
+impl UnraidArrayDisk {
+    fn free_pct(&self) -> Option<f64> {
+        match (self.fs_size, self.fs_free) {
+            (Some(size), Some(free)) if size > 0.0 => Some(free / size * 100.0),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -269,40 +902,107 @@ impl UnraidContainer {
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct UnraidUpsData {
+    #[serde(rename = "upsDevices", default)]
+    ups_devices: Vec<UnraidUps>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnraidUps {
+    name: String,
+    status: String,
+    battery: Option<UnraidUpsBattery>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnraidUpsBattery {
+    #[serde(rename = "chargeLevel")]
+    charge_level: Option<f64>,
+    /// Seconds of runtime left at the current load.
+    #[serde(rename = "estimatedRuntime")]
+    estimated_runtime: Option<u64>,
+}
+
+impl UnraidUps {
+    fn on_battery(&self) -> bool {
+        self.status.to_lowercase().contains("onbatt")
+    }
+
+    fn charge_level(&self) -> Option<f64> {
+        self.battery.as_ref().and_then(|b| b.charge_level)
+    }
+
+    fn runtime_summary(&self) -> String {
+        match self.battery.as_ref().and_then(|b| b.estimated_runtime) {
+            Some(secs) => format!("~{} min runtime left", secs / 60),
+            None => "runtime unknown".into(),
+        }
+    }
+}
+
 struct UnraidPoller {
     client: Client,
     base_url: String,
     api_key: String,
-    temp_threshold: f64,
+    settings: SharedSettings,
     last_array_state: Option<String>,
+    low_space_disks: HashSet<String>,
+    ups_on_battery: HashMap<String, bool>,
+    ups_low_battery: HashSet<String>,
     last_container_states: HashMap<String, String>,
+    temp_alerted_at: HashMap<String, Instant>,
+    container_alerted_at: HashMap<String, Instant>,
     first_poll: bool,
 }
 
 impl UnraidPoller {
-    fn new(url: &str, key: &str, temp_threshold: f64) -> Self {
-        let client = Client::builder()
-            .danger_accept_invalid_certs(true)
-            .build()
-            .expect("Failed to build HTTP client");
+    fn new(url: &str, key: &str, client: Client, settings: SharedSettings) -> Self {
         Self {
             client,
             base_url: url.trim_end_matches('/').to_string(),
             api_key: key.to_string(),
-            temp_threshold,
+            settings,
             last_array_state: None,
+            low_space_disks: HashSet::new(),
+            ups_on_battery: HashMap::new(),
+            ups_low_battery: HashSet::new(),
             last_container_states: HashMap::new(),
+            temp_alerted_at: HashMap::new(),
+            container_alerted_at: HashMap::new(),
             first_poll: true,
         }
     }
 
-    async fn query(&self) -> Result<UnraidPollData, String> {
-        let query = r#"{
-            array { state }
-            disks { name temperature }
-            docker { containers { names state } }
-        }"#;
+    async fn query(&self) -> UnraidPollData {
+        let (array, disks, docker, ups) = tokio::join!(
+            self.graphql::<UnraidArrayQuery>("{ array { state disks { name fsSize fsFree } } }"),
+            self.graphql::<UnraidDisksQuery>("{ disks { name temperature } }"),
+            self.graphql::<UnraidDockerQuery>("{ docker { containers { names state } } }"),
+            self.query_ups(),
+        );
+        UnraidPollData {
+            array: query_section("array", array).map(|q| q.array),
+            disks: query_section("disks", disks).map(|q| q.disks),
+            containers: query_section("docker", docker).map(|q| q.docker.containers),
+            ups,
+        }
+    }
 
+    /// UPS devices, queried separately so servers without a UPS still get the
+    /// other alerts.
+    async fn query_ups(&self) -> Vec<UnraidUps> {
+        let query = "{ upsDevices { name status battery { chargeLevel estimatedRuntime } } }";
+        match self.graphql::<UnraidUpsData>(query).await {
+            Ok(data) => data.ups_devices,
+            Err(e) => {
+                debug!("Unraid UPS query failed: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn graphql<T: serde::de::DeserializeOwned>(&self, query: &str) -> Result<T, String> {
         let body = serde_json::json!({ "query": query });
         let resp = self
             .client
@@ -313,85 +1013,1175 @@ impl UnraidPoller {
             .await
             .map_err(|e| e.to_string())?;
 
-        let parsed: UnraidGraphQLResponse<UnraidPollData> =
-            resp.json().await.map_err(|e| e.to_string())?;
+        let parsed: UnraidGraphQLResponse<T> = resp.json().await.map_err(|e| e.to_string())?;
 
         parsed.data.ok_or_else(|| "No data in response".to_string())
     }
 }
 
+#[async_trait]
 impl Poller for UnraidPoller {
-    fn poll(
-        &mut self,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Vec<NotificationEvent>> + Send + '_>>
-    {
-        Box::pin(async move {
-            let data = match self.query().await {
-                Ok(d) => d,
-                Err(e) => {
-                    warn!("Unraid poll failed: {e}");
-                    return Vec::new();
-                }
-            };
+    fn name(&self) -> &str {
+        "unraid"
+    }
 
-            let mut events = Vec::new();
+    async fn poll(&mut self) -> Vec<NotificationEvent> {
+        let data = self.query().await;
+        self.process(data, Instant::now())
+    }
 
-            if self.first_poll {
-                self.last_array_state = Some(data.array.state.clone());
-                for container in &data.docker.containers {
-                    self.last_container_states
-                        .insert(container.display_name().to_string(), container.state.clone());
-                }
-                self.first_poll = false;
-                return events;
-            }
+    fn save(&self, state: &mut PersistedState) {
+        if self.first_poll {
+            return;
+        }
+        state.unraid = Some(UnraidState {
+            last_array_state: self.last_array_state.clone(),
+            low_space_disks: self.low_space_disks.iter().cloned().collect(),
+            ups_on_battery: self.ups_on_battery.clone().into_iter().collect(),
+            ups_low_battery: self.ups_low_battery.iter().cloned().collect(),
+            last_container_states: self.last_container_states.clone().into_iter().collect(),
+        });
+    }
+
+    /// Transitions are then judged against the saved state, so changes made
+    /// while the bot was down are announced on the first poll.
+    fn restore(&mut self, state: &PersistedState) {
+        let Some(ref saved) = state.unraid else { return };
+        self.last_array_state = saved.last_array_state.clone();
+        self.low_space_disks = saved.low_space_disks.iter().cloned().collect();
+        self.ups_on_battery = saved.ups_on_battery.clone().into_iter().collect();
+        self.ups_low_battery = saved.ups_low_battery.iter().cloned().collect();
+        self.last_container_states = saved.last_container_states.clone().into_iter().collect();
+        self.first_poll = false;
+    }
+}
 
+impl UnraidPoller {
+    /// Compare a poll against the previous state. Sections missing from `data` are
+    /// skipped and keep their previous state.
+    fn process(&mut self, data: UnraidPollData, now: Instant) -> Vec<NotificationEvent> {
+        let first_poll = std::mem::replace(&mut self.first_poll, false);
+        let settings = read_settings(&self.settings);
+        let mut events = Vec::new();
+
+        if let Some(array) = data.array {
             // Check array state transitions
             if let Some(ref last_state) = self.last_array_state
-                && *last_state != data.array.state
+                && *last_state != array.state
             {
                 events.push(NotificationEvent {
+                    category: NotificationCategory::ServerAlert,
+                    service: "Unraid".into(),
+                    critical: false,
                     title: "Unraid Array".into(),
-                    body: format!("State changed: {} -> {}", last_state, data.array.state),
+                    body: format!("State changed: {} -> {}", last_state, array.state),
+                });
+            }
+            self.last_array_state = Some(array.state.clone());
+
+            events.extend(low_space_transitions(
+                &mut self.low_space_disks,
+                &array.disks,
+                settings.free_space_threshold_pct,
+            ));
+        }
+
+        if let Some(disks) = data.disks
+            && !first_poll
+        {
+            events.extend(temp_alerts(
+                &mut self.temp_alerted_at,
+                &disks,
+                settings.temp_threshold,
+                now,
+            ));
+        }
+
+        events.extend(ups_transitions(
+            &mut self.ups_on_battery,
+            &mut self.ups_low_battery,
+            &data.ups,
+            settings.ups_battery_threshold_pct,
+        ));
+
+        if let Some(containers) = data.containers {
+            events.extend(container_transitions(
+                &mut self.last_container_states,
+                &mut self.container_alerted_at,
+                &containers,
+                now,
+            ));
+        }
+
+        // The first poll only records state
+        if first_poll {
+            events.clear();
+        }
+        events
+    }
+}
+
+/// Unwrap one section of the Unraid poll, logging (not failing) on error.
+fn query_section<T>(name: &str, result: Result<T, String>) -> Option<T> {
+    match result {
+        Ok(section) => Some(section),
+        Err(e) => {
+            warn!("Unraid {name} query failed: {e}");
+            None
+        }
+    }
+}
+
+/// Alert when a UPS switches to battery (critical) or back to mains, and when its
+/// charge drops below `threshold_pct`.
+fn ups_transitions(
+    on_battery: &mut HashMap<String, bool>,
+    low_battery: &mut HashSet<String>,
+    devices: &[UnraidUps],
+    threshold_pct: f64,
+) -> Vec<NotificationEvent> {
+    let mut events = Vec::new();
+    for ups in devices {
+        let now_on_battery = ups.on_battery();
+        let charge = ups
+            .charge_level()
+            .map(|c| format!("{c:.0}% charge"))
+            .unwrap_or_else(|| "charge unknown".into());
+
+        match on_battery.insert(ups.name.clone(), now_on_battery) {
+            Some(false) if now_on_battery => events.push(NotificationEvent {
+                category: NotificationCategory::ServerAlert,
+                service: "Unraid".into(),
+                critical: true,
+                title: "UPS On Battery".into(),
+                body: format!("{}: on battery, {charge}, {}", ups.name, ups.runtime_summary()),
+            }),
+            Some(true) if !now_on_battery => events.push(NotificationEvent {
+                category: NotificationCategory::ServerAlert,
+                service: "Unraid".into(),
+                critical: false,
+                title: "UPS Power Restored".into(),
+                body: format!("{}: back on mains power, {charge}", ups.name),
+            }),
+            _ => {}
+        }
+
+        match ups.charge_level() {
+            Some(level) if level >= threshold_pct => {
+                low_battery.remove(&ups.name);
+            }
+            Some(level) if low_battery.insert(ups.name.clone()) => {
+                events.push(NotificationEvent {
+                    category: NotificationCategory::ServerAlert,
+                    service: "Unraid".into(),
+                    critical: now_on_battery,
+                    title: "UPS Battery Low".into(),
+                    body: format!(
+                        "{}: {level:.0}% charge (threshold: {threshold_pct:.0}%), {}",
+                        ups.name,
+                        ups.runtime_summary()
+                    ),
                 });
             }
-            self.last_array_state = Some(data.array.state.clone());
+            _ => {}
+        }
+    }
+    events
+}
 
-            // Check disk temperatures
-            for disk in &data.disks {
-                if let Some(temp) = disk.temperature
-                    && temp >= self.temp_threshold
-                {
+/// Returns true if `key` has not alerted within `ALERT_COOLDOWN`, recording `now`.
+fn cooldown_passed(alerted_at: &mut HashMap<String, Instant>, key: &str, now: Instant) -> bool {
+    match alerted_at.get(key) {
+        Some(last) if now.duration_since(*last) < ALERT_COOLDOWN => false,
+        _ => {
+            alerted_at.insert(key.to_string(), now);
+            true
+        }
+    }
+}
+
+/// Critical alert on disks at or above the temperature threshold. Repeats are
+/// suppressed for `ALERT_COOLDOWN` unless the disk cools down and crosses the
+/// threshold again.
+fn temp_alerts(
+    alerted_at: &mut HashMap<String, Instant>,
+    disks: &[UnraidDiskInfo],
+    threshold: f64,
+    now: Instant,
+) -> Vec<NotificationEvent> {
+    let mut events = Vec::new();
+    for disk in disks {
+        match disk.temperature {
+            Some(temp) if temp >= threshold => {
+                if cooldown_passed(alerted_at, &disk.name, now) {
                     events.push(NotificationEvent {
+                        category: NotificationCategory::ServerAlert,
+                        service: "Unraid".into(),
+                        critical: true,
                         title: "Unraid Disk Temp".into(),
-                        body: format!(
-                            "{}: {:.0}C (threshold: {:.0}C)",
-                            disk.name, temp, self.temp_threshold
-                        ),
+                        body: format!("{}: {temp:.0}C (threshold: {threshold:.0}C)", disk.name),
                     });
                 }
             }
+            _ => {
+                alerted_at.remove(&disk.name);
+            }
+        }
+    }
+    events
+}
 
-            // Check container state transitions (detect crashes: RUNNING -> EXITED)
-            let mut current_states = HashMap::new();
-            for container in &data.docker.containers {
-                let name = container.display_name().to_string();
-                let state = &container.state;
+/// Detect crashes (RUNNING -> anything else), as critical alerts. A container
+/// stuck in a restart loop is announced at most once per `ALERT_COOLDOWN`.
+fn container_transitions(
+    last_states: &mut HashMap<String, String>,
+    alerted_at: &mut HashMap<String, Instant>,
+    containers: &[UnraidContainer],
+    now: Instant,
+) -> Vec<NotificationEvent> {
+    let mut events = Vec::new();
+    let mut current_states = HashMap::new();
+    for container in containers {
+        let name = container.display_name().to_string();
+        let state = &container.state;
 
-                if let Some(last_state) = self.last_container_states.get(&name)
-                    && last_state == "RUNNING"
-                    && state != "RUNNING"
-                {
-                    events.push(NotificationEvent {
-                        title: "Unraid Container".into(),
-                        body: format!("{name}: {last_state} -> {state}"),
-                    });
-                }
-                current_states.insert(name, state.clone());
+        if let Some(last_state) = last_states.get(&name)
+            && last_state == "RUNNING"
+            && state != "RUNNING"
+            && cooldown_passed(alerted_at, &name, now)
+        {
+            events.push(NotificationEvent {
+                category: NotificationCategory::ServerAlert,
+                service: "Unraid".into(),
+                critical: true,
+                title: "Unraid Container".into(),
+                body: format!("{name}: {last_state} -> {state}"),
+            });
+        }
+        current_states.insert(name, state.clone());
+    }
+    *last_states = current_states;
+    events
+}
+
+/// Emit an alert for each disk whose free space just dropped below `threshold_pct`.
+/// Disks already below it stay quiet until they recover and cross again.
+fn low_space_transitions(
+    low_disks: &mut HashSet<String>,
+    disks: &[UnraidArrayDisk],
+    threshold_pct: f64,
+) -> Vec<NotificationEvent> {
+    let mut events = Vec::new();
+    for disk in disks {
+        let Some(free_pct) = disk.free_pct() else {
+            continue;
+        };
+        if free_pct >= threshold_pct {
+            low_disks.remove(&disk.name);
+        } else if low_disks.insert(disk.name.clone()) {
+            events.push(NotificationEvent {
+                category: NotificationCategory::ServerAlert,
+                service: "Unraid".into(),
+                critical: false,
+                title: "Unraid Disk Space Low".into(),
+                body: format!(
+                    "{}: {free_pct:.1}% free (threshold: {threshold_pct:.0}%)",
+                    disk.name
+                ),
+            });
+        }
+    }
+    events
+}
+
+// --- qBittorrent Poller ---
+
+struct QbitPoller {
+    client: QbitClient,
+    seen_hashes: HashSet<String>,
+    first_poll: bool,
+}
+
+impl QbitPoller {
+    fn new(url: &str, username: &str, password: &str, accept_invalid_certs: bool) -> Self {
+        Self {
+            client: QbitClient::new(url, username, password, accept_invalid_certs),
+            seen_hashes: HashSet::new(),
+            first_poll: true,
+        }
+    }
+}
+
+#[async_trait]
+impl Poller for QbitPoller {
+    fn name(&self) -> &str {
+        "qbit"
+    }
+
+    async fn poll(&mut self) -> Vec<NotificationEvent> {
+        let torrents = match self.client.torrents().await {
+            Ok(t) => t,
+            Err(e) => {
+                warn!("qBittorrent poll failed: {e}");
+                return Vec::new();
             }
-            self.last_container_states = current_states;
+        };
 
-            events
-        })
+        let events = qbit_completions(&mut self.seen_hashes, &torrents, self.first_poll);
+        self.first_poll = false;
+        events
+    }
+}
+
+/// Emit an import event for each torrent completed since the last poll. On the
+/// first poll, already-completed torrents are only recorded.
+fn qbit_completions(
+    seen_hashes: &mut HashSet<String>,
+    torrents: &[TorrentInfo],
+    first_poll: bool,
+) -> Vec<NotificationEvent> {
+    let mut events = Vec::new();
+    for torrent in torrents.iter().filter(|t| t.is_complete()) {
+        if seen_hashes.insert(torrent.hash.clone()) && !first_poll {
+            events.push(NotificationEvent {
+                category: NotificationCategory::MediaImport,
+                service: "qBittorrent".into(),
+                critical: false,
+                title: "Download Complete".into(),
+                body: format!("Completed: {}", torrent.name),
+            });
+        }
+    }
+
+    // Forget torrents that have been removed from qBittorrent
+    let current: HashSet<&str> = torrents.iter().map(|t| t.hash.as_str()).collect();
+    seen_hashes.retain(|hash| current.contains(hash.as_str()));
+    events
+}
+
+// --- Plex Poller ---
+
+struct PlexPoller {
+    client: PlexClient,
+    last_added_at: u64,
+    first_poll: bool,
+}
+
+impl PlexPoller {
+    fn new(client: PlexClient) -> Self {
+        Self {
+            client,
+            last_added_at: 0,
+            first_poll: true,
+        }
+    }
+}
+
+#[async_trait]
+impl Poller for PlexPoller {
+    fn name(&self) -> &str {
+        "plex"
+    }
+
+    async fn poll(&mut self) -> Vec<NotificationEvent> {
+        let items = match self.client.recently_added().await {
+            Ok(items) => items,
+            Err(e) => {
+                warn!("Plex recently added poll failed: {e}");
+                return Vec::new();
+            }
+        };
+
+        let events = plex_additions(&mut self.last_added_at, &items, self.first_poll);
+        self.first_poll = false;
+        events
+    }
+}
+
+/// Emit an import event for each item added after `last_added_at`, then advance it
+/// to the newest timestamp seen. On the first poll nothing is announced.
+fn plex_additions(
+    last_added_at: &mut u64,
+    items: &[RecentMetadata],
+    first_poll: bool,
+) -> Vec<NotificationEvent> {
+    let mut events = Vec::new();
+    let mut newest = *last_added_at;
+    for item in items {
+        if item.added_at > *last_added_at && !first_poll {
+            events.push(NotificationEvent {
+                category: NotificationCategory::MediaImport,
+                service: "Plex".into(),
+                critical: false,
+                title: "Added to Plex".into(),
+                body: item.display_title(),
+            });
+        }
+        newest = newest.max(item.added_at);
+    }
+    *last_added_at = newest;
+    events
+}
+
+// --- Health Poller ---
+
+struct HealthPoller {
+    checker: Arc<HealthChecker>,
+    last_up: HashMap<String, bool>,
+}
+
+impl HealthPoller {
+    fn new(checker: Arc<HealthChecker>) -> Self {
+        Self {
+            checker,
+            last_up: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Poller for HealthPoller {
+    fn name(&self) -> &str {
+        "health"
+    }
+
+    async fn poll(&mut self) -> Vec<NotificationEvent> {
+        let statuses = self.checker.check().await;
+        health_transitions(&mut self.last_up, &statuses)
+    }
+}
+
+/// Emit an alert for each service whose UP/DOWN state changed since the last check.
+/// Services seen for the first time only record their state.
+fn health_transitions(
+    last_up: &mut HashMap<String, bool>,
+    statuses: &[ServiceStatus],
+) -> Vec<NotificationEvent> {
+    let mut events = Vec::new();
+    for status in statuses {
+        let up = status.is_up();
+        if let Some(was_up) = last_up.insert(status.name.clone(), up)
+            && was_up != up
+        {
+            let (title, state) = if up {
+                ("Service Recovered", "UP")
+            } else {
+                ("Service Down", "DOWN")
+            };
+            events.push(NotificationEvent {
+                category: NotificationCategory::ServerAlert,
+                service: status.name.clone(),
+                critical: false,
+                title: title.into(),
+                body: format!("{} is {state} ({})", status.name, status.detail),
+            });
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use discord_assist_health::ServiceState;
+
+    fn status(name: &str, up: bool) -> ServiceStatus {
+        ServiceStatus {
+            name: name.into(),
+            state: if up { ServiceState::Up } else { ServiceState::Down },
+            detail: if up { "12ms".into() } else { "timeout".into() },
+        }
+    }
+
+    fn test_settings() -> SharedSettings {
+        Arc::new(RwLock::new(NotificationSettings {
+            poll_interval_secs: 3600,
+            poll_intervals: BTreeMap::new(),
+            temp_threshold: 50.0,
+            free_space_threshold_pct: 5.0,
+            ups_battery_threshold_pct: 50.0,
+        }))
+    }
+
+    #[test]
+    fn health_transitions_only_alert_on_edges() {
+        let mut last_up = HashMap::new();
+
+        // First sighting records state without alerting
+        let events = health_transitions(&mut last_up, &[status("Sonarr", true)]);
+        assert!(events.is_empty());
+
+        // Still up: no alert
+        let events = health_transitions(&mut last_up, &[status("Sonarr", true)]);
+        assert!(events.is_empty());
+
+        // UP -> DOWN
+        let events = health_transitions(&mut last_up, &[status("Sonarr", false)]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Service Down");
+        assert_eq!(events[0].category, NotificationCategory::ServerAlert);
+        assert_eq!(events[0].body, "Sonarr is DOWN (timeout)");
+
+        // Still down: no repeat
+        let events = health_transitions(&mut last_up, &[status("Sonarr", false)]);
+        assert!(events.is_empty());
+
+        // DOWN -> UP
+        let events = health_transitions(&mut last_up, &[status("Sonarr", true)]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Service Recovered");
+    }
+
+    fn record(event_type: &str, message: Option<&str>) -> HistoryRecord {
+        HistoryRecord {
+            id: 1,
+            event_type: event_type.into(),
+            source_title: Some("Severance.S02E01.1080p".into()),
+            quality: None,
+            data: HistoryData { message: message.map(str::to_string), ..Default::default() },
+        }
+    }
+
+    #[test]
+    fn history_event_maps_types() {
+        let grab = history_event("Sonarr", &record("grabbed", None)).unwrap();
+        assert_eq!(grab.category, NotificationCategory::MediaGrab);
+        assert!(!grab.critical);
+        assert_eq!(grab.title, "Sonarr Grab");
+        assert_eq!(grab.body, "Grabbed: Severance.S02E01.1080p");
+
+        let import = history_event("Radarr", &record("downloadFolderImported", None)).unwrap();
+        assert_eq!(import.category, NotificationCategory::MediaImport);
+        assert_eq!(import.body, "Imported: Severance.S02E01.1080p");
+
+        let failed = history_event("Sonarr", &record("downloadFailed", Some("Unpacking failed"))).unwrap();
+        assert_eq!(failed.category, NotificationCategory::ServerAlert);
+        assert!(failed.critical);
+        assert_eq!(failed.color(), COLOR_ALERT_CRIT);
+        assert_eq!(failed.title, "Sonarr Download Failed");
+        assert_eq!(failed.body, "Failed: Severance.S02E01.1080p\nUnpacking failed");
+
+        assert_eq!(history_event("Sonarr", &record("grabFailed", None)).unwrap().title, "Sonarr Grab Failed");
+        let import_failed = history_event("Sonarr", &record("importFailed", Some(""))).unwrap();
+        assert_eq!(import_failed.title, "Sonarr Import Failed");
+        assert_eq!(import_failed.body, "Failed: Severance.S02E01.1080p");
+
+        assert!(history_event("Sonarr", &record("episodeFileRenamed", None)).is_none());
+    }
+
+    #[test]
+    fn history_record_parses_failure_message() {
+        let record: HistoryRecord = serde_json::from_str(
+            r#"{"id": 7, "eventType": "downloadFailed", "sourceTitle": "X", "data": {"message": "Disk full", "indexer": "Nyaa"}}"#,
+        )
+        .unwrap();
+        assert_eq!(record.data.message.as_deref(), Some("Disk full"));
+        let record: HistoryRecord =
+            serde_json::from_str(r#"{"id": 8, "eventType": "grabbed"}"#).unwrap();
+        assert!(record.data.message.is_none());
+    }
+
+    #[test]
+    fn grab_body_includes_quality_size_and_indexer() {
+        let record: HistoryRecord = serde_json::from_str(
+            r#"{
+                "episodeId": 101,
+                "seriesId": 12,
+                "sourceTitle": "Severance.S02E01.1080p.WEB.h264-GROUP",
+                "quality": {
+                    "quality": {"id": 3, "name": "WEBDL-1080p", "source": "web", "resolution": 1080},
+                    "revision": {"version": 1, "real": 0, "isRepack": false}
+                },
+                "date": "2025-01-17T02:00:00Z",
+                "downloadId": "ABCDEF",
+                "eventType": "grabbed",
+                "data": {
+                    "indexer": "Nyaa",
+                    "releaseGroup": "GROUP",
+                    "size": "1610612736",
+                    "downloadClient": "qBittorrent",
+                    "protocol": "2"
+                },
+                "id": 9001
+            }"#,
+        )
+        .unwrap();
+        let event = history_event("Sonarr", &record).unwrap();
+        assert_eq!(
+            event.body,
+            "Grabbed: Severance.S02E01.1080p.WEB.h264-GROUP\nWEBDL-1080p · 1.5 GB · Nyaa"
+        );
+
+        // Partial details: only what's there
+        let record: HistoryRecord = serde_json::from_str(
+            r#"{"id": 2, "eventType": "grabbed", "sourceTitle": "X", "data": {"size": "not a number"},
+                "quality": {"quality": {"name": "HDTV-720p"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(history_event("Radarr", &record).unwrap().body, "Grabbed: X\nHDTV-720p");
+    }
+
+    fn issue(kind: &str, message: &str) -> HealthIssue {
+        HealthIssue {
+            source: Some("IndexerStatusCheck".into()),
+            kind: Some(kind.into()),
+            message: Some(message.into()),
+        }
+    }
+
+    #[test]
+    fn health_issues_alert_once_when_new() {
+        let mut seen = None;
+
+        // Issues present at startup are only recorded
+        let events = new_health_issues(&mut seen, "Sonarr", &[issue("warning", "Indexers unavailable")]);
+        assert!(events.is_empty());
+
+        let events = new_health_issues(
+            &mut seen,
+            "Sonarr",
+            &[issue("warning", "Indexers unavailable"), issue("error", "Root folder missing")],
+        );
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Sonarr Health Issue");
+        assert_eq!(events[0].body, "**IndexerStatusCheck**: Root folder missing");
+        assert_eq!(events[0].category, NotificationCategory::ServerAlert);
+        assert!(events[0].critical);
+
+        // Resolved, then back again
+        assert!(new_health_issues(&mut seen, "Sonarr", &[]).is_empty());
+        let events = new_health_issues(&mut seen, "Sonarr", &[issue("warning", "Indexers unavailable")]);
+        assert_eq!(events.len(), 1);
+        assert!(!events[0].critical);
+    }
+
+    fn torrent(hash: &str, state: &str, progress: f64) -> TorrentInfo {
+        TorrentInfo {
+            name: format!("Torrent {hash}"),
+            hash: hash.into(),
+            state: state.into(),
+            progress,
+            size: None,
+            dlspeed: None,
+        }
+    }
+
+    #[test]
+    fn qbit_completions_announce_each_torrent_once() {
+        let mut seen = HashSet::new();
+
+        // Already-complete torrents on the first poll are not announced
+        let events = qbit_completions(
+            &mut seen,
+            &[torrent("a", "uploading", 1.0), torrent("b", "downloading", 0.4)],
+            true,
+        );
+        assert!(events.is_empty());
+
+        // b finishes
+        let events = qbit_completions(
+            &mut seen,
+            &[torrent("a", "stalledUP", 1.0), torrent("b", "uploading", 1.0)],
+            false,
+        );
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].category, NotificationCategory::MediaImport);
+        assert_eq!(events[0].body, "Completed: Torrent b");
+
+        // Pausing after completion does not re-announce
+        let events = qbit_completions(
+            &mut seen,
+            &[torrent("a", "pausedUP", 1.0), torrent("b", "pausedUP", 1.0)],
+            false,
+        );
+        assert!(events.is_empty());
+
+        // Removed torrents are forgotten
+        qbit_completions(&mut seen, &[torrent("b", "pausedUP", 1.0)], false);
+        assert!(!seen.contains("a"));
+    }
+
+    fn array_disk(name: &str, free: f64) -> UnraidArrayDisk {
+        UnraidArrayDisk {
+            name: name.into(),
+            fs_size: Some(100.0),
+            fs_free: Some(free),
+        }
+    }
+
+    #[test]
+    fn low_space_alerts_only_on_crossing() {
+        let mut low = HashSet::new();
+
+        let events = low_space_transitions(&mut low, &[array_disk("disk1", 20.0)], 5.0);
+        assert!(events.is_empty());
+
+        let events = low_space_transitions(&mut low, &[array_disk("disk1", 4.0)], 5.0);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Unraid Disk Space Low");
+        assert_eq!(events[0].body, "disk1: 4.0% free (threshold: 5%)");
+
+        // Still low: no repeat
+        let events = low_space_transitions(&mut low, &[array_disk("disk1", 3.0)], 5.0);
+        assert!(events.is_empty());
+
+        // Recovers, then drops again
+        assert!(low_space_transitions(&mut low, &[array_disk("disk1", 10.0)], 5.0).is_empty());
+        let events = low_space_transitions(&mut low, &[array_disk("disk1", 2.0)], 5.0);
+        assert_eq!(events.len(), 1);
+
+        // Unmounted disks are ignored
+        let unmounted = UnraidArrayDisk {
+            name: "disk2".into(),
+            fs_size: None,
+            fs_free: None,
+        };
+        assert!(low_space_transitions(&mut low, &[unmounted], 5.0).is_empty());
+    }
+
+    fn disk(temp: f64) -> UnraidDiskInfo {
+        UnraidDiskInfo {
+            name: "disk1".into(),
+            temperature: Some(temp),
+        }
+    }
+
+    fn container(state: &str) -> UnraidContainer {
+        UnraidContainer {
+            names: vec!["/plex".into()],
+            state: state.into(),
+        }
+    }
+
+    #[test]
+    fn temp_alerts_respect_cooldown() {
+        let mut alerted = HashMap::new();
+        let start = Instant::now();
+        let minutes = |m: u64| start + Duration::from_secs(m * 60);
+
+        assert_eq!(temp_alerts(&mut alerted, &[disk(52.0)], 50.0, start).len(), 1);
+        // Hovering above the threshold stays quiet within the cooldown
+        assert!(temp_alerts(&mut alerted, &[disk(51.0)], 50.0, minutes(1)).is_empty());
+        assert!(temp_alerts(&mut alerted, &[disk(52.0)], 50.0, minutes(29)).is_empty());
+        // Cooldown expired
+        assert_eq!(temp_alerts(&mut alerted, &[disk(52.0)], 50.0, minutes(31)).len(), 1);
+        // Clearing and re-crossing alerts immediately
+        assert!(temp_alerts(&mut alerted, &[disk(45.0)], 50.0, minutes(32)).is_empty());
+        assert_eq!(temp_alerts(&mut alerted, &[disk(50.0)], 50.0, minutes(33)).len(), 1);
+    }
+
+    #[test]
+    fn container_crash_loop_announced_once_per_cooldown() {
+        let mut last = HashMap::new();
+        let mut alerted = HashMap::new();
+        let start = Instant::now();
+        let minutes = |m: u64| start + Duration::from_secs(m * 60);
+        let mut poll = |state: &str, at: Instant| {
+            container_transitions(&mut last, &mut alerted, &[container(state)], at)
+        };
+
+        assert!(poll("RUNNING", start).is_empty());
+        let events = poll("EXITED", minutes(1));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].body, "plex: RUNNING -> EXITED");
+
+        // Restart loop within the cooldown
+        assert!(poll("RUNNING", minutes(2)).is_empty());
+        assert!(poll("EXITED", minutes(3)).is_empty());
+
+        // After the cooldown a new crash is announced
+        assert!(poll("RUNNING", minutes(40)).is_empty());
+        assert_eq!(poll("EXITED", minutes(41)).len(), 1);
+    }
+
+    #[test]
+    fn overheating_and_crashes_mention() {
+        let mut alerted = HashMap::new();
+        let mut last = HashMap::new();
+        let now = Instant::now();
+        let mut events = temp_alerts(&mut alerted, &[disk(60.0)], 50.0, now);
+        container_transitions(&mut last, &mut alerted, &[container("RUNNING")], now);
+        events.extend(container_transitions(&mut last, &mut alerted, &[container("EXITED")], now));
+        assert_eq!(events.len(), 2);
+
+        let templates = NotificationTemplates::default();
+        for event in events {
+            assert!(event.critical, "{}", event.title);
+            let message = serde_json::to_value(event.to_message(&templates, Some(AlertMention::User(7)))).unwrap();
+            assert_eq!(message["content"], "<@7>");
+        }
+    }
+
+    fn ups(status: &str, charge: f64) -> UnraidUps {
+        UnraidUps {
+            name: "APC".into(),
+            status: status.into(),
+            battery: Some(UnraidUpsBattery {
+                charge_level: Some(charge),
+                estimated_runtime: Some(1200),
+            }),
+        }
+    }
+
+    #[test]
+    fn ups_on_battery_is_critical_edge() {
+        let mut on_battery = HashMap::new();
+        let mut low = HashSet::new();
+
+        let mut poll = |status: &str, charge: f64| {
+            ups_transitions(&mut on_battery, &mut low, &[ups(status, charge)], 50.0)
+        };
+
+        assert!(poll("ONLINE", 100.0).is_empty());
+
+        let events = poll("ONBATT", 95.0);
+        assert_eq!(events.len(), 1);
+        assert!(events[0].critical);
+        assert_eq!(events[0].color(), COLOR_ALERT_CRIT);
+        assert_eq!(events[0].body, "APC: on battery, 95% charge, ~20 min runtime left");
+
+        // Still on battery: quiet until the charge crosses the threshold
+        assert!(poll("ONBATT", 60.0).is_empty());
+        let events = poll("ONBATT", 45.0);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "UPS Battery Low");
+
+        let events = poll("ONLINE", 46.0);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "UPS Power Restored");
+        assert!(!events[0].critical);
+    }
+
+    #[test]
+    fn unraid_partial_poll_still_reports_array_changes() {
+        let mut poller = UnraidPoller::new("http://unraid", "key", Client::new(), test_settings());
+        let now = Instant::now();
+        let array = |state: &str| UnraidArrayState {
+            state: state.into(),
+            disks: Vec::new(),
+        };
+
+        let events = poller.process(
+            UnraidPollData {
+                array: Some(array("STARTED")),
+                disks: Some(vec![disk(30.0)]),
+                containers: Some(vec![container("RUNNING")]),
+                ups: Vec::new(),
+            },
+            now,
+        );
+        assert!(events.is_empty());
+
+        // Docker query failed this time
+        let events = poller.process(
+            UnraidPollData {
+                array: Some(array("STOPPED")),
+                ..Default::default()
+            },
+            now,
+        );
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].body, "State changed: STARTED -> STOPPED");
+        assert_eq!(poller.last_container_states["plex"], "RUNNING");
+    }
+
+    #[test]
+    fn persisted_state_round_trips() {
+        let client = || ArrClient::with_client(Client::new(), "http://sonarr", "key", "v3");
+        let mut sonarr = ArrHistoryPoller::new("Sonarr", client());
+        sonarr.seen_ids.extend([3, 1, 2]);
+        sonarr.first_poll = false;
+        let mut unraid = UnraidPoller::new("http://unraid", "key", Client::new(), test_settings());
+        unraid.process(
+            UnraidPollData {
+                array: Some(UnraidArrayState {
+                    state: "STARTED".into(),
+                    disks: vec![array_disk("disk1", 1.0)],
+                }),
+                containers: Some(vec![container("RUNNING")]),
+                ..Default::default()
+            },
+            Instant::now(),
+        );
+
+        let mut state = PersistedState::default();
+        sonarr.save(&mut state);
+        unraid.save(&mut state);
+        let json = serde_json::to_string(&state).unwrap();
+        let loaded: PersistedState = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, state);
+
+        let mut restored = ArrHistoryPoller::new("Sonarr", client());
+        restored.restore(&loaded);
+        assert!(!restored.first_poll);
+        assert_eq!(restored.seen_ids, HashSet::from([1, 2, 3]));
+        // Radarr had nothing saved, so it still starts by recording history
+        let mut radarr = ArrHistoryPoller::new("Radarr", client());
+        radarr.restore(&loaded);
+        assert!(radarr.first_poll);
+
+        let mut restored = UnraidPoller::new("http://unraid", "key", Client::new(), test_settings());
+        restored.restore(&loaded);
+        assert_eq!(restored.last_array_state.as_deref(), Some("STARTED"));
+        assert!(restored.low_space_disks.contains("disk1"));
+        assert_eq!(restored.last_container_states["plex"], "RUNNING");
+        let events = restored.process(
+            UnraidPollData {
+                array: Some(UnraidArrayState {
+                    state: "STOPPED".into(),
+                    disks: Vec::new(),
+                }),
+                ..Default::default()
+            },
+            Instant::now(),
+        );
+        assert_eq!(events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unreadable_state_starts_fresh() {
+        let path = std::env::temp_dir().join("discord-assist-missing-state.json");
+        assert_eq!(load_state(&path), PersistedState::default());
+
+        let path = std::env::temp_dir().join(format!("discord-assist-state-{}.json", std::process::id()));
+        let mut state = PersistedState::default();
+        state.arr_seen_ids.insert("Sonarr".into(), BTreeSet::from([1, 2]));
+        let json = serde_json::to_string(&state).unwrap();
+        write_atomic(&path, &json).await.unwrap();
+        assert_eq!(load_state(&path), state);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        // A file cut off mid-write is ignored rather than half-restored
+        write_atomic(&path, &json[..json.len() / 2]).await.unwrap();
+        assert_eq!(load_state(&path), PersistedState::default());
+        write_atomic(&path, "not json").await.unwrap();
+        assert_eq!(load_state(&path), PersistedState::default());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn plex_item(title: &str, show: Option<&str>, added_at: u64) -> RecentMetadata {
+        RecentMetadata {
+            title: title.into(),
+            parent_title: None,
+            grandparent_title: show.map(Into::into),
+            added_at,
+            media_type: None,
+        }
+    }
+
+    #[test]
+    fn plex_additions_only_announce_newer_items() {
+        let mut last = 0;
+
+        // First poll records the newest timestamp without announcing
+        let events = plex_additions(&mut last, &[plex_item("Dune", None, 100)], true);
+        assert!(events.is_empty());
+        assert_eq!(last, 100);
+
+        let items = [
+            plex_item("Pilot", Some("Severance"), 200),
+            plex_item("Dune", None, 100),
+        ];
+        let events = plex_additions(&mut last, &items, false);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].category, NotificationCategory::MediaImport);
+        assert_eq!(events[0].body, "Severance — Pilot");
+        assert_eq!(last, 200);
+
+        // Nothing new
+        assert!(plex_additions(&mut last, &items, false).is_empty());
+    }
+
+    #[tokio::test]
+    async fn run_returns_on_shutdown() {
+        let starter = NotificationStarter {
+            channel_id: 1,
+            settings: test_settings(),
+            sonarr: None,
+            radarr: None,
+            unraid: None,
+            qbit: None,
+            plex: None,
+            health: None,
+            clients: SharedClients::default(),
+            templates: NotificationTemplates::default(),
+            mention: None,
+            state_path: None,
+        };
+        let (tx, rx) = watch::channel(false);
+        let mut manager = NotificationManager::from_starter(starter, Arc::new(Http::new("")), rx);
+        let handle = tokio::spawn(async move { manager.run().await });
+
+        tx.send(true).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .expect("run did not return after shutdown")
+            .unwrap();
+    }
+
+    #[test]
+    fn render_template_substitutes_placeholders() {
+        let values = HashMap::from([("title", "Sonarr Grab"), ("service", "Sonarr")]);
+        assert_eq!(render_template("📥 {title} via {service}", &values), "📥 Sonarr Grab via Sonarr");
+        assert_eq!(render_template("{title}{title}", &values), "Sonarr GrabSonarr Grab");
+        assert_eq!(render_template("no placeholders", &values), "no placeholders");
+    }
+
+    #[test]
+    fn render_template_passes_unknown_placeholders_through() {
+        let values = HashMap::from([("body", "Imported: X")]);
+        assert_eq!(render_template("{nope} {body}", &values), "{nope} Imported: X");
+        assert_eq!(render_template("{body", &values), "{body");
+        assert_eq!(render_template("}{ {{body}}", &values), "}{ {Imported: X}");
+    }
+
+    #[test]
+    fn templates_apply_per_category() {
+        let event = NotificationEvent {
+            category: NotificationCategory::ServerAlert,
+            service: "Unraid".into(),
+            critical: false,
+            title: "Unraid Disk Temp".into(),
+            body: "disk1: 55C".into(),
+        };
+        assert_eq!(
+            event.render(&NotificationTemplates::default()),
+            ("Unraid Disk Temp".to_string(), "disk1: 55C".to_string())
+        );
+
+        let templates = NotificationTemplates {
+            server_alert: Some(MessageTemplate {
+                title: Some("🚨 {service}".into()),
+                body: None,
+            }),
+            media_grab: Some(MessageTemplate {
+                title: Some("ignored".into()),
+                body: Some("ignored".into()),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            event.render(&templates),
+            ("🚨 Unraid".to_string(), "disk1: 55C".to_string())
+        );
+    }
+
+    #[test]
+    fn only_critical_alerts_mention() {
+        let event = |critical| NotificationEvent {
+            category: NotificationCategory::ServerAlert,
+            service: "Unraid".into(),
+            critical,
+            title: "UPS On Battery".into(),
+            body: "ups: on battery".into(),
+        };
+        let templates = NotificationTemplates::default();
+        let json = |message: CreateMessage| serde_json::to_value(message).unwrap();
+
+        let critical = json(event(true).to_message(&templates, Some(AlertMention::Role(42))));
+        assert_eq!(critical["content"], "<@&42>");
+        assert_eq!(critical["allowed_mentions"]["roles"], serde_json::json!(["42"]));
+        assert_eq!(critical["embeds"][0]["color"], COLOR_ALERT_CRIT);
+
+        let owner = json(event(true).to_message(&templates, Some(AlertMention::User(7))));
+        assert_eq!(owner["content"], "<@7>");
+        assert_eq!(owner["allowed_mentions"]["users"], serde_json::json!(["7"]));
+
+        let quiet = json(event(false).to_message(&templates, Some(AlertMention::Role(42))));
+        assert!(quiet.get("content").is_none(), "{quiet}");
+        assert!(quiet.get("allowed_mentions").is_none());
+
+        let unset = json(event(true).to_message(&templates, None));
+        assert!(unset.get("content").is_none());
+    }
+
+    #[test]
+    fn test_notifications_go_to_the_notifications_channel() {
+        let notifier = TestNotifier {
+            channel_id: ChannelId::new(42),
+            templates: NotificationTemplates::default(),
+        };
+        for category in NotificationCategory::ALL {
+            assert_eq!(NotificationCategory::from_label(category.label()), Some(category));
+            assert_eq!(notifier.channel_for(category), ChannelId::new(42));
+            let sample = category.sample();
+            assert_eq!(sample.category, category);
+            assert!(!sample.critical);
+        }
+        assert_eq!(NotificationCategory::from_label("grabs"), None);
+    }
+
+    struct CountingPoller {
+        name: &'static str,
+        polls: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Poller for CountingPoller {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn poll(&mut self) -> Vec<NotificationEvent> {
+            self.polls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Vec::new()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pollers_run_on_their_own_intervals() {
+        let settings = test_settings();
+        {
+            let mut s = settings.write().unwrap();
+            s.poll_interval_secs = 60;
+            s.poll_intervals.insert("fast".into(), 10);
+        }
+        let state = Arc::new(Mutex::new(PersistedState::default()));
+        let (tx, mut rx) = mpsc::channel(16);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut counts = Vec::new();
+        for name in ["fast", "slow"] {
+            let polls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+            counts.push(polls.clone());
+            let poller = Box::new(CountingPoller { name, polls });
+            let task = run_poller(poller, settings.clone(), state.clone(), tx.clone(), shutdown_rx.clone());
+            tokio::spawn(task);
+        }
+        drop(tx);
+        let drain = tokio::spawn(async move { while rx.recv().await.is_some() {} });
+
+        tokio::time::sleep(Duration::from_secs(120)).await;
+        shutdown_tx.send(true).unwrap();
+        drain.await.unwrap();
+
+        let fast = counts[0].load(std::sync::atomic::Ordering::SeqCst);
+        let slow = counts[1].load(std::sync::atomic::Ordering::SeqCst);
+        // 10s (+ up to 1s jitter) over 120s, versus 60s (+ up to 6s)
+        assert!((11..=13).contains(&fast), "fast polled {fast} times");
+        assert_eq!(slow, 2);
+    }
+
+    #[test]
+    fn jitter_is_at_most_a_tenth() {
+        for _ in 0..100 {
+            assert!(jitter(Duration::from_secs(60)) < Duration::from_secs(6));
+        }
+        assert_eq!(jitter(Duration::from_millis(5)), Duration::ZERO);
+    }
+
+    #[test]
+    fn permission_failures_are_told_apart() {
+        assert_eq!(classify_send_error(Some(403), Some(MISSING_PERMISSIONS)), SendFailure::Permission);
+        assert_eq!(classify_send_error(Some(404), Some(MISSING_ACCESS)), SendFailure::Permission);
+        assert_eq!(classify_send_error(Some(403), None), SendFailure::Permission);
+        assert_eq!(classify_send_error(Some(429), Some(0)), SendFailure::Transient);
+        assert_eq!(classify_send_error(Some(502), None), SendFailure::Transient);
+        assert_eq!(classify_send_error(None, None), SendFailure::Transient);
+    }
+
+    #[test]
+    fn truncate_chars_respects_boundaries() {
+        assert_eq!(truncate_chars("héllo", 2), "hé");
+        assert_eq!(truncate_chars("short", 10), "short");
     }
 }
@@ -0,0 +1,306 @@
+//! Config reload on SIGHUP. Health targets, notification tunables, and size
+//! units are swapped in place; anything else is logged as needing a restart.
+
+use crate::config::{Config, NotificationsConfig};
+use crate::health_targets;
+use crate::notifications::{NotificationSettings, SharedSettings};
+use discord_assist_health::HealthChecker;
+use discord_assist_util::http::SharedClients;
+use discord_assist_util::size;
+use std::sync::{Arc, RwLock};
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+/// Live components a reload can update without restarting.
+pub struct ReloadHandles {
+    pub health: Option<Arc<HealthChecker>>,
+    pub notifications: Option<SharedSettings>,
+}
+
+impl ReloadHandles {
+    pub fn new(config: &Config, clients: &SharedClients) -> Self {
+        Self {
+            health: config
+                .health
+                .as_ref()
+                .map(|c| {
+                    let targets = health_targets(c, config.accept_invalid_certs);
+                    Arc::new(HealthChecker::with_clients(targets, clients.clone()))
+                }),
+            notifications: config
+                .notifications
+                .as_ref()
+                .map(|n| Arc::new(RwLock::new(notification_settings(n)))),
+        }
+    }
+
+    /// Swap in the reloadable parts of `new`. Returns the changes that only take
+    /// effect after a restart.
+    pub fn apply(&self, old: &Config, new: &Config) -> Vec<String> {
+        size::set_unit(new.size_units);
+        if let (Some(checker), Some(cfg)) = (&self.health, &new.health) {
+            checker.set_services(health_targets(cfg, new.accept_invalid_certs));
+        }
+        if let (Some(settings), Some(cfg)) = (&self.notifications, &new.notifications) {
+            *settings.write().unwrap_or_else(|e| e.into_inner()) = notification_settings(cfg);
+        }
+        restart_required(old, new)
+    }
+}
+
+pub fn notification_settings(cfg: &NotificationsConfig) -> NotificationSettings {
+    NotificationSettings {
+        poll_interval_secs: cfg.poll_interval_secs,
+        poll_intervals: cfg.poll_intervals.clone(),
+        temp_threshold: cfg.temp_threshold,
+        free_space_threshold_pct: cfg.free_space_threshold_pct,
+        ups_battery_threshold_pct: cfg.ups_battery_threshold_pct,
+    }
+}
+
+/// Changes a reload can't apply: Discord settings, sections added or removed
+/// (commands and pollers are only built at startup), and alert routing.
+fn restart_required(old: &Config, new: &Config) -> Vec<String> {
+    let mut changes = Vec::new();
+    if old.discord.token != new.discord.token
+        || old.discord.owner_id != new.discord.owner_id
+        || old.discord.guest_ids != new.discord.guest_ids
+        || old.discord.guild_id != new.discord.guild_id
+        || old.discord.command_cooldown_secs != new.discord.command_cooldown_secs
+        || old.discord.scopes != new.discord.scopes
+    {
+        changes.push("[discord] changed".to_string());
+    }
+    if old.permissions != new.permissions {
+        changes.push("[permissions] changed".to_string());
+    }
+    if old.dry_run != new.dry_run {
+        changes.push("dry_run changed".to_string());
+    }
+    if old.accept_invalid_certs != new.accept_invalid_certs {
+        changes.push("accept_invalid_certs changed".to_string());
+    }
+
+    let sections = [
+        ("unraid", old.unraid.is_some(), new.unraid.is_some()),
+        ("claude", old.claude.is_some(), new.claude.is_some()),
+        ("sonarr", old.sonarr.is_some(), new.sonarr.is_some()),
+        ("radarr", old.radarr.is_some(), new.radarr.is_some()),
+        ("prowlarr", old.prowlarr.is_some(), new.prowlarr.is_some()),
+        ("health", old.health.is_some(), new.health.is_some()),
+        ("qbit", old.qbit.is_some(), new.qbit.is_some()),
+        ("plex", old.plex.is_some(), new.plex.is_some()),
+        ("request", old.request.is_some(), new.request.is_some()),
+        ("notifications", old.notifications.is_some(), new.notifications.is_some()),
+        ("notes", old.notes.is_some(), new.notes.is_some()),
+        ("metrics", old.metrics.is_some(), new.metrics.is_some()),
+        ("audit", old.audit.is_some(), new.audit.is_some()),
+    ];
+    for (name, was, is) in sections {
+        if was != is {
+            let verb = if is { "added" } else { "removed" };
+            changes.push(format!("[{name}] {verb}"));
+        }
+    }
+
+    let alerts = |c: &Config| c.health.as_ref().is_some_and(|h| h.alerts);
+    if alerts(old) != alerts(new) {
+        changes.push("health.alerts changed".to_string());
+    }
+    if let (Some(o), Some(n)) = (&old.notifications, &new.notifications)
+        && o.channel_id != n.channel_id
+    {
+        changes.push("notifications.channel_id changed".to_string());
+    }
+    if let (Some(o), Some(n)) = (&old.notifications, &new.notifications)
+        && o.templates != n.templates
+    {
+        changes.push("notifications.templates changed".to_string());
+    }
+    if let (Some(o), Some(n)) = (&old.notifications, &new.notifications)
+        && (o.mention_on_critical != n.mention_on_critical || o.alert_role_id != n.alert_role_id)
+    {
+        changes.push("notifications alert mentions changed".to_string());
+    }
+    if let (Some(o), Some(n)) = (&old.notifications, &new.notifications)
+        && o.persist_path != n.persist_path
+    {
+        changes.push("notifications.persist_path changed".to_string());
+    }
+    if let (Some(o), Some(n)) = (&old.metrics, &new.metrics)
+        && (o.bind != n.bind || o.port != n.port)
+    {
+        changes.push("[metrics] address changed".to_string());
+    }
+    changes
+}
+
+/// Reload `path` on every SIGHUP until shutdown. There's no SIGHUP off
+/// Unix, so elsewhere this does nothing.
+#[cfg(unix)]
+pub fn spawn_sighup_reload(
+    path: String,
+    mut current: Config,
+    handles: ReloadHandles,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            error!("Failed to install SIGHUP handler, config reload disabled: {e}");
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                received = hangup.recv() => {
+                    if received.is_none() {
+                        return;
+                    }
+                }
+                changed = shutdown.changed() => {
+                    if changed.is_err() || *shutdown.borrow() {
+                        return;
+                    }
+                    continue;
+                }
+            }
+            if let Some(new) = reload(&path, &current, &handles) {
+                current = new;
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_sighup_reload(
+    _path: String,
+    _current: Config,
+    _handles: ReloadHandles,
+    _shutdown: watch::Receiver<bool>,
+) {
+}
+
+/// Load and validate `path`, then apply it. Keeps the running config on error.
+fn reload(path: &str, current: &Config, handles: &ReloadHandles) -> Option<Config> {
+    info!("SIGHUP received, reloading {path}");
+    let new = match Config::load(path) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Config reload failed, keeping previous config: {e}");
+            return None;
+        }
+    };
+    if let Err(problems) = new.validate() {
+        for problem in &problems {
+            error!("Config reload: {problem}");
+        }
+        error!("Config reload rejected, keeping previous config");
+        return None;
+    }
+
+    for change in handles.apply(current, &new) {
+        warn!("Config change requires restart: {change}");
+    }
+    info!("Reloaded health targets and notification settings");
+    Some(new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(toml_str: &str) -> Config {
+        toml::from_str(toml_str).unwrap()
+    }
+
+    #[test]
+    fn apply_swaps_health_services_and_settings() {
+        let old = parse(
+            r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [health]
+            [[health.services]]
+            name = "Sonarr"
+            url = "http://sonarr:8989"
+
+            [notifications]
+            channel_id = 10
+        "#,
+        );
+        let new = parse(
+            r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [health]
+            [[health.services]]
+            name = "Radarr"
+            url = "http://radarr:7878"
+            [[health.services]]
+            name = "Plex"
+            url = "http://plex:32400/identity"
+
+            [notifications]
+            channel_id = 10
+            poll_interval_secs = 300
+            poll_intervals = { unraid = 120 }
+            temp_threshold = 45.0
+
+            [plex]
+            api_url = "http://plex:32400"
+            api_key = "k"
+        "#,
+        );
+        let handles = ReloadHandles::new(&old, &SharedClients::default());
+        let checker = handles.health.clone().unwrap();
+        assert_eq!(checker.services()[0].name, "Sonarr");
+
+        let restart = handles.apply(&old, &new);
+
+        let names: Vec<String> = checker.services().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["Radarr", "Plex"]);
+        let settings = handles.notifications.as_ref().unwrap().read().unwrap().clone();
+        assert_eq!(settings.poll_interval_secs, 300);
+        assert_eq!(settings.poll_intervals["unraid"], 120);
+        assert_eq!(settings.temp_threshold, 45.0);
+        assert_eq!(restart, vec!["[plex] added"]);
+    }
+
+    #[test]
+    fn restart_required_for_discord_and_channel() {
+        let old = parse(
+            r#"
+            [discord]
+            token = "t"
+            owner_id = 1
+
+            [notifications]
+            channel_id = 10
+        "#,
+        );
+        let new = parse(
+            r#"
+            [discord]
+            token = "t2"
+            owner_id = 1
+
+            [notifications]
+            channel_id = 11
+        "#,
+        );
+        assert_eq!(
+            restart_required(&old, &new),
+            vec!["[discord] changed", "notifications.channel_id changed"]
+        );
+        assert!(restart_required(&old, &old).is_empty());
+    }
+}
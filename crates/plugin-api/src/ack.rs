@@ -0,0 +1,249 @@
+//! Who answers a command first: the plugin or the bot's defer watchdog.
+//! Each tracked interaction has a gate so only one initial response is sent;
+//! once the watchdog has deferred, replies go out as followups instead.
+
+use crate::{PluginError, Reply};
+use serde_json::Value;
+use serenity::builder::{CreateInteractionResponse, CreateInteractionResponseMessage};
+use serenity::model::application::CommandInteraction;
+use serenity::model::id::InteractionId;
+use serenity::prelude::Context;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ack {
+    /// Nothing has been sent yet.
+    Pending,
+    /// The plugin responded, or deferred itself.
+    Responded,
+    /// The watchdog deferred; the reply has to be a followup.
+    Deferred,
+}
+
+struct Tracked {
+    state: tokio::sync::Mutex<Ack>,
+    /// Whether the reply is expected to be ephemeral, so a watchdog defer
+    /// has to be too.
+    ephemeral: bool,
+}
+
+type Gate = Arc<Tracked>;
+
+static GATES: LazyLock<Mutex<HashMap<InteractionId, Gate>>> = LazyLock::new(Default::default);
+
+fn gates() -> MutexGuard<'static, HashMap<InteractionId, Gate>> {
+    GATES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn gate(id: InteractionId) -> Option<Gate> {
+    gates().get(&id).cloned()
+}
+
+/// Start tracking `id`, whose reply is expected to be `ephemeral`. The bot
+/// calls this before dispatching a command and [`forget`] once it's done;
+/// untracked interactions respond directly.
+pub fn track(id: InteractionId, ephemeral: bool) {
+    let tracked = Tracked {
+        state: tokio::sync::Mutex::new(Ack::Pending),
+        ephemeral,
+    };
+    gates().insert(id, Arc::new(tracked));
+}
+
+pub fn forget(id: InteractionId) {
+    gates().remove(&id);
+}
+
+/// Where `id` stands, or `None` if it isn't tracked.
+pub async fn state(id: InteractionId) -> Option<Ack> {
+    match gate(id) {
+        Some(gate) => Some(*gate.state.lock().await),
+        None => None,
+    }
+}
+
+/// Reply to `command` with `data`: as its response, or as a followup if the
+/// watchdog already deferred it. Replies with a file should go through
+/// [`respond_reply`], which can still attach it to the followup.
+pub async fn respond(
+    ctx: &Context,
+    command: &CommandInteraction,
+    data: CreateInteractionResponseMessage,
+) -> Result<(), PluginError> {
+    let ephemeral = is_ephemeral(&data);
+    let body = followup_body(&data);
+    let follow_up = async move {
+        let body = body.map_err(|e| PluginError::Other(format!("Failed to build followup: {e}")))?;
+        ctx.http
+            .create_followup_message(&command.token, &body, Vec::new())
+            .await?;
+        Ok(())
+    };
+    respond_or(ctx, command, data, ephemeral, follow_up).await
+}
+
+/// [`respond`] with a [`Reply`], keeping its file if it has to go out as a
+/// followup.
+pub async fn respond_reply(
+    ctx: &Context,
+    command: &CommandInteraction,
+    reply: Reply,
+) -> Result<(), PluginError> {
+    let followup = reply.followup();
+    let follow_up = async move {
+        command.create_followup(&ctx.http, followup).await?;
+        Ok(())
+    };
+    respond_or(ctx, command, reply.message(), reply.ephemeral, follow_up).await
+}
+
+/// Send `data` as the response to `command`, or run `follow_up` instead if
+/// the watchdog already deferred it.
+async fn respond_or(
+    ctx: &Context,
+    command: &CommandInteraction,
+    data: CreateInteractionResponseMessage,
+    ephemeral: bool,
+    follow_up: impl Future<Output = Result<(), PluginError>>,
+) -> Result<(), PluginError> {
+    let gate = gate(command.id);
+    let mut state = match &gate {
+        Some(gate) => Some(gate.state.lock().await),
+        None => None,
+    };
+    if state.as_deref() == Some(&Ack::Deferred) {
+        if ephemeral && gate.as_ref().is_some_and(|g| !g.ephemeral) {
+            // The first followup would fill in the public "thinking"
+            // message; delete it so this reply is sent privately instead.
+            command.delete_response(&ctx.http).await?;
+        }
+        return follow_up.await;
+    }
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(data))
+        .await?;
+    if let Some(state) = state.as_deref_mut() {
+        *state = Ack::Responded;
+    }
+    Ok(())
+}
+
+/// Defer `command` so the plugin can take its time and edit the response
+/// later. Does nothing if the watchdog already deferred it.
+pub async fn defer(ctx: &Context, command: &CommandInteraction) -> Result<(), PluginError> {
+    let gate = gate(command.id);
+    let mut state = match &gate {
+        Some(gate) => Some(gate.state.lock().await),
+        None => None,
+    };
+    if state.as_deref() == Some(&Ack::Deferred) {
+        return Ok(());
+    }
+    command.defer(&ctx.http).await?;
+    if let Some(state) = state.as_deref_mut() {
+        *state = Ack::Responded;
+    }
+    Ok(())
+}
+
+/// For the watchdog: defer `command` if nothing has answered it yet,
+/// ephemerally if its reply will be. Returns whether it did.
+pub async fn defer_if_pending(
+    ctx: &Context,
+    command: &CommandInteraction,
+) -> Result<bool, serenity::Error> {
+    let Some(gate) = gate(command.id) else {
+        return Ok(false);
+    };
+    let mut state = gate.state.lock().await;
+    if *state != Ack::Pending {
+        return Ok(false);
+    }
+    if gate.ephemeral {
+        command.defer_ephemeral(&ctx.http).await?;
+    } else {
+        command.defer(&ctx.http).await?;
+    }
+    *state = Ack::Deferred;
+    Ok(true)
+}
+
+/// Whether `data` is flagged ephemeral.
+fn is_ephemeral(data: &CreateInteractionResponseMessage) -> bool {
+    const EPHEMERAL: u64 = 64;
+    serde_json::to_value(data)
+        .ok()
+        .and_then(|body| body["flags"].as_u64())
+        .is_some_and(|flags| flags & EPHEMERAL != 0)
+}
+
+/// `data` as a followup body. A file attached to the message can't be
+/// carried over (serenity keeps it private), so it's dropped with a note;
+/// [`respond_reply`] avoids that.
+fn followup_body(data: &CreateInteractionResponseMessage) -> Result<Value, serde_json::Error> {
+    let mut body = serde_json::to_value(data)?;
+    let has_files = body["attachments"].as_array().is_some_and(|a| !a.is_empty());
+    if has_files {
+        body["attachments"] = Value::Array(Vec::new());
+        let content = body["content"].as_str().unwrap_or_default().to_string();
+        body["content"] = Value::String(format!(
+            "{content}\n*(attachment dropped: the reply took too long)*"
+        ));
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{inline_or_attachment, response_message};
+
+    #[tokio::test]
+    async fn tracks_and_forgets_interactions() {
+        let id = InteractionId::new(4242);
+        assert_eq!(state(id).await, None);
+        track(id, true);
+        assert_eq!(state(id).await, Some(Ack::Pending));
+        assert!(gate(id).unwrap().ephemeral);
+        *gate(id).unwrap().state.lock().await = Ack::Deferred;
+        assert_eq!(state(id).await, Some(Ack::Deferred));
+        forget(id);
+        assert_eq!(state(id).await, None);
+    }
+
+    #[test]
+    fn followup_body_keeps_content_and_drops_files() {
+        assert!(is_ephemeral(&response_message().content("hi").ephemeral(true)));
+        assert!(!is_ephemeral(&response_message().content("hi")));
+        let body = followup_body(&response_message().content("hi").ephemeral(true)).unwrap();
+        assert_eq!(body["content"], "hi");
+        assert_eq!(body["flags"], 64);
+
+        let long = "x".repeat(3000);
+        let body = followup_body(&inline_or_attachment("**Answer**", &long, "a.md").message()).unwrap();
+        assert_eq!(body["attachments"], serde_json::json!([]));
+        let content = body["content"].as_str().unwrap();
+        assert!(content.starts_with("**Answer**\n"), "{content}");
+        assert!(content.ends_with("*(attachment dropped: the reply took too long)*"), "{content}");
+    }
+
+    #[test]
+    fn deferred_reply_keeps_its_file() {
+        let long = "x".repeat(3000);
+        let reply = inline_or_attachment("**Answer**", &long, "answer.md").ephemeral(true);
+        assert_eq!(reply.file, Some(("answer.md".to_string(), long.clone().into_bytes())));
+
+        // The followup sent after the watchdog deferred still has the file.
+        let followup = serde_json::to_value(reply.followup()).unwrap();
+        assert_eq!(followup["content"], "**Answer**\n*(too long to show inline, attached as `answer.md`)*");
+        assert_eq!(followup["attachments"][0]["filename"], "answer.md");
+        assert_eq!(followup["flags"], 64);
+        let message = serde_json::to_value(reply.message()).unwrap();
+        assert_eq!(message["attachments"], followup["attachments"]);
+
+        let short = inline_or_attachment("**Answer**", "42", "answer.md");
+        assert_eq!(short, Reply::text("**Answer**\n42"));
+        assert_eq!(serde_json::to_value(short.followup()).unwrap()["attachments"], serde_json::json!([]));
+    }
+}
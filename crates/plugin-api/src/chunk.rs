@@ -0,0 +1,232 @@
+//! Splitting long replies into Discord-sized messages.
+
+/// Appended to a chunk that ends inside a code block.
+const FENCE_CLOSE: &str = "\n```";
+
+/// Longest language tag carried over when a code block is reopened.
+const MAX_LANG_LEN: usize = 32;
+
+/// Split `text` into messages of at most `max_len` chars. A code block that has
+/// to be split is closed at the end of one chunk and reopened, with the same
+/// language tag, at the start of the next, so every chunk is valid markdown.
+pub fn chunk_message(text: &str, max_len: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut remaining = text.to_string();
+    let mut remaining_len = remaining.chars().count();
+    while remaining_len > max_len {
+        let mut split_at = split_point(&remaining, max_len);
+        if open_fence(&remaining[..split_at]).is_some() {
+            // Leave room to close the block in this chunk.
+            let limit = max_len.saturating_sub(FENCE_CLOSE.len()).max(1);
+            split_at = split_point(&remaining, limit);
+            // Never emit a chunk holding only the reopened fence line.
+            if open_fence(&remaining[..split_at]) == Some(remaining[..split_at].trim_end()) {
+                split_at = char_boundary(&remaining, limit);
+            }
+        }
+        let fence = open_fence(&remaining[..split_at]).map(reopen_fence);
+        let mut head = remaining[..split_at].to_string();
+        let rest = &remaining[split_at..];
+        let rest = rest.strip_prefix('\n').unwrap_or(rest);
+        let mut next = rest.to_string();
+        if let Some(fence) = fence {
+            head.push_str(FENCE_CLOSE);
+            // Reopen with the tag if that still leaves less to split, else bare.
+            for reopened in [fence, "```"] {
+                let candidate = format!("{reopened}\n{rest}");
+                if candidate.chars().count() < remaining_len {
+                    next = candidate;
+                    break;
+                }
+            }
+        }
+        let next_len = next.chars().count();
+        assert!(next_len < remaining_len, "chunk_message made no progress");
+        remaining = next;
+        remaining_len = next_len;
+        chunks.push(head);
+    }
+    chunks.push(remaining);
+    chunks
+}
+
+/// The opening fence line (e.g. "```rust") when `text` ends inside a code block.
+fn open_fence(text: &str) -> Option<&str> {
+    if text.matches("```").count().is_multiple_of(2) {
+        return None;
+    }
+    let fence = text.rfind("```")?;
+    text[fence..].lines().next()
+}
+
+/// The fence to reopen a block with: "```" plus the language tag of
+/// `fence_line`, if it has a plausible one. Anything else after the
+/// backticks is code and stays where it was.
+fn reopen_fence(fence_line: &str) -> &str {
+    let info = &fence_line[3..];
+    let tag_len = info.find(char::is_whitespace).unwrap_or(info.len());
+    let tag = &info[..tag_len];
+    let plausible = tag.len() <= MAX_LANG_LEN
+        && tag.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '_' | '.' | '#'));
+    if plausible { &fence_line[..3 + tag_len] } else { "```" }
+}
+
+/// Byte index of the `max_len`th char, or the end of `text`.
+fn char_boundary(text: &str, max_len: usize) -> usize {
+    text.char_indices()
+        .nth(max_len)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len())
+}
+
+/// Byte index to split `text` at so the head holds at most `max_len` chars.
+/// Prefers the last newline in the window, falling back to a char boundary, and
+/// backs out to before an opening code fence rather than splitting inside a block.
+fn split_point(text: &str, max_len: usize) -> usize {
+    let byte_limit = char_boundary(text, max_len);
+    let split_at = text[..byte_limit]
+        .rfind('\n')
+        .filter(|&i| i > 0)
+        .unwrap_or(byte_limit);
+
+    let head = &text[..split_at];
+    if head.matches("```").count() % 2 == 1
+        && let Some(fence) = head.rfind("```")
+    {
+        let before_fence = if head[..fence].ends_with('\n') { fence - 1 } else { fence };
+        if before_fence > 0 {
+            return before_fence;
+        }
+    }
+    split_at
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_message_short() {
+        let chunks = chunk_message("Hello", 2000);
+        assert_eq!(chunks, vec!["Hello"]);
+    }
+
+    #[test]
+    fn test_chunk_message_long() {
+        let long = "a".repeat(3000);
+        let chunks = chunk_message(&long, 2000);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].len(), 2000);
+        assert_eq!(chunks[1].len(), 1000);
+    }
+
+    #[test]
+    fn test_chunk_message_splits_at_newline() {
+        let text = format!("{}\n{}", "a".repeat(1500), "b".repeat(1000));
+        let chunks = chunk_message(&text, 2000);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], "a".repeat(1500));
+        assert_eq!(chunks[1], "b".repeat(1000));
+    }
+
+    #[test]
+    fn test_chunk_message_multibyte() {
+        // Each emoji is 4 bytes but 1 character
+        let text = "🦀".repeat(2500);
+        let chunks = chunk_message(&text, 2000);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].chars().count(), 2000);
+        assert_eq!(chunks[1].chars().count(), 500);
+    }
+
+    #[test]
+    fn test_chunk_message_mixed_multibyte_no_newlines() {
+        // 3000 chars alternating 1- and 2-byte chars; the window never ends on a newline
+        let text = "aé".repeat(1500);
+        let chunks = chunk_message(&text, 2000);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].chars().count(), 2000);
+        assert_eq!(chunks[1].chars().count(), 1000);
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn test_chunk_message_avoids_splitting_fence() {
+        let prose = "p".repeat(1500);
+        let block = format!("```rust\n{}```", "let x = 1;\n".repeat(60));
+        let text = format!("{prose}\n{block}");
+        let chunks = chunk_message(&text, 2000);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], prose);
+        assert_eq!(chunks[1], block);
+    }
+
+    #[test]
+    fn test_chunk_message_reopens_large_fence() {
+        let body = "let x = 1;\n".repeat(500);
+        let text = format!("Here you go:\n```rust\n{body}```\nDone.");
+        let chunks = chunk_message(&text, 2000);
+        assert!(chunks.len() > 2);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 2000);
+            assert_eq!(chunk.matches("```").count() % 2, 0, "unbalanced chunk: {chunk}");
+        }
+        for chunk in &chunks[1..] {
+            assert!(chunk.starts_with("```rust\n"));
+        }
+        let lines: usize = chunks.iter().map(|c| c.matches("let x = 1;").count()).sum();
+        assert_eq!(lines, 500);
+        assert!(chunks.last().unwrap().ends_with("```\nDone."));
+    }
+
+    #[test]
+    fn test_chunk_message_fence_line_longer_than_limit() {
+        // No newline before the limit, so the fence "line" is the whole window.
+        let text = format!("```{}```", "x".repeat(3000));
+        let chunks = chunk_message(&text, 2000);
+        assert_eq!(chunks.len(), 2);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 2000, "{}", chunk.len());
+            assert_eq!(chunk.matches("```").count() % 2, 0);
+        }
+        assert!(chunks[1].starts_with("```\n"));
+        let xs: usize = chunks.iter().map(|c| c.matches('x').count()).sum();
+        assert_eq!(xs, 3000);
+    }
+
+    #[test]
+    fn test_chunk_message_fence_opens_mid_line() {
+        let text = format!("see ```{}", "y".repeat(2500));
+        let chunks = chunk_message(&text, 2000);
+        assert_eq!(chunks[0], "see ");
+        assert!(chunks.len() <= 3, "{}", chunks.len());
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 2000);
+        }
+        let ys: usize = chunks.iter().map(|c| c.matches('y').count()).sum();
+        assert_eq!(ys, 2500);
+    }
+
+    #[test]
+    fn test_reopen_fence_keeps_only_a_language_tag() {
+        assert_eq!(reopen_fence("```rust"), "```rust");
+        assert_eq!(reopen_fence("```c++ title"), "```c++");
+        assert_eq!(reopen_fence("```"), "```");
+        assert_eq!(reopen_fence(&format!("```{}", "x".repeat(40))), "```");
+        assert_eq!(reopen_fence("```let x = (1);"), "```let");
+    }
+
+    #[test]
+    fn test_chunk_message_fence_without_newlines() {
+        let text = format!("```\n{}\n```", "x".repeat(5000));
+        let chunks = chunk_message(&text, 2000);
+        assert_eq!(chunks.len(), 3);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 2000);
+            assert!(chunk.starts_with("```\n"));
+            assert!(chunk.ends_with("```"));
+        }
+        let xs: usize = chunks.iter().map(|c| c.matches('x').count()).sum();
+        assert_eq!(xs, 5000);
+    }
+}
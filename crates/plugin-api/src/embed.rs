@@ -0,0 +1,136 @@
+//! Rendering lists as embed fields, which hold far more text than a plain
+//! message and lay out better.
+
+use crate::chunk_message;
+use serenity::builder::CreateEmbed;
+
+/// Discord's limit on a single field value, in characters.
+pub const EMBED_FIELD_LIMIT: usize = 1024;
+
+/// Discord's limit on fields per embed.
+pub const EMBED_MAX_FIELDS: usize = 25;
+
+/// Discord's limit on all text in an embed, in characters.
+pub const EMBED_TOTAL_LIMIT: usize = 6000;
+
+/// Left out of the field budget for the title and a short description.
+const HEADER_RESERVE: usize = 1024;
+
+/// Discord rejects empty field values; a zero-width space renders as blank.
+const BLANK: &str = "\u{200b}";
+
+/// Pack `(label, value)` pairs into embed fields. A value over
+/// [`EMBED_FIELD_LIMIT`] continues in further fields labelled "label (cont.)".
+/// When everything won't fit in [`EMBED_MAX_FIELDS`] fields or the total
+/// budget, the last field says how many entries were left out.
+pub fn embed_fields(items: &[(String, String)]) -> Vec<(String, String)> {
+    // (index of the item it came from, name, value)
+    let mut all = Vec::new();
+    for (item, (label, value)) in items.iter().enumerate() {
+        if value.is_empty() {
+            all.push((item, label.clone(), BLANK.to_string()));
+            continue;
+        }
+        for (i, chunk) in chunk_message(value, EMBED_FIELD_LIMIT).into_iter().enumerate() {
+            let name = if i == 0 { label.clone() } else { format!("{label} (cont.)") };
+            all.push((item, name, chunk));
+        }
+    }
+
+    let len = |(_, name, value): &(usize, String, String)| name.chars().count() + value.chars().count();
+    let budget = EMBED_TOTAL_LIMIT - HEADER_RESERVE;
+    if all.len() <= EMBED_MAX_FIELDS && all.iter().map(len).sum::<usize>() <= budget {
+        return all.into_iter().map(|(_, name, value)| (name, value)).collect();
+    }
+
+    // Leave room for the trailer field.
+    let budget = budget - 64;
+    let mut total = 0;
+    let mut kept = 0;
+    for field in all.iter().take(EMBED_MAX_FIELDS - 1) {
+        total += len(field);
+        if total > budget {
+            break;
+        }
+        kept += 1;
+    }
+    // An item cut off partway through its continuations counts as left out.
+    let complete = match all.get(kept) {
+        Some((item, _, _)) => *item,
+        None => items.len(),
+    };
+    let mut fields: Vec<_> = all
+        .into_iter()
+        .take_while(|(item, _, _)| *item < complete)
+        .map(|(_, name, value)| (name, value))
+        .collect();
+    fields.push(("…".into(), format!("and {} more", items.len() - complete)));
+    fields
+}
+
+/// An embed titled `title` with one field per `(label, value)` pair, split
+/// by [`embed_fields`].
+pub fn list_embed(title: &str, items: &[(String, String)]) -> CreateEmbed {
+    let fields = embed_fields(items)
+        .into_iter()
+        .map(|(name, value)| (name, value, false));
+    CreateEmbed::new().title(title).fields(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(label: &str, value: &str) -> (String, String) {
+        (label.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn short_items_map_one_to_one() {
+        let items = vec![item("disk1", "4.0 TB"), item("disk2", "")];
+        let fields = embed_fields(&items);
+        assert_eq!(fields, vec![item("disk1", "4.0 TB"), item("disk2", BLANK)]);
+    }
+
+    #[test]
+    fn long_value_continues_in_next_field() {
+        let value = "line\n".repeat(300);
+        let fields = embed_fields(&[item("Queue", &value)]);
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].0, "Queue");
+        assert_eq!(fields[1].0, "Queue (cont.)");
+        assert!(fields.iter().all(|(_, v)| v.chars().count() <= EMBED_FIELD_LIMIT));
+        let lines: usize = fields.iter().map(|(_, v)| v.matches("line").count()).sum();
+        assert_eq!(lines, 300);
+    }
+
+    #[test]
+    fn too_many_items_are_summarised() {
+        let items: Vec<_> = (0..40).map(|i| item(&format!("item {i}"), "ok")).collect();
+        let fields = embed_fields(&items);
+        assert_eq!(fields.len(), EMBED_MAX_FIELDS);
+        assert_eq!(fields[23].0, "item 23");
+        assert_eq!(fields[24], item("…", "and 16 more"));
+    }
+
+    #[test]
+    fn total_length_is_capped() {
+        let value = "x".repeat(EMBED_FIELD_LIMIT);
+        let items: Vec<_> = (0..10).map(|i| item(&format!("item {i}"), &value)).collect();
+        let fields = embed_fields(&items);
+        let total: usize = fields.iter().map(|(n, v)| n.chars().count() + v.chars().count()).sum();
+        assert!(total <= EMBED_TOTAL_LIMIT - HEADER_RESERVE);
+        assert_eq!(fields.len(), 5);
+        assert_eq!(fields[4], item("…", "and 6 more"));
+    }
+
+    #[test]
+    fn partly_shown_item_counts_as_left_out() {
+        let mut items: Vec<_> = (0..23).map(|i| item(&format!("item {i}"), "ok")).collect();
+        items.push(item("big", &"word ".repeat(500)));
+        let fields = embed_fields(&items);
+        assert_eq!(fields.len(), 24);
+        assert_eq!(fields[22].0, "item 22");
+        assert_eq!(fields[23], item("…", "and 1 more"));
+    }
+}
@@ -1,13 +1,31 @@
+pub mod ack;
+mod chunk;
+mod embed;
+mod pending;
+
+pub use chunk::chunk_message;
+pub use embed::{EMBED_FIELD_LIMIT, EMBED_MAX_FIELDS, EMBED_TOTAL_LIMIT, embed_fields, list_embed};
+pub use pending::PendingStore;
+
 use async_trait::async_trait;
-use serenity::builder::CreateCommand;
+use discord_assist_util::redact_secrets;
+use serenity::builder::{
+    CreateAllowedMentions, CreateAttachment, CreateCommand, CreateEmbed,
+    CreateInteractionResponseFollowup, CreateInteractionResponseMessage, EditInteractionResponse,
+};
 use serenity::model::application::{CommandInteraction, ComponentInteraction};
 use serenity::prelude::Context;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum PluginError {
-    #[error("API request failed: {0}")]
+    /// Usually wraps an HTTP client error, so secrets are redacted on display.
+    #[error("API request failed: {}", redact_secrets(.0))]
     ApiError(String),
+    /// The service rejected our credentials; retrying won't help until the
+    /// config is fixed.
+    #[error("authentication failed: {0}")]
+    AuthError(String),
     #[error("configuration error: {0}")]
     ConfigError(String),
     #[error("Discord response failed: {0}")]
@@ -22,6 +40,7 @@ impl PluginError {
     pub fn user_message(&self) -> &str {
         match self {
             Self::ApiError(_) => "A plugin API request failed. Check bot logs for details.",
+            Self::AuthError(_) => "Authentication failed — check credentials.",
             Self::ConfigError(_) => "Plugin configuration error. Check bot logs for details.",
             Self::DiscordError(_) => "Discord API error. Check bot logs for details.",
             Self::Other(_) => "Something went wrong. Check bot logs for details.",
@@ -29,6 +48,150 @@ impl PluginError {
     }
 }
 
+/// Reply for a mutating command skipped because dry-run mode is on, e.g.
+/// `dry_run_message("started container **plex**")`.
+pub fn dry_run_message(action: &str) -> String {
+    format!("[dry-run] would have {action}")
+}
+
+/// Replies quote titles, names, and note text from remote services; even
+/// escaped, they should never be able to ping anyone.
+pub fn no_mentions() -> CreateAllowedMentions {
+    CreateAllowedMentions::new()
+        .empty_roles()
+        .empty_users()
+        .everyone(false)
+}
+
+/// Start every interaction reply from these rather than the serenity
+/// builders' `new()`, so no bot response can mention anyone.
+pub fn response_message() -> CreateInteractionResponseMessage {
+    CreateInteractionResponseMessage::new().allowed_mentions(no_mentions())
+}
+
+/// A followup message that can't mention anyone. See [`response_message`].
+pub fn followup_message() -> CreateInteractionResponseFollowup {
+    CreateInteractionResponseFollowup::new().allowed_mentions(no_mentions())
+}
+
+/// An edit of a deferred reply that can't mention anyone. See [`response_message`].
+pub fn edit_message() -> EditInteractionResponse {
+    EditInteractionResponse::new().allowed_mentions(no_mentions())
+}
+
+/// Replies longer than this (in characters) go out as a file attachment
+/// instead of being truncated or split across messages.
+pub const INLINE_LIMIT: usize = 1900;
+
+/// Whether `text` is too long to post inline.
+pub fn exceeds_inline_limit(text: &str) -> bool {
+    text.chars().count() > INLINE_LIMIT
+}
+
+/// A text reply, possibly with a file. Serenity keeps a built message's
+/// attachments private, so this holds on to the file until it's known
+/// whether it goes out as the response or, once deferred, as a followup
+/// (see [`ack::respond_reply`]).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reply {
+    pub content: String,
+    /// `(filename, contents)`
+    pub file: Option<(String, Vec<u8>)>,
+    pub ephemeral: bool,
+}
+
+impl Reply {
+    pub fn text(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn ephemeral(mut self, ephemeral: bool) -> Self {
+        self.ephemeral = ephemeral;
+        self
+    }
+
+    /// As the interaction response.
+    pub fn message(&self) -> CreateInteractionResponseMessage {
+        let mut message = response_message()
+            .content(&self.content)
+            .ephemeral(self.ephemeral);
+        if let Some((filename, contents)) = &self.file {
+            message = message.add_file(CreateAttachment::bytes(contents.clone(), filename.as_str()));
+        }
+        message
+    }
+
+    /// As a followup, for when the response was already deferred.
+    pub fn followup(&self) -> CreateInteractionResponseFollowup {
+        let mut followup = followup_message()
+            .content(&self.content)
+            .ephemeral(self.ephemeral);
+        if let Some((filename, contents)) = &self.file {
+            followup = followup.add_file(CreateAttachment::bytes(contents.clone(), filename.as_str()));
+        }
+        followup
+    }
+}
+
+/// Build a reply with `header` and `body` inline, or with just `header` and
+/// `body` attached as `filename` when the combined text is too long.
+pub fn inline_or_attachment(header: &str, body: &str, filename: &str) -> Reply {
+    let inline = if header.is_empty() {
+        body.to_string()
+    } else {
+        format!("{header}\n{body}")
+    };
+    if !exceeds_inline_limit(&inline) {
+        return Reply::text(inline);
+    }
+    let note = format!("*(too long to show inline, attached as `{filename}`)*");
+    let content = if header.is_empty() {
+        note
+    } else {
+        format!("{header}\n{note}")
+    };
+    Reply {
+        content,
+        file: Some((filename.to_string(), body.as_bytes().to_vec())),
+        ephemeral: false,
+    }
+}
+
+/// Discord rejects message content longer than this many characters.
+pub const DISCORD_MAX_LEN: usize = 2000;
+
+/// Reply to `command` with `content`, split by [`chunk_message`] into an
+/// initial response plus followups when it's over [`DISCORD_MAX_LEN`].
+pub async fn respond_chunked(
+    ctx: &Context,
+    command: &CommandInteraction,
+    content: &str,
+) -> Result<(), PluginError> {
+    let chunks = chunk_message(content, DISCORD_MAX_LEN);
+    let first = chunks.first().cloned().unwrap_or_default();
+
+    ack::respond(ctx, command, response_message().content(first)).await?;
+    for chunk in chunks.iter().skip(1) {
+        command
+            .create_followup(&ctx.http, followup_message().content(chunk))
+            .await
+            .map_err(PluginError::DiscordError)?;
+    }
+    Ok(())
+}
+
+/// Reply to `command` with a single embed, e.g. one built by [`list_embed`].
+pub async fn respond_embed(
+    ctx: &Context,
+    command: &CommandInteraction,
+    embed: CreateEmbed,
+) -> Result<(), PluginError> {
+    ack::respond(ctx, command, response_message().embed(embed)).await
+}
+
 /// Trait that all plugins must implement.
 #[async_trait]
 pub trait Plugin: Send + Sync {
@@ -38,6 +201,27 @@ pub trait Plugin: Send + Sync {
     /// Return slash command definitions to register with Discord.
     fn register_commands(&self) -> Vec<CreateCommand>;
 
+    /// `(usage, description)` pairs shown by `/help`. `CreateCommand` can't be
+    /// read back after construction, so plugins list their commands here.
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![(format!("/{}", self.name()), String::new())]
+    }
+
+    /// Whether the command at `path` (its name, then any subcommand group
+    /// and subcommand, e.g. `["unraid", "docker", "list"]`) only reads
+    /// state. Guests may run read-only commands; anything else is treated
+    /// as mutating.
+    fn is_read_only(&self, _path: &[&str]) -> bool {
+        false
+    }
+
+    /// The command that sent the button or select menu `custom_id`, if it's
+    /// one of this plugin's. Pressing it is then open to whoever that
+    /// command's `[permissions]` allowlist admits; otherwise only the owner.
+    fn component_command(&self, _custom_id: &str) -> Option<&str> {
+        None
+    }
+
     /// Handle an incoming command interaction.
     /// Return Ok(true) if this plugin handled the command, Ok(false) if not.
     async fn handle_command(
@@ -55,6 +239,12 @@ pub trait Plugin: Send + Sync {
     ) -> Result<bool, PluginError> {
         Ok(false)
     }
+
+    /// Drop accumulated in-memory state (pending requests, conversations, ...).
+    /// Returns how many entries were removed. Used by `/admin clear`.
+    async fn clear_state(&self) -> usize {
+        0
+    }
 }
 
 #[cfg(test)]
@@ -68,5 +258,51 @@ mod tests {
 
         let err = PluginError::ConfigError("missing key".into());
         assert_eq!(err.to_string(), "configuration error: missing key");
+
+        let err = PluginError::AuthError("qBittorrent rejected the username or password".into());
+        assert_eq!(
+            err.to_string(),
+            "authentication failed: qBittorrent rejected the username or password"
+        );
+        assert_eq!(err.user_message(), "Authentication failed — check credentials.");
+
+        let err = PluginError::ApiError("GET http://plex/?X-Plex-Token=abc failed".into());
+        assert_eq!(err.to_string(), "API request failed: GET http://plex/?X-Plex-Token=[redacted] failed");
+    }
+
+    #[test]
+    fn inline_limit_threshold() {
+        assert!(!exceeds_inline_limit("short"));
+        assert!(!exceeds_inline_limit(&"a".repeat(INLINE_LIMIT)));
+        assert!(exceeds_inline_limit(&"a".repeat(INLINE_LIMIT + 1)));
+        // Counted in characters, not bytes.
+        assert!(!exceeds_inline_limit(&"é".repeat(INLINE_LIMIT)));
+    }
+
+    #[test]
+    fn over_discord_limit_is_chunked() {
+        let text = "line of text\n".repeat(200);
+        assert!(text.chars().count() > DISCORD_MAX_LEN);
+        let chunks = chunk_message(&text, DISCORD_MAX_LEN);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.chars().count() <= DISCORD_MAX_LEN));
+        assert_eq!(chunks.concat().matches("line of text").count(), 200);
+    }
+
+    #[test]
+    fn replies_never_mention() {
+        let none = serde_json::json!({"parse": [], "users": [], "roles": []});
+        let reply = inline_or_attachment("", "hi @everyone", "x.txt");
+        assert_eq!(serde_json::to_value(reply.message()).unwrap()["allowed_mentions"], none);
+        assert_eq!(serde_json::to_value(reply.followup()).unwrap()["allowed_mentions"], none);
+        let followup = serde_json::to_value(followup_message().content("<@&1>")).unwrap();
+        assert_eq!(followup["allowed_mentions"], none);
+        let edit = serde_json::to_value(edit_message().content("<@2>")).unwrap();
+        assert_eq!(edit["allowed_mentions"], none);
+    }
+
+    #[test]
+    fn dry_run_message_prefix() {
+        assert_eq!(dry_run_message("stopped VM **win11**"), "[dry-run] would have stopped VM **win11**");
     }
 }
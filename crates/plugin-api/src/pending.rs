@@ -0,0 +1,119 @@
+//! Short-lived state behind select menus and buttons, keyed by interaction id.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+type Entries<T> = Arc<RwLock<HashMap<String, (Instant, T)>>>;
+
+/// Values stashed when a menu is sent and looked up when the user picks from
+/// it. Entries older than the store's TTL are treated as gone.
+pub struct PendingStore<T> {
+    entries: Entries<T>,
+    ttl: Duration,
+}
+
+impl<T: Clone + Send + Sync + 'static> PendingStore<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    /// Drop expired entries every `interval` in the background. The task
+    /// holds only a weak reference, so it ends when the store is dropped.
+    pub fn spawn_cleanup(&self, interval: Duration) {
+        let weak = Arc::downgrade(&self.entries);
+        let ttl = self.ttl;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let Some(entries) = weak.upgrade() else { break };
+                retain_fresh(&entries, ttl).await;
+            }
+        });
+    }
+
+    pub async fn insert(&self, id: String, value: T) {
+        self.entries.write().await.insert(id, (Instant::now(), value));
+    }
+
+    /// The value for `id`, unless it's missing or expired.
+    pub async fn get(&self, id: &str) -> Option<T> {
+        let entries = self.entries.read().await;
+        entries
+            .get(id)
+            .filter(|(created_at, _)| created_at.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub async fn remove(&self, id: &str) -> Option<T> {
+        self.entries.write().await.remove(id).map(|(_, value)| value)
+    }
+
+    /// Drop entries older than `ttl`. Returns how many were removed.
+    pub async fn cleanup_expired(&self, ttl: Duration) -> usize {
+        retain_fresh(&self.entries, ttl).await
+    }
+
+    /// Drop every entry. Returns how many were removed.
+    pub async fn clear(&self) -> usize {
+        let mut entries = self.entries.write().await;
+        let removed = entries.len();
+        entries.clear();
+        removed
+    }
+}
+
+async fn retain_fresh<T>(entries: &RwLock<HashMap<String, (Instant, T)>>, ttl: Duration) -> usize {
+    let mut entries = entries.write().await;
+    let before = entries.len();
+    entries.retain(|_, (created_at, _)| created_at.elapsed() < ttl);
+    before - entries.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn insert_get_remove() {
+        let store = PendingStore::new(TTL);
+        store.insert("a".into(), vec![1, 2]).await;
+        store.insert("b".into(), vec![3]).await;
+        assert_eq!(store.get("a").await, Some(vec![1, 2]));
+        assert_eq!(store.get("missing").await, None);
+
+        assert_eq!(store.remove("a").await, Some(vec![1, 2]));
+        assert_eq!(store.get("a").await, None);
+        assert_eq!(store.clear().await, 1);
+        assert_eq!(store.get("b").await, None);
+    }
+
+    #[tokio::test]
+    async fn get_after_expiry_is_none() {
+        let store = PendingStore::new(Duration::ZERO);
+        store.insert("a".into(), "menu").await;
+        assert_eq!(store.get("a").await, None);
+        // Still stored until cleaned up.
+        assert_eq!(store.remove("a").await, Some("menu"));
+    }
+
+    #[tokio::test]
+    async fn cleanup_drops_only_expired() {
+        let store = PendingStore::new(TTL);
+        store.insert("old".into(), 1).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        store.insert("new".into(), 2).await;
+
+        assert_eq!(store.cleanup_expired(Duration::from_millis(10)).await, 1);
+        assert_eq!(store.get("old").await, None);
+        assert_eq!(store.get("new").await, Some(2));
+        assert_eq!(store.cleanup_expired(TTL).await, 0);
+    }
+}
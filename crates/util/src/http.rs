@@ -0,0 +1,114 @@
+//! Outgoing HTTP client factory. Every service client is built here so the
+//! timeout, certificate policy, and user-agent are set in one place, and
+//! services share connection pools instead of each building their own.
+
+use reqwest::Client;
+use std::time::Duration;
+
+/// Overall request timeout unless a caller sets a tighter per-request one.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub const USER_AGENT: &str = concat!("discord-assist/", env!("CARGO_PKG_VERSION"));
+
+/// Policy for a built client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpOptions {
+    pub timeout: Duration,
+    /// Skip TLS certificate verification (self-signed certs).
+    pub accept_invalid_certs: bool,
+    /// Keep cookies between requests, for session-based APIs like qBittorrent.
+    pub cookie_store: bool,
+}
+
+impl HttpOptions {
+    pub fn new(accept_invalid_certs: bool) -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            accept_invalid_certs,
+            cookie_store: false,
+        }
+    }
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+/// Build a client with `options` applied.
+pub fn build_client(options: &HttpOptions) -> Client {
+    Client::builder()
+        .timeout(options.timeout)
+        .user_agent(USER_AGENT)
+        .danger_accept_invalid_certs(options.accept_invalid_certs)
+        .cookie_store(options.cookie_store)
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+/// The pooled clients every service shares: one that verifies certificates and
+/// one for services with `accept_invalid_certs`. Cloning is cheap.
+#[derive(Clone)]
+pub struct SharedClients {
+    verified: Client,
+    insecure: Client,
+}
+
+impl SharedClients {
+    pub fn new(timeout: Duration) -> Self {
+        let options = |accept_invalid_certs| HttpOptions {
+            timeout,
+            ..HttpOptions::new(accept_invalid_certs)
+        };
+        Self {
+            verified: build_client(&options(false)),
+            insecure: build_client(&options(true)),
+        }
+    }
+
+    /// The shared client for a service's certificate policy.
+    pub fn get(&self, accept_invalid_certs: bool) -> Client {
+        if accept_invalid_certs {
+            self.insecure.clone()
+        } else {
+            self.verified.clone()
+        }
+    }
+}
+
+impl Default for SharedClients {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMEOUT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_build_client_applies_timeout() {
+        // Accept the connection but never answer
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let _conn = listener.accept();
+            std::thread::sleep(Duration::from_secs(5));
+        });
+
+        let options = HttpOptions {
+            timeout: Duration::from_millis(200),
+            ..HttpOptions::default()
+        };
+        let start = std::time::Instant::now();
+        let err = build_client(&options)
+            .get(format!("http://{addr}/"))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(err.is_timeout(), "{err}");
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,404 @@
+//! Small helpers shared across plugins: minimal UTC calendar math (enough for
+//! `YYYY-MM-DD` query params and note front matter without a date crate),
+//! human-readable relative times, secret redaction for error messages,
+//! markdown escaping, byte size formatting, and the shared HTTP client factory.
+
+pub mod http;
+pub mod size;
+
+use std::time::SystemTime;
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// Current Unix time in seconds.
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Today's UTC date as `YYYY-MM-DD`.
+pub fn today_iso() -> String {
+    iso_date(now_secs())
+}
+
+/// The UTC date `days` days from now as `YYYY-MM-DD`.
+pub fn iso_date_in_days(days: u64) -> String {
+    iso_date(now_secs() + days * SECS_PER_DAY)
+}
+
+/// The UTC date `days` days ago as `YYYY-MM-DD`.
+pub fn iso_date_days_ago(days: u64) -> String {
+    iso_date(now_secs().saturating_sub(days * SECS_PER_DAY))
+}
+
+/// Format a Unix timestamp as a UTC `YYYY-MM-DD` date.
+pub fn iso_date(unix_secs: u64) -> String {
+    let mut remaining = unix_secs / SECS_PER_DAY;
+    let mut year = 1970u32;
+
+    loop {
+        let days_in_year = if is_leap_year(year) { 366 } else { 365 };
+        if remaining < days_in_year {
+            break;
+        }
+        remaining -= days_in_year;
+        year += 1;
+    }
+
+    let mut month = 12u32;
+    for (i, &d) in month_days(year).iter().enumerate() {
+        if remaining < d {
+            month = i as u32 + 1;
+            break;
+        }
+        remaining -= d;
+    }
+
+    let day = remaining + 1;
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Parse an RFC 3339 timestamp such as `2024-01-02T20:00:00Z` or
+/// `2024-01-02T15:00:00.123-05:00` into Unix seconds. Returns `None` for
+/// malformed input and anything before 1970.
+pub fn parse_rfc3339(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let b = s.as_bytes();
+    if b.len() < 20
+        || b[4] != b'-'
+        || b[7] != b'-'
+        || !matches!(b[10], b'T' | b't' | b' ')
+        || b[13] != b':'
+        || b[16] != b':'
+    {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| -> Option<u64> {
+        let part = s.get(range)?;
+        if !part.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        part.parse().ok()
+    };
+    let year = u32::try_from(num(0..4)?).ok()?;
+    let month = num(5..7)?;
+    let day = num(8..10)?;
+    let (hour, minute, second) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    if year < 1970 || !(1..=12).contains(&month) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let days_this_month = month_days(year)[month as usize - 1];
+    if day == 0 || day > days_this_month {
+        return None;
+    }
+
+    // Skip fractional seconds; only whole seconds matter here.
+    let mut rest = &s[19..];
+    if let Some(frac) = rest.strip_prefix('.') {
+        let digits = frac.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        rest = &frac[digits..];
+    }
+
+    let offset_secs: i64 = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+            let digits = [*h1, *h2, *m1, *m2];
+            if !digits.iter().all(u8::is_ascii_digit) {
+                return None;
+            }
+            let h = i64::from((h1 - b'0') * 10 + (h2 - b'0'));
+            let m = i64::from((m1 - b'0') * 10 + (m2 - b'0'));
+            if h > 23 || m > 59 {
+                return None;
+            }
+            let secs = h * 3600 + m * 60;
+            if *sign == b'+' { secs } else { -secs }
+        }
+        _ => return None,
+    };
+
+    let mut days: u64 = (1970..year)
+        .map(|y| if is_leap_year(y) { 366 } else { 365 })
+        .sum();
+    days += month_days(year)[..month as usize - 1].iter().sum::<u64>();
+    days += day - 1;
+    let local = days * SECS_PER_DAY + hour * 3600 + minute * 60 + second;
+    u64::try_from(local as i64 - offset_secs).ok()
+}
+
+pub fn is_leap_year(year: u32) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+fn month_days(year: u32) -> [u64; 12] {
+    let feb = if is_leap_year(year) { 29 } else { 28 };
+    [31, feb, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31]
+}
+
+/// "10m ago" / "2h ago" / "3d ago". Zero or future timestamps read as "just now".
+pub fn format_relative_time(now: u64, timestamp: u64) -> String {
+    if timestamp == 0 || timestamp > now {
+        return "just now".into();
+    }
+    let diff = now - timestamp;
+    if diff < 3600 {
+        format!("{}m ago", diff / 60)
+    } else if diff < 86400 {
+        format!("{}h ago", diff / 3600)
+    } else {
+        format!("{}d ago", diff / 86400)
+    }
+}
+
+/// Discord timestamp markdown (`<t:UNIX:R>`), rendered as a relative time in
+/// each viewer's locale. Unknown (zero) timestamps fall back to
+/// [`format_relative_time`].
+pub fn discord_relative(unix: u64) -> String {
+    if unix == 0 {
+        return format_relative_time(now_secs(), unix);
+    }
+    format!("<t:{unix}:R>")
+}
+
+const REDACTED: &str = "[redacted]";
+
+/// Parameter and header names whose values are secrets, lowercase. Longest
+/// first so `x-api-key` wins over shorter overlaps. `bearer ` takes its value
+/// after the space rather than a `=`/`:` separator.
+const SECRET_KEYS: &[&str] = &[
+    "x-plex-token",
+    "access_token",
+    "x-api-key",
+    "password",
+    "api_key",
+    "api-key",
+    "apikey",
+    "bearer ",
+    "passwd",
+    "token",
+];
+
+/// Replace secret values (`apikey=...`, `X-Api-Key: ...`, `"token":"..."`,
+/// `Bearer ...`) in `s` with `[redacted]`, so error text from HTTP clients and
+/// API responses is safe to log or show.
+pub fn redact_secrets(s: &str) -> String {
+    let lower = s.to_ascii_lowercase();
+    let mut out = String::with_capacity(s.len());
+    let mut copied = 0;
+    let mut skip_to = 0;
+
+    for (i, _) in s.char_indices() {
+        if i < skip_to {
+            continue;
+        }
+        let at_boundary = s[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !(c.is_ascii_alphanumeric() || c == '_'));
+        if !at_boundary {
+            continue;
+        }
+        let Some(key) = SECRET_KEYS.iter().find(|k| lower[i..].starts_with(**k)) else {
+            continue;
+        };
+        let after_key = i + key.len();
+        let Some(offset) = secret_value_offset(&s[after_key..], key.ends_with(' ')) else {
+            continue;
+        };
+        let start = after_key + offset;
+        let len = s[start..]
+            .find(|c: char| c.is_whitespace() || "&,;\"'<>()[]{}".contains(c))
+            .unwrap_or(s.len() - start);
+        if len == 0 {
+            continue;
+        }
+        out.push_str(&s[copied..start]);
+        out.push_str(REDACTED);
+        copied = start + len;
+        skip_to = copied;
+    }
+    out.push_str(&s[copied..]);
+    out
+}
+
+/// Offset from the end of a key to the start of its value: skips a closing
+/// quote, the `=`/`:` separator, spaces, and an opening quote. `None` when the
+/// key isn't followed by a separator (it's just a word in the text).
+fn secret_value_offset(rest: &str, no_separator: bool) -> Option<usize> {
+    let trimmed = if no_separator {
+        rest
+    } else {
+        let r = rest.trim_start_matches(['"', '\'']).trim_start();
+        r.strip_prefix(['=', ':'])?
+    };
+    let value = trimmed.trim_start().trim_start_matches(['"', '\'']);
+    Some(rest.len() - value.len())
+}
+
+/// Escape Discord markdown and mention syntax in remote or user-supplied
+/// text, so a title like `**x** @everyone` shows literally.
+pub fn escape_discord(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('*', "\\*")
+        .replace('_', "\\_")
+        .replace('`', "\\`")
+        .replace('~', "\\~")
+        .replace('|', "\\|")
+        .replace('@', "\\@")
+        .replace('<', "\\<")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-31T00:00:00Z
+    const JAN_31_2024: u64 = 1_706_659_200;
+
+    #[test]
+    fn test_iso_date_epoch() {
+        assert_eq!(iso_date(0), "1970-01-01");
+    }
+
+    #[test]
+    fn test_iso_date_month_boundary() {
+        assert_eq!(iso_date(JAN_31_2024), "2024-01-31");
+        assert_eq!(iso_date(JAN_31_2024 + SECS_PER_DAY), "2024-02-01");
+    }
+
+    #[test]
+    fn test_iso_date_leap_day() {
+        assert_eq!(iso_date(JAN_31_2024 + 29 * SECS_PER_DAY), "2024-02-29");
+        assert_eq!(iso_date(JAN_31_2024 + 30 * SECS_PER_DAY), "2024-03-01");
+    }
+
+    #[test]
+    fn test_iso_date_year_boundary() {
+        // 2023-12-31T23:59:59Z
+        assert_eq!(iso_date(1_704_067_199), "2023-12-31");
+        assert_eq!(iso_date(1_704_067_200), "2024-01-01");
+    }
+
+    #[test]
+    fn test_is_leap_year() {
+        assert!(is_leap_year(2024));
+        assert!(is_leap_year(2000));
+        assert!(!is_leap_year(1900));
+        assert!(!is_leap_year(2023));
+    }
+
+    #[test]
+    fn test_parse_rfc3339_utc() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_rfc3339("2024-01-02T20:00:00Z"), Some(1_704_225_600));
+        assert_eq!(parse_rfc3339("2024-02-29T12:00:00Z"), Some(1_709_208_000));
+    }
+
+    #[test]
+    fn test_parse_rfc3339_offset_and_fraction() {
+        assert_eq!(parse_rfc3339("2024-01-02T15:00:00-05:00"), Some(1_704_225_600));
+        assert_eq!(parse_rfc3339("2024-01-03T01:30:00+05:30"), Some(1_704_225_600));
+        assert_eq!(parse_rfc3339("2024-01-02T20:00:00.1234567Z"), Some(1_704_225_600));
+    }
+
+    #[test]
+    fn test_parse_rfc3339_invalid() {
+        assert_eq!(parse_rfc3339(""), None);
+        assert_eq!(parse_rfc3339("TBA"), None);
+        assert_eq!(parse_rfc3339("2024-01-02"), None);
+        assert_eq!(parse_rfc3339("2024-01-02T20:00:00"), None);
+        assert_eq!(parse_rfc3339("2023-02-29T00:00:00Z"), None);
+        assert_eq!(parse_rfc3339("2024-13-01T00:00:00Z"), None);
+        assert_eq!(parse_rfc3339("1969-12-31T23:59:59Z"), None);
+        assert_eq!(parse_rfc3339("2024-01-02T20:00:00.Z"), None);
+    }
+
+    #[test]
+    fn test_relative_time_minutes() {
+        assert_eq!(format_relative_time(1000, 400), "10m ago");
+    }
+
+    #[test]
+    fn test_relative_time_hours() {
+        assert_eq!(format_relative_time(10000, 3000), "1h ago");
+        assert_eq!(format_relative_time(10000, 10000 - 7200), "2h ago");
+    }
+
+    #[test]
+    fn test_relative_time_days() {
+        assert_eq!(format_relative_time(200000, 200000 - 172800), "2d ago");
+    }
+
+    #[test]
+    fn test_relative_time_future() {
+        assert_eq!(format_relative_time(100, 200), "just now");
+    }
+
+    #[test]
+    fn test_relative_time_zero() {
+        assert_eq!(format_relative_time(100, 0), "just now");
+    }
+
+    #[test]
+    fn test_discord_relative() {
+        assert_eq!(discord_relative(1_704_225_600), "<t:1704225600:R>");
+        assert_eq!(discord_relative(0), "just now");
+    }
+
+    #[test]
+    fn test_redact_query_param() {
+        assert_eq!(
+            redact_secrets("error sending request for url (http://sonarr:8989/api/v3/series?apikey=abc123&term=x)"),
+            "error sending request for url (http://sonarr:8989/api/v3/series?apikey=[redacted]&term=x)"
+        );
+        assert_eq!(
+            redact_secrets("http://plex:32400/library?X-Plex-Token=s3cr3t"),
+            "http://plex:32400/library?X-Plex-Token=[redacted]"
+        );
+    }
+
+    #[test]
+    fn test_redact_headers_and_json() {
+        assert_eq!(redact_secrets("X-Api-Key: deadbeef"), "X-Api-Key: [redacted]");
+        assert_eq!(
+            redact_secrets(r#"{"apiKey":"deadbeef","name":"Sonarr"}"#),
+            r#"{"apiKey":"[redacted]","name":"Sonarr"}"#
+        );
+        assert_eq!(
+            redact_secrets("Authorization: Bearer eyJhbGciOi.x.y"),
+            "Authorization: Bearer [redacted]"
+        );
+        assert_eq!(redact_secrets("password = hunter2"), "password = [redacted]");
+    }
+
+    #[test]
+    fn test_redact_leaves_plain_text() {
+        let msg = "Invalid API key or token expired";
+        assert_eq!(redact_secrets(msg), msg);
+        assert_eq!(redact_secrets("csrftoken=abc"), "csrftoken=abc");
+        assert_eq!(redact_secrets(""), "");
+    }
+
+    #[test]
+    fn test_today_iso_format() {
+        let result = today_iso();
+        assert_eq!(result.len(), 10);
+        assert_eq!(&result[4..5], "-");
+        assert_eq!(&result[7..8], "-");
+        let year: u32 = result[..4].parse().unwrap();
+        assert!(year >= 2024);
+    }
+
+    #[test]
+    fn test_escape_discord() {
+        assert_eq!(escape_discord("**bold**"), "\\*\\*bold\\*\\*");
+        assert_eq!(escape_discord("@everyone"), "\\@everyone");
+        assert_eq!(escape_discord("<@123>"), "\\<\\@123>");
+        assert_eq!(escape_discord("normal text"), "normal text");
+    }
+}
@@ -0,0 +1,107 @@
+//! Human-readable byte sizes and speeds, in binary (1 KB = 1024 B) or
+//! decimal (1 KB = 1000 B) units.
+
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const SUFFIXES: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+/// Which multiplier the `KB`/`MB`/... suffixes stand for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    /// Powers of 1024, like qBittorrent and most *arr UIs.
+    #[default]
+    Binary,
+    /// Powers of 1000, like drive labels.
+    Decimal,
+}
+
+impl Unit {
+    fn base(self) -> f64 {
+        match self {
+            Unit::Binary => 1024.0,
+            Unit::Decimal => 1000.0,
+        }
+    }
+}
+
+/// Process-wide choice set from config at startup (and on reload), so
+/// plugins don't each need it threaded through their constructors.
+static DECIMAL: AtomicBool = AtomicBool::new(false);
+
+/// The configured unit system.
+pub fn unit() -> Unit {
+    if DECIMAL.load(Ordering::Relaxed) { Unit::Decimal } else { Unit::Binary }
+}
+
+pub fn set_unit(unit: Unit) {
+    DECIMAL.store(unit == Unit::Decimal, Ordering::Relaxed);
+}
+
+/// e.g. `512 B`, `1.5 MB`, `8.0 TB`. Picks the largest suffix that keeps the
+/// rounded value at least 1, so `1023.99 KB` shows as `1.0 MB`.
+pub fn format_size(bytes: u64, unit: Unit) -> String {
+    let base = unit.base();
+    if (bytes as f64) < base {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / base;
+    let mut suffix = SUFFIXES[0];
+    for next in &SUFFIXES[1..] {
+        if (value * 10.0).round() / 10.0 < base {
+            break;
+        }
+        value /= base;
+        suffix = next;
+    }
+    format!("{value:.1} {suffix}")
+}
+
+/// [`format_size`] per second, e.g. `2.5 MB/s`.
+pub fn format_speed(bytes_per_sec: u64, unit: Unit) -> String {
+    format!("{}/s", format_size(bytes_per_sec, unit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_boundaries() {
+        let u = Unit::Binary;
+        assert_eq!(format_size(0, u), "0 B");
+        assert_eq!(format_size(1023, u), "1023 B");
+        assert_eq!(format_size(1024, u), "1.0 KB");
+        assert_eq!(format_size(1_048_575, u), "1.0 MB");
+        assert_eq!(format_size(1_048_576, u), "1.0 MB");
+        assert_eq!(format_size(524_288_000, u), "500.0 MB");
+        assert_eq!(format_size(1_073_741_824, u), "1.0 GB");
+        assert_eq!(format_size(1_610_612_736, u), "1.5 GB");
+        assert_eq!(format_size(1_099_511_627_776, u), "1.0 TB");
+        assert_eq!(format_size(1_649_267_441_664, u), "1.5 TB");
+        // Stays in TB past the last suffix.
+        assert_eq!(format_size(2_251_799_813_685_248, u), "2048.0 TB");
+    }
+
+    #[test]
+    fn decimal_boundaries() {
+        let u = Unit::Decimal;
+        assert_eq!(format_size(999, u), "999 B");
+        assert_eq!(format_size(1000, u), "1.0 KB");
+        assert_eq!(format_size(999_999, u), "1.0 MB");
+        assert_eq!(format_size(1_000_000, u), "1.0 MB");
+        assert_eq!(format_size(1_500_000_000, u), "1.5 GB");
+        assert_eq!(format_size(1_000_000_000_000, u), "1.0 TB");
+        // The same byte count reads larger in decimal.
+        assert_eq!(format_size(1_073_741_824, u), "1.1 GB");
+    }
+
+    #[test]
+    fn speed() {
+        assert_eq!(format_speed(500, Unit::Binary), "500 B/s");
+        assert_eq!(format_speed(1536, Unit::Binary), "1.5 KB/s");
+        assert_eq!(format_speed(2_621_440, Unit::Binary), "2.5 MB/s");
+        assert_eq!(format_speed(2_500_000, Unit::Decimal), "2.5 MB/s");
+    }
+}
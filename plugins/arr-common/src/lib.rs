@@ -1,15 +1,84 @@
+use discord_assist_plugin_api::PluginError;
+use discord_assist_util::http::{HttpOptions, build_client};
+use discord_assist_util::{escape_discord, redact_secrets};
+use discord_assist_util::size::{self, format_size};
 use reqwest::Client;
+use serde::Deserialize;
 use serde::de::DeserializeOwned;
 use thiserror::Error;
 
+/// Display output is passed through `redact_secrets`, since reqwest errors
+/// include the request URL and API error bodies can echo credentials.
 #[derive(Error, Debug)]
 pub enum ArrError {
-    #[error("HTTP error: {0}")]
+    #[error("HTTP error: {}", redact_secrets(&.0.to_string()))]
     Http(#[from] reqwest::Error),
-    #[error("API error ({status}): {body}")]
+    #[error("API error ({status}): {}", redact_secrets(body))]
     Api { status: u16, body: String },
 }
 
+/// A 401 means the API key is wrong, which is worth telling apart from a
+/// transient failure.
+impl From<ArrError> for PluginError {
+    fn from(e: ArrError) -> Self {
+        match e {
+            ArrError::Api { status: 401, .. } => {
+                PluginError::AuthError(format!("API key rejected ({e})"))
+            }
+            _ => PluginError::ApiError(e.to_string()),
+        }
+    }
+}
+
+/// Envelope for paged endpoints such as `history` and `wanted/missing`.
+#[derive(Debug, Deserialize)]
+pub struct PagedResponse<T> {
+    pub records: Vec<T>,
+    #[serde(rename = "totalRecords", default)]
+    pub total_records: u32,
+}
+
+/// `/queue/status`, trimmed to what we show.
+#[derive(Debug, Deserialize)]
+struct QueueStatus {
+    #[serde(rename = "totalCount")]
+    total_count: Option<u32>,
+}
+
+/// One entry from `/diskspace`.
+#[derive(Debug, Deserialize)]
+pub struct DiskSpace {
+    pub path: String,
+    #[serde(rename = "freeSpace")]
+    pub free_space: u64,
+    #[serde(rename = "totalSpace")]
+    pub total_space: u64,
+}
+
+/// Free space below this percentage gets a warning marker.
+const LOW_SPACE_PCT: f64 = 5.0;
+
+impl DiskSpace {
+    pub fn free_pct(&self) -> f64 {
+        if self.total_space == 0 {
+            return 0.0;
+        }
+        self.free_space as f64 / self.total_space as f64 * 100.0
+    }
+
+    /// e.g. "- /tv: 1.2 TB free of 8.0 TB (15%)", flagged LOW under 5% free.
+    pub fn render(&self) -> String {
+        let pct = self.free_pct();
+        let warning = if pct < LOW_SPACE_PCT { " **LOW**" } else { "" };
+        format!(
+            "- {}: {} free of {} ({pct:.0}%){warning}",
+            escape_discord(&self.path),
+            format_size(self.free_space, size::unit()),
+            format_size(self.total_space, size::unit())
+        )
+    }
+}
+
 #[derive(Clone)]
 pub struct ArrClient {
     client: Client,
@@ -24,8 +93,15 @@ impl ArrClient {
     }
 
     pub fn with_api_version(base_url: &str, api_key: &str, api_version: &str) -> Self {
+        let client = build_client(&HttpOptions::default());
+        Self::with_client(client, base_url, api_key, api_version)
+    }
+
+    /// Use a shared `client` (see `discord_assist_util::http::SharedClients`)
+    /// instead of building one.
+    pub fn with_client(client: Client, base_url: &str, api_key: &str, api_version: &str) -> Self {
         Self {
-            client: Client::new(),
+            client,
             base_url: base_url.trim_end_matches('/').to_string(),
             api_key: api_key.to_string(),
             api_version: api_version.to_string(),
@@ -96,6 +172,41 @@ impl ArrClient {
         Ok(resp.json().await?)
     }
 
+    /// Replace a resource, e.g. `indexer/3`. The *arr APIs expect the whole
+    /// object, so fetch it first and send it back modified.
+    pub async fn put<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: &serde_json::Value,
+    ) -> Result<T, ArrError> {
+        let url = format!("{}/api/{}/{}", self.base_url, self.api_version, endpoint.trim_start_matches('/'));
+        let resp = self
+            .client
+            .put(&url)
+            .header("X-Api-Key", &self.api_key)
+            .json(body)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status().as_u16();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ArrError::Api { status, body });
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    pub async fn disk_space(&self) -> Result<Vec<DiskSpace>, ArrError> {
+        self.get("diskspace").await
+    }
+
+    /// Items in the download queue.
+    pub async fn queue_count(&self) -> Result<u32, ArrError> {
+        let queue: QueueStatus = self.get("queue/status").await?;
+        Ok(queue.total_count.unwrap_or(0))
+    }
+
     pub async fn health(&self) -> Result<bool, ArrError> {
         let url = format!("{}/api/{}/health", self.base_url, self.api_version);
         let resp = self
@@ -114,7 +225,7 @@ impl ArrClient {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use wiremock::matchers::{header, method, path};
+    use wiremock::matchers::{header, method, path, query_param};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     #[tokio::test]
@@ -135,6 +246,105 @@ mod tests {
         assert_eq!(resp["version"], "4.0");
     }
 
+    #[test]
+    fn test_unauthorized_is_auth_error() {
+        let err = PluginError::from(ArrError::Api { status: 401, body: "Unauthorized".into() });
+        assert!(matches!(err, PluginError::AuthError(_)), "{err:?}");
+        let err = PluginError::from(ArrError::Api { status: 500, body: "boom".into() });
+        assert!(matches!(err, PluginError::ApiError(_)), "{err:?}");
+    }
+
+    #[test]
+    fn test_api_error_redacts_body() {
+        let err = ArrError::Api {
+            status: 401,
+            body: "Unauthorized: apikey=abc123 rejected".into(),
+        };
+        assert_eq!(err.to_string(), "API error (401): Unauthorized: apikey=[redacted] rejected");
+    }
+
+    #[test]
+    fn test_paged_response() {
+        let paged: PagedResponse<serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "page": 1,
+            "pageSize": 10,
+            "totalRecords": 42,
+            "records": [{"id": 1}]
+        }))
+        .unwrap();
+        assert_eq!(paged.total_records, 42);
+        assert_eq!(paged.records.len(), 1);
+    }
+
+    #[test]
+    fn test_disk_space_render() {
+        let disk = DiskSpace {
+            path: "/tv".into(),
+            free_space: 1_649_267_441_664,
+            total_space: 8_796_093_022_208,
+        };
+        assert_eq!(disk.render(), "- /tv: 1.5 TB free of 8.0 TB (19%)");
+    }
+
+    #[test]
+    fn test_disk_space_low_warning() {
+        let disk = DiskSpace {
+            path: "/movies".into(),
+            free_space: 429_496_729,
+            total_space: 10_737_418_240,
+        };
+        assert_eq!(disk.free_pct().round(), 4.0);
+        assert_eq!(disk.render(), "- /movies: 409.6 MB free of 10.0 GB (4%) **LOW**");
+
+        let empty = DiskSpace {
+            path: "/none".into(),
+            free_space: 0,
+            total_space: 0,
+        };
+        assert_eq!(empty.free_pct(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_sends_user_agent() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/system/status"))
+            .and(header("User-Agent", discord_assist_util::http::USER_AGENT))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(discord_assist_util::http::USER_AGENT.starts_with("discord-assist/"));
+        let client = ArrClient::new(&mock_server.uri(), "test-key");
+        let _: serde_json::Value = client.get("system/status").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_with_params_encodes_special_characters() {
+        let term = "Tom & Jerry: #1 + 2? a=b 100% é/ü";
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/movie/lookup"))
+            .and(query_param("term", term))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = ArrClient::new(&mock_server.uri(), "test-key");
+        let _: serde_json::Value = client
+            .get_with_params("movie/lookup", &[("term", term)])
+            .await
+            .unwrap();
+
+        let requests = mock_server.received_requests().await.unwrap();
+        let query = requests[0].url.query().unwrap();
+        assert!(!query.contains(' ') && !query.contains('#'), "{query}");
+        assert_eq!(query.matches('&').count(), 0, "{query}");
+        assert_eq!(query.matches('=').count(), 1, "{query}");
+    }
+
     #[tokio::test]
     async fn test_api_error() {
         let mock_server = MockServer::start().await;
@@ -1,26 +1,198 @@
 use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use discord_assist_util::http::{HttpOptions, build_client};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use thiserror::Error;
 
+/// Completions on long conversations can take minutes on local models.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
 #[derive(Error, Debug)]
 pub enum LlmError {
     #[error("HTTP error: {0}")]
-    Http(#[from] reqwest::Error),
+    Http(reqwest::Error),
     #[error("API error: {0}")]
     Api(String),
+    /// No answer within the backend's timeout.
+    #[error("the model took too long to answer")]
+    Timeout,
+}
+
+impl From<reqwest::Error> for LlmError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() { Self::Timeout } else { Self::Http(e) }
+    }
+}
+
+fn llm_client(timeout: Duration) -> Client {
+    build_client(&HttpOptions {
+        timeout,
+        ..HttpOptions::default()
+    })
 }
 
 #[async_trait]
 pub trait LlmBackend: Send + Sync {
-    async fn complete(&self, messages: &[Message]) -> Result<String, LlmError>;
+    async fn complete(&self, messages: &[Message]) -> Result<Completion, LlmError>;
     async fn health_check(&self) -> Result<bool, LlmError>;
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
-    pub content: String,
+    pub content: Content,
+}
+
+/// Plain text, or Anthropic-style content blocks when the message carries an
+/// image. Plain text serializes as a bare string, so text-only requests and
+/// saved conversations look the same as before.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Content {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text { text: String },
+    Image { source: ImageSource },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImageSource {
+    /// Always `base64`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub media_type: String,
+    pub data: String,
+}
+
+impl Content {
+    /// `text` asked about an image of `media_type`. The image goes first,
+    /// which is what vision models expect.
+    pub fn with_image(text: &str, media_type: &str, bytes: &[u8]) -> Self {
+        Self::Blocks(vec![
+            ContentBlock::Image {
+                source: ImageSource {
+                    kind: "base64".into(),
+                    media_type: media_type.into(),
+                    data: BASE64.encode(bytes),
+                },
+            },
+            ContentBlock::Text { text: text.into() },
+        ])
+    }
+
+    /// The text parts, without images.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|b| match b {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    ContentBlock::Image { .. } => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    fn images(&self) -> impl Iterator<Item = &ImageSource> {
+        let blocks = match self {
+            Self::Text(_) => &[][..],
+            Self::Blocks(blocks) => blocks.as_slice(),
+        };
+        blocks.iter().filter_map(|b| match b {
+            ContentBlock::Image { source } => Some(source),
+            ContentBlock::Text { .. } => None,
+        })
+    }
+}
+
+impl From<&str> for Content {
+    fn from(text: &str) -> Self {
+        Self::Text(text.into())
+    }
+}
+
+impl From<String> for Content {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+/// Ollama takes images as a list of base64 strings beside the text.
+fn ollama_message(message: &Message) -> serde_json::Value {
+    let mut json = serde_json::json!({
+        "role": message.role,
+        "content": message.content.text(),
+    });
+    let images: Vec<&str> = message.content.images().map(|i| i.data.as_str()).collect();
+    if !images.is_empty() {
+        json["images"] = serde_json::json!(images);
+    }
+    json
+}
+
+/// OpenAI takes images as `image_url` parts holding a data URL.
+fn openai_message(message: &Message) -> serde_json::Value {
+    let content = match &message.content {
+        Content::Text(text) => serde_json::json!(text),
+        Content::Blocks(blocks) => blocks
+            .iter()
+            .map(|b| match b {
+                ContentBlock::Text { text } => serde_json::json!({"type": "text", "text": text}),
+                ContentBlock::Image { source } => serde_json::json!({
+                    "type": "image_url",
+                    "image_url": {"url": format!("data:{};base64,{}", source.media_type, source.data)},
+                }),
+            })
+            .collect(),
+    };
+    serde_json::json!({"role": message.role, "content": content})
+}
+
+/// A backend's answer, with token counts when the backend reports them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Completion {
+    pub text: String,
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+}
+
+impl Completion {
+    fn new(text: String, usage: Option<Usage>) -> Self {
+        let usage = usage.unwrap_or_default();
+        Self {
+            text,
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+        }
+    }
+
+    /// Prompt plus completion tokens, or `None` if the backend reported neither.
+    pub fn total_tokens(&self) -> Option<u64> {
+        match (self.prompt_tokens, self.completion_tokens) {
+            (None, None) => None,
+            (p, c) => Some(p.unwrap_or(0) + c.unwrap_or(0)),
+        }
+    }
+}
+
+/// The `usage` object, in either Anthropic (`input_tokens`/`output_tokens`)
+/// or OpenAI (`prompt_tokens`/`completion_tokens`) naming.
+#[derive(Debug, Default, Deserialize)]
+struct Usage {
+    #[serde(default, alias = "input_tokens")]
+    prompt_tokens: Option<u64>,
+    #[serde(default, alias = "output_tokens")]
+    completion_tokens: Option<u64>,
 }
 
 pub struct HttpLlmBackend {
@@ -32,16 +204,22 @@ pub struct HttpLlmBackend {
 impl HttpLlmBackend {
     pub fn new(api_url: &str, api_key: Option<String>) -> Self {
         Self {
-            client: Client::new(),
+            client: llm_client(DEFAULT_TIMEOUT),
             api_url: api_url.trim_end_matches('/').to_string(),
             api_key,
         }
     }
+
+    /// Give up on a request after `timeout` instead of [`DEFAULT_TIMEOUT`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = llm_client(timeout);
+        self
+    }
 }
 
 #[async_trait]
 impl LlmBackend for HttpLlmBackend {
-    async fn complete(&self, messages: &[Message]) -> Result<String, LlmError> {
+    async fn complete(&self, messages: &[Message]) -> Result<Completion, LlmError> {
         let body = serde_json::json!({
             "messages": messages,
             "stream": false,
@@ -60,16 +238,15 @@ impl LlmBackend for HttpLlmBackend {
         }
 
         let json: serde_json::Value = resp.json().await?;
+        let usage = serde_json::from_value(json["usage"].clone()).ok();
 
         // Try common response formats
-        if let Some(content) = json["content"][0]["text"].as_str() {
-            return Ok(content.to_string());
-        }
-        if let Some(content) = json["choices"][0]["message"]["content"].as_str() {
-            return Ok(content.to_string());
-        }
-        if let Some(content) = json["response"].as_str() {
-            return Ok(content.to_string());
+        let content = json["content"][0]["text"]
+            .as_str()
+            .or_else(|| json["choices"][0]["message"]["content"].as_str())
+            .or_else(|| json["response"].as_str());
+        if let Some(content) = content {
+            return Ok(Completion::new(content.to_string(), usage));
         }
 
         Err(LlmError::Api(format!(
@@ -91,10 +268,173 @@ impl LlmBackend for HttpLlmBackend {
     }
 }
 
+/// Talks to Ollama's native chat API, which takes a model name and answers
+/// with a single `message`.
+pub struct OllamaBackend {
+    client: Client,
+    api_url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OllamaChatResponse {
+    message: Message,
+    #[serde(default)]
+    prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    eval_count: Option<u64>,
+}
+
+impl OllamaBackend {
+    /// `api_key` is only sent if set, for Ollama behind an authenticating proxy.
+    pub fn new(api_url: &str, model: &str, api_key: Option<String>) -> Self {
+        Self {
+            client: llm_client(DEFAULT_TIMEOUT),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+
+    /// Give up on a request after `timeout` instead of [`DEFAULT_TIMEOUT`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = llm_client(timeout);
+        self
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OllamaBackend {
+    async fn complete(&self, messages: &[Message]) -> Result<Completion, LlmError> {
+        let messages: Vec<_> = messages.iter().map(ollama_message).collect();
+        let body = serde_json::json!({
+            "model": self.model,
+            "messages": messages,
+            "stream": false,
+        });
+
+        let mut req = self.client.post(format!("{}/api/chat", self.api_url));
+        if let Some(ref key) = self.api_key {
+            req = req.bearer_auth(key);
+        }
+
+        let resp = req.json(&body).send().await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(LlmError::Api(format!("{status}: {text}")));
+        }
+
+        let text = resp.text().await?;
+        let parsed: OllamaChatResponse = serde_json::from_str(&text)
+            .map_err(|e| LlmError::Api(format!("Could not parse response: {e}: {text}")))?;
+        Ok(Completion {
+            text: parsed.message.content.text(),
+            prompt_tokens: parsed.prompt_eval_count,
+            completion_tokens: parsed.eval_count,
+        })
+    }
+
+    async fn health_check(&self) -> Result<bool, LlmError> {
+        let resp = self
+            .client
+            .get(format!("{}/api/tags", self.api_url))
+            .send()
+            .await;
+        match resp {
+            Ok(r) => Ok(r.status().is_success()),
+            Err(_) => Ok(false),
+        }
+    }
+}
+
+/// Talks to an OpenAI-compatible `/v1/chat/completions` endpoint (OpenAI,
+/// llama.cpp, vLLM, LM Studio, ...).
+pub struct OpenAiBackend {
+    client: Client,
+    api_url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiChatResponse {
+    choices: Vec<OpenAiChoice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiChoice {
+    message: Message,
+}
+
+impl OpenAiBackend {
+    pub fn new(api_url: &str, model: &str, api_key: Option<String>) -> Self {
+        Self {
+            client: llm_client(DEFAULT_TIMEOUT),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+
+    /// Give up on a request after `timeout` instead of [`DEFAULT_TIMEOUT`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = llm_client(timeout);
+        self
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OpenAiBackend {
+    async fn complete(&self, messages: &[Message]) -> Result<Completion, LlmError> {
+        let messages: Vec<_> = messages.iter().map(openai_message).collect();
+        let body = serde_json::json!({
+            "model": self.model,
+            "messages": messages,
+        });
+
+        let mut req = self.client.post(format!("{}/v1/chat/completions", self.api_url));
+        if let Some(ref key) = self.api_key {
+            req = req.bearer_auth(key);
+        }
+
+        let resp = req.json(&body).send().await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(LlmError::Api(format!("{status}: {text}")));
+        }
+
+        let text = resp.text().await?;
+        let parsed: OpenAiChatResponse = serde_json::from_str(&text)
+            .map_err(|e| LlmError::Api(format!("Could not parse response: {e}: {text}")))?;
+        let choice = parsed
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| LlmError::Api("Response has no choices".into()))?;
+        Ok(Completion::new(choice.message.content.text(), parsed.usage))
+    }
+
+    async fn health_check(&self) -> Result<bool, LlmError> {
+        let mut req = self.client.get(format!("{}/v1/models", self.api_url));
+        if let Some(ref key) = self.api_key {
+            req = req.bearer_auth(key);
+        }
+        match req.send().await {
+            Ok(r) => Ok(r.status().is_success()),
+            Err(_) => Ok(false),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use wiremock::matchers::{method, path};
+    use wiremock::matchers::{body_json, header, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     #[tokio::test]
@@ -111,7 +451,8 @@ mod tests {
         let backend = HttpLlmBackend::new(&mock_server.uri(), None);
         let messages = vec![Message { role: "user".into(), content: "Hi".into() }];
         let result = backend.complete(&messages).await.unwrap();
-        assert_eq!(result, "Hello! How can I help?");
+        assert_eq!(result.text, "Hello! How can I help?");
+        assert_eq!(result.total_tokens(), None);
     }
 
     #[tokio::test]
@@ -128,7 +469,7 @@ mod tests {
         let backend = HttpLlmBackend::new(&mock_server.uri(), None);
         let messages = vec![Message { role: "user".into(), content: "Hi".into() }];
         let result = backend.complete(&messages).await.unwrap();
-        assert_eq!(result, "Hello from OpenAI format");
+        assert_eq!(result.text, "Hello from OpenAI format");
     }
 
     #[tokio::test]
@@ -149,4 +490,239 @@ mod tests {
         let backend = HttpLlmBackend::new("http://127.0.0.1:1", None);
         assert!(!backend.health_check().await.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_ollama_chat_request_and_response() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_json(serde_json::json!({
+                "model": "llama3.1",
+                "messages": [
+                    {"role": "user", "content": "Hi"},
+                    {"role": "assistant", "content": "Hello"},
+                    {"role": "user", "content": "How are you?"}
+                ],
+                "stream": false
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "model": "llama3.1",
+                "created_at": "2024-07-23T12:00:00Z",
+                "message": {"role": "assistant", "content": "Doing well."},
+                "done": true
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let backend = OllamaBackend::new(&format!("{}/", mock_server.uri()), "llama3.1", None);
+        let messages = vec![
+            Message { role: "user".into(), content: "Hi".into() },
+            Message { role: "assistant".into(), content: "Hello".into() },
+            Message { role: "user".into(), content: "How are you?".into() },
+        ];
+        assert_eq!(backend.complete(&messages).await.unwrap().text, "Doing well.");
+    }
+
+    #[tokio::test]
+    async fn test_ollama_error_status() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(
+                ResponseTemplate::new(404)
+                    .set_body_json(serde_json::json!({"error": "model \"nope\" not found"})),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let backend = OllamaBackend::new(&mock_server.uri(), "nope", None);
+        let messages = vec![Message { role: "user".into(), content: "Hi".into() }];
+        let err = backend.complete(&messages).await.unwrap_err().to_string();
+        assert!(err.contains("404") && err.contains("not found"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_ollama_health_check() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"models": []})))
+            .mount(&mock_server)
+            .await;
+
+        let backend = OllamaBackend::new(&mock_server.uri(), "llama3.1", None);
+        assert!(backend.health_check().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_openai_chat_completions_request_and_response() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", "Bearer sk-test"))
+            .and(body_json(serde_json::json!({
+                "model": "gpt-4o-mini",
+                "messages": [{"role": "user", "content": "Hi"}]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hello from chat completions"},
+                    "finish_reason": "stop"
+                }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let backend = OpenAiBackend::new(&mock_server.uri(), "gpt-4o-mini", Some("sk-test".into()));
+        let messages = vec![Message { role: "user".into(), content: "Hi".into() }];
+        assert_eq!(backend.complete(&messages).await.unwrap().text, "Hello from chat completions");
+    }
+
+    #[tokio::test]
+    async fn test_openai_empty_choices_is_an_error() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"choices": []})))
+            .mount(&mock_server)
+            .await;
+
+        let backend = OpenAiBackend::new(&mock_server.uri(), "local", None);
+        let messages = vec![Message { role: "user".into(), content: "Hi".into() }];
+        let err = backend.complete(&messages).await.unwrap_err().to_string();
+        assert!(err.contains("no choices"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_openai_health_check_lists_models() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"data": []})))
+            .mount(&mock_server)
+            .await;
+
+        let backend = OpenAiBackend::new(&mock_server.uri(), "local", None);
+        assert!(backend.health_check().await.unwrap());
+    }
+
+    #[test]
+    fn test_usage_anthropic_shape() {
+        let usage: Usage =
+            serde_json::from_value(serde_json::json!({"input_tokens": 12, "output_tokens": 30})).unwrap();
+        let completion = Completion::new("Hi".into(), Some(usage));
+        assert_eq!(completion.prompt_tokens, Some(12));
+        assert_eq!(completion.completion_tokens, Some(30));
+        assert_eq!(completion.total_tokens(), Some(42));
+    }
+
+    #[test]
+    fn test_usage_openai_shape() {
+        let usage: Usage = serde_json::from_value(serde_json::json!({
+            "prompt_tokens": 100,
+            "completion_tokens": 23,
+            "total_tokens": 123
+        }))
+        .unwrap();
+        let completion = Completion::new("Hi".into(), Some(usage));
+        assert_eq!(completion.total_tokens(), Some(123));
+
+        let completion = Completion::new("Hi".into(), None);
+        assert_eq!(completion.total_tokens(), None);
+    }
+
+    #[tokio::test]
+    async fn test_complete_reports_usage() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": [{"type": "text", "text": "Hello"}],
+                "usage": {"input_tokens": 8, "output_tokens": 2}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let backend = HttpLlmBackend::new(&mock_server.uri(), None);
+        let messages = vec![Message { role: "user".into(), content: "Hi".into() }];
+        let result = backend.complete(&messages).await.unwrap();
+        assert_eq!(result.prompt_tokens, Some(8));
+        assert_eq!(result.completion_tokens, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_slow_backend_times_out() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"content": [{"type": "text", "text": "late"}]}))
+                    .set_delay(Duration::from_secs(2)),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let backend =
+            HttpLlmBackend::new(&mock_server.uri(), None).with_timeout(Duration::from_millis(200));
+        let messages = vec![Message { role: "user".into(), content: "Hi".into() }];
+        let err = backend.complete(&messages).await.unwrap_err();
+        assert!(matches!(err, LlmError::Timeout), "{err:?}");
+    }
+
+    fn image_message() -> Message {
+        Message {
+            role: "user".into(),
+            content: Content::with_image("What is this?", "image/png", b"png"),
+        }
+    }
+
+    #[test]
+    fn test_text_message_body_is_unchanged() {
+        let message = Message { role: "user".into(), content: "Hi".into() };
+        let expected = serde_json::json!({"role": "user", "content": "Hi"});
+        assert_eq!(serde_json::to_value(&message).unwrap(), expected);
+        assert_eq!(ollama_message(&message), expected);
+        assert_eq!(openai_message(&message), expected);
+    }
+
+    #[test]
+    fn test_image_message_body() {
+        let message = image_message();
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({
+                "role": "user",
+                "content": [
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "cG5n"}},
+                    {"type": "text", "text": "What is this?"}
+                ]
+            })
+        );
+        assert_eq!(
+            ollama_message(&message),
+            serde_json::json!({"role": "user", "content": "What is this?", "images": ["cG5n"]})
+        );
+        assert_eq!(
+            openai_message(&message),
+            serde_json::json!({
+                "role": "user",
+                "content": [
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,cG5n"}},
+                    {"type": "text", "text": "What is this?"}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_message_round_trips_both_shapes() {
+        for message in [Message { role: "assistant".into(), content: "Hi".into() }, image_message()] {
+            let json = serde_json::to_string(&message).unwrap();
+            assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), message);
+        }
+    }
 }
@@ -1,62 +1,184 @@
 pub mod backend;
 
 use async_trait::async_trait;
-use backend::{HttpLlmBackend, LlmBackend, Message};
-use discord_assist_plugin_api::{Plugin, PluginError};
-use serenity::builder::{
-    CreateCommand, CreateCommandOption, CreateInteractionResponse,
-    CreateInteractionResponseFollowup, CreateInteractionResponseMessage,
+use backend::{Completion, Content, HttpLlmBackend, LlmBackend, LlmError, Message};
+use discord_assist_plugin_api::{
+    Plugin, PluginError, ack, exceeds_inline_limit, inline_or_attachment, respond_chunked,
 };
+use serenity::builder::{CreateCommand, CreateCommandOption};
 use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
 use serenity::prelude::Context;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tracing::{debug, warn};
 
-const DISCORD_MAX_LEN: usize = 2000;
+/// How often conversations are flushed to `persist_path` in the background.
+const PERSIST_FLUSH_SECS: u64 = 300;
+
+/// Largest image `/claude ask` accepts unless configured otherwise.
+pub const DEFAULT_MAX_IMAGE_MB: u64 = 5;
+
+/// Image types vision models accept.
+const IMAGE_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+type Conversations = HashMap<u64, Vec<Message>>;
 
 pub struct ClaudePlugin {
     backend: Box<dyn LlmBackend>,
-    conversations: Arc<RwLock<HashMap<u64, Vec<Message>>>>,
+    conversations: Arc<RwLock<Conversations>>,
+    /// Per-channel locks held for a whole ask/retry turn, so two turns on the same
+    /// channel can't interleave their history updates around the backend call.
+    turn_locks: Mutex<HashMap<u64, Arc<tokio::sync::Mutex<()>>>>,
+    persist_path: Option<PathBuf>,
+    max_image_bytes: u64,
 }
 
 impl ClaudePlugin {
-    pub fn new(api_url: &str, api_key: Option<String>) -> Self {
+    pub fn new(api_url: &str, api_key: Option<String>, persist_path: Option<&str>) -> Self {
+        Self::with_backend(Box::new(HttpLlmBackend::new(api_url, api_key)), persist_path)
+    }
+
+    pub fn with_backend(backend: Box<dyn LlmBackend>, persist_path: Option<&str>) -> Self {
+        let persist_path = persist_path.map(PathBuf::from);
+        let initial = persist_path
+            .as_deref()
+            .map(load_conversations)
+            .unwrap_or_default();
+        let conversations = Arc::new(RwLock::new(initial));
+
+        if let Some(path) = persist_path.clone() {
+            let conversations = conversations.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(PERSIST_FLUSH_SECS));
+                // First tick completes immediately; nothing has changed yet.
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    let snapshot = conversations.read().await.clone();
+                    save_conversations(&path, &snapshot).await;
+                }
+            });
+        }
+
         Self {
-            backend: Box::new(HttpLlmBackend::new(api_url, api_key)),
-            conversations: Arc::new(RwLock::new(HashMap::new())),
+            backend,
+            conversations,
+            turn_locks: Mutex::new(HashMap::new()),
+            persist_path,
+            max_image_bytes: DEFAULT_MAX_IMAGE_MB * 1024 * 1024,
         }
     }
-}
 
-fn chunk_message(text: &str, max_len: usize) -> Vec<String> {
-    if text.chars().count() <= max_len {
-        return vec![text.to_string()];
+    /// Reject image attachments larger than `mb` megabytes.
+    pub fn with_max_image_mb(mut self, mb: u64) -> Self {
+        self.max_image_bytes = mb * 1024 * 1024;
+        self
     }
-    let mut chunks = Vec::new();
-    let mut remaining = text;
-    while !remaining.is_empty() {
-        if remaining.chars().count() <= max_len {
-            chunks.push(remaining.to_string());
-            break;
+
+    fn turn_lock(&self, channel_id: u64) -> Arc<tokio::sync::Mutex<()>> {
+        let mut locks = self.turn_locks.lock().unwrap_or_else(|e| e.into_inner());
+        locks.entry(channel_id).or_default().clone()
+    }
+
+    async fn persist(&self) {
+        if let Some(ref path) = self.persist_path {
+            let snapshot = self.conversations.read().await.clone();
+            save_conversations(path, &snapshot).await;
         }
-        // Find a safe split point: try newline first, then char boundary
-        let byte_limit = remaining
-            .char_indices()
-            .take(max_len)
-            .last()
-            .map(|(i, c)| i + c.len_utf8())
-            .unwrap_or(remaining.len());
-        let split_at = remaining[..byte_limit]
-            .rfind('\n')
-            .unwrap_or(byte_limit);
-        chunks.push(remaining[..split_at].to_string());
-        remaining = &remaining[split_at..];
-        if remaining.starts_with('\n') {
-            remaining = &remaining[1..];
+    }
+}
+
+/// Load saved conversations. A missing file is a fresh start; a corrupt one is
+/// logged and discarded rather than failing plugin startup.
+fn load_conversations(path: &Path) -> Conversations {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            debug!("No saved conversations at {}: {e}", path.display());
+            return HashMap::new();
+        }
+    };
+    parse_conversations(&content).unwrap_or_else(|e| {
+        warn!("Ignoring corrupt conversation file {}: {e}", path.display());
+        HashMap::new()
+    })
+}
+
+fn parse_conversations(content: &str) -> Result<Conversations, serde_json::Error> {
+    serde_json::from_str(content)
+}
+
+async fn save_conversations(path: &Path, conversations: &Conversations) {
+    let json = match serde_json::to_string(conversations) {
+        Ok(j) => j,
+        Err(e) => {
+            warn!("Failed to serialize conversations: {e}");
+            return;
         }
+    };
+    if let Err(e) = write_atomic(path, &json).await {
+        warn!("Failed to save conversations to {}: {e}", path.display());
+    }
+}
+
+/// Write `contents` to `<path>.tmp` and rename it over `path`, so a crash
+/// mid-write never truncates the saved history.
+async fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    tokio::fs::write(&tmp, contents).await?;
+    tokio::fs::rename(&tmp, path).await
+}
+
+/// The history to re-send for a retry: everything before the last assistant turn.
+/// Returns `None` when the conversation doesn't end in an assistant reply.
+fn retry_history(history: &[Message]) -> Option<&[Message]> {
+    match history.split_last() {
+        Some((last, rest)) if last.role == "assistant" => Some(rest),
+        _ => None,
+    }
+}
+
+/// The media type to send an attachment as, or why it can't be sent.
+fn check_image(content_type: Option<&str>, size: u64, max_bytes: u64) -> Result<&str, String> {
+    // Discord may add parameters, e.g. `image/png; charset=...`
+    let media_type = content_type.and_then(|t| t.split(';').next()).map(str::trim);
+    let Some(media_type) = media_type.filter(|t| IMAGE_TYPES.contains(t)) else {
+        return Err("Only PNG, JPEG, GIF and WebP images are supported.".into());
+    };
+    if size > max_bytes {
+        return Err(format!(
+            "That image is {:.1} MB; the limit is {} MB.",
+            size as f64 / (1024.0 * 1024.0),
+            max_bytes / (1024 * 1024)
+        ));
+    }
+    Ok(media_type)
+}
+
+/// Reply for a backend that didn't answer within its timeout.
+const TIMEOUT_REPLY: &str =
+    "The model took too long to answer. Try again, or raise `timeout_secs` under `[claude]`.";
+
+async fn reply(
+    ctx: &Context,
+    command: &CommandInteraction,
+    content: &str,
+) -> Result<bool, PluginError> {
+    respond_chunked(ctx, command, content).await?;
+    Ok(true)
+}
+
+/// The answer with a small-print token count, when the backend reported one.
+/// Only the plain answer is kept in the conversation history.
+fn with_usage_footer(completion: Completion) -> String {
+    match completion.total_tokens() {
+        Some(tokens) => format!("{}\n-# ({tokens} tokens)", completion.text),
+        None => completion.text,
     }
-    chunks
 }
 
 #[async_trait]
@@ -73,7 +195,20 @@ impl Plugin for ClaudePlugin {
                     .add_sub_option(
                         CreateCommandOption::new(CommandOptionType::String, "prompt", "Your question")
                             .required(true),
-                    ),
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "format", "How to deliver long answers (default: text)")
+                            .add_string_choice("text", "text")
+                            .add_string_choice("file", "file"),
+                    )
+                    .add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::Attachment,
+                        "image",
+                        "An image to ask about (PNG, JPEG, GIF or WebP)",
+                    )),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "retry", "Regenerate the last answer in this conversation"),
             )
             .add_option(
                 CreateCommandOption::new(CommandOptionType::SubCommand, "status", "Check Claude backend health"),
@@ -93,6 +228,20 @@ impl Plugin for ClaudePlugin {
             )]
     }
 
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        matches!(path, ["claude", "status"])
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![
+            ("/claude ask".into(), "Ask Claude a question, optionally about an image".into()),
+            ("/claude retry".into(), "Regenerate the last answer in this conversation".into()),
+            ("/claude status".into(), "Check Claude backend health".into()),
+            ("/claude conversation start".into(), "Start a new conversation".into()),
+            ("/claude conversation end".into(), "End the current conversation".into()),
+        ]
+    }
+
     async fn handle_command(
         &self,
         ctx: &Context,
@@ -109,6 +258,8 @@ impl Plugin for ClaudePlugin {
         };
 
         let channel_id = command.channel_id.get();
+        // `format:file` sends long answers as an attachment instead of chunking them.
+        let mut as_file = false;
 
         let content = match subopt.name {
             "ask" => {
@@ -121,32 +272,88 @@ impl Plugin for ClaudePlugin {
                             _ => None,
                         })
                         .ok_or_else(|| PluginError::Other("Missing prompt".into()))?;
-
-                    let mut conversations = self.conversations.write().await;
-                    let messages = if let Some(history) = conversations.get_mut(&channel_id) {
-                        history.push(Message { role: "user".into(), content: prompt.to_string() });
-                        history.clone()
-                    } else {
-                        vec![Message { role: "user".into(), content: prompt.to_string() }]
+                    as_file = opts
+                        .iter()
+                        .any(|o| o.name == "format" && matches!(o.value, ResolvedValue::String("file")));
+                    let image = opts.iter().find_map(|o| match o.value {
+                        ResolvedValue::Attachment(a) if o.name == "image" => Some(a),
+                        _ => None,
+                    });
+                    let content = match image {
+                        Some(image) => {
+                            let size = u64::from(image.size);
+                            let media_type =
+                                match check_image(image.content_type.as_deref(), size, self.max_image_bytes) {
+                                    Ok(t) => t,
+                                    Err(reason) => return reply(ctx, command, &reason).await,
+                                };
+                            let bytes = image.download().await.map_err(PluginError::DiscordError)?;
+                            Content::with_image(prompt, media_type, &bytes)
+                        }
+                        None => prompt.into(),
                     };
-                    drop(conversations);
 
-                    let response = self
-                        .backend
-                        .complete(&messages)
+                    let turn = self.turn_lock(channel_id);
+                    let _turn = turn.lock().await;
+
+                    let user_message = Message { role: "user".into(), content };
+                    let mut messages = self
+                        .conversations
+                        .read()
                         .await
-                        .map_err(|e| PluginError::ApiError(e.to_string()))?;
+                        .get(&channel_id)
+                        .cloned()
+                        .unwrap_or_default();
+                    messages.push(user_message.clone());
+
+                    let completion = match self.backend.complete(&messages).await {
+                        Ok(completion) => completion,
+                        Err(LlmError::Timeout) => return reply(ctx, command, TIMEOUT_REPLY).await,
+                        Err(e) => return Err(PluginError::ApiError(e.to_string())),
+                    };
 
                     let mut conversations = self.conversations.write().await;
                     if let Some(history) = conversations.get_mut(&channel_id) {
-                        history.push(Message { role: "assistant".into(), content: response.clone() });
+                        history.push(user_message);
+                        history.push(Message {
+                            role: "assistant".into(),
+                            content: completion.text.clone().into(),
+                        });
                     }
 
-                    response
+                    with_usage_footer(completion)
                 } else {
                     return Ok(false);
                 }
             }
+            "retry" => {
+                let turn = self.turn_lock(channel_id);
+                let _turn = turn.lock().await;
+
+                let history = self.conversations.read().await.get(&channel_id).cloned();
+                match history.as_deref().and_then(retry_history) {
+                    Some(messages) => {
+                        let completion = match self.backend.complete(messages).await {
+                            Ok(completion) => completion,
+                            Err(LlmError::Timeout) => {
+                                return reply(ctx, command, TIMEOUT_REPLY).await;
+                            }
+                            Err(e) => return Err(PluginError::ApiError(e.to_string())),
+                        };
+
+                        let mut conversations = self.conversations.write().await;
+                        if let Some(last) = conversations
+                            .get_mut(&channel_id)
+                            .and_then(|h| h.last_mut())
+                            .filter(|m| m.role == "assistant")
+                        {
+                            last.content = completion.text.clone().into();
+                        }
+                        with_usage_footer(completion)
+                    }
+                    None => "Nothing to retry in this channel.".into(),
+                }
+            }
             "status" => {
                 let healthy = self
                     .backend
@@ -169,8 +376,9 @@ impl Plugin for ClaudePlugin {
                                 "Conversation started. Use `/claude ask` to chat. Use `/claude conversation end` to finish.".into()
                             }
                             "end" => {
-                                let mut conversations = self.conversations.write().await;
-                                if conversations.remove(&channel_id).is_some() {
+                                let removed = self.conversations.write().await.remove(&channel_id);
+                                if removed.is_some() {
+                                    self.persist().await;
                                     "Conversation ended.".into()
                                 } else {
                                     "No active conversation in this channel.".into()
@@ -188,24 +396,25 @@ impl Plugin for ClaudePlugin {
             _ => return Ok(false),
         };
 
-        let chunks = chunk_message(&content, DISCORD_MAX_LEN);
-        let first = chunks.first().cloned().unwrap_or_default();
-
-        let data = CreateInteractionResponseMessage::new().content(&first);
-        let builder = CreateInteractionResponse::Message(data);
-        command
-            .create_response(&ctx.http, builder)
-            .await
-            .map_err(PluginError::DiscordError)?;
-
-        for chunk in chunks.iter().skip(1) {
-            command
-                .create_followup(&ctx.http, CreateInteractionResponseFollowup::new().content(chunk))
-                .await
-                .map_err(PluginError::DiscordError)?;
+        if as_file && exceeds_inline_limit(&content) {
+            ack::respond_reply(ctx, command, inline_or_attachment("", &content, "answer.md")).await?;
+            return Ok(true);
         }
 
-        Ok(true)
+        reply(ctx, command, &content).await
+    }
+
+    async fn clear_state(&self) -> usize {
+        let removed = {
+            let mut conversations = self.conversations.write().await;
+            let removed = conversations.len();
+            conversations.clear();
+            removed
+        };
+        if removed > 0 {
+            self.persist().await;
+        }
+        removed
     }
 }
 
@@ -214,36 +423,96 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_chunk_message_short() {
-        let chunks = chunk_message("Hello", 2000);
-        assert_eq!(chunks, vec!["Hello"]);
+    fn test_retry_history_drops_last_answer() {
+        let history = vec![
+            Message { role: "user".into(), content: "Hi".into() },
+            Message { role: "assistant".into(), content: "Bad answer".into() },
+        ];
+        let retry = retry_history(&history).unwrap();
+        assert_eq!(retry.len(), 1);
+        assert_eq!(retry[0].role, "user");
+    }
+
+    #[test]
+    fn test_retry_history_nothing_to_retry() {
+        assert!(retry_history(&[]).is_none());
+        let history = vec![Message { role: "user".into(), content: "Hi".into() }];
+        assert!(retry_history(&history).is_none());
+    }
+
+    #[test]
+    fn test_conversations_round_trip() {
+        let mut conversations: Conversations = HashMap::new();
+        conversations.insert(
+            1234567890,
+            vec![
+                Message { role: "user".into(), content: "Hi".into() },
+                Message { role: "assistant".into(), content: "Hello!".into() },
+            ],
+        );
+        conversations.insert(42, Vec::new());
+
+        let json = serde_json::to_string(&conversations).unwrap();
+        let parsed = parse_conversations(&json).unwrap();
+        assert_eq!(parsed.len(), 2);
+        let history = &parsed[&1234567890];
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].role, "assistant");
+        assert_eq!(history[1].content, Content::from("Hello!"));
+        assert!(parsed[&42].is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_save_keeps_previous_conversations() {
+        let path = std::env::temp_dir().join(format!("discord-assist-conversations-{}.json", std::process::id()));
+        let tmp = path.with_extension("json.tmp");
+        let mut conversations: Conversations = HashMap::new();
+        conversations.insert(7, vec![Message { role: "user".into(), content: "Hi".into() }]);
+        save_conversations(&path, &conversations).await;
+        assert_eq!(load_conversations(&path).len(), 1);
+        assert!(!tmp.exists());
+
+        // A partial write left behind by an earlier crash doesn't touch the saved file
+        std::fs::write(&tmp, "{\"7\": [{\"role\"").unwrap();
+        assert_eq!(load_conversations(&path)[&7].len(), 1);
+
+        // A save that can't write its temp file leaves the previous history intact
+        std::fs::remove_file(&tmp).unwrap();
+        std::fs::create_dir(&tmp).unwrap();
+        conversations.clear();
+        save_conversations(&path, &conversations).await;
+        assert_eq!(load_conversations(&path)[&7].len(), 1);
+
+        std::fs::remove_dir(&tmp).unwrap();
+        std::fs::remove_file(&path).unwrap();
     }
 
     #[test]
-    fn test_chunk_message_long() {
-        let long = "a".repeat(3000);
-        let chunks = chunk_message(&long, 2000);
-        assert_eq!(chunks.len(), 2);
-        assert_eq!(chunks[0].len(), 2000);
-        assert_eq!(chunks[1].len(), 1000);
+    fn test_check_image() {
+        const MB: u64 = 1024 * 1024;
+        assert_eq!(check_image(Some("image/png"), MB, 5 * MB), Ok("image/png"));
+        assert_eq!(check_image(Some("image/jpeg; charset=binary"), MB, 5 * MB), Ok("image/jpeg"));
+        assert!(check_image(Some("application/pdf"), MB, 5 * MB).unwrap_err().contains("PNG"));
+        assert!(check_image(None, MB, 5 * MB).is_err());
+        assert_eq!(
+            check_image(Some("image/webp"), 6 * MB, 5 * MB).unwrap_err(),
+            "That image is 6.0 MB; the limit is 5 MB."
+        );
     }
 
     #[test]
-    fn test_chunk_message_splits_at_newline() {
-        let text = format!("{}\n{}", "a".repeat(1500), "b".repeat(1000));
-        let chunks = chunk_message(&text, 2000);
-        assert_eq!(chunks.len(), 2);
-        assert_eq!(chunks[0], "a".repeat(1500));
-        assert_eq!(chunks[1], "b".repeat(1000));
+    fn test_usage_footer() {
+        let completion = Completion {
+            text: "Answer".into(),
+            prompt_tokens: Some(100),
+            completion_tokens: Some(23),
+        };
+        assert_eq!(with_usage_footer(completion), "Answer\n-# (123 tokens)");
+        assert_eq!(with_usage_footer(Completion { text: "Answer".into(), ..Default::default() }), "Answer");
     }
 
     #[test]
-    fn test_chunk_message_multibyte() {
-        // Each emoji is 4 bytes but 1 character
-        let text: String = std::iter::repeat('🦀').take(2500).collect();
-        let chunks = chunk_message(&text, 2000);
-        assert_eq!(chunks.len(), 2);
-        assert_eq!(chunks[0].chars().count(), 2000);
-        assert_eq!(chunks[1].chars().count(), 500);
+    fn test_parse_conversations_corrupt() {
+        assert!(parse_conversations("{not json").is_err());
     }
 }
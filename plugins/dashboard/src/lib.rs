@@ -0,0 +1,219 @@
+use async_trait::async_trait;
+use discord_assist_arr_common::ArrClient;
+use discord_assist_plex::PlexClient;
+use discord_assist_plugin_api::{Plugin, PluginError, ack, edit_message, list_embed};
+use discord_assist_qbit::QbitClient;
+use discord_assist_unraid::api::UnraidApi;
+use discord_assist_util::size;
+use serenity::builder::{CreateCommand, CreateEmbed};
+use serenity::model::application::CommandInteraction;
+use serenity::prelude::Context;
+use std::fmt::Display;
+use tracing::warn;
+
+/// Shown in place of a section whose query failed.
+const UNAVAILABLE: &str = "unavailable";
+
+/// The services `/dashboard` can summarise; each is optional.
+#[derive(Default)]
+pub struct DashboardClients {
+    pub unraid: Option<UnraidApi>,
+    pub plex: Option<PlexClient>,
+    pub qbit: Option<QbitClient>,
+    pub sonarr: Option<ArrClient>,
+    pub radarr: Option<ArrClient>,
+}
+
+impl DashboardClients {
+    pub fn is_empty(&self) -> bool {
+        self.unraid.is_none()
+            && self.plex.is_none()
+            && self.qbit.is_none()
+            && self.sonarr.is_none()
+            && self.radarr.is_none()
+    }
+}
+
+/// `/dashboard`: one embed summarising every configured service.
+pub struct DashboardPlugin {
+    clients: DashboardClients,
+}
+
+/// One service's line on the dashboard, or why it couldn't be fetched.
+type Section = (&'static str, Result<String, String>);
+
+impl DashboardPlugin {
+    pub fn with_clients(clients: DashboardClients) -> Self {
+        Self { clients }
+    }
+
+    /// Query every configured service at once. Unconfigured ones are left out.
+    async fn sections(&self) -> Vec<Section> {
+        let c = &self.clients;
+        let (unraid, plex, qbit, sonarr, radarr) = tokio::join!(
+            query(c.unraid.as_ref(), async |api: &UnraidApi| {
+                api.get_array_status().await.map(|a| format!("Array: {}", a.state))
+            }),
+            query(c.plex.as_ref(), async |plex: &PlexClient| {
+                plex.session_count().await.map(streams)
+            }),
+            query(c.qbit.as_ref(), async |qbit: &QbitClient| {
+                qbit.transfer_info().await.map(|info| {
+                    let unit = size::unit();
+                    format!(
+                        "↓ {} · ↑ {}",
+                        size::format_speed(info.dl_info_speed.unwrap_or(0), unit),
+                        size::format_speed(info.up_info_speed.unwrap_or(0), unit)
+                    )
+                })
+            }),
+            query(c.sonarr.as_ref(), async |arr: &ArrClient| arr.queue_count().await.map(queue)),
+            query(c.radarr.as_ref(), async |arr: &ArrClient| arr.queue_count().await.map(queue)),
+        );
+        [
+            ("Unraid", unraid),
+            ("Plex", plex),
+            ("qBittorrent", qbit),
+            ("Sonarr", sonarr),
+            ("Radarr", radarr),
+        ]
+        .into_iter()
+        .filter_map(|(name, result)| result.map(|r| (name, r)))
+        .collect()
+    }
+}
+
+/// Run `f` against `client` if it's configured, logging a failure.
+async fn query<C, T: Display>(
+    client: Option<&C>,
+    f: impl AsyncFnOnce(&C) -> Result<String, T>,
+) -> Option<Result<String, String>> {
+    let result = f(client?).await.map_err(|e| e.to_string());
+    if let Err(ref e) = result {
+        warn!("Dashboard query failed: {e}");
+    }
+    Some(result)
+}
+
+fn streams(count: usize) -> String {
+    match count {
+        0 => "No active streams".into(),
+        1 => "1 active stream".into(),
+        n => format!("{n} active streams"),
+    }
+}
+
+fn queue(count: u32) -> String {
+    match count {
+        1 => "Queue: 1 item".into(),
+        n => format!("Queue: {n} items"),
+    }
+}
+
+/// The dashboard embed: a field per service, failed ones marked unavailable.
+fn compose(sections: &[Section]) -> CreateEmbed {
+    let fields: Vec<(String, String)> = sections
+        .iter()
+        .map(|(name, result)| {
+            let value = match result {
+                Ok(summary) => summary.clone(),
+                Err(_) => UNAVAILABLE.to_string(),
+            };
+            (name.to_string(), value)
+        })
+        .collect();
+    let embed = list_embed("Dashboard", &fields);
+    if fields.is_empty() {
+        embed.description("No services configured.")
+    } else {
+        embed
+    }
+}
+
+#[async_trait]
+impl Plugin for DashboardPlugin {
+    fn name(&self) -> &str {
+        "dashboard"
+    }
+
+    fn register_commands(&self) -> Vec<CreateCommand> {
+        vec![CreateCommand::new("dashboard").description("Summarise every configured service")]
+    }
+
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        path == ["dashboard"]
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![("/dashboard".into(), "Summarise every configured service".into())]
+    }
+
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+    ) -> Result<bool, PluginError> {
+        if command.data.name != "dashboard" {
+            return Ok(false);
+        }
+
+        // A slow service shouldn't time out the interaction.
+        ack::defer(ctx, command).await?;
+        let embed = compose(&self.sections().await);
+        command
+            .edit_response(&ctx.http, edit_message().embed(embed))
+            .await
+            .map_err(PluginError::DiscordError)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(embed: CreateEmbed) -> Vec<(String, String)> {
+        let json = serde_json::to_value(embed).unwrap();
+        json["fields"]
+            .as_array()
+            .map(|fields| {
+                fields
+                    .iter()
+                    .map(|f| (f["name"].as_str().unwrap().into(), f["value"].as_str().unwrap().into()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn compose_marks_failed_sections_unavailable() {
+        let sections = vec![
+            ("Unraid", Ok("Array: STARTED".to_string())),
+            ("Plex", Err("connection refused".to_string())),
+            ("Sonarr", Ok(queue(3))),
+        ];
+        assert_eq!(
+            fields(compose(&sections)),
+            vec![
+                ("Unraid".to_string(), "Array: STARTED".to_string()),
+                ("Plex".to_string(), "unavailable".to_string()),
+                ("Sonarr".to_string(), "Queue: 3 items".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn compose_with_nothing_configured() {
+        let json = serde_json::to_value(compose(&[])).unwrap();
+        assert_eq!(json["description"], "No services configured.");
+    }
+
+    #[test]
+    fn summaries_pluralise() {
+        assert_eq!(streams(0), "No active streams");
+        assert_eq!(streams(1), "1 active stream");
+        assert_eq!(streams(2), "2 active streams");
+        assert_eq!(queue(1), "Queue: 1 item");
+        assert_eq!(queue(0), "Queue: 0 items");
+    }
+}
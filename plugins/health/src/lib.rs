@@ -1,79 +1,234 @@
 use async_trait::async_trait;
-use discord_assist_plugin_api::{Plugin, PluginError};
-use reqwest::Client;
-use serenity::builder::{
-    CreateCommand, CreateInteractionResponse, CreateInteractionResponseMessage,
-};
+use discord_assist_plugin_api::{Plugin, PluginError, respond_chunked};
+use discord_assist_util::http::SharedClients;
+use reqwest::{Client, Method};
+use serenity::builder::CreateCommand;
 use serenity::model::application::CommandInteraction;
 use serenity::prelude::Context;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
+use tracing::warn;
 
+#[derive(Clone)]
 pub struct ServiceTarget {
     pub name: String,
     pub url: String,
     pub api_key: Option<String>,
     pub key_header: Option<String>,
+    /// Successful responses slower than this are reported as SLOW.
+    pub warn_ms: Option<u64>,
+    /// If set, the response must have exactly this status (instead of any 2xx).
+    pub expect_status: Option<u16>,
+    /// If set, the response body must contain this substring.
+    pub expect_body_contains: Option<String>,
+    /// HTTP method to use: GET (default), HEAD, or POST.
+    pub method: Option<String>,
+    /// Form-encoded request body, e.g. login credentials for POST checks.
+    pub body: Option<String>,
+    /// Skip TLS certificate verification (self-signed certs).
+    pub accept_invalid_certs: bool,
 }
 
-pub struct HealthPlugin {
-    services: Vec<ServiceTarget>,
-    client: Client,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceState {
+    Up,
+    Slow,
+    Down,
 }
 
-impl HealthPlugin {
+impl ServiceState {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Up => "UP",
+            Self::Slow => "SLOW ⚠",
+            Self::Down => "DOWN",
+        }
+    }
+}
+
+/// Result of a single service check.
+#[derive(Debug, Clone)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub state: ServiceState,
+    /// Short detail shown after the state, e.g. "123ms" or "HTTP 503".
+    pub detail: String,
+}
+
+impl ServiceStatus {
+    /// SLOW services are still reachable, so they count as up.
+    pub fn is_up(&self) -> bool {
+        self.state != ServiceState::Down
+    }
+
+    fn render(&self) -> String {
+        format!("- {}: [{}] ({})", self.name, self.state.label(), self.detail)
+    }
+}
+
+/// Services that take longer than this to answer are DOWN.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Map a check result to a state: failed is DOWN, passed but over `warn_ms` is SLOW.
+fn classify(passed: bool, elapsed_ms: u128, warn_ms: Option<u64>) -> ServiceState {
+    if !passed {
+        ServiceState::Down
+    } else if warn_ms.is_some_and(|warn| elapsed_ms > u128::from(warn)) {
+        ServiceState::Slow
+    } else {
+        ServiceState::Up
+    }
+}
+
+/// Runs the configured service checks. Shared by the `/health` command and the
+/// background health alerts poller. The service list can be swapped at runtime
+/// (config reload) without rebuilding either.
+pub struct HealthChecker {
+    services: RwLock<Vec<ServiceTarget>>,
+    clients: SharedClients,
+}
+
+impl HealthChecker {
     pub fn new(services: Vec<ServiceTarget>) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(5))
-            .danger_accept_invalid_certs(true)
-            .build()
-            .expect("Failed to build HTTP client");
-        Self { services, client }
+        Self::with_clients(services, SharedClients::default())
     }
 
-    async fn check_all(&self) -> String {
-        let mut lines = vec![String::from("**Service Health**")];
+    /// Check through the shared client pool instead of building new clients.
+    pub fn with_clients(services: Vec<ServiceTarget>, clients: SharedClients) -> Self {
+        warn_unknown_methods(&services);
+        Self {
+            services: RwLock::new(services),
+            clients,
+        }
+    }
+
+    /// The currently configured services.
+    pub fn services(&self) -> Vec<ServiceTarget> {
+        self.services.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
 
+    /// Replace the service list; the next `check` uses it.
+    pub fn set_services(&self, services: Vec<ServiceTarget>) {
+        warn_unknown_methods(&services);
+        *self.services.write().unwrap_or_else(|e| e.into_inner()) = services;
+    }
+
+    /// Check every service concurrently, returning results in configured order.
+    pub async fn check(&self) -> Vec<ServiceStatus> {
         let mut handles = Vec::new();
-        for svc in &self.services {
-            let client = self.client.clone();
-            let name = svc.name.clone();
-            let url = svc.url.clone();
-            let api_key = svc.api_key.clone();
-            let key_header = svc.key_header.clone();
-
-            handles.push(tokio::spawn(async move {
-                let start = std::time::Instant::now();
-                let mut req = client.get(&url);
-                if let (Some(key), Some(header)) = (&api_key, &key_header) {
-                    req = req.header(header.as_str(), key.as_str());
-                }
-                let result = req.send().await;
-                let elapsed = start.elapsed();
-                let ms = elapsed.as_millis();
-
-                match result {
-                    Ok(resp) if resp.status().is_success() => {
-                        format!("- {name}: [UP] ({ms}ms)")
-                    }
-                    Ok(resp) => {
-                        format!("- {name}: [DOWN] (HTTP {})", resp.status().as_u16())
-                    }
-                    Err(e) if e.is_timeout() => {
-                        format!("- {name}: [DOWN] (timeout)")
-                    }
-                    Err(_) => {
-                        format!("- {name}: [DOWN] (connection error)")
-                    }
-                }
-            }));
+        for svc in self.services() {
+            let client = self.clients.get(svc.accept_invalid_certs);
+            handles.push(tokio::spawn(async move { check_service(&client, &svc).await }));
         }
 
+        let mut statuses = Vec::new();
         for handle in handles {
-            if let Ok(line) = handle.await {
-                lines.push(line);
+            if let Ok(status) = handle.await {
+                statuses.push(status);
             }
         }
+        statuses
+    }
+}
+
+fn warn_unknown_methods(services: &[ServiceTarget]) {
+    for svc in services {
+        if let Some(ref m) = svc.method
+            && parse_method(Some(m)).is_none()
+        {
+            warn!("Health check '{}': unknown method '{m}', using GET", svc.name);
+        }
+    }
+}
+
+async fn check_service(client: &Client, svc: &ServiceTarget) -> ServiceStatus {
+    let start = std::time::Instant::now();
+    let method = parse_method(svc.method.as_deref()).unwrap_or(Method::GET);
+    let mut req = client.request(method, &svc.url).timeout(CHECK_TIMEOUT);
+    if let (Some(key), Some(header)) = (&svc.api_key, &svc.key_header) {
+        req = req.header(header.as_str(), key.as_str());
+    }
+    if let Some(ref body) = svc.body {
+        req = req
+            .header(reqwest::header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body.clone());
+    }
+    let result = req.send().await;
+    let ms = start.elapsed().as_millis();
+
+    let (state, detail) = match result {
+        Ok(resp) => {
+            let status = resp.status().as_u16();
+            let body = match svc.expect_body_contains {
+                Some(_) => resp.text().await.ok(),
+                None => None,
+            };
+            let failure = unmet_expectation(svc, status, body.as_deref());
+            match classify(failure.is_none(), ms, svc.warn_ms) {
+                ServiceState::Up => (ServiceState::Up, format!("{ms}ms")),
+                ServiceState::Slow => (
+                    ServiceState::Slow,
+                    format!("{ms}ms, over {}ms", svc.warn_ms.unwrap_or_default()),
+                ),
+                ServiceState::Down => (ServiceState::Down, failure.unwrap_or_default()),
+            }
+        }
+        Err(e) if e.is_timeout() => (ServiceState::Down, "timeout".into()),
+        Err(_) => (ServiceState::Down, "connection error".into()),
+    };
+
+    ServiceStatus {
+        name: svc.name.clone(),
+        state,
+        detail,
+    }
+}
+
+/// Parse a configured check method. `None` means GET; unsupported verbs return `None`.
+fn parse_method(method: Option<&str>) -> Option<Method> {
+    match method.map(str::to_ascii_uppercase).as_deref() {
+        None | Some("GET") => Some(Method::GET),
+        Some("HEAD") => Some(Method::HEAD),
+        Some("POST") => Some(Method::POST),
+        Some(_) => None,
+    }
+}
+
+/// Describe why a response fails the service's assertions, or `None` if it passes.
+/// Without `expect_status`, any 2xx status passes.
+fn unmet_expectation(svc: &ServiceTarget, status: u16, body: Option<&str>) -> Option<String> {
+    match svc.expect_status {
+        Some(expected) if status != expected => {
+            return Some(format!("HTTP {status}, expected {expected}"));
+        }
+        None if !(200..300).contains(&status) => return Some(format!("HTTP {status}")),
+        _ => {}
+    }
+    if let Some(ref needle) = svc.expect_body_contains
+        && !body.is_some_and(|b| b.contains(needle.as_str()))
+    {
+        return Some("unexpected response body".into());
+    }
+    None
+}
+
+pub struct HealthPlugin {
+    checker: Arc<HealthChecker>,
+}
+
+impl HealthPlugin {
+    pub fn new(services: Vec<ServiceTarget>) -> Self {
+        Self::with_checker(Arc::new(HealthChecker::new(services)))
+    }
 
+    /// Use a checker shared with other components (alerts poller, config reload).
+    pub fn with_checker(checker: Arc<HealthChecker>) -> Self {
+        Self { checker }
+    }
+
+    async fn check_all(&self) -> String {
+        let mut lines = vec![String::from("**Service Health**")];
+        lines.extend(self.checker.check().await.iter().map(ServiceStatus::render));
         lines.join("\n")
     }
 }
@@ -88,6 +243,16 @@ impl Plugin for HealthPlugin {
         vec![CreateCommand::new("health").description("Check health of all configured services")]
     }
 
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        path == ["health"]
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![
+            ("/health".into(), "Check health of all configured services".into()),
+        ]
+    }
+
     async fn handle_command(
         &self,
         ctx: &Context,
@@ -98,12 +263,122 @@ impl Plugin for HealthPlugin {
         }
 
         let content = self.check_all().await;
-        let data = CreateInteractionResponseMessage::new().content(content);
-        let builder = CreateInteractionResponse::Message(data);
-        command
-            .create_response(&ctx.http, builder)
-            .await
-            .map_err(PluginError::DiscordError)?;
+        respond_chunked(ctx, command, &content).await?;
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn target(url: &str) -> ServiceTarget {
+        ServiceTarget {
+            name: "App".into(),
+            url: url.into(),
+            api_key: None,
+            key_header: None,
+            warn_ms: None,
+            expect_status: None,
+            expect_body_contains: None,
+            method: None,
+            body: None,
+            accept_invalid_certs: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_method() {
+        assert_eq!(parse_method(None), Some(Method::GET));
+        assert_eq!(parse_method(Some("head")), Some(Method::HEAD));
+        assert_eq!(parse_method(Some("POST")), Some(Method::POST));
+        assert_eq!(parse_method(Some("DELETE")), None);
+        assert_eq!(parse_method(Some("bogus")), None);
+    }
+
+    #[test]
+    fn test_classify_up() {
+        assert_eq!(classify(true, 120, None), ServiceState::Up);
+        assert_eq!(classify(true, 120, Some(500)), ServiceState::Up);
+        assert_eq!(classify(true, 500, Some(500)), ServiceState::Up);
+    }
+
+    #[test]
+    fn test_classify_slow() {
+        assert_eq!(classify(true, 1234, Some(500)), ServiceState::Slow);
+    }
+
+    #[test]
+    fn test_classify_down() {
+        assert_eq!(classify(false, 20, None), ServiceState::Down);
+        assert_eq!(classify(false, 2000, Some(500)), ServiceState::Down);
+    }
+
+    #[test]
+    fn test_unmet_expectation_status() {
+        let mut svc = target("http://app");
+        assert_eq!(unmet_expectation(&svc, 200, None), None);
+        assert_eq!(unmet_expectation(&svc, 503, None).as_deref(), Some("HTTP 503"));
+
+        svc.expect_status = Some(401);
+        assert_eq!(unmet_expectation(&svc, 401, None), None);
+        assert_eq!(
+            unmet_expectation(&svc, 200, None).as_deref(),
+            Some("HTTP 200, expected 401")
+        );
+    }
+
+    #[test]
+    fn test_unmet_expectation_body() {
+        let mut svc = target("http://app");
+        svc.expect_body_contains = Some("\"status\":\"ok\"".into());
+        assert_eq!(unmet_expectation(&svc, 200, Some(r#"{"status":"ok"}"#)), None);
+        assert!(unmet_expectation(&svc, 200, Some("<html>Login</html>")).is_some());
+        assert!(unmet_expectation(&svc, 200, None).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_wrong_body_reported_down() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>Login</html>"))
+            .mount(&mock_server)
+            .await;
+
+        let mut svc = target(&mock_server.uri());
+        svc.expect_body_contains = Some("\"status\":\"ok\"".into());
+        let statuses = HealthChecker::new(vec![svc]).check().await;
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].state, ServiceState::Down);
+        assert_eq!(statuses[0].detail, "unexpected response body");
+    }
+
+    #[tokio::test]
+    async fn test_head_method_issues_head_request() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut svc = target(&mock_server.uri());
+        svc.method = Some("HEAD".into());
+        let statuses = HealthChecker::new(vec![svc]).check().await;
+        assert_eq!(statuses[0].state, ServiceState::Up);
+    }
+
+    #[test]
+    fn test_render_slow() {
+        let status = ServiceStatus {
+            name: "Plex".into(),
+            state: ServiceState::Slow,
+            detail: "1234ms, over 500ms".into(),
+        };
+        assert_eq!(status.render(), "- Plex: [SLOW ⚠] (1234ms, over 500ms)");
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Weight of a title token relative to one occurrence in the body, so
+/// notes named after the query rank above ones that merely mention it.
+const TITLE_WEIGHT: u32 = 10;
+
+/// Inverted index over the vault: token → notes containing it, with how
+/// often it occurs in each.
+pub struct NoteIndex {
+    postings: HashMap<String, HashMap<PathBuf, u32>>,
+    /// Tokens each note was indexed under, so it can be re-indexed.
+    tokens: HashMap<PathBuf, Vec<String>>,
+    built_at: Instant,
+}
+
+impl Default for NoteIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NoteIndex {
+    pub fn new() -> Self {
+        Self {
+            postings: HashMap::new(),
+            tokens: HashMap::new(),
+            built_at: Instant::now(),
+        }
+    }
+
+    /// Number of indexed notes.
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Whether the index is older than `ttl` and should be rebuilt to pick
+    /// up edits made outside the bot.
+    pub fn is_stale(&self, ttl: Duration) -> bool {
+        self.built_at.elapsed() > ttl
+    }
+
+    /// Index `path` under the tokens of its `title` and `body`, replacing
+    /// whatever it was indexed under before.
+    pub fn insert(&mut self, path: PathBuf, title: &str, body: &str) {
+        self.remove(&path);
+
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        for token in tokenize(title) {
+            *counts.entry(token).or_default() += TITLE_WEIGHT;
+        }
+        for token in tokenize(body) {
+            *counts.entry(token).or_default() += 1;
+        }
+
+        let mut tokens = Vec::with_capacity(counts.len());
+        for (token, count) in counts {
+            self.postings
+                .entry(token.clone())
+                .or_default()
+                .insert(path.clone(), count);
+            tokens.push(token);
+        }
+        self.tokens.insert(path, tokens);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        let Some(tokens) = self.tokens.remove(path) else {
+            return;
+        };
+        for token in tokens {
+            if let Some(posting) = self.postings.get_mut(&token) {
+                posting.remove(path);
+                if posting.is_empty() {
+                    self.postings.remove(&token);
+                }
+            }
+        }
+    }
+
+    /// Notes containing every token of `query`, best first: ranked by the
+    /// summed occurrence counts, ties broken by path. At most `limit`.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<PathBuf> {
+        let mut tokens: Vec<String> = tokenize(query).collect();
+        tokens.sort();
+        tokens.dedup();
+        if tokens.is_empty() {
+            return Vec::new();
+        }
+
+        let mut lists = Vec::with_capacity(tokens.len());
+        for token in &tokens {
+            match self.postings.get(token) {
+                Some(posting) => lists.push(posting),
+                None => return Vec::new(),
+            }
+        }
+        // Intersect starting from the shortest posting list.
+        lists.sort_by_key(|posting| posting.len());
+        let (first, rest) = lists.split_first().expect("at least one token");
+
+        let mut hits: Vec<(&PathBuf, u32)> = first
+            .iter()
+            .filter_map(|(path, &count)| {
+                let mut score = count;
+                for posting in rest {
+                    score += posting.get(path)?;
+                }
+                Some((path, score))
+            })
+            .collect();
+        hits.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        hits.into_iter()
+            .take(limit)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+}
+
+/// Lowercased alphanumeric runs of `text`.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpus() -> NoteIndex {
+        let mut index = NoteIndex::new();
+        index.insert("groceries.md".into(), "groceries", "Milk, eggs and bread.");
+        index.insert(
+            "meeting.md".into(),
+            "meeting",
+            "Discussed the grocery budget. Budget review next week; budget approved.",
+        );
+        index.insert("budget.md".into(), "budget", "Yearly numbers.");
+        index
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<_> = tokenize("Hello, World! déjà-vu 2024").collect();
+        assert_eq!(tokens, ["hello", "world", "déjà", "vu", "2024"]);
+    }
+
+    #[test]
+    fn test_search_ranks_by_frequency() {
+        let index = corpus();
+        assert_eq!(index.len(), 3);
+        // The title hit outweighs three body mentions.
+        assert_eq!(
+            index.search("Budget", 10),
+            [PathBuf::from("budget.md"), PathBuf::from("meeting.md")]
+        );
+        assert_eq!(index.search("budget", 1), [PathBuf::from("budget.md")]);
+        assert!(index.search("nothing", 10).is_empty());
+        assert!(index.search("  ", 10).is_empty());
+    }
+
+    #[test]
+    fn test_search_intersects_tokens() {
+        let index = corpus();
+        assert_eq!(index.search("budget review", 10), [PathBuf::from("meeting.md")]);
+        assert!(index.search("budget eggs", 10).is_empty());
+    }
+
+    #[test]
+    fn test_insert_replaces_and_remove_drops() {
+        let mut index = corpus();
+        index.insert("groceries.md".into(), "groceries", "Apples only.");
+        assert!(index.search("eggs", 10).is_empty());
+        assert_eq!(index.search("apples", 10), [PathBuf::from("groceries.md")]);
+
+        index.remove(Path::new("groceries.md"));
+        assert!(index.search("apples", 10).is_empty());
+        assert!(!index.postings.contains_key("apples"));
+        assert_eq!(index.len(), 2);
+    }
+}
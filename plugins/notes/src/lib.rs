@@ -1,130 +1,267 @@
+pub mod index;
+pub mod query;
+
 use async_trait::async_trait;
-use discord_assist_plugin_api::{Plugin, PluginError};
-use serenity::builder::{
-    CreateCommand, CreateCommandOption, CreateInteractionResponse,
-    CreateInteractionResponseMessage,
-};
+use index::{NoteIndex, tokenize};
+use query::Query;
+use discord_assist_plugin_api::{Plugin, PluginError, Reply, ack, inline_or_attachment, response_message};
+use discord_assist_util::{discord_relative, escape_discord, now_secs, today_iso};
+use serenity::builder::{CreateCommand, CreateCommandOption};
 use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
 use serenity::prelude::Context;
+use std::collections::{BTreeMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
+use tokio::sync::RwLock;
+
+/// Characters of context on each side of a search match by default.
+pub const DEFAULT_SNIPPET_RADIUS: usize = 30;
+
+/// Where `/notes daily` keeps journal notes by default.
+pub const DEFAULT_DAILY_FOLDER: &str = "Daily";
+
+/// How many folders deep the vault walk goes by default.
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// The search index is rebuilt from disk once it's this old, picking up
+/// edits made outside the bot.
+const INDEX_TTL: Duration = Duration::from_secs(300);
+
+/// Notes larger than this aren't searched.
+const MAX_NOTE_BYTES: u64 = 1_048_576;
 
 pub struct NotesPlugin {
     vault_path: PathBuf,
+    /// Characters of context shown on each side of a content match.
+    snippet_radius: usize,
+    /// Glob patterns for vault paths to leave out; see [`should_ignore`].
+    ignore: Vec<String>,
+    /// Folders nested deeper than this below the vault root aren't walked.
+    max_depth: usize,
+    /// Folder, relative to the vault, holding `YYYY-MM-DD.md` daily notes.
+    daily_folder: String,
+    /// Built by the first search, refreshed when the bot writes a note.
+    index: RwLock<Option<NoteIndex>>,
 }
 
 impl NotesPlugin {
     pub fn new(vault_path: &str) -> Self {
         Self {
             vault_path: PathBuf::from(vault_path),
+            snippet_radius: DEFAULT_SNIPPET_RADIUS,
+            ignore: Vec::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            daily_folder: DEFAULT_DAILY_FOLDER.to_string(),
+            index: RwLock::new(None),
         }
     }
 
+    pub fn with_daily_folder(mut self, folder: &str) -> Self {
+        self.daily_folder = folder.to_string();
+        self
+    }
+
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    pub fn with_ignore(mut self, globs: Vec<String>) -> Self {
+        self.ignore = globs;
+        self
+    }
+
+    pub fn with_snippet_radius(mut self, radius: usize) -> Self {
+        self.snippet_radius = radius;
+        self
+    }
+
+    /// Plain word queries go through the index; ones with phrases or
+    /// filters (see [`Query`]) are checked against every note.
     async fn handle_search(&self, query: &str) -> Result<String, PluginError> {
-        let files = walk_md_files(&self.vault_path).await?;
-        let query_lower = query.to_lowercase();
+        let parsed = Query::parse(query);
+        let results = if parsed.is_plain() {
+            self.plain_matches(&query.to_lowercase()).await?
+        } else {
+            self.query_matches(&parsed).await?
+        };
+
+        if results.is_empty() {
+            Ok(format!("No notes matching \"{}\".", escape_discord(query)))
+        } else {
+            Ok(format!(
+                "**Search: {}** ({} results)\n{}",
+                escape_discord(query),
+                results.len(),
+                results.join("\n")
+            ))
+        }
+    }
+
+    async fn plain_matches(&self, query_lower: &str) -> Result<Vec<String>, PluginError> {
         let mut results = Vec::new();
+        let first_token = tokenize(query_lower).next();
+        for path in self.indexed_search(query_lower, 10).await? {
+            let mut needles = vec![query_lower];
+            needles.extend(first_token.as_deref());
+            let line = match self.result_line(&path, &needles).await {
+                Some(line) => line,
+                None => format!("- **{}**", self.relative(&path).display()),
+            };
+            results.push(line);
+        }
+
+        // Substrings within a word ("meet" in "meeting") aren't in the index.
+        if results.is_empty() {
+            for path in walk_md_files(&self.vault_path, &self.ignore, self.max_depth).await? {
+                if results.len() >= 10 {
+                    break;
+                }
+                if let Some(line) = self.result_line(&path, &[query_lower]).await {
+                    results.push(line);
+                }
+            }
+        }
+        Ok(results)
+    }
 
-        for path in &files {
+    async fn query_matches(&self, query: &Query) -> Result<Vec<String>, PluginError> {
+        // Walked paths are canonical, and `path:` compares against the
+        // path relative to the vault.
+        let canonical_vault = tokio::fs::canonicalize(&self.vault_path)
+            .await
+            .map_err(|e| PluginError::Other(format!("Vault path error: {e}")))?;
+        let mut results = Vec::new();
+        for path in walk_md_files(&self.vault_path, &self.ignore, self.max_depth).await? {
             if results.len() >= 10 {
                 break;
             }
-
-            let rel = path.strip_prefix(&self.vault_path).unwrap_or(path);
-            let stem = path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("");
-
-            if stem.to_lowercase().contains(&query_lower) {
-                results.push(format!("- **{}**", rel.display()));
+            let Some(content) = read_note(&path).await else {
                 continue;
-            }
-
-            let meta = match tokio::fs::metadata(path).await {
-                Ok(m) => m,
-                Err(_) => continue,
             };
-            if meta.len() > 1_048_576 {
+            let rel = path.strip_prefix(&canonical_vault).unwrap_or(&path);
+            if !query.matches(rel, &content) {
                 continue;
             }
+            let snippet = query
+                .needle()
+                .and_then(|needle| snippet(&content, needle, self.snippet_radius));
+            results.push(match snippet {
+                Some(snippet) => format!("- **{}**: ...{}...", rel.display(), escape_discord(&snippet)),
+                None => format!("- **{}**", rel.display()),
+            });
+        }
+        Ok(results)
+    }
 
-            let content = match tokio::fs::read_to_string(path).await {
-                Ok(c) => c,
-                Err(_) => continue,
-            };
+    /// Notes containing every word of `query_lower`, best first, building
+    /// the index if it's missing or stale.
+    async fn indexed_search(&self, query_lower: &str, limit: usize) -> Result<Vec<PathBuf>, PluginError> {
+        if let Some(index) = self.index.read().await.as_ref()
+            && !index.is_stale(INDEX_TTL)
+        {
+            return Ok(index.search(query_lower, limit));
+        }
 
-            if let Some(pos) = content.to_lowercase().find(&query_lower) {
-                let start = content[..pos]
-                    .char_indices()
-                    .rev()
-                    .nth(30)
-                    .map(|(i, _)| i)
-                    .unwrap_or(0);
-                let end = content[pos..]
-                    .char_indices()
-                    .nth(query.len() + 30)
-                    .map(|(i, _)| pos + i)
-                    .unwrap_or(content.len());
-                let snippet = content[start..end].replace('\n', " ");
-                results.push(format!(
-                    "- **{}**: ...{}...",
-                    rel.display(),
-                    escape_discord(&snippet)
-                ));
+        let mut index = self.index.write().await;
+        if index.as_ref().is_none_or(|index| index.is_stale(INDEX_TTL)) {
+            *index = Some(self.build_index().await?);
+        }
+        Ok(index
+            .as_ref()
+            .map(|index| index.search(query_lower, limit))
+            .unwrap_or_default())
+    }
+
+    async fn build_index(&self) -> Result<NoteIndex, PluginError> {
+        let mut index = NoteIndex::new();
+        for path in walk_md_files(&self.vault_path, &self.ignore, self.max_depth).await? {
+            if let Some(content) = read_note(&path).await {
+                let stem = note_stem(&path).to_string();
+                index.insert(path, &stem, &content);
             }
         }
+        Ok(index)
+    }
 
-        if results.is_empty() {
-            Ok(format!("No notes matching \"{}\".", escape_discord(query)))
-        } else {
-            Ok(format!(
-                "**Search: {}** ({} results)\n{}",
-                escape_discord(query),
-                results.len(),
-                results.join("\n")
-            ))
+    /// Re-index `path` after the bot wrote it. An index that hasn't been
+    /// built yet is left for the next search to build.
+    async fn reindex(&self, canonical_vault: &Path, path: &Path) {
+        let mut index = self.index.write().await;
+        let Some(index) = index.as_mut() else {
+            return;
+        };
+        let rel = path.strip_prefix(canonical_vault).unwrap_or(path);
+        if rel.ancestors().any(|a| !a.as_os_str().is_empty() && should_ignore(a, &self.ignore)) {
+            return;
+        }
+        if let Some(content) = read_note(path).await {
+            index.insert(path.to_path_buf(), note_stem(path), &content);
         }
     }
 
-    async fn handle_read(&self, name: &str) -> Result<String, PluginError> {
-        let name_lower = name.to_lowercase();
-        let files = walk_md_files(&self.vault_path).await?;
+    /// A search result line for `path`: just its name when that contains
+    /// one of `needles`, otherwise a snippet around the first needle found
+    /// in its content.
+    async fn result_line(&self, path: &Path, needles: &[&str]) -> Option<String> {
+        let rel = self.relative(path);
+        let stem = note_stem(path).to_lowercase();
+        if needles.iter().any(|needle| stem.contains(needle)) {
+            return Some(format!("- **{}**", rel.display()));
+        }
 
-        let found = files.iter().find(|p| {
-            p.file_stem()
-                .and_then(|s| s.to_str())
-                .map(|s| s.to_lowercase() == name_lower)
-                .unwrap_or(false)
-        });
+        let content = read_note(path).await?;
+        let snippet = needles
+            .iter()
+            .find_map(|needle| snippet(&content, needle, self.snippet_radius))?;
+        Some(format!("- **{}**: ...{}...", rel.display(), escape_discord(&snippet)))
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.vault_path).unwrap_or(path)
+    }
 
-        let path = match found {
+    /// The note named `name`, with its front-matter shown as a short header
+    /// unless `raw` asks for the file verbatim.
+    async fn handle_read(
+        &self,
+        name: &str,
+        raw: bool,
+    ) -> Result<Reply, PluginError> {
+        let path = match self.find_note(name).await? {
             Some(p) => p,
-            None => return Ok(format!("Note \"{}\" not found.", escape_discord(name))),
+            None => {
+                return Ok(text(format!(
+                    "Note \"{}\" not found.",
+                    escape_discord(name)
+                )));
+            }
         };
 
-        let content = tokio::fs::read_to_string(path)
+        let content = tokio::fs::read_to_string(&path)
             .await
             .map_err(|e| PluginError::Other(format!("Failed to read note: {e}")))?;
 
-        let rel = path.strip_prefix(&self.vault_path).unwrap_or(path);
+        let rel = path.strip_prefix(&self.vault_path).unwrap_or(&path);
+        let filename = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("note.md");
 
-        let truncated = if content.len() > 1900 {
-            let end = content[..1900]
-                .char_indices()
-                .last()
-                .map(|(i, c)| i + c.len_utf8())
-                .unwrap_or(0);
-            format!("{}...\n*(truncated)*", &content[..end])
-        } else {
-            content
-        };
-
-        Ok(format!("**{}**\n{}", rel.display(), truncated))
+        let mut header = format!("**{}**", rel.display());
+        if raw {
+            return Ok(inline_or_attachment(&header, &content, filename));
+        }
+        let (meta, body) = parse_frontmatter(&content);
+        for line in metadata_lines(&meta) {
+            header.push('\n');
+            header.push_str(&line);
+        }
+        Ok(inline_or_attachment(&header, body.trim_start(), filename))
     }
 
     async fn handle_recent(&self) -> Result<String, PluginError> {
-        let files = walk_md_files(&self.vault_path).await?;
+        let files = walk_md_files(&self.vault_path, &self.ignore, self.max_depth).await?;
 
         let mut entries: Vec<(PathBuf, u64)> = Vec::new();
         for path in files {
@@ -139,30 +276,98 @@ impl NotesPlugin {
             }
         }
 
-        entries.sort_by(|a, b| b.1.cmp(&a.1));
+        entries.sort_by_key(|e| std::cmp::Reverse(e.1));
         entries.truncate(10);
 
         if entries.is_empty() {
             return Ok("No notes found.".into());
         }
 
-        let now = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
-
         let mut msg = String::from("**Recent Notes**\n");
         for (path, mtime) in &entries {
             let rel = path.strip_prefix(&self.vault_path).unwrap_or(path);
             msg.push_str(&format!(
                 "- {} ({})\n",
                 rel.display(),
-                format_relative_time(now, *mtime)
+                discord_relative(*mtime)
             ));
         }
         Ok(msg)
     }
 
+    /// The walked note whose file name (without `.md`) is `name`, ignoring case.
+    async fn find_note(&self, name: &str) -> Result<Option<PathBuf>, PluginError> {
+        let name_lower = name.to_lowercase();
+        let files = walk_md_files(&self.vault_path, &self.ignore, self.max_depth).await?;
+        Ok(files.into_iter().find(|p| {
+            p.file_stem()
+                .and_then(|s| s.to_str())
+                .map(|s| s.to_lowercase() == name_lower)
+                .unwrap_or(false)
+        }))
+    }
+
+    /// Move the note named `name` into `dest_folder`, refusing to replace
+    /// a note already there.
+    async fn handle_move(&self, name: &str, dest_folder: &str) -> Result<String, PluginError> {
+        let Some(source) = self.find_note(name).await? else {
+            return Ok(format!("Note \"{}\" not found.", escape_discord(name)));
+        };
+        let Some((canonical_vault, canonical_dir)) = self.writable_folder(dest_folder).await? else {
+            return Ok("Invalid folder path.".into());
+        };
+        let Some(filename) = source.file_name() else {
+            return Ok(format!("Note \"{}\" not found.", escape_discord(name)));
+        };
+        let dest = canonical_dir.join(filename);
+        let rel_source = source.strip_prefix(&canonical_vault).unwrap_or(&source);
+        let rel_dest = dest.strip_prefix(&canonical_vault).unwrap_or(&dest);
+
+        if dest == source {
+            return Ok(format!("**{}** is already there.", rel_source.display()));
+        }
+
+        match move_no_clobber(&source, &dest).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Ok(format!(
+                    "Can't move **{}**: **{}** already exists.",
+                    rel_source.display(),
+                    rel_dest.display()
+                ));
+            }
+            Err(e) => return Err(PluginError::Other(format!("Failed to move note: {e}"))),
+        }
+        if let Some(index) = self.index.write().await.as_mut() {
+            index.remove(&source);
+        }
+        self.reindex(&canonical_vault, &dest).await;
+
+        Ok(format!("Moved **{}** to **{}**", rel_source.display(), rel_dest.display()))
+    }
+
+    async fn handle_stats(&self) -> Result<String, PluginError> {
+        let canonical_vault = tokio::fs::canonicalize(&self.vault_path)
+            .await
+            .map_err(|e| PluginError::Other(format!("Vault path error: {e}")))?;
+        let mut stats = VaultStats::default();
+        for path in walk_md_files(&self.vault_path, &self.ignore, self.max_depth).await? {
+            let Ok(meta) = tokio::fs::metadata(&path).await else {
+                continue;
+            };
+            let mtime = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let content = read_note(&path).await;
+            let rel = path.strip_prefix(&canonical_vault).unwrap_or(&path).to_path_buf();
+            stats.add(rel, mtime, content.as_deref());
+        }
+        Ok(stats.render())
+    }
+
     async fn handle_quick(
         &self,
         title: &str,
@@ -174,9 +379,42 @@ impl NotesPlugin {
             return Ok("Invalid title.".into());
         }
 
-        let folder_name = folder.unwrap_or("Discord");
-        if !validate_folder(folder_name) {
+        let Some((canonical_vault, canonical_dir)) =
+            self.writable_folder(folder.unwrap_or("Discord")).await?
+        else {
             return Ok("Invalid folder path.".into());
+        };
+
+        let filename = format!("{}.md", sanitized);
+        let file_path = canonical_dir.join(&filename);
+
+        if tokio::fs::metadata(&file_path).await.is_ok() {
+            return Ok(format!("Note \"{}\" already exists.", filename));
+        }
+
+        let date = today_iso();
+        let body = format!("---\ncreated: {date}\n---\n\n{content}\n");
+
+        tokio::fs::write(&file_path, &body)
+            .await
+            .map_err(|e| PluginError::Other(format!("Failed to write note: {e}")))?;
+        self.reindex(&canonical_vault, &file_path).await;
+
+        let rel = file_path
+            .strip_prefix(&canonical_vault)
+            .unwrap_or(&file_path);
+        Ok(format!("Created **{}**", rel.display()))
+    }
+
+    /// Create `folder_name` inside the vault if needed and return the
+    /// canonical vault and folder paths, or `None` if the folder would
+    /// resolve outside the vault.
+    async fn writable_folder(
+        &self,
+        folder_name: &str,
+    ) -> Result<Option<(PathBuf, PathBuf)>, PluginError> {
+        if !validate_folder(folder_name) {
+            return Ok(None);
         }
 
         let canonical_vault = tokio::fs::canonicalize(&self.vault_path)
@@ -194,27 +432,51 @@ impl NotesPlugin {
             .map_err(|e| PluginError::Other(format!("Path error: {e}")))?;
 
         if !canonical_dir.starts_with(&canonical_vault) {
-            return Ok("Invalid folder path.".into());
-        }
-
-        let filename = format!("{}.md", sanitized);
-        let file_path = canonical_dir.join(&filename);
-
-        if tokio::fs::metadata(&file_path).await.is_ok() {
-            return Ok(format!("Note \"{}\" already exists.", filename));
+            return Ok(None);
         }
+        Ok(Some((canonical_vault, canonical_dir)))
+    }
 
+    /// Append a timestamped bullet to today's daily note, creating it if
+    /// needed, or show the note when there's no `content`.
+    async fn handle_daily(
+        &self,
+        content: Option<&str>,
+    ) -> Result<Reply, PluginError> {
+        let Some((canonical_vault, canonical_dir)) = self.writable_folder(&self.daily_folder).await?
+        else {
+            return Ok(text("Invalid daily notes folder.".into()));
+        };
         let date = today_iso();
-        let body = format!("---\ncreated: {date}\n---\n\n{content}\n");
+        let file_path = canonical_dir.join(daily_filename(&date));
+        let rel = file_path.strip_prefix(&canonical_vault).unwrap_or(&file_path);
+
+        let Some(content) = content else {
+            return Ok(match tokio::fs::read_to_string(&file_path).await {
+                Ok(note) => {
+                    let (_, body) = parse_frontmatter(&note);
+                    inline_or_attachment(&format!("**{}**", rel.display()), body.trim_start(), "daily.md")
+                }
+                Err(_) => text("No daily note yet today. Add to it with `/notes daily content:`.".into()),
+            });
+        };
 
-        tokio::fs::write(&file_path, &body)
+        let mut note = match tokio::fs::read_to_string(&file_path).await {
+            Ok(note) => note,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                format!("---\ncreated: {date}\n---\n\n")
+            }
+            Err(e) => return Err(PluginError::Other(format!("Failed to read daily note: {e}"))),
+        };
+        if !note.is_empty() && !note.ends_with('\n') {
+            note.push('\n');
+        }
+        note.push_str(&daily_entry(now_secs(), content));
+        tokio::fs::write(&file_path, &note)
             .await
-            .map_err(|e| PluginError::Other(format!("Failed to write note: {e}")))?;
-
-        let rel = file_path
-            .strip_prefix(&canonical_vault)
-            .unwrap_or(&file_path);
-        Ok(format!("Created **{}**", rel.display()))
+            .map_err(|e| PluginError::Other(format!("Failed to write daily note: {e}")))?;
+        self.reindex(&canonical_vault, &file_path).await;
+        Ok(text(format!("Added to **{}**", rel.display())))
     }
 
     async fn handle_list(&self, folder: Option<&str>) -> Result<String, PluginError> {
@@ -295,10 +557,11 @@ impl Plugin for NotesPlugin {
                     CreateCommandOption::new(
                         CommandOptionType::String,
                         "query",
-                        "Search query",
+                        "Words, \"exact phrases\", tag:name, or path:Folder/",
                     )
                     .required(true),
-                ),
+                )
+                .add_sub_option(private_option()),
             )
             .add_option(
                 CreateCommandOption::new(
@@ -313,13 +576,22 @@ impl Plugin for NotesPlugin {
                         "Note name (without .md extension)",
                     )
                     .required(true),
-                ),
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::Boolean,
+                    "raw",
+                    "Show the file verbatim, front-matter included",
+                ))
+                .add_sub_option(private_option()),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "recent",
+                    "List 10 most recently modified notes",
+                )
+                .add_sub_option(private_option()),
             )
-            .add_option(CreateCommandOption::new(
-                CommandOptionType::SubCommand,
-                "recent",
-                "List 10 most recently modified notes",
-            ))
             .add_option(
                 CreateCommandOption::new(
                     CommandOptionType::SubCommand,
@@ -348,6 +620,19 @@ impl Plugin for NotesPlugin {
                     "Folder path (default: Discord)",
                 )),
             )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "daily",
+                    "Show today's daily note, or add a line to it",
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "content",
+                    "Line to add (leave out to show the note)",
+                ))
+                .add_sub_option(private_option()),
+            )
             .add_option(
                 CreateCommandOption::new(
                     CommandOptionType::SubCommand,
@@ -358,10 +643,59 @@ impl Plugin for NotesPlugin {
                     CommandOptionType::String,
                     "folder",
                     "Folder path (default: vault root)",
-                )),
+                ))
+                .add_sub_option(private_option()),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "move",
+                    "Move a note to another folder",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "Note name (without .md extension)",
+                    )
+                    .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "dest_folder",
+                        "Destination folder, relative to the vault",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "stats",
+                    "Note and word counts for the vault",
+                )
+                .add_sub_option(private_option()),
             )]
     }
 
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        matches!(path, ["notes", "search" | "read" | "recent" | "list" | "stats"])
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![
+            ("/notes search".into(), "Search notes by filename or content".into()),
+            ("/notes read".into(), "Display a note's content (raw: include front-matter)".into()),
+            ("/notes recent".into(), "List 10 most recently modified notes".into()),
+            ("/notes quick".into(), "Create a quick note".into()),
+            ("/notes daily".into(), "Show today's daily note, or add a line to it".into()),
+            ("/notes list".into(), "List notes in a folder".into()),
+            ("/notes move".into(), "Move a note to another folder".into()),
+            ("/notes stats".into(), "Note and word counts for the vault".into()),
+        ]
+    }
+
     async fn handle_command(
         &self,
         ctx: &Context,
@@ -373,14 +707,10 @@ impl Plugin for NotesPlugin {
 
         // DM-only: reject if used in a server
         if command.guild_id.is_some() {
-            let data = CreateInteractionResponseMessage::new()
+            let data = response_message()
                 .content("Notes commands are only available in DMs.")
                 .ephemeral(true);
-            let builder = CreateInteractionResponse::Message(data);
-            command
-                .create_response(&ctx.http, builder)
-                .await
-                .map_err(PluginError::DiscordError)?;
+            ack::respond(ctx, command, data).await?;
             return Ok(true);
         }
 
@@ -390,43 +720,101 @@ impl Plugin for NotesPlugin {
             None => return Ok(false),
         };
 
-        let content = match subopt.name {
+        let private = extract_bool_option(&subopt.value, "private").unwrap_or(false);
+        let data = match subopt.name {
             "search" => {
                 let query = extract_string_option(&subopt.value, "query")
                     .ok_or_else(|| PluginError::Other("Missing query".into()))?;
-                self.handle_search(query).await?
+                let results = self.handle_search(query).await?;
+                inline_or_attachment("", &results, "search.md")
             }
             "read" => {
                 let name = extract_string_option(&subopt.value, "name")
                     .ok_or_else(|| PluginError::Other("Missing name".into()))?;
-                self.handle_read(name).await?
+                let raw = extract_bool_option(&subopt.value, "raw").unwrap_or(false);
+                self.handle_read(name, raw).await?
             }
-            "recent" => self.handle_recent().await?,
+            "recent" => text(self.handle_recent().await?),
             "quick" => {
                 let title = extract_string_option(&subopt.value, "title")
                     .ok_or_else(|| PluginError::Other("Missing title".into()))?;
                 let content = extract_string_option(&subopt.value, "content")
                     .ok_or_else(|| PluginError::Other("Missing content".into()))?;
                 let folder = extract_string_option(&subopt.value, "folder");
-                self.handle_quick(title, content, folder).await?
+                text(self.handle_quick(title, content, folder).await?)
+            }
+            "daily" => {
+                let content = extract_string_option(&subopt.value, "content");
+                self.handle_daily(content).await?
             }
             "list" => {
                 let folder = extract_string_option(&subopt.value, "folder");
-                self.handle_list(folder).await?
+                text(self.handle_list(folder).await?)
             }
+            "move" => {
+                let name = extract_string_option(&subopt.value, "name")
+                    .ok_or_else(|| PluginError::Other("Missing name".into()))?;
+                let dest_folder = extract_string_option(&subopt.value, "dest_folder")
+                    .ok_or_else(|| PluginError::Other("Missing dest_folder".into()))?;
+                text(self.handle_move(name, dest_folder).await?)
+            }
+            "stats" => text(self.handle_stats().await?),
             _ => return Ok(false),
         };
 
-        let data = CreateInteractionResponseMessage::new().content(content);
-        let builder = CreateInteractionResponse::Message(data);
-        command
-            .create_response(&ctx.http, builder)
-            .await
-            .map_err(PluginError::DiscordError)?;
+        ack::respond_reply(ctx, command, data.ephemeral(private)).await?;
         Ok(true)
     }
 }
 
+/// The first case-insensitive match of `query_lower` in `content`, with up to
+/// `radius` characters either side and newlines flattened.
+fn snippet(content: &str, query_lower: &str, radius: usize) -> Option<String> {
+    // Lowercasing can change byte lengths, so match in a lowercased copy and
+    // map each of its bytes back to the start of the original character.
+    let mut lower = String::with_capacity(content.len());
+    let mut origin = Vec::with_capacity(content.len());
+    for (i, c) in content.char_indices() {
+        for lc in c.to_lowercase() {
+            lower.push(lc);
+            origin.resize(lower.len(), i);
+        }
+    }
+    if query_lower.is_empty() {
+        return None;
+    }
+    let pos = lower.find(query_lower)?;
+    let match_start = origin[pos];
+    // Through the end of the character the match's last byte came from.
+    let last = origin[pos + query_lower.len() - 1];
+    let match_end = last + content[last..].chars().next().map_or(0, char::len_utf8);
+
+    let start = content[..match_start]
+        .char_indices()
+        .rev()
+        .take(radius)
+        .last()
+        .map_or(match_start, |(i, _)| i);
+    let end = content[match_end..]
+        .char_indices()
+        .nth(radius)
+        .map_or(content.len(), |(i, _)| match_end + i);
+    Some(content[start..end].replace('\n', " "))
+}
+
+fn text(content: String) -> Reply {
+    Reply::text(content)
+}
+
+/// Optional `private` flag for read-only subcommands; replies only the caller can see.
+fn private_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::Boolean,
+        "private",
+        "Only show the result to you",
+    )
+}
+
 fn extract_string_option<'a>(value: &'a ResolvedValue<'a>, name: &str) -> Option<&'a str> {
     if let ResolvedValue::SubCommand(opts) = value {
         opts.iter()
@@ -440,14 +828,72 @@ fn extract_string_option<'a>(value: &'a ResolvedValue<'a>, name: &str) -> Option
     }
 }
 
-async fn walk_md_files(dir: &Path) -> Result<Vec<PathBuf>, PluginError> {
+fn extract_bool_option(value: &ResolvedValue<'_>, name: &str) -> Option<bool> {
+    if let ResolvedValue::SubCommand(opts) = value {
+        opts.iter()
+            .find(|o| o.name == name)
+            .and_then(|o| match o.value {
+                ResolvedValue::Boolean(b) => Some(b),
+                _ => None,
+            })
+    } else {
+        None
+    }
+}
+
+/// Move `source` to `dest`, failing with `AlreadyExists` rather than
+/// replacing a file at `dest`, even one created a moment ago (a plain rename
+/// would silently overwrite it). Hard-links where the filesystem allows it,
+/// otherwise claims `dest` with `create_new` and copies.
+async fn move_no_clobber(source: &Path, dest: &Path) -> std::io::Result<()> {
+    match tokio::fs::hard_link(source, dest).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Err(e),
+        Err(_) => {
+            tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(dest)
+                .await?;
+            tokio::fs::copy(source, dest).await?;
+        }
+    }
+    tokio::fs::remove_file(source).await
+}
+
+/// A note's file name without the `.md` extension.
+fn note_stem(path: &Path) -> &str {
+    path.file_stem().and_then(|s| s.to_str()).unwrap_or("")
+}
+
+/// The content of the note at `path`, or `None` if it's unreadable or
+/// too large to search.
+async fn read_note(path: &Path) -> Option<String> {
+    let meta = tokio::fs::metadata(path).await.ok()?;
+    if meta.len() > MAX_NOTE_BYTES {
+        return None;
+    }
+    tokio::fs::read_to_string(path).await.ok()
+}
+
+/// Markdown files under `dir`, skipping dotfiles and anything matching an
+/// `ignore` glob (a matched folder is skipped whole). Symlinks are followed
+/// only within the vault, each folder and file is visited once however many
+/// links lead to it, and folders more than `max_depth` deep are left out.
+async fn walk_md_files(
+    dir: &Path,
+    ignore: &[String],
+    max_depth: usize,
+) -> Result<Vec<PathBuf>, PluginError> {
     let canonical_root = tokio::fs::canonicalize(dir)
         .await
         .map_err(|e| PluginError::Other(format!("Cannot resolve vault path: {e}")))?;
     let mut files = Vec::new();
-    let mut stack = vec![canonical_root.clone()];
+    let mut seen_files = HashSet::new();
+    let mut visited = HashSet::from([canonical_root.clone()]);
+    let mut stack = vec![(canonical_root.clone(), 0)];
 
-    while let Some(current) = stack.pop() {
+    while let Some((current, depth)) = stack.pop() {
         let mut entries = match tokio::fs::read_dir(&current).await {
             Ok(e) => e,
             Err(_) => continue,
@@ -461,21 +907,30 @@ async fn walk_md_files(dir: &Path) -> Result<Vec<PathBuf>, PluginError> {
                 continue;
             }
 
-            let file_type = match entry.file_type().await {
-                Ok(ft) => ft,
+            let path = entry.path();
+            // Follows symlinks, so a linked folder is walked like a real one.
+            let file_type = match tokio::fs::metadata(&path).await {
+                Ok(meta) => meta.file_type(),
                 Err(_) => continue,
             };
 
-            let path = entry.path();
+            if let Ok(rel) = path.strip_prefix(&canonical_root)
+                && should_ignore(rel, ignore)
+            {
+                continue;
+            }
             if file_type.is_dir() {
-                if let Ok(canonical) = tokio::fs::canonicalize(&path).await
+                if depth < max_depth
+                    && let Ok(canonical) = tokio::fs::canonicalize(&path).await
                     && canonical.starts_with(&canonical_root)
+                    && visited.insert(canonical.clone())
                 {
-                    stack.push(canonical);
+                    stack.push((canonical, depth + 1));
                 }
             } else if path.extension().and_then(|e| e.to_str()) == Some("md")
                 && let Ok(canonical) = tokio::fs::canonicalize(&path).await
                 && canonical.starts_with(&canonical_root)
+                && seen_files.insert(canonical.clone())
             {
                 files.push(canonical);
             }
@@ -485,15 +940,181 @@ async fn walk_md_files(dir: &Path) -> Result<Vec<PathBuf>, PluginError> {
     Ok(files)
 }
 
-fn escape_discord(s: &str) -> String {
-    s.replace('\\', "\\\\")
-        .replace('*', "\\*")
-        .replace('_', "\\_")
-        .replace('`', "\\`")
-        .replace('~', "\\~")
-        .replace('|', "\\|")
-        .replace('@', "\\@")
-        .replace('<', "\\<")
+/// Split a note into its YAML front-matter and body. Only the flat subset
+/// notes use is understood: `key: value`, `key: [a, b]`, and `key:` followed
+/// by `- item` lines. Anything else (or a missing closing `---`) counts as
+/// malformed, and the whole file is returned as the body.
+fn parse_frontmatter(content: &str) -> (BTreeMap<String, Vec<String>>, &str) {
+    let none = (BTreeMap::new(), content);
+    let Some(rest) = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n")) else {
+        return none;
+    };
+
+    let mut meta: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut last_key: Option<String> = None;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == "---" {
+            return (meta, &rest[offset..]);
+        }
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            let Some(key) = &last_key else { return none };
+            meta.entry(key.clone()).or_default().push(unquote(item));
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            return none;
+        };
+        // Nested maps (indented keys) aren't supported.
+        let key = key.trim();
+        if key.is_empty() || line.starts_with(' ') {
+            return none;
+        }
+        let value = value.trim();
+        let values = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Some(list) => list.split(',').map(unquote).filter(|v| !v.is_empty()).collect(),
+            None if value.is_empty() => Vec::new(),
+            None => vec![unquote(value)],
+        };
+        meta.insert(key.to_string(), values);
+        last_key = Some(key.to_string());
+    }
+    none
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// "Created …", "Tags …", and "Aliases …" lines for whichever of those the
+/// front-matter has.
+fn metadata_lines(meta: &BTreeMap<String, Vec<String>>) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(created) = meta.get("created").and_then(|v| v.first()) {
+        lines.push(format!("-# Created {}", escape_discord(created)));
+    }
+    if let Some(tags) = meta.get("tags").filter(|t| !t.is_empty()) {
+        let tags: Vec<_> = tags
+            .iter()
+            .map(|t| format!("#{}", escape_discord(t.trim_start_matches('#'))))
+            .collect();
+        lines.push(format!("-# Tags {}", tags.join(" ")));
+    }
+    if let Some(aliases) = meta.get("aliases").filter(|a| !a.is_empty()) {
+        let aliases: Vec<_> = aliases.iter().map(|a| escape_discord(a)).collect();
+        lines.push(format!("-# Aliases {}", aliases.join(", ")));
+    }
+    lines
+}
+
+/// Whether `rel` (a path relative to the vault root) matches one of `globs`.
+/// A pattern without a `/` matches any single file or folder name, like
+/// `.gitignore`; one with a `/` matches the whole relative path. `*` and `?`
+/// stay within one path component, `**` spans any number of them.
+fn should_ignore(rel: &Path, globs: &[String]) -> bool {
+    let rel = rel.to_string_lossy().replace('\\', "/");
+    let name = rel.rsplit('/').next().unwrap_or(&rel);
+    globs.iter().any(|glob| {
+        let glob = glob.trim_matches('/');
+        let target = if glob.contains('/') { rel.as_str() } else { name };
+        glob_match(
+            &glob.chars().collect::<Vec<_>>(),
+            &target.chars().collect::<Vec<_>>(),
+        )
+    })
+}
+
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => (0..=text.len())
+            .filter(|&i| i == 0 || text[i - 1] == '/')
+            .any(|i| glob_match(rest, &text[i..])),
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob_match(rest, &text[i..])),
+        ['?', rest @ ..] => text.first().is_some_and(|&c| c != '/') && glob_match(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Daily notes are named by their UTC date, e.g. `2024-01-02.md`.
+fn daily_filename(date: &str) -> String {
+    format!("{date}.md")
+}
+
+/// A daily note line stamped with the UTC time, e.g. `- 14:05 fed the cat`.
+fn daily_entry(unix_secs: u64, content: &str) -> String {
+    let secs_of_day = unix_secs % 86_400;
+    let (hour, minute) = (secs_of_day / 3600, secs_of_day % 3600 / 60);
+    format!("- {hour:02}:{minute:02} {}\n", content.trim().replace('\n', " "))
+}
+
+/// Totals for `/notes stats`, accumulated one note at a time.
+#[derive(Debug, Default)]
+struct VaultStats {
+    notes: usize,
+    words: usize,
+    /// Notes too large to read, counted but not in `words`.
+    unread: usize,
+    /// Vault-relative path and mtime of the most recently modified note.
+    latest: Option<(PathBuf, u64)>,
+}
+
+impl VaultStats {
+    /// Count a note; `content` is `None` when it was too large to read.
+    /// Front-matter isn't counted as words.
+    fn add(&mut self, rel: PathBuf, mtime: u64, content: Option<&str>) {
+        self.notes += 1;
+        match content {
+            Some(content) => self.words += parse_frontmatter(content).1.split_whitespace().count(),
+            None => self.unread += 1,
+        }
+        if self.latest.as_ref().is_none_or(|(_, latest)| mtime > *latest) {
+            self.latest = Some((rel, mtime));
+        }
+    }
+
+    fn average_words(&self) -> usize {
+        self.words.checked_div(self.notes - self.unread).unwrap_or(0)
+    }
+
+    fn render(&self) -> String {
+        if self.notes == 0 {
+            return "No notes found.".into();
+        }
+        let mut msg = format!(
+            "**Vault Stats**\nNotes: {}\nWords: {} (avg {} per note)\n",
+            self.notes,
+            self.words,
+            self.average_words()
+        );
+        if let Some((rel, mtime)) = &self.latest {
+            let name = rel.display().to_string();
+            let name: String = name.chars().take(200).collect();
+            msg.push_str(&format!(
+                "Last modified: {} ({})\n",
+                escape_discord(&name),
+                discord_relative(*mtime)
+            ));
+        }
+        if self.unread > 0 {
+            msg.push_str(&format!("-# {} notes over 1 MB weren't counted\n", self.unread));
+        }
+        msg
+    }
 }
 
 fn validate_folder(name: &str) -> bool {
@@ -529,75 +1150,10 @@ fn sanitize_title(title: &str) -> String {
     result.trim_matches('-').to_string()
 }
 
-fn format_relative_time(now: u64, timestamp: u64) -> String {
-    if timestamp == 0 || timestamp > now {
-        return "just now".into();
-    }
-    let diff = now - timestamp;
-    if diff < 3600 {
-        format!("{}m ago", diff / 60)
-    } else if diff < 86400 {
-        format!("{}h ago", diff / 3600)
-    } else {
-        format!("{}d ago", diff / 86400)
-    }
-}
-
-fn today_iso() -> String {
-    let secs = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-
-    let days = secs / 86400;
-    let mut remaining = days;
-    let mut year = 1970u32;
-
-    loop {
-        let days_in_year = if is_leap_year(year) { 366 } else { 365 };
-        if remaining < days_in_year {
-            break;
-        }
-        remaining -= days_in_year;
-        year += 1;
-    }
-
-    let leap = is_leap_year(year);
-    let month_days: [u64; 12] = [
-        31,
-        if leap { 29 } else { 28 },
-        31,
-        30,
-        31,
-        30,
-        31,
-        31,
-        30,
-        31,
-        30,
-        31,
-    ];
-
-    let mut month = 12u32;
-    for (i, &d) in month_days.iter().enumerate() {
-        if remaining < d {
-            month = i as u32 + 1;
-            break;
-        }
-        remaining -= d;
-    }
-
-    let day = remaining + 1;
-    format!("{year:04}-{month:02}-{day:02}")
-}
-
-fn is_leap_year(year: u32) -> bool {
-    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use discord_assist_util::iso_date;
 
     #[test]
     fn test_sanitize_title_basic() {
@@ -620,65 +1176,303 @@ mod tests {
     }
 
     #[test]
-    fn test_format_relative_time_minutes() {
-        assert_eq!(format_relative_time(1000, 400), "10m ago");
+    fn test_validate_folder() {
+        assert!(validate_folder("Discord"));
+        assert!(validate_folder("foo/bar"));
+        assert!(!validate_folder("../evil"));
+        assert!(!validate_folder("/absolute"));
+        assert!(!validate_folder("foo/../bar"));
+        assert!(!validate_folder("foo/.."));
+        assert!(!validate_folder(".."));
     }
 
     #[test]
-    fn test_format_relative_time_hours() {
-        assert_eq!(format_relative_time(10000, 3000), "1h ago");
+    fn test_snippet_radius() {
+        let content = "The quick brown fox jumps over the lazy dog";
+        assert_eq!(snippet(content, "fox", 6).as_deref(), Some("brown fox jumps"));
+        assert_eq!(snippet(content, "fox", 0).as_deref(), Some("fox"));
+        assert_eq!(snippet(content, "the", 4).as_deref(), Some("The qui"));
+        assert_eq!(snippet(content, "dog", 9).as_deref(), Some("the lazy dog"));
+        assert_eq!(snippet(content, "cat", 10), None);
+        assert_eq!(snippet(content, "", 10), None);
     }
 
     #[test]
-    fn test_format_relative_time_days() {
-        assert_eq!(format_relative_time(200000, 10000), "2d ago");
+    fn test_snippet_multibyte() {
+        // A 3-char query of 2-byte chars: byte length must not be used as a char count.
+        let content = "ааааа ЖЖЖ ббббб";
+        assert_eq!(snippet(content, "жжж", 2).as_deref(), Some("а ЖЖЖ б"));
+        assert_eq!(snippet("line one\nline two", "two", 4).as_deref(), Some("ine two"));
+        // 'İ' lowercases to two chars, shifting byte offsets after it.
+        assert_eq!(snippet("İİ café crème", "crème", 3).as_deref(), Some("fé crème"));
     }
 
     #[test]
-    fn test_format_relative_time_future() {
-        assert_eq!(format_relative_time(100, 200), "just now");
+    fn test_should_ignore() {
+        let globs = vec!["Templates".to_string(), "*.excalidraw.md".into(), "Archive/**/old-*".into()];
+        let ignored = |p: &str| should_ignore(Path::new(p), &globs);
+        // A folder name matches at any depth.
+        assert!(ignored("Templates"));
+        assert!(ignored("Work/Templates"));
+        assert!(!ignored("Work/Templates.md"));
+        // A filename pattern.
+        assert!(ignored("drawing.excalidraw.md"));
+        assert!(ignored("Work/drawing.excalidraw.md"));
+        assert!(!ignored("drawing.md"));
+        // A path pattern matches from the vault root only.
+        assert!(ignored("Archive/old-notes.md"));
+        assert!(ignored("Archive/2023/q1/old-notes.md"));
+        assert!(!ignored("Work/Archive/old-notes.md"));
+        assert!(!should_ignore(Path::new("anything.md"), &[]));
     }
 
     #[test]
-    fn test_format_relative_time_zero() {
-        assert_eq!(format_relative_time(100, 0), "just now");
+    fn test_glob_star_stays_in_one_component() {
+        let glob = |p: &str, t: &str| glob_match(&p.chars().collect::<Vec<_>>(), &t.chars().collect::<Vec<_>>());
+        assert!(glob("a/*.md", "a/b.md"));
+        assert!(!glob("a/*.md", "a/b/c.md"));
+        assert!(glob("a/**", "a/b/c.md"));
+        assert!(glob("?.md", "x.md"));
+        assert!(!glob("?.md", "xy.md"));
     }
 
     #[test]
-    fn test_today_iso_format() {
-        let result = today_iso();
-        assert_eq!(result.len(), 10);
-        assert_eq!(&result[4..5], "-");
-        assert_eq!(&result[7..8], "-");
-        let year: u32 = result[..4].parse().unwrap();
-        assert!(year >= 2024);
-        let month: u32 = result[5..7].parse().unwrap();
-        assert!((1..=12).contains(&month));
-        let day: u32 = result[8..10].parse().unwrap();
-        assert!((1..=31).contains(&day));
+    fn test_parse_frontmatter_present() {
+        let note = "---\ncreated: 2024-01-02\ntags: [work, \"meeting\"]\naliases:\n  - Standup\n  - Daily\n---\n\n# Notes\nbody\n";
+        let (meta, body) = parse_frontmatter(note);
+        assert_eq!(meta["created"], ["2024-01-02"]);
+        assert_eq!(meta["tags"], ["work", "meeting"]);
+        assert_eq!(meta["aliases"], ["Standup", "Daily"]);
+        assert_eq!(body, "\n# Notes\nbody\n");
+        assert_eq!(
+            metadata_lines(&meta),
+            ["-# Created 2024-01-02", "-# Tags #work #meeting", "-# Aliases Standup, Daily"]
+        );
     }
 
     #[test]
-    fn test_validate_folder() {
-        assert!(validate_folder("Discord"));
-        assert!(validate_folder("foo/bar"));
-        assert!(!validate_folder("../evil"));
-        assert!(!validate_folder("/absolute"));
-        assert!(!validate_folder("foo/../bar"));
-        assert!(!validate_folder("foo/.."));
-        assert!(!validate_folder(".."));
+    fn test_parse_frontmatter_absent() {
+        let note = "# Title\n---\nnot front-matter\n";
+        let (meta, body) = parse_frontmatter(note);
+        assert!(meta.is_empty());
+        assert_eq!(body, note);
+        assert!(metadata_lines(&meta).is_empty());
     }
 
     #[test]
-    fn test_sanitize_title_path_traversal() {
-        assert_eq!(sanitize_title("../../etc/passwd"), "etc-passwd");
+    fn test_parse_frontmatter_malformed() {
+        // No closing fence.
+        let unclosed = "---\ncreated: 2024-01-02\n# Title\n";
+        assert_eq!(parse_frontmatter(unclosed), (BTreeMap::new(), unclosed));
+        // A line that isn't `key: value` or a list item.
+        let garbage = "---\ncreated 2024-01-02\n---\nbody\n";
+        assert_eq!(parse_frontmatter(garbage), (BTreeMap::new(), garbage));
+        // A list item with no key above it.
+        let orphan = "---\n- stray\n---\nbody\n";
+        assert_eq!(parse_frontmatter(orphan), (BTreeMap::new(), orphan));
+    }
+
+    #[test]
+    fn test_daily_filename() {
+        assert_eq!(daily_filename("2024-01-02"), "2024-01-02.md");
+        // 2024-01-31T23:59:00Z is still the 31st in UTC.
+        assert_eq!(daily_filename(&iso_date(1_706_745_540)), "2024-01-31.md");
+        assert_eq!(daily_filename(&iso_date(1_706_745_600)), "2024-02-01.md");
+    }
+
+    #[test]
+    fn test_daily_entry() {
+        assert_eq!(daily_entry(1_706_745_540, "  fed the cat "), "- 23:59 fed the cat\n");
+        assert_eq!(daily_entry(1_706_659_200, "a\nb"), "- 00:00 a b\n");
+    }
+
+    /// A scratch vault under the temp dir, removed on drop.
+    struct TempVault(PathBuf);
+
+    impl TempVault {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("discord-assist-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn file(&self, rel: &str) {
+            let path = self.0.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "note").unwrap();
+        }
+    }
+
+    impl Drop for TempVault {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn names(files: &[PathBuf]) -> Vec<String> {
+        let mut names: Vec<_> = files
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_walk_bounds_depth() {
+        let vault = TempVault::new("notes-depth");
+        vault.file("top.md");
+        vault.file("a/one.md");
+        vault.file("a/b/two.md");
+        vault.file("a/b/c/three.md");
+        vault.file(".hidden/secret.md");
+
+        let all = walk_md_files(&vault.0, &[], DEFAULT_MAX_DEPTH).await.unwrap();
+        assert_eq!(names(&all), ["one.md", "three.md", "top.md", "two.md"]);
+        let shallow = walk_md_files(&vault.0, &[], 2).await.unwrap();
+        assert_eq!(names(&shallow), ["one.md", "top.md", "two.md"]);
+        let root_only = walk_md_files(&vault.0, &[], 0).await.unwrap();
+        assert_eq!(names(&root_only), ["top.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_daily_appends_to_todays_note() {
+        let vault = TempVault::new("notes-daily");
+        let plugin = NotesPlugin::new(vault.0.to_str().unwrap()).with_daily_folder("Journal");
+        let content = |reply: Reply| reply.content;
+        let empty = content(plugin.handle_daily(None).await.unwrap());
+        assert!(empty.starts_with("No daily note yet today"));
+
+        let filename = daily_filename(&today_iso());
+        let added = content(plugin.handle_daily(Some("first")).await.unwrap());
+        assert_eq!(added, format!("Added to **Journal/{filename}**"));
+        content(plugin.handle_daily(Some("second")).await.unwrap());
+
+        let shown = content(plugin.handle_daily(None).await.unwrap());
+        assert!(shown.contains(" second"), "{shown}");
+
+        let path = vault.0.join("Journal").join(filename);
+        let note = std::fs::read_to_string(path).unwrap();
+        let (meta, body) = parse_frontmatter(&note);
+        assert_eq!(meta["created"], [today_iso()]);
+        let lines: Vec<_> = body.trim().lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" first") && lines[1].ends_with(" second"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn test_search_uses_index_and_refreshes_on_write() {
+        let vault = TempVault::new("notes-index");
+        std::fs::write(vault.0.join("plans.md"), "Budget review on Friday.\nMeeting notes follow.").unwrap();
+        std::fs::write(vault.0.join("other.md"), "Nothing about money here.").unwrap();
+        let root = std::fs::canonicalize(&vault.0).unwrap();
+        let plugin = NotesPlugin::new(root.to_str().unwrap());
+
+        let found = plugin.handle_search("review budget").await.unwrap();
+        assert!(found.contains("(1 results)") && found.contains("**plans.md**: ...Budget"), "{found}");
+        assert_eq!(plugin.index.read().await.as_ref().map(NoteIndex::len), Some(2));
+
+        // Not a whole word, so it's found by the fallback scan.
+        let partial = plugin.handle_search("meet").await.unwrap();
+        assert!(partial.contains("**plans.md**: ...") && partial.contains("Meeting"), "{partial}");
+
+        plugin.handle_quick("Groceries", "apples and pears", None).await.unwrap();
+        let written = plugin.handle_search("pears").await.unwrap();
+        assert!(written.contains("**Discord/groceries.md**"), "{written}");
+        assert_eq!(plugin.index.read().await.as_ref().map(NoteIndex::len), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_move_validates_and_never_overwrites() {
+        let vault = TempVault::new("notes-move");
+        std::fs::write(vault.0.join("plan.md"), "original").unwrap();
+        std::fs::create_dir_all(vault.0.join("Archive")).unwrap();
+        std::fs::write(vault.0.join("Archive/plan.md"), "archived").unwrap();
+        let plugin = NotesPlugin::new(vault.0.to_str().unwrap());
+
+        assert_eq!(plugin.handle_move("plan", "../outside").await.unwrap(), "Invalid folder path.");
+        assert_eq!(plugin.handle_move("plan", "/etc").await.unwrap(), "Invalid folder path.");
+        assert!(plugin.handle_move("missing", "Work").await.unwrap().contains("not found"));
+
+        // Either copy may be found first; both moves collide with the other.
+        let collided = plugin.handle_move("plan", "Archive").await.unwrap();
+        assert!(collided.contains("already"), "{collided}");
+        assert_eq!(std::fs::read_to_string(vault.0.join("Archive/plan.md")).unwrap(), "archived");
+
+        std::fs::remove_file(vault.0.join("Archive/plan.md")).unwrap();
+        let moved = plugin.handle_move("PLAN", "Work/2024").await.unwrap();
+        assert_eq!(moved, "Moved **plan.md** to **Work/2024/plan.md**");
+        assert!(!vault.0.join("plan.md").exists());
+        assert_eq!(std::fs::read_to_string(vault.0.join("Work/2024/plan.md")).unwrap(), "original");
+
+        // A note that appears at the destination after the check isn't clobbered
+        std::fs::write(vault.0.join("late.md"), "late").unwrap();
+        let err = move_no_clobber(&vault.0.join("Work/2024/plan.md"), &vault.0.join("late.md"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(vault.0.join("late.md")).unwrap(), "late");
+        assert!(vault.0.join("Work/2024/plan.md").exists());
     }
 
     #[test]
-    fn test_escape_discord() {
-        assert_eq!(escape_discord("**bold**"), "\\*\\*bold\\*\\*");
-        assert_eq!(escape_discord("@everyone"), "\\@everyone");
-        assert_eq!(escape_discord("<@123>"), "\\<\\@123>");
-        assert_eq!(escape_discord("normal text"), "normal text");
+    fn test_vault_stats_accumulates() {
+        let notes = [
+            ("a.md", 100, Some("---\ntags: [x]\n---\none two three")),
+            ("Work/b.md", 300, Some("four five\n\nsix seven eight")),
+            ("big.md", 200, None),
+        ];
+        let mut stats = VaultStats::default();
+        for (rel, mtime, content) in notes {
+            stats.add(rel.into(), mtime, content);
+        }
+        assert_eq!((stats.notes, stats.words, stats.unread), (3, 8, 1));
+        assert_eq!(stats.average_words(), 4);
+        assert_eq!(stats.latest, Some((PathBuf::from("Work/b.md"), 300)));
+
+        let msg = stats.render();
+        assert!(msg.contains("Notes: 3\nWords: 8 (avg 4 per note)"), "{msg}");
+        assert!(msg.contains("Last modified: Work/b.md (<t:300:R>)"), "{msg}");
+        assert!(msg.contains("1 notes over 1 MB"), "{msg}");
+        assert!(msg.len() < 2000);
+        assert_eq!(VaultStats::default().render(), "No notes found.");
+    }
+
+    #[tokio::test]
+    async fn test_search_with_filters() {
+        let vault = TempVault::new("notes-query");
+        std::fs::create_dir_all(vault.0.join("Work")).unwrap();
+        std::fs::write(vault.0.join("Work/plan.md"), "---\ntags: [project]\n---\nShip the beta soon.").unwrap();
+        std::fs::write(vault.0.join("ideas.md"), "Ship the beta #project").unwrap();
+        let plugin = NotesPlugin::new(vault.0.to_str().unwrap());
+
+        let found = plugin.handle_search(r#"tag:project path:work/ "the beta""#).await.unwrap();
+        assert!(found.contains("(1 results)") && found.contains("**Work/plan.md**: ...") && found.contains("the beta soon"), "{found}");
+        let both = plugin.handle_search("tag:project").await.unwrap();
+        assert!(both.contains("(2 results)"), "{both}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_walk_survives_symlink_loops() {
+        use std::os::unix::fs::symlink;
+
+        let vault = TempVault::new("notes-symlinks");
+        vault.file("a/one.md");
+        vault.file("a/b/two.md");
+        // A loop back to an ancestor, a second path to the same folder, and
+        // a second path to the same file.
+        symlink(&vault.0, vault.0.join("a/b/loop")).unwrap();
+        symlink(vault.0.join("a/b"), vault.0.join("shortcut")).unwrap();
+        symlink(vault.0.join("a/one.md"), vault.0.join("alias.md")).unwrap();
+
+        let files = walk_md_files(&vault.0, &[], DEFAULT_MAX_DEPTH).await.unwrap();
+        assert_eq!(names(&files), ["one.md", "two.md"]);
+    }
+
+    #[test]
+    fn test_sanitize_title_path_traversal() {
+        assert_eq!(sanitize_title("../../etc/passwd"), "etc-passwd");
     }
 }
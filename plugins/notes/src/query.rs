@@ -0,0 +1,198 @@
+use crate::parse_frontmatter;
+use std::path::Path;
+
+/// A parsed `/notes search` query. Words match as case-insensitive
+/// substrings of a note's name or content and `"quoted phrases"` match
+/// verbatim; `tag:x` requires the tag in the front-matter or inline as
+/// `#x`, and `path:Work/` keeps notes whose vault path starts with that.
+/// Every part has to match.
+#[derive(Debug, Default, PartialEq)]
+pub struct Query {
+    pub terms: Vec<String>,
+    pub phrases: Vec<String>,
+    pub tags: Vec<String>,
+    pub path: Option<String>,
+}
+
+impl Query {
+    /// Parse `input`, lowercasing everything since matching ignores case.
+    /// A `key:"quoted value"` may contain spaces; an unterminated quote
+    /// runs to the end of the input.
+    pub fn parse(input: &str) -> Self {
+        let mut query = Query::default();
+        let mut chars = input.chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            let Some(&first) = chars.peek() else {
+                break;
+            };
+
+            let mut word = String::new();
+            let mut in_quotes = false;
+            while let Some(c) = chars.next_if(|&c| in_quotes || !c.is_whitespace()) {
+                if c == '"' {
+                    in_quotes = !in_quotes;
+                } else {
+                    word.push(c);
+                }
+            }
+
+            let word = word.to_lowercase();
+            if first == '"' {
+                let phrase = word.trim();
+                if !phrase.is_empty() {
+                    query.phrases.push(phrase.to_string());
+                }
+            } else if let Some(tag) = word.strip_prefix("tag:") {
+                let tag = tag.trim_start_matches('#');
+                if !tag.is_empty() {
+                    query.tags.push(tag.to_string());
+                }
+            } else if let Some(path) = word.strip_prefix("path:") {
+                let path = path.trim_start_matches('/');
+                if !path.is_empty() {
+                    query.path = Some(path.to_string());
+                }
+            } else if !word.is_empty() {
+                query.terms.push(word);
+            }
+        }
+        query
+    }
+
+    /// Whether the query is just words, with no phrases or filters.
+    pub fn is_plain(&self) -> bool {
+        self.phrases.is_empty() && self.tags.is_empty() && self.path.is_none()
+    }
+
+    /// The phrase or word search snippets are centred on, if any.
+    pub fn needle(&self) -> Option<&str> {
+        self.phrases.first().or(self.terms.first()).map(String::as_str)
+    }
+
+    /// Whether the note at vault-relative `rel` with `content` matches.
+    pub fn matches(&self, rel: &Path, content: &str) -> bool {
+        let rel_lower = rel.to_string_lossy().replace('\\', "/").to_lowercase();
+        if let Some(prefix) = &self.path
+            && !rel_lower.starts_with(prefix.as_str())
+        {
+            return false;
+        }
+
+        if !self.tags.is_empty() {
+            let tags = note_tags(content);
+            let has_tag = |wanted: &String| {
+                tags.iter().any(|tag| {
+                    // Nested tags count towards their parent, as in Obsidian.
+                    tag == wanted || tag.strip_prefix(wanted.as_str()).is_some_and(|rest| rest.starts_with('/'))
+                })
+            };
+            if !self.tags.iter().all(has_tag) {
+                return false;
+            }
+        }
+
+        let name = rel
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let content = content.to_lowercase();
+        self.terms
+            .iter()
+            .chain(&self.phrases)
+            .all(|needle| name.contains(needle.as_str()) || content.contains(needle.as_str()))
+    }
+}
+
+/// A note's tags, lowercased and without `#`: the front-matter `tags` plus
+/// inline `#tags` in the body. A lone `#` (a heading) isn't a tag.
+fn note_tags(content: &str) -> Vec<String> {
+    let (meta, body) = parse_frontmatter(content);
+    let mut tags: Vec<String> = meta
+        .get("tags")
+        .into_iter()
+        .flatten()
+        .map(|t| t.trim_start_matches('#').to_lowercase())
+        .collect();
+    for word in body.split_whitespace() {
+        let Some(tag) = word.strip_prefix('#') else {
+            continue;
+        };
+        let tag: String = tag
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/'))
+            .collect();
+        if tag.chars().next().is_some_and(char::is_alphanumeric) {
+            tags.push(tag.to_lowercase());
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_queries() {
+        assert_eq!(
+            Query::parse("Budget review"),
+            Query { terms: strings(&["budget", "review"]), ..Default::default() }
+        );
+        assert_eq!(
+            Query::parse(r#"  "Weekly Sync" tag:#Project path:/Work/ notes "#),
+            Query {
+                terms: strings(&["notes"]),
+                phrases: strings(&["weekly sync"]),
+                tags: strings(&["project"]),
+                path: Some("work/".into()),
+            }
+        );
+        assert_eq!(
+            Query::parse(r#"path:"Work Notes/" tag:a tag:b/c "unterminated phrase"#),
+            Query {
+                phrases: strings(&["unterminated phrase"]),
+                tags: strings(&["a", "b/c"]),
+                path: Some("work notes/".into()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(Query::parse(r#"tag: path: """#), Query::default());
+        assert!(Query::parse("plain words").is_plain());
+        assert!(!Query::parse("tag:x").is_plain());
+    }
+
+    #[test]
+    fn test_matches_fixtures() {
+        let standup = (
+            Path::new("Work/Meetings/standup.md"),
+            "---\ntags: [project/alpha, meeting]\n---\n\nWeekly sync with the team.\nBudget is #Approved.\n",
+        );
+        let groceries = (Path::new("Home/groceries.md"), "# Groceries\nMilk and eggs. #errand\n");
+        let matching = |query: &str| {
+            let query = Query::parse(query);
+            [standup, groceries]
+                .iter()
+                .filter(|(rel, content)| query.matches(rel, content))
+                .map(|(rel, _)| rel.file_stem().unwrap().to_str().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(matching("team"), ["standup"]);
+        assert_eq!(matching("GROCER"), ["groceries"]);
+        assert_eq!(matching(r#""weekly sync""#), ["standup"]);
+        assert!(matching(r#""sync weekly""#).is_empty());
+        assert_eq!(matching("tag:project"), ["standup"]);
+        assert_eq!(matching("tag:project/alpha tag:approved"), ["standup"]);
+        assert!(matching("tag:proj").is_empty());
+        assert_eq!(matching("tag:errand milk"), ["groceries"]);
+        assert!(matching("tag:groceries").is_empty());
+        assert_eq!(matching("path:work/ budget"), ["standup"]);
+        assert!(matching("path:Home/ budget").is_empty());
+        assert_eq!(matching("path:home"), ["groceries"]);
+    }
+}
@@ -0,0 +1,117 @@
+use async_trait::async_trait;
+use discord_assist_health::{HealthChecker, ServiceState, ServiceStatus, ServiceTarget};
+use discord_assist_plugin_api::{Plugin, PluginError, ack, edit_message};
+use serenity::builder::CreateCommand;
+use serenity::model::application::CommandInteraction;
+use serenity::prelude::Context;
+use std::time::Instant;
+
+/// `/ping`: Discord REST round-trip plus latency to each configured service.
+pub struct PingPlugin {
+    checker: HealthChecker,
+}
+
+impl PingPlugin {
+    /// `targets` are built from the configured service sections; may be empty.
+    pub fn new(targets: Vec<ServiceTarget>) -> Self {
+        Self::with_checker(HealthChecker::new(targets))
+    }
+
+    pub fn with_checker(checker: HealthChecker) -> Self {
+        Self { checker }
+    }
+}
+
+#[async_trait]
+impl Plugin for PingPlugin {
+    fn name(&self) -> &str {
+        "ping"
+    }
+
+    fn register_commands(&self) -> Vec<CreateCommand> {
+        vec![CreateCommand::new("ping").description("Show Discord and service latency")]
+    }
+
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        path == ["ping"]
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![("/ping".into(), "Show Discord and service latency".into())]
+    }
+
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+    ) -> Result<bool, PluginError> {
+        if command.data.name != "ping" {
+            return Ok(false);
+        }
+
+        // Time the deferred ACK as the Discord REST round-trip.
+        let start = Instant::now();
+        ack::defer(ctx, command).await?;
+        let rest_ms = start.elapsed().as_millis();
+
+        let statuses = self.checker.check().await;
+        command
+            .edit_response(
+                &ctx.http,
+                edit_message().content(format_ping(rest_ms, &statuses)),
+            )
+            .await
+            .map_err(PluginError::DiscordError)?;
+        Ok(true)
+    }
+}
+
+/// Render a `service  latency` table in a code block so the columns line up.
+fn format_ping(rest_ms: u128, statuses: &[ServiceStatus]) -> String {
+    let mut rows = vec![("Discord REST".to_string(), format!("{rest_ms}ms"))];
+    rows.extend(statuses.iter().map(|s| {
+        let value = match s.state {
+            ServiceState::Down => format!("DOWN ({})", s.detail),
+            _ => s.detail.clone(),
+        };
+        (s.name.clone(), value)
+    }));
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+    let mut msg = String::from("**Ping**\n```\n");
+    for (name, value) in rows {
+        msg.push_str(&format!("{name:<width$}  {value}\n"));
+    }
+    msg.push_str("```");
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(name: &str, state: ServiceState, detail: &str) -> ServiceStatus {
+        ServiceStatus {
+            name: name.into(),
+            state,
+            detail: detail.into(),
+        }
+    }
+
+    #[test]
+    fn test_format_ping() {
+        let statuses = vec![
+            status("Sonarr", ServiceState::Up, "12ms"),
+            status("qBittorrent", ServiceState::Down, "timeout"),
+        ];
+        assert_eq!(
+            format_ping(45, &statuses),
+            "**Ping**\n```\nDiscord REST  45ms\nSonarr        12ms\nqBittorrent   DOWN (timeout)\n```"
+        );
+    }
+
+    #[test]
+    fn test_format_ping_no_services() {
+        assert_eq!(format_ping(80, &[]), "**Ping**\n```\nDiscord REST  80ms\n```");
+    }
+}
@@ -1,13 +1,22 @@
 use async_trait::async_trait;
-use discord_assist_plugin_api::{Plugin, PluginError};
+use discord_assist_plugin_api::{
+    Plugin, PluginError, dry_run_message, list_embed, respond_chunked, respond_embed,
+};
+use discord_assist_util::{discord_relative, escape_discord};
+use discord_assist_util::http::{HttpOptions, build_client};
+use futures::future::join_all;
 use reqwest::Client;
 use serde::Deserialize;
-use serenity::builder::{
-    CreateCommand, CreateCommandOption, CreateInteractionResponse,
-    CreateInteractionResponseMessage,
-};
-use serenity::model::application::{CommandInteraction, CommandOptionType};
+use serenity::builder::{CreateCommand, CreateCommandOption, CreateEmbed};
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
 use serenity::prelude::Context;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How long a library's item count is reused before asking Plex again.
+const LIBRARY_COUNT_TTL: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Deserialize)]
 struct MediaContainer<T> {
@@ -42,17 +51,65 @@ struct RecentlyAdded {
 }
 
 #[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-struct RecentMetadata {
-    title: String,
+pub struct RecentMetadata {
+    pub title: String,
     #[serde(rename = "parentTitle")]
-    parent_title: Option<String>,
+    pub parent_title: Option<String>,
     #[serde(rename = "grandparentTitle")]
-    grandparent_title: Option<String>,
+    pub grandparent_title: Option<String>,
     #[serde(rename = "addedAt", default)]
-    added_at: u64,
+    pub added_at: u64,
     #[serde(rename = "type")]
-    media_type: Option<String>,
+    pub media_type: Option<String>,
+}
+
+impl RecentMetadata {
+    /// "Show — Episode" for episodes, otherwise just the title.
+    pub fn display_title(&self) -> String {
+        match &self.grandparent_title {
+            Some(show) => format!("{show} — {}", self.title),
+            None => self.title.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OnDeck {
+    #[serde(rename = "Metadata", default)]
+    metadata: Vec<OnDeckMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OnDeckMetadata {
+    title: String,
+    #[serde(rename = "grandparentTitle")]
+    grandparent_title: Option<String>,
+    /// Milliseconds watched so far.
+    #[serde(rename = "viewOffset", default)]
+    view_offset: u64,
+    /// Total length in milliseconds.
+    #[serde(default)]
+    duration: u64,
+}
+
+impl OnDeckMetadata {
+    /// "- Show — Episode [42%]", leaving off the progress when the length is unknown.
+    fn line(&self) -> String {
+        let title = match &self.grandparent_title {
+            Some(show) => format!("{show} — {}", self.title),
+            None => self.title.clone(),
+        };
+        let title = escape_discord(&title);
+        match progress(self.view_offset, self.duration) {
+            Some(percent) => format!("- {title} [{percent}%]\n"),
+            None => format!("- {title}\n"),
+        }
+    }
+}
+
+/// How far through, as a whole percentage capped at 100.
+fn progress(view_offset: u64, duration: u64) -> Option<u64> {
+    (duration > 0).then(|| (view_offset * 100 / duration).min(100))
 }
 
 #[derive(Debug, Deserialize)]
@@ -83,18 +140,22 @@ struct SessionPlayer {
     state: Option<String>,
 }
 
-struct PlexClient {
+/// Plex API client. Shared by the `/plex` command and the recently-added
+/// notification poller.
+pub struct PlexClient {
     client: Client,
     base_url: String,
     token: String,
 }
 
 impl PlexClient {
-    fn new(base_url: &str, token: &str) -> Self {
-        let client = Client::builder()
-            .danger_accept_invalid_certs(true)
-            .build()
-            .expect("Failed to build HTTP client");
+    pub fn new(base_url: &str, token: &str, accept_invalid_certs: bool) -> Self {
+        let client = build_client(&HttpOptions::new(accept_invalid_certs));
+        Self::with_client(client, base_url, token)
+    }
+
+    /// Use a shared `client` instead of building one.
+    pub fn with_client(client: Client, base_url: &str, token: &str) -> Self {
         Self {
             client,
             base_url: base_url.trim_end_matches('/').to_string(),
@@ -103,6 +164,15 @@ impl PlexClient {
     }
 
     async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, PluginError> {
+        self.send(path)
+            .await?
+            .json()
+            .await
+            .map_err(|e| PluginError::ApiError(e.to_string()))
+    }
+
+    /// GET `path`, failing on a non-success status.
+    async fn send(&self, path: &str) -> Result<reqwest::Response, PluginError> {
         let url = format!("{}{}", self.base_url, path);
         let resp = self
             .client
@@ -119,68 +189,227 @@ impl PlexClient {
                 resp.status()
             )));
         }
+        Ok(resp)
+    }
 
-        resp.json()
-            .await
-            .map_err(|e| PluginError::ApiError(e.to_string()))
+    /// Ask Plex to rescan a library section. Plex uses GET for this.
+    pub async fn refresh_section(&self, key: &str) -> Result<(), PluginError> {
+        self.send(&format!("/library/sections/{key}/refresh")).await?;
+        Ok(())
+    }
+
+    pub async fn recently_added(&self) -> Result<Vec<RecentMetadata>, PluginError> {
+        let recent: MediaContainer<RecentlyAdded> = self.get("/library/recentlyAdded").await?;
+        Ok(recent.media_container.metadata)
+    }
+
+    /// How many streams are playing (or paused) right now.
+    pub async fn session_count(&self) -> Result<usize, PluginError> {
+        let sessions: MediaContainer<Sessions> = self.get("/status/sessions").await?;
+        Ok(sessions.media_container.metadata.len())
     }
 }
 
 pub struct PlexPlugin {
     client: PlexClient,
+    /// Item counts by section key, so repeated `/plex status` stays cheap.
+    library_counts: CountCache,
+    /// Reply with what would have happened instead of starting a scan.
+    dry_run: bool,
+}
+
+/// Values remembered for a fixed time, keyed by string.
+struct CountCache {
+    entries: Mutex<HashMap<String, (Instant, u64)>>,
+    ttl: Duration,
+}
+
+impl CountCache {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<u64> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(key)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.ttl)
+            .map(|(_, count)| *count)
+    }
+
+    fn insert(&self, key: &str, count: u64) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(key.to_string(), (Instant::now(), count));
+    }
+}
+
+/// The sections named `library` (case-insensitive), or all of them when no
+/// name is given. An unknown name lists the valid ones.
+fn select_sections<'a>(
+    dirs: &'a [LibraryDirectory],
+    library: Option<&str>,
+) -> Result<Vec<&'a LibraryDirectory>, String> {
+    let Some(name) = library.map(str::trim) else {
+        return Ok(dirs.iter().collect());
+    };
+    let matches: Vec<_> = dirs.iter().filter(|d| d.title.eq_ignore_ascii_case(name)).collect();
+    if !matches.is_empty() {
+        return Ok(matches);
+    }
+    let names: Vec<_> = dirs.iter().map(|d| escape_discord(&d.title)).collect();
+    Err(format!(
+        "No library named **{}**. Libraries: {}",
+        escape_discord(name),
+        names.join(", ")
+    ))
+}
+
+/// One `(title, "N items (type)")` field per library sorted by title,
+/// fetching every count at once rather than one after another. A library
+/// whose count fails is marked unavailable instead of failing the rest.
+async fn library_fields<F, Fut>(dirs: &[LibraryDirectory], count: F) -> Vec<(String, String)>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<u64, PluginError>>,
+{
+    let counts = join_all(dirs.iter().map(|dir| count(dir.key.clone()))).await;
+    let mut fields: Vec<_> = dirs
+        .iter()
+        .zip(counts)
+        .map(|(dir, count)| {
+            let value = match count {
+                Ok(count) => format!("{count} items ({})", dir.lib_type),
+                Err(e) => {
+                    warn!("Plex library {} count failed: {e}", dir.title);
+                    format!("unavailable ({})", dir.lib_type)
+                }
+            };
+            (escape_discord(&dir.title), value)
+        })
+        .collect();
+    fields.sort_by_key(|(title, _)| title.to_lowercase());
+    fields
 }
 
 impl PlexPlugin {
-    pub fn new(api_url: &str, api_key: &str) -> Self {
+    pub fn new(api_url: &str, api_key: &str, accept_invalid_certs: bool, dry_run: bool) -> Self {
+        Self::with_client(PlexClient::new(api_url, api_key, accept_invalid_certs), dry_run)
+    }
+
+    pub fn with_client(client: PlexClient, dry_run: bool) -> Self {
         Self {
-            client: PlexClient::new(api_url, api_key),
+            client,
+            library_counts: CountCache::new(LIBRARY_COUNT_TTL),
+            dry_run,
+        }
+    }
+
+    async fn library_count(&self, key: &str) -> Result<u64, PluginError> {
+        if let Some(count) = self.library_counts.get(key) {
+            return Ok(count);
         }
+        let size_resp: MediaContainer<LibrarySize> = self
+            .client
+            .get(&format!("/library/sections/{key}/all?X-Plex-Container-Size=0"))
+            .await?;
+        let count = size_resp.media_container.total_size;
+        self.library_counts.insert(key, count);
+        Ok(count)
     }
 
-    async fn handle_status(&self) -> Result<String, PluginError> {
+    async fn library_status(&self) -> Result<Vec<(String, String)>, PluginError> {
         let sections: MediaContainer<LibrarySections> =
             self.client.get("/library/sections").await?;
 
-        let mut lines = vec![String::from("**Plex Library Status**")];
-        for dir in &sections.media_container.directories {
-            let size_resp: MediaContainer<LibrarySize> = self
-                .client
-                .get(&format!(
-                    "/library/sections/{}/all?X-Plex-Container-Size=0",
-                    dir.key
-                ))
-                .await?;
-            let count = size_resp.media_container.total_size;
-            lines.push(format!("- {}: {} items ({})", dir.title, count, dir.lib_type));
-        }
-        Ok(lines.join("\n"))
+        Ok(library_fields(&sections.media_container.directories, |key| async move {
+            self.library_count(&key).await
+        })
+        .await)
+    }
+
+    async fn status_embed(&self) -> Result<CreateEmbed, PluginError> {
+        let libraries = self.library_status().await?;
+        let embed = list_embed("Plex Library Status", &libraries);
+        Ok(if libraries.is_empty() { embed.description("No libraries found.") } else { embed })
     }
 
     async fn handle_recent(&self) -> Result<String, PluginError> {
-        let recent: MediaContainer<RecentlyAdded> =
-            self.client.get("/library/recentlyAdded").await?;
+        let recent = self.client.recently_added().await?;
 
-        if recent.media_container.metadata.is_empty() {
+        if recent.is_empty() {
             return Ok("No recently added items.".into());
         }
 
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-
         let mut msg = String::from("**Recently Added**\n");
-        for item in recent.media_container.metadata.iter().take(10) {
-            let display = match (&item.grandparent_title, &item.parent_title) {
-                (Some(show), _) => format!("{show} — {}", item.title),
-                _ => item.title.clone(),
-            };
-            let ago = format_relative_time(now, item.added_at);
+        for item in recent.iter().take(10) {
+            let display = escape_discord(&item.display_title());
+            let ago = discord_relative(item.added_at);
             msg.push_str(&format!("- {display} ({ago})\n"));
         }
         Ok(msg)
     }
 
+    async fn handle_scan(&self, library: Option<&str>) -> Result<String, PluginError> {
+        let sections: MediaContainer<LibrarySections> =
+            self.client.get("/library/sections").await?;
+        let selected = match select_sections(&sections.media_container.directories, library) {
+            Ok(selected) if selected.is_empty() => return Ok("No libraries found.".into()),
+            Ok(selected) => selected,
+            Err(msg) => return Ok(msg),
+        };
+        let titles = |dirs: &[&LibraryDirectory]| {
+            dirs.iter()
+                .map(|d| format!("**{}**", escape_discord(&d.title)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        if self.dry_run {
+            return Ok(dry_run_message(&format!("scanned {}", titles(&selected))));
+        }
+
+        let results = join_all(selected.iter().map(|d| self.client.refresh_section(&d.key))).await;
+        let (mut started, mut failed) = (Vec::new(), Vec::new());
+        for (dir, result) in selected.into_iter().zip(results) {
+            match result {
+                Ok(()) => started.push(dir),
+                Err(e) => {
+                    warn!("Plex scan of {} failed: {e}", dir.title);
+                    failed.push(dir);
+                }
+            }
+        }
+
+        let mut msg = String::new();
+        if !started.is_empty() {
+            msg.push_str(&format!("Scan started for {}.", titles(&started)));
+        }
+        if !failed.is_empty() {
+            if !msg.is_empty() {
+                msg.push('\n');
+            }
+            msg.push_str(&format!("Couldn't start a scan for {}.", titles(&failed)));
+        }
+        Ok(msg)
+    }
+
+    async fn handle_ondeck(&self) -> Result<String, PluginError> {
+        let on_deck: MediaContainer<OnDeck> = self.client.get("/library/onDeck").await?;
+
+        if on_deck.media_container.metadata.is_empty() {
+            return Ok("Nothing on deck.".into());
+        }
+
+        let mut msg = String::from("**On Deck**\n");
+        for item in on_deck.media_container.metadata.iter().take(10) {
+            msg.push_str(&item.line());
+        }
+        Ok(msg)
+    }
+
     async fn handle_streams(&self) -> Result<String, PluginError> {
         let sessions: MediaContainer<Sessions> = self.client.get("/status/sessions").await?;
 
@@ -209,6 +438,8 @@ impl PlexPlugin {
                 Some(show) => format!("{show} — {}", s.title),
                 None => s.title.clone(),
             };
+            let (user, title) = (escape_discord(user), escape_discord(&title));
+            let (state, device) = (escape_discord(state), escape_discord(device));
             msg.push_str(&format!("- **{user}**: {title} [{state}] ({device})\n"));
         }
         Ok(msg)
@@ -234,11 +465,42 @@ impl Plugin for PlexPlugin {
                 "recent",
                 "Show recently added media",
             ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "ondeck",
+                "Show what you can continue watching",
+            ))
             .add_option(CreateCommandOption::new(
                 CommandOptionType::SubCommand,
                 "streams",
                 "Show active streams",
-            ))]
+            ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "scan",
+                    "Rescan a library, or all of them",
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "library",
+                    "Library name (default: all)",
+                )),
+            )]
+    }
+
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        matches!(path, ["plex", "status" | "recent" | "ondeck" | "streams"])
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![
+            ("/plex status".into(), "Show library counts".into()),
+            ("/plex recent".into(), "Show recently added media".into()),
+            ("/plex ondeck".into(), "Show what you can continue watching".into()),
+            ("/plex streams".into(), "Show active streams".into()),
+            ("/plex scan [library]".into(), "Rescan a library, or all of them".into()),
+        ]
     }
 
     async fn handle_command(
@@ -257,62 +519,243 @@ impl Plugin for PlexPlugin {
         };
 
         let content = match subopt.name {
-            "status" => self.handle_status().await?,
+            "status" => {
+                respond_embed(ctx, command, self.status_embed().await?).await?;
+                return Ok(true);
+            }
             "recent" => self.handle_recent().await?,
+            "ondeck" => self.handle_ondeck().await?,
+            "scan" => {
+                let library = match &subopt.value {
+                    ResolvedValue::SubCommand(opts) => {
+                        opts.iter().find(|o| o.name == "library").and_then(|o| match &o.value {
+                            ResolvedValue::String(s) => Some(*s),
+                            _ => None,
+                        })
+                    }
+                    _ => None,
+                };
+                self.handle_scan(library).await?
+            }
             "streams" => self.handle_streams().await?,
             _ => return Ok(false),
         };
 
-        let data = CreateInteractionResponseMessage::new().content(content);
-        let builder = CreateInteractionResponse::Message(data);
-        command
-            .create_response(&ctx.http, builder)
-            .await
-            .map_err(PluginError::DiscordError)?;
+        respond_chunked(ctx, command, &content).await?;
         Ok(true)
     }
 }
 
-fn format_relative_time(now: u64, timestamp: u64) -> String {
-    if timestamp == 0 || timestamp > now {
-        return "just now".into();
-    }
-    let diff = now - timestamp;
-    if diff < 3600 {
-        format!("{}m ago", diff / 60)
-    } else if diff < 86400 {
-        format!("{}h ago", diff / 3600)
-    } else {
-        format!("{}d ago", diff / 86400)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     #[test]
-    fn test_relative_time_minutes() {
-        assert_eq!(format_relative_time(1000, 400), "10m ago");
+    fn test_display_title() {
+        let mut item = RecentMetadata {
+            title: "Pilot".into(),
+            parent_title: Some("Season 1".into()),
+            grandparent_title: Some("Severance".into()),
+            added_at: 0,
+            media_type: Some("episode".into()),
+        };
+        assert_eq!(item.display_title(), "Severance — Pilot");
+
+        item.grandparent_title = None;
+        assert_eq!(item.display_title(), "Pilot");
     }
 
     #[test]
-    fn test_relative_time_hours() {
-        assert_eq!(format_relative_time(10000, 10000 - 7200), "2h ago");
+    fn on_deck_progress() {
+        assert_eq!(progress(0, 0), None);
+        assert_eq!(progress(1_260_000, 3_000_000), Some(42));
+        assert_eq!(progress(3_100_000, 3_000_000), Some(100));
+
+        let item: OnDeckMetadata = serde_json::from_value(serde_json::json!({
+            "title": "Half Loop",
+            "grandparentTitle": "Severance",
+            "viewOffset": 1_260_000,
+            "duration": 3_000_000,
+        }))
+        .unwrap();
+        assert_eq!(item.line(), "- Severance — Half Loop [42%]\n");
+
+        let hostile: OnDeckMetadata =
+            serde_json::from_value(serde_json::json!({"title": "**x** @everyone"})).unwrap();
+        assert_eq!(hostile.line(), "- \\*\\*x\\*\\* \\@everyone\n");
+
+        let movie: OnDeckMetadata = serde_json::from_value(serde_json::json!({"title": "Heat"})).unwrap();
+        assert_eq!(movie.line(), "- Heat\n");
     }
 
-    #[test]
-    fn test_relative_time_days() {
-        assert_eq!(format_relative_time(200000, 200000 - 172800), "2d ago");
+    fn dir(title: &str, lib_type: &str, key: &str) -> LibraryDirectory {
+        LibraryDirectory {
+            title: title.into(),
+            lib_type: lib_type.into(),
+            key: key.into(),
+        }
     }
 
     #[test]
-    fn test_relative_time_future() {
-        assert_eq!(format_relative_time(100, 200), "just now");
+    fn scan_selects_named_or_all_libraries() {
+        let dirs = vec![dir("Movies", "movie", "1"), dir("TV Shows", "show", "2")];
+        let keys = |library| {
+            select_sections(&dirs, library).map(|s| s.iter().map(|d| d.key.as_str()).collect::<Vec<_>>())
+        };
+        assert_eq!(keys(None), Ok(vec!["1", "2"]));
+        assert_eq!(keys(Some("tv shows")), Ok(vec!["2"]));
+        assert_eq!(
+            keys(Some("Anime")),
+            Err("No library named **Anime**. Libraries: Movies, TV Shows".into())
+        );
+    }
+
+    async fn mock_sections(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path("/library/sections"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "MediaContainer": {"Directory": [
+                    {"title": "Movies", "type": "movie", "key": "1"},
+                    {"title": "TV Shows", "type": "show", "key": "2"},
+                ]}
+            })))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn scan_refreshes_every_library() {
+        let server = MockServer::start().await;
+        mock_sections(&server).await;
+        for key in ["1", "2"] {
+            Mock::given(method("GET"))
+                .and(path(format!("/library/sections/{key}/refresh")))
+                .respond_with(ResponseTemplate::new(200))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let plugin = PlexPlugin::new(&server.uri(), "token", false, false);
+        assert_eq!(
+            plugin.handle_scan(None).await.unwrap(),
+            "Scan started for **Movies**, **TV Shows**."
+        );
+    }
+
+    #[tokio::test]
+    async fn dry_run_scan_skips_api() {
+        let server = MockServer::start().await;
+        mock_sections(&server).await;
+        Mock::given(method("GET"))
+            .and(path("/library/sections/1/refresh"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let plugin = PlexPlugin::new(&server.uri(), "token", false, true);
+        assert_eq!(
+            plugin.handle_scan(Some("movies")).await.unwrap(),
+            "[dry-run] would have scanned **Movies**"
+        );
+    }
+
+    /// A fake count that finishes later the smaller the key is, so the
+    /// concurrent requests complete out of order.
+    async fn fake_count(key: String) -> Result<u64, PluginError> {
+        let n: u64 = key.parse().unwrap();
+        for _ in 0..(10 - n) {
+            tokio::task::yield_now().await;
+        }
+        Ok(n * 100)
+    }
+
+    #[tokio::test]
+    async fn concurrent_counts_match_sequential() {
+        let dirs = vec![dir("Movies", "movie", "1"), dir("TV", "show", "2"), dir("Music", "artist", "3")];
+
+        let mut sequential = Vec::new();
+        for d in &dirs {
+            let count = fake_count(d.key.clone()).await.unwrap();
+            sequential.push((d.title.clone(), format!("{count} items ({})", d.lib_type)));
+        }
+        sequential.sort();
+
+        let concurrent = library_fields(&dirs, fake_count).await;
+        assert_eq!(concurrent, sequential);
+        assert_eq!(concurrent[0], ("Movies".to_string(), "100 items (movie)".to_string()));
+        assert_eq!(concurrent[1].0, "Music");
+    }
+
+    #[tokio::test]
+    async fn failed_count_is_marked_unavailable() {
+        let dirs = vec![dir("TV", "show", "bad"), dir("Movies", "movie", "1")];
+        let fields = library_fields(&dirs, |key| async move {
+            key.parse::<u64>().map_err(|e| PluginError::ApiError(e.to_string()))
+        })
+        .await;
+        assert_eq!(
+            fields,
+            vec![
+                ("Movies".to_string(), "1 items (movie)".to_string()),
+                ("TV".to_string(), "unavailable (show)".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn status_queries_every_section() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/library/sections"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "MediaContainer": {"Directory": [
+                    {"title": "TV Shows", "type": "show", "key": "2"},
+                    {"title": "Movies", "type": "movie", "key": "1"},
+                    {"title": "Music", "type": "artist", "key": "3"},
+                ]}
+            })))
+            .mount(&server)
+            .await;
+        for (key, total) in [("1", 812), ("2", 95)] {
+            Mock::given(method("GET"))
+                .and(path(format!("/library/sections/{key}/all")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "MediaContainer": {"totalSize": total}
+                })))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/library/sections/3/all"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let plugin = PlexPlugin::new(&server.uri(), "token", false, false);
+        let expected = vec![
+            ("Movies".to_string(), "812 items (movie)".to_string()),
+            ("Music".to_string(), "unavailable (artist)".to_string()),
+            ("TV Shows".to_string(), "95 items (show)".to_string()),
+        ];
+        assert_eq!(plugin.library_status().await.unwrap(), expected);
+        // Counts that succeeded come from the cache the second time.
+        assert_eq!(plugin.library_status().await.unwrap(), expected);
     }
 
     #[test]
-    fn test_relative_time_zero() {
-        assert_eq!(format_relative_time(100, 0), "just now");
+    fn cached_counts_expire() {
+        let cache = CountCache::new(Duration::from_secs(60));
+        cache.insert("1", 42);
+        assert_eq!(cache.get("1"), Some(42));
+        assert_eq!(cache.get("2"), None);
+
+        let expired = CountCache::new(Duration::ZERO);
+        expired.insert("1", 42);
+        assert_eq!(expired.get("1"), None);
     }
 }
@@ -1,17 +1,21 @@
 use async_trait::async_trait;
-use discord_assist_arr_common::ArrClient;
-use discord_assist_plugin_api::{Plugin, PluginError};
-use serde::Deserialize;
-use serenity::builder::{
-    CreateCommand, CreateCommandOption, CreateInteractionResponse,
-    CreateInteractionResponseMessage,
+use discord_assist_arr_common::{ArrClient, ArrError};
+use discord_assist_plugin_api::{
+    Plugin, PluginError, dry_run_message, list_embed, respond_chunked, respond_embed,
 };
+use discord_assist_util::{escape_discord, iso_date_days_ago};
+use discord_assist_util::size::{self, format_size};
+use serde::Deserialize;
+use serenity::builder::{CreateCommand, CreateCommandOption};
 use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
 use serenity::prelude::Context;
+use tracing::warn;
 
 #[derive(Debug, Deserialize)]
 struct Indexer {
+    id: u32,
     name: String,
+    enable: Option<bool>,
     #[serde(rename = "enableRss")]
     enable_rss: Option<bool>,
     #[serde(rename = "enableSearch")]
@@ -32,14 +36,267 @@ struct HealthCheck {
     message: Option<String>,
 }
 
+/// How far back `/prowlarr status` counts grabs and queries.
+const STATS_DAYS: u64 = 30;
+
+/// How many indexers `/prowlarr status` lists, busiest first.
+const STATS_TOP: usize = 5;
+
+#[derive(Debug, Default, Deserialize)]
+struct IndexerStatsResponse {
+    #[serde(default)]
+    indexers: Vec<IndexerStats>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexerStats {
+    indexer_name: String,
+    #[serde(default)]
+    number_of_grabs: u64,
+    #[serde(default)]
+    number_of_queries: u64,
+    #[serde(default)]
+    number_of_failed_queries: u64,
+}
+
+/// One entry of the `indexer/testall` response.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexerTestResult {
+    id: u32,
+    is_valid: bool,
+    #[serde(default)]
+    validation_failures: Vec<ValidationFailure>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ValidationFailure {
+    error_message: String,
+}
+
+/// Indexer flags flipped together by `/prowlarr toggle`. Prowlarr itself only
+/// has `enable`; the others are set too when an indexer carries them.
+const ENABLE_FLAGS: [&str; 3] = ["enable", "enableRss", "enableSearch"];
+
 pub struct ProwlarrPlugin {
     client: ArrClient,
+    /// Reply with what would have happened instead of toggling indexers.
+    dry_run: bool,
 }
 
 impl ProwlarrPlugin {
-    pub fn new(api_url: &str, api_key: &str) -> Self {
-        Self {
-            client: ArrClient::with_api_version(api_url, api_key, "v1"),
+    pub fn new(api_url: &str, api_key: &str, dry_run: bool) -> Self {
+        Self::with_client(ArrClient::with_api_version(api_url, api_key, "v1"), dry_run)
+    }
+
+    pub fn with_client(client: ArrClient, dry_run: bool) -> Self {
+        Self { client, dry_run }
+    }
+
+    /// Health issues, then grab/query counts for the busiest indexers.
+    async fn handle_status(&self) -> Result<String, PluginError> {
+        let health: Vec<HealthCheck> = self
+            .client
+            .get("health")
+            .await?;
+
+        let mut msg = if health.is_empty() {
+            "**Prowlarr Status:** All healthy".to_string()
+        } else {
+            let mut msg = String::from("**Prowlarr Health Issues:**");
+            for h in &health {
+                let source = escape_discord(h.source.as_deref().unwrap_or("unknown"));
+                let message = escape_discord(h.message.as_deref().unwrap_or("no details"));
+                msg.push_str(&format!("\n- **{source}**: {message}"));
+            }
+            msg
+        };
+
+        // Stats are extra; still report health if they can't be fetched.
+        let start = iso_date_days_ago(STATS_DAYS);
+        match self
+            .client
+            .get_with_params::<IndexerStatsResponse>("indexerstats", &[("startDate", start.as_str())])
+            .await
+        {
+            Ok(stats) => {
+                msg.push_str("\n\n");
+                msg.push_str(&format_stats(&stats.indexers));
+            }
+            Err(e) => warn!("Failed to fetch Prowlarr indexer stats: {e}"),
+        }
+        Ok(msg)
+    }
+
+    /// Test every enabled indexer's connectivity and report each result.
+    async fn handle_test(&self) -> Result<String, PluginError> {
+        let indexers: Vec<Indexer> = self
+            .client
+            .get("indexer")
+            .await?;
+        // Prowlarr answers 400 with the same result array when any test fails.
+        let results: Vec<IndexerTestResult> =
+            match self.client.post("indexer/testall", &serde_json::json!({})).await {
+                Ok(results) => results,
+                Err(ArrError::Api { status: 400, body }) => serde_json::from_str(&body)
+                    .map_err(|e| PluginError::ApiError(format!("unexpected test response: {e}")))?,
+                Err(e) => return Err(e.into()),
+            };
+        Ok(format_test_results(&results, &indexers))
+    }
+
+    /// Enable or disable the indexer matching `name`. The PUT replaces the
+    /// whole indexer, so the full object is fetched, flipped, and sent back.
+    async fn handle_toggle(&self, name: &str) -> Result<String, PluginError> {
+        let indexers: Vec<Indexer> = self
+            .client
+            .get("indexer")
+            .await?;
+        let indexer = find_indexer(&indexers, name).map_err(PluginError::Other)?;
+
+        let endpoint = format!("indexer/{}", indexer.id);
+        let mut full: serde_json::Value = self.client.get(&endpoint).await?;
+        let enable = !full.get("enable").and_then(serde_json::Value::as_bool).unwrap_or(false);
+        let verb = if enable { "enabled" } else { "disabled" };
+        if self.dry_run {
+            let name = escape_discord(&indexer.name);
+            return Ok(dry_run_message(&format!("{verb} indexer **{name}**")));
+        }
+
+        set_enabled(&mut full, enable);
+        let updated: serde_json::Value = self.client.put(&endpoint, &full).await?;
+        let state = match updated.get("enable").and_then(serde_json::Value::as_bool) {
+            Some(true) => "enabled",
+            Some(false) => "disabled",
+            None => verb,
+        };
+        Ok(format!("Indexer **{}** is now **{state}**", escape_discord(&indexer.name)))
+    }
+}
+
+/// The busiest indexers by grabs as an aligned table in a code block.
+/// One embed field per indexer: its enabled features, e.g. `RSS, Search`,
+/// and whether it's disabled.
+fn indexer_fields(indexers: &[Indexer]) -> Vec<(String, String)> {
+    indexers
+        .iter()
+        .map(|idx| {
+            let mut parts = Vec::new();
+            if idx.enable_rss.unwrap_or(false) {
+                parts.push("RSS");
+            }
+            if idx.enable_search.unwrap_or(false) {
+                parts.push("Search");
+            }
+            let mut value = parts.join(", ");
+            if idx.enable == Some(false) {
+                if !value.is_empty() {
+                    value.push_str(" · ");
+                }
+                value.push_str("disabled");
+            }
+            (escape_discord(&idx.name), value)
+        })
+        .collect()
+}
+
+fn format_stats(stats: &[IndexerStats]) -> String {
+    let mut active: Vec<&IndexerStats> = stats
+        .iter()
+        .filter(|s| s.number_of_grabs > 0 || s.number_of_queries > 0)
+        .collect();
+    if active.is_empty() {
+        return format!("No indexer activity in the last {STATS_DAYS} days.");
+    }
+    active.sort_by(|a, b| {
+        b.number_of_grabs
+            .cmp(&a.number_of_grabs)
+            .then(b.number_of_queries.cmp(&a.number_of_queries))
+    });
+    active.truncate(STATS_TOP);
+
+    let width = active
+        .iter()
+        .map(|s| s.indexer_name.len())
+        .chain(["Indexer".len()])
+        .max()
+        .unwrap_or(0);
+    let mut msg = format!("**Top indexers (last {STATS_DAYS} days)**\n```\n");
+    msg.push_str(&format!("{:<width$}  {:>6}  {:>7}  {:>6}\n", "Indexer", "Grabs", "Queries", "Failed"));
+    for s in active {
+        // Inside a code block only a backtick can break out.
+        msg.push_str(&format!(
+            "{:<width$}  {:>6}  {:>7}  {:>6}\n",
+            s.indexer_name.replace('`', "'"),
+            s.number_of_grabs, s.number_of_queries, s.number_of_failed_queries
+        ));
+    }
+    msg.push_str("```");
+    msg
+}
+
+/// One line per indexer, failures first, or a single line when all passed.
+fn format_test_results(results: &[IndexerTestResult], indexers: &[Indexer]) -> String {
+    if results.is_empty() {
+        return "No indexers to test.".into();
+    }
+    let name = |id: u32| {
+        indexers
+            .iter()
+            .find(|i| i.id == id)
+            .map_or_else(|| format!("indexer {id}"), |i| escape_discord(&i.name))
+    };
+    if results.iter().all(|r| r.is_valid) {
+        return format!("**Indexer test:** all {} passed", results.len());
+    }
+
+    let mut msg = String::from("**Indexer test:**\n");
+    for r in results.iter().filter(|r| !r.is_valid) {
+        let reason = r
+            .validation_failures
+            .iter()
+            .map(|f| escape_discord(&f.error_message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let reason = if reason.is_empty() { "no details".into() } else { reason };
+        msg.push_str(&format!("- **{}**: FAILED — {reason}\n", name(r.id)));
+    }
+    for r in results.iter().filter(|r| r.is_valid) {
+        msg.push_str(&format!("- **{}**: OK\n", name(r.id)));
+    }
+    msg
+}
+
+/// The indexer named `name` (case-insensitive), or the only one containing
+/// it. Errors are the reply text.
+fn find_indexer<'a>(indexers: &'a [Indexer], name: &str) -> Result<&'a Indexer, String> {
+    let lower = name.to_lowercase();
+    if let Some(exact) = indexers.iter().find(|i| i.name.to_lowercase() == lower) {
+        return Ok(exact);
+    }
+    let matches: Vec<&Indexer> = indexers
+        .iter()
+        .filter(|i| i.name.to_lowercase().contains(&lower))
+        .collect();
+    match matches.as_slice() {
+        [] => Err(format!("No indexer matching \"{}\"", escape_discord(name))),
+        [one] => Ok(one),
+        many => Err(format!(
+            "{} indexers match \"{}\" — be more specific",
+            many.len(),
+            escape_discord(name)
+        )),
+    }
+}
+
+/// Set `enable`, and whichever other [`ENABLE_FLAGS`] `indexer` carries.
+fn set_enabled(indexer: &mut serde_json::Value, enable: bool) {
+    let Some(obj) = indexer.as_object_mut() else { return };
+    for flag in ENABLE_FLAGS {
+        if obj.contains_key(flag) || flag == "enable" {
+            obj.insert(flag.into(), serde_json::Value::Bool(enable));
         }
     }
 }
@@ -79,7 +336,41 @@ impl Plugin for ProwlarrPlugin {
                 CommandOptionType::SubCommand,
                 "status",
                 "Indexer health overview",
-            ))]
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "test",
+                "Test connectivity of every indexer",
+            ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "toggle",
+                    "Enable or disable an indexer",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "Indexer name",
+                    )
+                    .required(true),
+                ),
+            )]
+    }
+
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        matches!(path, ["prowlarr", "indexers" | "search" | "status"])
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![
+            ("/prowlarr indexers".into(), "List configured indexers".into()),
+            ("/prowlarr search".into(), "Search across all indexers".into()),
+            ("/prowlarr status".into(), "Indexer health overview".into()),
+            ("/prowlarr test".into(), "Test connectivity of every indexer".into()),
+            ("/prowlarr toggle".into(), "Enable or disable an indexer".into()),
+        ]
     }
 
     async fn handle_command(
@@ -102,39 +393,16 @@ impl Plugin for ProwlarrPlugin {
                 let indexers: Vec<Indexer> = self
                     .client
                     .get("indexer")
-                    .await
-                    .map_err(|e| PluginError::ApiError(e.to_string()))?;
+                    .await?;
 
-                if indexers.is_empty() {
-                    "No indexers configured.".into()
+                let embed = list_embed("Indexers", &indexer_fields(&indexers));
+                let embed = if indexers.is_empty() {
+                    embed.description("No indexers configured.")
                 } else {
-                    let mut msg = String::from("**Indexers:**\n");
-                    for idx in &indexers {
-                        let rss = if idx.enable_rss.unwrap_or(false) {
-                            "RSS"
-                        } else {
-                            ""
-                        };
-                        let search = if idx.enable_search.unwrap_or(false) {
-                            "Search"
-                        } else {
-                            ""
-                        };
-                        let features = [rss, search]
-                            .iter()
-                            .filter(|s| !s.is_empty())
-                            .cloned()
-                            .collect::<Vec<_>>()
-                            .join(", ");
-                        let features_str = if features.is_empty() {
-                            String::new()
-                        } else {
-                            format!(" [{}]", features)
-                        };
-                        msg.push_str(&format!("- **{}**{}\n", idx.name, features_str));
-                    }
-                    msg
-                }
+                    embed
+                };
+                respond_embed(ctx, command, embed).await?;
+                return Ok(true);
             }
             "search" => {
                 if let ResolvedValue::SubCommand(opts) = &subopt.value {
@@ -150,9 +418,9 @@ impl Plugin for ProwlarrPlugin {
                     let results: Vec<SearchResult> = self
                         .client
                         .get_with_params("search", &[("query", query)])
-                        .await
-                        .map_err(|e| PluginError::ApiError(e.to_string()))?;
+                        .await?;
 
+                    let query = escape_discord(query);
                     if results.is_empty() {
                         format!("No results for \"{query}\"")
                     } else {
@@ -160,13 +428,13 @@ impl Plugin for ProwlarrPlugin {
                         for (i, r) in results.iter().take(10).enumerate() {
                             let size = r
                                 .size
-                                .map(|s| format!(" ({:.1} MB)", s as f64 / 1_048_576.0))
+                                .map(|s| format!(" ({})", format_size(s, size::unit())))
                                 .unwrap_or_default();
-                            let indexer = r.indexer_name.as_deref().unwrap_or("unknown");
+                            let indexer = escape_discord(r.indexer_name.as_deref().unwrap_or("unknown"));
                             msg.push_str(&format!(
                                 "{}. **{}**{} — {}\n",
                                 i + 1,
-                                r.title,
+                                escape_discord(&r.title),
                                 size,
                                 indexer
                             ));
@@ -177,35 +445,212 @@ impl Plugin for ProwlarrPlugin {
                     return Ok(false);
                 }
             }
-            "status" => {
-                let health: Vec<HealthCheck> = self
-                    .client
-                    .get("health")
-                    .await
-                    .map_err(|e| PluginError::ApiError(e.to_string()))?;
-
-                if health.is_empty() {
-                    "**Prowlarr Status:** All healthy".into()
+            "status" => self.handle_status().await?,
+            "test" => self.handle_test().await?,
+            "toggle" => {
+                if let ResolvedValue::SubCommand(opts) = &subopt.value {
+                    let name = opts
+                        .iter()
+                        .find(|o| o.name == "name")
+                        .and_then(|o| match &o.value {
+                            ResolvedValue::String(s) => Some(*s),
+                            _ => None,
+                        })
+                        .ok_or_else(|| PluginError::Other("Missing name".into()))?;
+                    self.handle_toggle(name).await?
                 } else {
-                    let mut msg = String::from("**Prowlarr Health Issues:**\n");
-                    for h in &health {
-                        let source = h.source.as_deref().unwrap_or("unknown");
-                        let message = h.message.as_deref().unwrap_or("no details");
-                        msg.push_str(&format!("- **{source}**: {message}\n"));
-                    }
-                    msg
+                    return Ok(false);
                 }
             }
             _ => return Ok(false),
         };
 
-        let data = CreateInteractionResponseMessage::new().content(content);
-        let builder = CreateInteractionResponse::Message(data);
-        command
-            .create_response(&ctx.http, builder)
-            .await
-            .map_err(PluginError::DiscordError)?;
+        respond_chunked(ctx, command, &content).await?;
         Ok(true)
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn indexer(id: u32, name: &str) -> Indexer {
+        Indexer { id, name: name.into(), enable: Some(true), enable_rss: None, enable_search: None }
+    }
+
+    #[test]
+    fn test_find_indexer() {
+        let indexers = [indexer(1, "Nyaa"), indexer(2, "Nyaa.si Mirror"), indexer(3, "1337x")];
+        assert_eq!(find_indexer(&indexers, "nyaa").unwrap().id, 1);
+        assert_eq!(find_indexer(&indexers, "1337").unwrap().id, 3);
+        assert_eq!(
+            find_indexer(&indexers, "a").unwrap_err(),
+            "2 indexers match \"a\" — be more specific"
+        );
+        assert_eq!(find_indexer(&indexers, "rarbg").unwrap_err(), "No indexer matching \"rarbg\"");
+    }
+
+    #[test]
+    fn test_indexer_fields() {
+        let mut both = indexer(1, "Nyaa");
+        both.enable_rss = Some(true);
+        both.enable_search = Some(true);
+        let mut off = indexer(2, "1337x");
+        off.enable = Some(false);
+        off.enable_search = Some(true);
+        let mut bare = indexer(3, "Dead");
+        bare.enable = Some(false);
+        assert_eq!(
+            indexer_fields(&[both, off, bare, indexer(4, "Plain")]),
+            vec![
+                ("Nyaa".into(), "RSS, Search".into()),
+                ("1337x".into(), "Search · disabled".into()),
+                ("Dead".into(), "disabled".into()),
+                ("Plain".into(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_mixed_test_results() {
+        let results: Vec<IndexerTestResult> = serde_json::from_str(
+            r#"[
+                {"id": 1, "isValid": true, "validationFailures": []},
+                {"id": 3, "isValid": false, "validationFailures": [
+                    {"propertyName": "BaseUrl", "errorMessage": "Unable to connect to indexer", "severity": "error"},
+                    {"propertyName": "", "errorMessage": "Cloudflare protection", "severity": "error"}
+                ]},
+                {"id": 9, "isValid": false}
+            ]"#,
+        )
+        .unwrap();
+        let indexers = [indexer(1, "Nyaa"), indexer(3, "1337x")];
+        assert_eq!(
+            format_test_results(&results, &indexers),
+            "**Indexer test:**\n\
+             - **1337x**: FAILED — Unable to connect to indexer; Cloudflare protection\n\
+             - **indexer 9**: FAILED — no details\n\
+             - **Nyaa**: OK\n"
+        );
+    }
+
+    #[test]
+    fn test_malicious_indexer_name_is_escaped() {
+        let results: Vec<IndexerTestResult> =
+            serde_json::from_str(r#"[{"id": 1, "isValid": false, "validationFailures": [
+                {"errorMessage": "ping <@123>"}
+            ]}]"#)
+            .unwrap();
+        let indexers = [indexer(1, "**x** @everyone")];
+        assert_eq!(
+            format_test_results(&results, &indexers),
+            "**Indexer test:**\n- **\\*\\*x\\*\\* \\@everyone**: FAILED — ping \\<\\@123>\n"
+        );
+    }
+
+    #[test]
+    fn test_format_all_passed() {
+        let results: Vec<IndexerTestResult> =
+            serde_json::from_str(r#"[{"id": 1, "isValid": true}, {"id": 3, "isValid": true}]"#).unwrap();
+        assert_eq!(format_test_results(&results, &[]), "**Indexer test:** all 2 passed");
+        assert_eq!(format_test_results(&[], &[]), "No indexers to test.");
+    }
+
+    #[tokio::test]
+    async fn test_testall_reads_failures_from_400() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/indexer"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"id": 3, "name": "1337x", "enable": true},
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/indexer/testall"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!([
+                {"id": 3, "isValid": false, "validationFailures": [{"errorMessage": "timed out"}]},
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let plugin = ProwlarrPlugin::new(&server.uri(), "key", false);
+        let msg = plugin.handle_test().await.unwrap();
+        assert_eq!(msg, "**Indexer test:**\n- **1337x**: FAILED — timed out\n");
+    }
+
+    #[test]
+    fn test_format_stats_top_by_grabs() {
+        let response: IndexerStatsResponse = serde_json::from_str(
+            r#"{"indexers": [
+                {"indexerId": 1, "indexerName": "Nyaa", "numberOfGrabs": 4, "numberOfQueries": 120, "numberOfFailedQueries": 2},
+                {"indexerId": 3, "indexerName": "1337x", "numberOfGrabs": 12, "numberOfQueries": 340, "numberOfFailedQueries": 0},
+                {"indexerId": 5, "indexerName": "Idle", "numberOfGrabs": 0, "numberOfQueries": 0}
+            ], "userAgents": [], "hosts": []}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            format_stats(&response.indexers),
+            "**Top indexers (last 30 days)**\n```\n\
+             Indexer   Grabs  Queries  Failed\n\
+             1337x        12      340       0\n\
+             Nyaa          4      120       2\n\
+             ```"
+        );
+    }
+
+    #[test]
+    fn test_format_stats_empty() {
+        let response: IndexerStatsResponse = serde_json::from_str("{}").unwrap();
+        assert_eq!(format_stats(&response.indexers), "No indexer activity in the last 30 days.");
+    }
+
+    #[test]
+    fn test_set_enabled_keeps_other_fields() {
+        let mut full = serde_json::json!({"id": 3, "enable": true, "enableRss": true, "priority": 25});
+        set_enabled(&mut full, false);
+        assert_eq!(
+            full,
+            serde_json::json!({"id": 3, "enable": false, "enableRss": false, "priority": 25})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_toggle_fetches_mutates_and_puts() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/indexer"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"id": 3, "name": "1337x", "enable": true},
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/indexer/3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!(
+                {"id": 3, "name": "1337x", "enable": true, "priority": 25, "fields": [{"name": "baseUrl"}]}
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/api/v1/indexer/3"))
+            .and(body_partial_json(serde_json::json!(
+                {"enable": false, "priority": 25, "fields": [{"name": "baseUrl"}]}
+            )))
+            .respond_with(ResponseTemplate::new(202).set_body_json(serde_json::json!(
+                {"id": 3, "name": "1337x", "enable": false}
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let plugin = ProwlarrPlugin::new(&server.uri(), "key", false);
+        let msg = plugin.handle_toggle("1337x").await.unwrap();
+        assert_eq!(msg, "Indexer **1337x** is now **disabled**");
+    }
+}
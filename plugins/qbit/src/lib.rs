@@ -1,60 +1,130 @@
 use async_trait::async_trait;
-use discord_assist_plugin_api::{Plugin, PluginError};
+use discord_assist_plugin_api::{
+    Plugin, PluginError, ack, dry_run_message, respond_chunked, response_message,
+};
+use discord_assist_util::http::{HttpOptions, build_client};
+use discord_assist_util::size;
 use reqwest::Client;
 use serde::Deserialize;
 use serenity::builder::{
-    CreateCommand, CreateCommandOption, CreateInteractionResponse,
-    CreateInteractionResponseMessage,
+    CreateActionRow, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
+};
+use serenity::model::application::{
+    CommandInteraction, CommandOptionType, ComponentInteraction, ComponentInteractionDataKind,
+    ResolvedValue,
 };
-use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
 use serenity::prelude::Context;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
-use tracing::debug;
+use tracing::{debug, warn};
+
+/// Log in again after this long even if qBittorrent hasn't rejected the
+/// session, since a restart invalidates it without always answering 403.
+const SESSION_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// After a failed login, don't try again for this long so wrong credentials
+/// don't turn every command and poll into a login request.
+const LOGIN_BACKOFF: Duration = Duration::from_secs(60);
 
+/// How long the torrent picker shown for an ambiguous name stays usable.
+const PICK_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Discord allows at most this many options in a select menu.
+const MAX_PICK_OPTIONS: usize = 25;
+
+/// Global transfer speeds (bytes/s) and session totals (bytes).
 #[derive(Debug, Deserialize)]
-struct TransferInfo {
-    dl_info_speed: Option<u64>,
-    up_info_speed: Option<u64>,
-    dl_info_data: Option<u64>,
-    up_info_data: Option<u64>,
+pub struct TransferInfo {
+    pub dl_info_speed: Option<u64>,
+    pub up_info_speed: Option<u64>,
+    pub dl_info_data: Option<u64>,
+    pub up_info_data: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
-struct TorrentInfo {
-    name: String,
-    hash: String,
-    state: String,
-    progress: f64,
-    size: Option<u64>,
-    dlspeed: Option<u64>,
+pub struct TorrentInfo {
+    pub name: String,
+    pub hash: String,
+    pub state: String,
+    pub progress: f64,
+    pub size: Option<u64>,
+    pub dlspeed: Option<u64>,
 }
 
-struct QbitClient {
+/// One entry of `/torrents/categories`, which is an object keyed by name.
+#[derive(Debug, Deserialize)]
+struct Category {
+    #[serde(rename = "savePath", default)]
+    save_path: String,
+}
+
+impl TorrentInfo {
+    /// Fully downloaded and seeding (or paused after seeding).
+    pub fn is_complete(&self) -> bool {
+        self.progress >= 1.0
+            && matches!(self.state.as_str(), "uploading" | "stalledUP" | "pausedUP")
+    }
+}
+
+/// qBittorrent Web API client. Shared by the `/qbit` command and the
+/// completed-downloads notification poller. Builds its own HTTP client rather
+/// than using the shared pool, since the session cookie needs a cookie store.
+pub struct QbitClient {
     client: Client,
     base_url: String,
     username: String,
     password: String,
-    logged_in: Arc<RwLock<bool>>,
+    session: Arc<RwLock<Session>>,
+    session_ttl: Duration,
+}
+
+#[derive(Default)]
+struct Session {
+    last_login: Option<Instant>,
+    last_failed_login: Option<Instant>,
+}
+
+/// qBittorrent answers 403 to an expired cookie, but some versions and
+/// reverse proxies answer 401.
+fn is_auth_failure(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 401 | 403)
 }
 
 impl QbitClient {
-    fn new(base_url: &str, username: &str, password: &str) -> Self {
-        let client = Client::builder()
-            .cookie_store(true)
-            .danger_accept_invalid_certs(true)
-            .build()
-            .expect("Failed to build HTTP client");
+    pub fn new(
+        base_url: &str,
+        username: &str,
+        password: &str,
+        accept_invalid_certs: bool,
+    ) -> Self {
+        let client = build_client(&HttpOptions {
+            cookie_store: true,
+            ..HttpOptions::new(accept_invalid_certs)
+        });
         Self {
             client,
             base_url: base_url.trim_end_matches('/').to_string(),
             username: username.to_string(),
             password: password.to_string(),
-            logged_in: Arc::new(RwLock::new(false)),
+            session: Arc::new(RwLock::new(Session::default())),
+            session_ttl: SESSION_TTL,
         }
     }
 
     async fn login(&self) -> Result<(), PluginError> {
+        let mut session = self.session.write().await;
+        if let Some(failed) = session.last_failed_login
+            && failed.elapsed() < LOGIN_BACKOFF
+        {
+            let wait = (LOGIN_BACKOFF - failed.elapsed()).as_secs() + 1;
+            return Err(PluginError::AuthError(format!(
+                "qBittorrent login failed recently, not retrying for {wait}s"
+            )));
+        }
+
         let url = format!("{}/api/v2/auth/login", self.base_url);
         let resp = self
             .client
@@ -70,51 +140,76 @@ impl QbitClient {
             .map_err(|e| PluginError::ApiError(e.to_string()))?;
 
         if text.contains("Ok") {
-            *self.logged_in.write().await = true;
+            *session = Session {
+                last_login: Some(Instant::now()),
+                last_failed_login: None,
+            };
             debug!("qBittorrent login successful");
             Ok(())
         } else {
-            Err(PluginError::ApiError(
-                "qBittorrent login failed".to_string(),
+            warn!("qBittorrent login failed, backing off for {}s", LOGIN_BACKOFF.as_secs());
+            *session = Session {
+                last_login: None,
+                last_failed_login: Some(Instant::now()),
+            };
+            Err(PluginError::AuthError(
+                "qBittorrent rejected the username or password".to_string(),
             ))
         }
     }
 
     async fn ensure_logged_in(&self) -> Result<(), PluginError> {
-        if !*self.logged_in.read().await {
+        let fresh = self
+            .session
+            .read()
+            .await
+            .last_login
+            .is_some_and(|t| t.elapsed() < self.session_ttl);
+        if !fresh {
             self.login().await?;
         }
         Ok(())
     }
 
-    async fn get<T: serde::de::DeserializeOwned>(&self, endpoint: &str) -> Result<T, PluginError> {
+    /// Send the request built by `build`, logging in first if needed and once
+    /// more if qBittorrent rejects the session.
+    async fn send(
+        &self,
+        build: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, PluginError> {
         self.ensure_logged_in().await?;
-        let url = format!("{}/api/v2{}", self.base_url, endpoint);
-        let resp = self
-            .client
-            .get(&url)
+        let resp = build()
             .send()
             .await
             .map_err(|e| PluginError::ApiError(e.to_string()))?;
-
-        if resp.status() == reqwest::StatusCode::FORBIDDEN {
-            // Session expired, re-login and retry
-            *self.logged_in.write().await = false;
-            self.login().await?;
-            let resp = self
-                .client
-                .get(&url)
-                .send()
-                .await
-                .map_err(|e| PluginError::ApiError(e.to_string()))?;
-            resp.json()
-                .await
-                .map_err(|e| PluginError::ApiError(e.to_string()))
-        } else {
-            resp.json()
-                .await
-                .map_err(|e| PluginError::ApiError(e.to_string()))
+        if !is_auth_failure(resp.status()) {
+            return Ok(resp);
         }
+
+        debug!("qBittorrent session rejected ({}), logging in again", resp.status());
+        self.session.write().await.last_login = None;
+        self.login().await?;
+        build()
+            .send()
+            .await
+            .map_err(|e| PluginError::ApiError(e.to_string()))
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, endpoint: &str) -> Result<T, PluginError> {
+        let url = format!("{}/api/v2{}", self.base_url, endpoint);
+        self.send(|| self.client.get(&url))
+            .await?
+            .json()
+            .await
+            .map_err(|e| PluginError::ApiError(e.to_string()))
+    }
+
+    pub async fn torrents(&self) -> Result<Vec<TorrentInfo>, PluginError> {
+        self.get("/torrents/info").await
+    }
+
+    pub async fn transfer_info(&self) -> Result<TransferInfo, PluginError> {
+        self.get("/transfer/info").await
     }
 
     async fn post_form(
@@ -122,43 +217,43 @@ impl QbitClient {
         endpoint: &str,
         form: &[(&str, &str)],
     ) -> Result<(), PluginError> {
-        self.ensure_logged_in().await?;
         let url = format!("{}/api/v2{}", self.base_url, endpoint);
-        let resp = self
-            .client
-            .post(&url)
-            .form(form)
-            .send()
-            .await
-            .map_err(|e| PluginError::ApiError(e.to_string()))?;
-
-        if resp.status() == reqwest::StatusCode::FORBIDDEN {
-            *self.logged_in.write().await = false;
-            self.login().await?;
-            self.client
-                .post(&url)
-                .form(form)
-                .send()
-                .await
-                .map_err(|e| PluginError::ApiError(e.to_string()))?;
-        }
+        self.send(|| self.client.post(&url).form(form)).await?;
         Ok(())
     }
 }
 
+/// Torrents offered by a picker, keyed by the command interaction id.
+struct PendingPick {
+    /// `(hash, name)` in menu order.
+    candidates: Vec<(String, String)>,
+    created_at: Instant,
+}
+
 pub struct QbitPlugin {
     client: QbitClient,
+    picks: Arc<RwLock<HashMap<String, PendingPick>>>,
+    /// Reply with what would have happened instead of pausing/resuming.
+    dry_run: bool,
 }
 
 impl QbitPlugin {
-    pub fn new(api_url: &str, username: &str, password: &str) -> Self {
+    pub fn new(
+        api_url: &str,
+        username: &str,
+        password: &str,
+        accept_invalid_certs: bool,
+        dry_run: bool,
+    ) -> Self {
         Self {
-            client: QbitClient::new(api_url, username, password),
+            client: QbitClient::new(api_url, username, password, accept_invalid_certs),
+            picks: Arc::new(RwLock::new(HashMap::new())),
+            dry_run,
         }
     }
 
     async fn handle_status(&self) -> Result<String, PluginError> {
-        let info: TransferInfo = self.client.get("/transfer/info").await?;
+        let info = self.client.transfer_info().await?;
         let dl = format_speed(info.dl_info_speed.unwrap_or(0));
         let ul = format_speed(info.up_info_speed.unwrap_or(0));
         let dl_total = format_bytes(info.dl_info_data.unwrap_or(0));
@@ -169,7 +264,7 @@ impl QbitPlugin {
     }
 
     async fn handle_list(&self) -> Result<String, PluginError> {
-        let torrents: Vec<TorrentInfo> = self.client.get("/torrents/info").await?;
+        let torrents = self.client.torrents().await?;
         if torrents.is_empty() {
             return Ok("No torrents.".into());
         }
@@ -193,35 +288,194 @@ impl QbitPlugin {
         Ok(msg)
     }
 
-    async fn handle_pause(&self, name: &str) -> Result<String, PluginError> {
-        let hash = self.find_torrent_hash(name).await?;
-        self.client
-            .post_form("/torrents/pause", &[("hashes", &hash)])
-            .await?;
-        Ok(format!("Paused torrent matching \"{name}\""))
+    /// Pause, resume, recheck or reannounce one torrent. Recheck and
+    /// reannounce are safe to repeat, so nothing asks for confirmation.
+    async fn run_action(&self, action: &str, hash: &str, name: &str) -> Result<String, PluginError> {
+        let (endpoint, done, verb) = match action {
+            "pause" => ("/torrents/pause", "Paused", "paused"),
+            "resume" => ("/torrents/resume", "Resumed", "resumed"),
+            "recheck" => ("/torrents/recheck", "Rechecking", "sent recheck for"),
+            _ => ("/torrents/reannounce", "Reannounced", "sent reannounce for"),
+        };
+        if self.dry_run {
+            return Ok(dry_run_message(&format!("{verb} **{name}**")));
+        }
+        self.client.post_form(endpoint, &[("hashes", hash)]).await?;
+        Ok(format!("{done} **{name}**"))
+    }
+
+    /// Reply with a select menu of `matches` so the user can pick which
+    /// torrent `action` applies to.
+    async fn send_picker(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        action: &str,
+        name: &str,
+        matches: Vec<TorrentInfo>,
+    ) -> Result<(), PluginError> {
+        let id = command.id.to_string();
+        {
+            let mut picks = self.picks.write().await;
+            picks.retain(|_, p| p.created_at.elapsed() < PICK_TTL);
+            picks.insert(
+                id.clone(),
+                PendingPick {
+                    candidates: matches
+                        .iter()
+                        .take(MAX_PICK_OPTIONS)
+                        .map(|t| (t.hash.clone(), t.name.clone()))
+                        .collect(),
+                    created_at: Instant::now(),
+                },
+            );
+        }
+
+        let options = matches
+            .iter()
+            .take(MAX_PICK_OPTIONS)
+            .enumerate()
+            .map(|(i, t)| {
+                let pct = (t.progress * 100.0) as u32;
+                CreateSelectMenuOption::new(truncate(&t.name, 100), i.to_string())
+                    .description(format!("{pct}% [{}]", t.state))
+            })
+            .collect();
+        let select = CreateSelectMenu::new(
+            format!("qbit_pick:{action}:{id}"),
+            CreateSelectMenuKind::String { options },
+        )
+        .placeholder("Select a torrent...");
+
+        let mut content = format!("{} torrents match \"{name}\" — which one?", matches.len());
+        if matches.len() > MAX_PICK_OPTIONS {
+            content.push_str(&format!(" (showing the first {MAX_PICK_OPTIONS})"));
+        }
+        let data = response_message()
+            .content(content)
+            .components(vec![CreateActionRow::SelectMenu(select)]);
+        ack::respond(ctx, command, data).await?;
+        Ok(())
+    }
+
+    /// Run `action` on the torrent at `index` of the picker `id`. Each picker
+    /// is single-use.
+    async fn handle_pick(&self, action: &str, id: &str, index: usize) -> Result<String, PluginError> {
+        let pick = self
+            .picks
+            .write()
+            .await
+            .remove(id)
+            .filter(|p| p.created_at.elapsed() < PICK_TTL)
+            .ok_or_else(|| {
+                PluginError::Other("This selection has expired. Run the command again.".into())
+            })?;
+        let (hash, name) = pick
+            .candidates
+            .get(index)
+            .ok_or_else(|| PluginError::Other("Invalid selection.".into()))?;
+        self.run_action(action, hash, name).await
     }
 
-    async fn handle_resume(&self, name: &str) -> Result<String, PluginError> {
-        let hash = self.find_torrent_hash(name).await?;
-        self.client
-            .post_form("/torrents/resume", &[("hashes", &hash)])
-            .await?;
-        Ok(format!("Resumed torrent matching \"{name}\""))
+    /// With no limits, toggle alternative speed limits; otherwise set the
+    /// global download/upload limits. Either way, report the resulting mode.
+    async fn handle_limit(
+        &self,
+        download: Option<&str>,
+        upload: Option<&str>,
+    ) -> Result<String, PluginError> {
+        if let Some(bad) = [download, upload]
+            .into_iter()
+            .flatten()
+            .find(|v| parse_rate(v).is_none())
+        {
+            return Err(PluginError::Other(format!(
+                "Couldn't read \"{bad}\" as a speed (e.g. 500k, 5mb, or 0 for unlimited)"
+            )));
+        }
+        let download = download.and_then(parse_rate);
+        let upload = upload.and_then(parse_rate);
+
+        let mut lines = Vec::new();
+        if download.is_none() && upload.is_none() {
+            if self.dry_run {
+                return Ok(dry_run_message("toggled alternative speed limits"));
+            }
+            self.client
+                .post_form("/transfer/toggleSpeedLimitsMode", &[])
+                .await?;
+        }
+        for (endpoint, label, limit) in [
+            ("/transfer/setDownloadLimit", "Download", download),
+            ("/transfer/setUploadLimit", "Upload", upload),
+        ] {
+            let Some(limit) = limit else { continue };
+            let shown = if limit == 0 { "unlimited".to_string() } else { format_speed(limit) };
+            if self.dry_run {
+                lines.push(dry_run_message(&format!("set the {} limit to {shown}", label.to_lowercase())));
+                continue;
+            }
+            self.client
+                .post_form(endpoint, &[("limit", &limit.to_string())])
+                .await?;
+            lines.push(format!("{label} limit: {shown}"));
+        }
+
+        let mode: u8 = self.client.get("/transfer/speedLimitsMode").await?;
+        let state = if mode == 1 { "on" } else { "off" };
+        lines.push(format!("Alternative speed limits: **{state}**"));
+        Ok(lines.join("\n"))
+    }
+
+    async fn handle_categories(&self) -> Result<String, PluginError> {
+        let categories: BTreeMap<String, Category> =
+            self.client.get("/torrents/categories").await?;
+        Ok(format_categories(&categories))
     }
 
-    async fn find_torrent_hash(&self, name: &str) -> Result<String, PluginError> {
-        let torrents: Vec<TorrentInfo> = self.client.get("/torrents/info").await?;
+    async fn handle_set_category(&self, name: &str, category: &str) -> Result<String, PluginError> {
+        let torrent = self.find_torrent(name).await?;
+        if self.dry_run {
+            return Ok(dry_run_message(&format!(
+                "set category of **{}** to \"{category}\"",
+                torrent.name
+            )));
+        }
+        let url = format!("{}/api/v2/torrents/setCategory", self.client.base_url);
+        let form = [("hashes", torrent.hash.as_str()), ("category", category)];
+        let resp = self.client.send(|| self.client.client.post(&url).form(&form)).await?;
+        match resp.status().as_u16() {
+            200 => Ok(format!("Set category of **{}** to \"{category}\"", torrent.name)),
+            // qBittorrent answers 409 when the category hasn't been created.
+            // Reply rather than error so the user sees why.
+            409 => Ok(format!(
+                "Category \"{category}\" doesn't exist. Use `/qbit categories` to list them."
+            )),
+            status => Err(PluginError::ApiError(format!("qBittorrent returned {status}"))),
+        }
+    }
+
+    /// Torrents whose name contains `name`, case-insensitively. Errors if
+    /// there are none.
+    async fn matching_torrents(&self, name: &str) -> Result<Vec<TorrentInfo>, PluginError> {
+        let torrents = self.client.torrents().await?;
         let lower = name.to_lowercase();
-        let matches: Vec<&TorrentInfo> = torrents
-            .iter()
+        let matches: Vec<TorrentInfo> = torrents
+            .into_iter()
             .filter(|t| t.name.to_lowercase().contains(&lower))
             .collect();
+        if matches.is_empty() {
+            return Err(PluginError::Other(format!(
+                "No torrent matching \"{name}\""
+            )));
+        }
+        Ok(matches)
+    }
 
+    async fn find_torrent(&self, name: &str) -> Result<TorrentInfo, PluginError> {
+        let mut matches = self.matching_torrents(name).await?;
         match matches.len() {
-            0 => Err(PluginError::Other(format!(
-                "No torrent matching \"{name}\""
-            ))),
-            1 => Ok(matches[0].hash.clone()),
+            1 => Ok(matches.remove(0)),
             n => Err(PluginError::Other(format!(
                 "{n} torrents match \"{name}\" — be more specific"
             ))),
@@ -277,9 +531,109 @@ impl Plugin for QbitPlugin {
                     )
                     .required(true),
                 ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "recheck",
+                    "Recheck a torrent's downloaded data",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "Torrent name (substring match)",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "reannounce",
+                    "Reannounce a torrent to its trackers",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "Torrent name (substring match)",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "categories",
+                "List torrent categories",
+            ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "setcategory",
+                    "Set a torrent's category",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "Torrent name (substring match)",
+                    )
+                    .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "category",
+                        "Existing category name (empty to clear)",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "limit",
+                    "Toggle alternative speed limits, or set global limits",
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "download",
+                    "Global download limit, e.g. 500k or 5mb (0 = unlimited)",
+                ))
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "upload",
+                    "Global upload limit, e.g. 500k or 5mb (0 = unlimited)",
+                )),
             )]
     }
 
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        matches!(path, ["qbit", "status" | "list" | "categories"])
+    }
+
+    fn component_command(&self, custom_id: &str) -> Option<&str> {
+        custom_id.starts_with("qbit_pick:").then_some("qbit")
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![
+            ("/qbit status".into(), "Show transfer speeds and totals".into()),
+            ("/qbit list".into(), "List active torrents".into()),
+            ("/qbit pause".into(), "Pause a torrent by name".into()),
+            ("/qbit resume".into(), "Resume a paused torrent by name".into()),
+            ("/qbit recheck".into(), "Recheck a torrent's downloaded data".into()),
+            ("/qbit reannounce".into(), "Reannounce a torrent to its trackers".into()),
+            ("/qbit categories".into(), "List torrent categories".into()),
+            ("/qbit setcategory".into(), "Set a torrent's category".into()),
+            (
+                "/qbit limit [download] [upload]".into(),
+                "Toggle alternative speed limits, or set global limits".into(),
+            ),
+        ]
+    }
+
     async fn handle_command(
         &self,
         ctx: &Context,
@@ -298,7 +652,7 @@ impl Plugin for QbitPlugin {
         let content = match subopt.name {
             "status" => self.handle_status().await?,
             "list" => self.handle_list().await?,
-            "pause" | "resume" => {
+            "pause" | "resume" | "recheck" | "reannounce" => {
                 if let ResolvedValue::SubCommand(opts) = &subopt.value {
                     let name = opts
                         .iter()
@@ -309,46 +663,137 @@ impl Plugin for QbitPlugin {
                         })
                         .ok_or_else(|| PluginError::Other("Missing name".into()))?;
 
-                    if subopt.name == "pause" {
-                        self.handle_pause(name).await?
-                    } else {
-                        self.handle_resume(name).await?
+                    let mut matches = self.matching_torrents(name).await?;
+                    if matches.len() > 1 {
+                        self.send_picker(ctx, command, subopt.name, name, matches).await?;
+                        return Ok(true);
                     }
+                    let torrent = matches.remove(0);
+                    self.run_action(subopt.name, &torrent.hash, &torrent.name).await?
                 } else {
                     return Ok(false);
                 }
             }
+            "categories" => self.handle_categories().await?,
+            "setcategory" => {
+                let opts = match &subopt.value {
+                    ResolvedValue::SubCommand(opts) => opts.as_slice(),
+                    _ => &[],
+                };
+                let string_opt = |name: &str| {
+                    opts.iter().find(|o| o.name == name).and_then(|o| match &o.value {
+                        ResolvedValue::String(s) => Some(*s),
+                        _ => None,
+                    })
+                };
+                let name = string_opt("name")
+                    .ok_or_else(|| PluginError::Other("Missing name".into()))?;
+                let category = string_opt("category")
+                    .ok_or_else(|| PluginError::Other("Missing category".into()))?;
+                self.handle_set_category(name, category.trim()).await?
+            }
+            "limit" => {
+                let opts = match &subopt.value {
+                    ResolvedValue::SubCommand(opts) => opts.as_slice(),
+                    _ => &[],
+                };
+                let string_opt = |name: &str| {
+                    opts.iter().find(|o| o.name == name).and_then(|o| match &o.value {
+                        ResolvedValue::String(s) => Some(*s),
+                        _ => None,
+                    })
+                };
+                self.handle_limit(string_opt("download"), string_opt("upload"))
+                    .await?
+            }
             _ => return Ok(false),
         };
 
-        let data = CreateInteractionResponseMessage::new().content(content);
-        let builder = CreateInteractionResponse::Message(data);
-        command
-            .create_response(&ctx.http, builder)
+        respond_chunked(ctx, command, &content).await?;
+        Ok(true)
+    }
+
+    async fn handle_component(
+        &self,
+        ctx: &Context,
+        component: &ComponentInteraction,
+    ) -> Result<bool, PluginError> {
+        // Select menu: qbit_pick:<action>:<id>
+        let Some(rest) = component.data.custom_id.strip_prefix("qbit_pick:") else {
+            return Ok(false);
+        };
+        let Some((action, id)) = rest.split_once(':') else {
+            return Ok(false);
+        };
+        let ComponentInteractionDataKind::StringSelect { values } = &component.data.kind else {
+            return Ok(false);
+        };
+        let index: usize = values
+            .first()
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| PluginError::Other("Invalid selection".into()))?;
+        let content = self.handle_pick(action, id, index).await?;
+
+        // Replace the menu so the same pick can't be made twice
+        let data = response_message()
+            .content(content)
+            .components(Vec::new());
+        component
+            .create_response(&ctx.http, CreateInteractionResponse::UpdateMessage(data))
             .await
             .map_err(PluginError::DiscordError)?;
         Ok(true)
     }
+
+    async fn clear_state(&self) -> usize {
+        let mut picks = self.picks.write().await;
+        let removed = picks.len();
+        picks.clear();
+        removed
+    }
 }
 
-fn format_speed(bytes_per_sec: u64) -> String {
-    if bytes_per_sec < 1024 {
-        format!("{} B/s", bytes_per_sec)
-    } else if bytes_per_sec < 1_048_576 {
-        format!("{:.1} KB/s", bytes_per_sec as f64 / 1024.0)
-    } else {
-        format!("{:.1} MB/s", bytes_per_sec as f64 / 1_048_576.0)
+fn format_categories(categories: &BTreeMap<String, Category>) -> String {
+    if categories.is_empty() {
+        return "No categories.".into();
     }
+    let mut msg = String::from("**Categories**\n");
+    for (name, category) in categories {
+        if category.save_path.is_empty() {
+            msg.push_str(&format!("- {name}\n"));
+        } else {
+            msg.push_str(&format!("- {name} (`{}`)\n", category.save_path));
+        }
+    }
+    msg
+}
+
+/// Parse a speed like `500`, `500k`, `5mb`, or `1.5 MiB/s` into bytes/sec.
+/// Bare numbers are KiB/s, matching qBittorrent's UI; units are binary.
+fn parse_rate(input: &str) -> Option<u64> {
+    let s = input.trim().to_ascii_lowercase();
+    let s = s.strip_suffix("/s").unwrap_or(&s).trim_end();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: f64 = number.parse().ok()?;
+    let multiplier: u64 = match unit.trim() {
+        "b" => 1,
+        "" | "k" | "kb" | "kib" => 1024,
+        "m" | "mb" | "mib" => 1024 * 1024,
+        "g" | "gb" | "gib" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    Some((value * multiplier as f64).round() as u64)
+}
+
+fn format_speed(bytes_per_sec: u64) -> String {
+    size::format_speed(bytes_per_sec, size::unit())
 }
 
 fn format_bytes(bytes: u64) -> String {
-    if bytes < 1_073_741_824 {
-        format!("{:.1} MB", bytes as f64 / 1_048_576.0)
-    } else if bytes < 1_099_511_627_776 {
-        format!("{:.1} GB", bytes as f64 / 1_073_741_824.0)
-    } else {
-        format!("{:.1} TB", bytes as f64 / 1_099_511_627_776.0)
-    }
+    size::format_size(bytes, size::unit())
 }
 
 fn truncate(s: &str, max: usize) -> String {
@@ -366,6 +811,198 @@ fn truncate(s: &str, max: usize) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wiremock::matchers::{body_string, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mock_login(server: &MockServer, body: &str, times: u64) {
+        Mock::given(method("POST"))
+            .and(path("/api/v2/auth/login"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .expect(times)
+            .mount(server)
+            .await;
+    }
+
+    async fn mock_torrents(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path("/api/v2/torrents/info"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_logs_in_once() {
+        let server = MockServer::start().await;
+        // Initial login plus exactly one re-login after the session is rejected
+        mock_login(&server, "Ok.", 2).await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/torrents/info"))
+            .respond_with(ResponseTemplate::new(401))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        mock_torrents(&server).await;
+
+        let client = QbitClient::new(&server.uri(), "admin", "pass", false);
+        assert!(client.torrents().await.unwrap().is_empty());
+        assert!(client.torrents().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_session_ttl_forces_login() {
+        let server = MockServer::start().await;
+        mock_login(&server, "Ok.", 2).await;
+        mock_torrents(&server).await;
+
+        let mut client = QbitClient::new(&server.uri(), "admin", "pass", false);
+        client.session_ttl = Duration::ZERO;
+        client.torrents().await.unwrap();
+        client.torrents().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_recheck_posts_matching_hash() {
+        let server = MockServer::start().await;
+        mock_login(&server, "Ok.", 1).await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/torrents/info"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"name": "Ubuntu 24.04 ISO", "hash": "abc", "state": "stalledDL", "progress": 0.4},
+                {"name": "Debian 12 ISO", "hash": "def", "state": "uploading", "progress": 1.0},
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/recheck"))
+            .and(body_string("hashes=abc"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let plugin = QbitPlugin::new(&server.uri(), "admin", "pass", false, false);
+        let torrent = plugin.find_torrent("ubuntu").await.unwrap();
+        let msg = plugin.run_action("recheck", &torrent.hash, &torrent.name).await.unwrap();
+        assert_eq!(msg, "Rechecking **Ubuntu 24.04 ISO**");
+
+        let err = plugin.find_torrent("iso").await.unwrap_err();
+        assert!(err.to_string().contains("2 torrents match"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_set_category_unknown_category() {
+        let server = MockServer::start().await;
+        mock_login(&server, "Ok.", 1).await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/torrents/info"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"name": "Ubuntu 24.04 ISO", "hash": "abc", "state": "stalledDL", "progress": 0.4},
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/setCategory"))
+            .and(body_string("hashes=abc&category=linux"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/setCategory"))
+            .and(body_string("hashes=abc&category=nope"))
+            .respond_with(ResponseTemplate::new(409).set_body_string("Category name does not exist"))
+            .mount(&server)
+            .await;
+
+        let plugin = QbitPlugin::new(&server.uri(), "admin", "pass", false, false);
+        let msg = plugin.handle_set_category("ubuntu", "linux").await.unwrap();
+        assert_eq!(msg, "Set category of **Ubuntu 24.04 ISO** to \"linux\"");
+
+        let msg = plugin.handle_set_category("ubuntu", "nope").await.unwrap();
+        assert_eq!(
+            msg,
+            "Category \"nope\" doesn't exist. Use `/qbit categories` to list them."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pick_runs_action_once() {
+        let server = MockServer::start().await;
+        mock_login(&server, "Ok.", 1).await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/pause"))
+            .and(body_string("hashes=def"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let plugin = QbitPlugin::new(&server.uri(), "admin", "pass", false, false);
+        plugin.picks.write().await.insert("42".into(), pick(&[("abc", "Ubuntu"), ("def", "Debian")]));
+        let msg = plugin.handle_pick("pause", "42", 1).await.unwrap();
+        assert_eq!(msg, "Paused **Debian**");
+
+        let err = plugin.handle_pick("pause", "42", 1).await.unwrap_err();
+        assert!(err.to_string().contains("expired"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_failed_login_backs_off() {
+        let server = MockServer::start().await;
+        mock_login(&server, "Fails.", 1).await;
+
+        let client = QbitClient::new(&server.uri(), "admin", "wrong", false);
+        let err = client.torrents().await.unwrap_err();
+        assert!(matches!(err, PluginError::AuthError(_)), "{err:?}");
+        let err = client.torrents().await.unwrap_err();
+        assert!(err.to_string().contains("not retrying"), "{err}");
+    }
+
+    fn pick(candidates: &[(&str, &str)]) -> PendingPick {
+        PendingPick {
+            candidates: candidates
+                .iter()
+                .map(|(hash, name)| (hash.to_string(), name.to_string()))
+                .collect(),
+            created_at: Instant::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pick_rejects_expired_and_invalid() {
+        let plugin = QbitPlugin::new("http://localhost:1", "admin", "pass", false, false);
+        let mut stale = pick(&[("abc", "Ubuntu")]);
+        stale.created_at = Instant::now() - PICK_TTL;
+        plugin.picks.write().await.insert("1".into(), stale);
+        plugin.picks.write().await.insert("2".into(), pick(&[("abc", "Ubuntu")]));
+
+        let err = plugin.handle_pick("pause", "1", 0).await.unwrap_err();
+        assert!(err.to_string().contains("expired"), "{err}");
+        let err = plugin.handle_pick("pause", "2", 5).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid selection.");
+        assert_eq!(plugin.clear_state().await, 0);
+    }
+
+    fn torrent(state: &str, progress: f64) -> TorrentInfo {
+        TorrentInfo {
+            name: "Ubuntu ISO".into(),
+            hash: "abc".into(),
+            state: state.into(),
+            progress,
+            size: None,
+            dlspeed: None,
+        }
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert!(torrent("uploading", 1.0).is_complete());
+        assert!(torrent("stalledUP", 1.0).is_complete());
+        assert!(torrent("pausedUP", 1.0).is_complete());
+        assert!(!torrent("downloading", 0.5).is_complete());
+        assert!(!torrent("stalledDL", 0.99).is_complete());
+        assert!(!torrent("checkingUP", 1.0).is_complete());
+    }
 
     #[test]
     fn test_format_speed() {
@@ -374,6 +1011,41 @@ mod tests {
         assert_eq!(format_speed(2_621_440), "2.5 MB/s");
     }
 
+    #[test]
+    fn test_format_categories() {
+        let categories: BTreeMap<String, Category> = serde_json::from_str(
+            r#"{
+                "tv": {"name": "tv", "savePath": "/data/tv"},
+                "movies": {"name": "movies", "savePath": ""}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            format_categories(&categories),
+            "**Categories**\n- movies\n- tv (`/data/tv`)\n"
+        );
+        assert_eq!(format_categories(&BTreeMap::new()), "No categories.");
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("500"), Some(500 * 1024));
+        assert_eq!(parse_rate("500k"), Some(500 * 1024));
+        assert_eq!(parse_rate("5mb"), Some(5 * 1024 * 1024));
+        assert_eq!(parse_rate(" 1.5 MiB/s "), Some(1_572_864));
+        assert_eq!(parse_rate("2G"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_rate("100b"), Some(100));
+        assert_eq!(parse_rate("0"), Some(0));
+    }
+
+    #[test]
+    fn test_parse_rate_invalid() {
+        assert_eq!(parse_rate(""), None);
+        assert_eq!(parse_rate("fast"), None);
+        assert_eq!(parse_rate("5 tb"), None);
+        assert_eq!(parse_rate("1.2.3m"), None);
+    }
+
     #[test]
     fn test_format_bytes() {
         assert_eq!(format_bytes(524_288_000), "500.0 MB");
@@ -1,24 +1,54 @@
 use async_trait::async_trait;
-use discord_assist_arr_common::ArrClient;
-use discord_assist_plugin_api::{Plugin, PluginError};
+use discord_assist_arr_common::{ArrClient, PagedResponse};
+use discord_assist_plugin_api::{
+    Plugin, PluginError, ack, list_embed, respond_chunked, respond_embed, response_message,
+};
+use discord_assist_util::{escape_discord, iso_date_in_days, today_iso};
 use serde::Deserialize;
 use serenity::builder::{
-    CreateCommand, CreateCommandOption, CreateInteractionResponse,
-    CreateInteractionResponseMessage,
+    CreateActionRow, CreateButton, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse,
+};
+use serenity::model::application::{
+    ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, ResolvedValue,
 };
-use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
 use serenity::prelude::Context;
 
+/// Discord message content limit.
+const MAX_MESSAGE_LEN: usize = 2000;
+
+/// Calendar window for `upcoming` when `days` is not given.
+const DEFAULT_UPCOMING_DAYS: u64 = 7;
+
+const SEARCH_MISSING_CONFIRM: &str = "radarr_searchmissing:confirm";
+const SEARCH_MISSING_CANCEL: &str = "radarr_searchmissing:cancel";
+
 #[derive(Debug, Deserialize)]
 struct Movie {
     title: String,
     year: Option<u32>,
+    #[serde(rename = "digitalRelease")]
+    digital_release: Option<String>,
+    #[serde(rename = "physicalRelease")]
+    physical_release: Option<String>,
+    #[serde(rename = "inCinemas")]
+    in_cinemas: Option<String>,
+}
+
+impl Movie {
+    /// Best known release date (YYYY-MM-DD): digital, then physical, then cinemas.
+    fn release_date(&self) -> Option<&str> {
+        self.digital_release
+            .as_deref()
+            .or(self.physical_release.as_deref())
+            .or(self.in_cinemas.as_deref())
+            .map(|d| d.get(..10).unwrap_or(d))
+    }
 }
 
 #[derive(Debug, Deserialize)]
-struct QueueStatus {
-    #[serde(rename = "totalCount")]
-    total_count: Option<u32>,
+struct CommandResponse {
+    id: u64,
 }
 
 pub struct RadarrPlugin {
@@ -27,9 +57,11 @@ pub struct RadarrPlugin {
 
 impl RadarrPlugin {
     pub fn new(api_url: &str, api_key: &str) -> Self {
-        Self {
-            client: ArrClient::new(api_url, api_key),
-        }
+        Self::with_client(ArrClient::new(api_url, api_key))
+    }
+
+    pub fn with_client(client: ArrClient) -> Self {
+        Self { client }
     }
 }
 
@@ -57,18 +89,57 @@ impl Plugin for RadarrPlugin {
                     .required(true),
                 ),
             )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "upcoming",
+                    "Show upcoming releases",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "days",
+                        "How many days ahead to look (default 7)",
+                    )
+                    .min_int_value(1)
+                    .max_int_value(90),
+                ),
+            )
             .add_option(CreateCommandOption::new(
                 CommandOptionType::SubCommand,
-                "upcoming",
-                "Show upcoming releases",
+                "status",
+                "Show queue status",
             ))
             .add_option(CreateCommandOption::new(
                 CommandOptionType::SubCommand,
-                "status",
-                "Show queue status",
+                "missing",
+                "Show monitored movies that are missing",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "searchmissing",
+                "Search for every missing movie (asks for confirmation)",
             ))]
     }
 
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        matches!(path, ["radarr", "search" | "upcoming" | "status" | "missing"])
+    }
+
+    fn component_command(&self, custom_id: &str) -> Option<&str> {
+        custom_id.starts_with("radarr_searchmissing:").then_some("radarr")
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![
+            ("/radarr search".into(), "Search for a movie".into()),
+            ("/radarr upcoming".into(), "Show upcoming releases".into()),
+            ("/radarr status".into(), "Show queue status".into()),
+            ("/radarr missing".into(), "Show monitored movies that are missing".into()),
+            ("/radarr searchmissing".into(), "Search for every missing movie (asks for confirmation)".into()),
+        ]
+    }
+
     async fn handle_command(
         &self,
         ctx: &Context,
@@ -99,16 +170,16 @@ impl Plugin for RadarrPlugin {
                     let results: Vec<Movie> = self
                         .client
                         .get_with_params("movie/lookup", &[("term", title)])
-                        .await
-                        .map_err(|e| PluginError::ApiError(e.to_string()))?;
+                        .await?;
 
+                    let title = escape_discord(title);
                     if results.is_empty() {
                         format!("No results found for \"{title}\"")
                     } else {
                         let mut msg = format!("**Search results for \"{title}\":**\n");
                         for (i, m) in results.iter().take(10).enumerate() {
                             let year = m.year.map(|y| format!(" ({y})")).unwrap_or_default();
-                            msg.push_str(&format!("{}. **{}**{}\n", i + 1, m.title, year));
+                            msg.push_str(&format!("{}. **{}**{}\n", i + 1, escape_discord(&m.title), year));
                         }
                         msg
                     }
@@ -117,42 +188,199 @@ impl Plugin for RadarrPlugin {
                 }
             }
             "upcoming" => {
+                let days = match &subopt.value {
+                    ResolvedValue::SubCommand(opts) => opts
+                        .iter()
+                        .find(|o| o.name == "days")
+                        .and_then(|o| match o.value {
+                            ResolvedValue::Integer(n) => u64::try_from(n).ok(),
+                            _ => None,
+                        }),
+                    _ => None,
+                }
+                .unwrap_or(DEFAULT_UPCOMING_DAYS);
+                let start = today_iso();
+                let end = iso_date_in_days(days);
                 let movies: Vec<Movie> = self
                     .client
-                    .get("calendar")
-                    .await
-                    .map_err(|e| PluginError::ApiError(e.to_string()))?;
+                    .get_with_params(
+                        "calendar",
+                        &[("start", start.as_str()), ("end", end.as_str())],
+                    )
+                    .await?;
 
-                if movies.is_empty() {
-                    "No upcoming releases.".into()
+                let embed = list_embed("Upcoming Releases", &upcoming_fields(&movies));
+                let embed = if movies.is_empty() {
+                    embed.description("No upcoming releases.")
                 } else {
-                    let mut msg = String::from("**Upcoming Releases:**\n");
-                    for m in movies.iter().take(10) {
-                        let year = m.year.map(|y| format!(" ({y})")).unwrap_or_default();
-                        msg.push_str(&format!("- **{}**{}\n", m.title, year));
+                    embed
+                };
+                respond_embed(ctx, command, embed).await?;
+                return Ok(true);
+            }
+            "status" => {
+                let count = self.client.queue_count().await?;
+                let mut msg = format!("**Radarr Status**\nQueue: {count} items");
+                let disks = self
+                    .client
+                    .disk_space()
+                    .await?;
+                if !disks.is_empty() {
+                    msg.push_str("\n**Disk Space**");
+                    for disk in &disks {
+                        msg.push('\n');
+                        msg.push_str(&disk.render());
                     }
-                    msg
                 }
+                msg
             }
-            "status" => {
-                let queue: QueueStatus = self
+            "missing" => {
+                let missing: PagedResponse<Movie> = self
                     .client
-                    .get("queue/status")
-                    .await
-                    .map_err(|e| PluginError::ApiError(e.to_string()))?;
-                let count = queue.total_count.unwrap_or(0);
-                format!("**Radarr Status**\nQueue: {count} items")
+                    .get_with_params("wanted/missing", &[("pageSize", "10")])
+                    .await?;
+                format_missing(&missing)
+            }
+            "searchmissing" => {
+                let buttons = vec![
+                    CreateButton::new(SEARCH_MISSING_CONFIRM)
+                        .label("Search all missing")
+                        .style(ButtonStyle::Danger),
+                    CreateButton::new(SEARCH_MISSING_CANCEL)
+                        .label("Cancel")
+                        .style(ButtonStyle::Secondary),
+                ];
+                let data = response_message()
+                    .content("This searches indexers for every missing movie and can take a while on large libraries. Continue?")
+                    .components(vec![CreateActionRow::Buttons(buttons)]);
+                ack::respond(ctx, command, data).await?;
+                return Ok(true);
             }
             _ => return Ok(false),
         };
 
-        let data = CreateInteractionResponseMessage::new().content(content);
-        let builder = CreateInteractionResponse::Message(data);
-        command
-            .create_response(&ctx.http, builder)
+        respond_chunked(ctx, command, &content).await?;
+        Ok(true)
+    }
+
+    async fn handle_component(
+        &self,
+        ctx: &Context,
+        component: &ComponentInteraction,
+    ) -> Result<bool, PluginError> {
+        let content = match component.data.custom_id.as_str() {
+            SEARCH_MISSING_CONFIRM => {
+                let started: CommandResponse = self
+                    .client
+                    .post("command", &serde_json::json!({ "name": "MissingMoviesSearch" }))
+                    .await?;
+                format!(
+                    "Started MissingMoviesSearch (command {}). It runs in the background; check Radarr for progress.",
+                    started.id
+                )
+            }
+            SEARCH_MISSING_CANCEL => "Search cancelled.".to_string(),
+            _ => return Ok(false),
+        };
+
+        let data = response_message()
+            .content(content)
+            .components(Vec::new());
+        component
+            .create_response(&ctx.http, CreateInteractionResponse::UpdateMessage(data))
             .await
             .map_err(PluginError::DiscordError)?;
         Ok(true)
     }
 }
 
+/// One `("title (year)", "release date")` embed field per movie.
+fn upcoming_fields(movies: &[Movie]) -> Vec<(String, String)> {
+    movies
+        .iter()
+        .take(10)
+        .map(|m| {
+            let year = m.year.map(|y| format!(" ({y})")).unwrap_or_default();
+            let date = m.release_date().unwrap_or("TBA");
+            (format!("{}{}", escape_discord(&m.title), year), format!("releases {date}"))
+        })
+        .collect()
+}
+
+fn format_missing(missing: &PagedResponse<Movie>) -> String {
+    if missing.records.is_empty() {
+        return "No missing movies.".into();
+    }
+    let mut msg = format!("**Missing Movies** ({} total)\n", missing.total_records);
+    for (i, m) in missing.records.iter().enumerate() {
+        let year = m.year.map(|y| format!(" ({y})")).unwrap_or_default();
+        let date = m.release_date().unwrap_or("TBA");
+        let line = format!("- **{}**{} — released {}\n", escape_discord(&m.title), year, date);
+        if msg.len() + line.len() > MAX_MESSAGE_LEN - 20 {
+            msg.push_str(&format!("... and {} more\n", missing.records.len() - i));
+            break;
+        }
+        msg.push_str(&line);
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn movie(title: &str) -> Movie {
+        Movie {
+            title: title.into(),
+            year: Some(2024),
+            digital_release: None,
+            physical_release: Some("2024-05-14T00:00:00Z".into()),
+            in_cinemas: Some("2024-03-01T00:00:00Z".into()),
+        }
+    }
+
+    #[test]
+    fn test_upcoming_fields() {
+        let mut undated = movie("Untitled");
+        undated.year = None;
+        undated.physical_release = None;
+        undated.in_cinemas = None;
+        let fields = upcoming_fields(&[movie("Dune: Part Two"), undated]);
+        assert_eq!(
+            fields,
+            vec![
+                ("Dune: Part Two (2024)".into(), "releases 2024-05-14".into()),
+                ("Untitled".into(), "releases TBA".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_missing() {
+        let missing = PagedResponse {
+            records: vec![movie("Dune: Part Two")],
+            total_records: 1,
+        };
+        assert_eq!(
+            format_missing(&missing),
+            "**Missing Movies** (1 total)\n- **Dune: Part Two** (2024) — released 2024-05-14\n"
+        );
+    }
+
+    #[test]
+    fn test_malicious_title_is_escaped() {
+        let fields = upcoming_fields(&[movie("**x** @everyone")]);
+        assert_eq!(fields[0].0, "\\*\\*x\\*\\* \\@everyone (2024)");
+    }
+
+    #[test]
+    fn test_format_missing_stays_under_limit() {
+        let missing = PagedResponse {
+            records: (0..10).map(|_| movie(&"x".repeat(400))).collect(),
+            total_records: 10,
+        };
+        let msg = format_missing(&missing);
+        assert!(msg.len() <= MAX_MESSAGE_LEN);
+        assert!(msg.ends_with("more\n"));
+    }
+}
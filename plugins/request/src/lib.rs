@@ -1,20 +1,26 @@
 use async_trait::async_trait;
 use discord_assist_arr_common::ArrClient;
-use discord_assist_plugin_api::{Plugin, PluginError};
+use discord_assist_plugin_api::{
+    PendingStore, Plugin, PluginError, ack, dry_run_message, response_message,
+};
+use discord_assist_util::size::{self, format_size};
 use serde::Deserialize;
 use serenity::builder::{
     CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateInteractionResponse,
-    CreateInteractionResponseMessage, CreateSelectMenu, CreateSelectMenuKind,
-    CreateSelectMenuOption,
+    CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
 };
 use serenity::model::application::{
     CommandInteraction, CommandOptionType, ComponentInteraction,
     ComponentInteractionDataKind, ResolvedValue,
 };
 use serenity::prelude::Context;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+
+/// Search results can be acted on for this long.
+const PENDING_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// How often the background task drops expired search results.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Deserialize)]
 struct ProwlarrResult {
@@ -24,36 +30,208 @@ struct ProwlarrResult {
     indexer_name: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-struct PendingRequest {
-    results: Vec<PendingItem>,
-    created_at: std::time::Instant,
-}
-
 #[derive(Debug, Clone)]
 struct PendingItem {
     title: String,
     size: Option<u64>,
     indexer: String,
+    /// Set for clean-title searches: the Sonarr/Radarr lookup result this
+    /// item came from, added as-is instead of looking the title up again.
+    lookup: Option<LookupHit>,
+}
+
+#[derive(Debug, Clone)]
+struct LookupHit {
+    service: &'static str,
+    result: serde_json::Value,
+}
+
+impl PendingItem {
+    fn from_lookup(service: &'static str, result: serde_json::Value) -> Self {
+        let summary = LookupSummary::from_value(&result);
+        Self {
+            title: summary.title,
+            size: None,
+            indexer: String::new(),
+            lookup: Some(LookupHit { service, result }),
+        }
+    }
+
+    /// `(label, description)` for the search results menu.
+    fn menu_text(&self) -> (String, String) {
+        match &self.lookup {
+            Some(hit) => {
+                let summary = LookupSummary::from_value(&hit.result);
+                (clean_label(&summary), clean_description(hit.service, &summary))
+            }
+            None => {
+                let size_str = self
+                    .size
+                    .map(|s| format!(" ({})", format_size(s, size::unit())))
+                    .unwrap_or_default();
+                (self.title.clone(), format!("{}{}", self.indexer, size_str))
+            }
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct RootFolder {
+    id: u32,
     path: String,
 }
 
+/// The fields of a Sonarr/Radarr lookup result used for display and to tell
+/// whether it's already in the library. Lookups for new titles have no `id`
+/// (or `0`). `monitored` isn't used: it's set on new titles too.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LookupSummary {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    year: u32,
+    /// Series only.
+    network: Option<String>,
+    /// Series only.
+    season_count: Option<u32>,
+    /// Movies only.
+    studio: Option<String>,
+    #[serde(default)]
+    id: u64,
+    #[serde(default)]
+    has_file: bool,
+}
+
+impl LookupSummary {
+    fn from_value(result: &serde_json::Value) -> Self {
+        LookupSummary::deserialize(result).unwrap_or_default()
+    }
+
+    fn in_library(&self) -> bool {
+        self.id != 0 || self.has_file
+    }
+}
+
+/// e.g. `Severance (2022)`; the year is left off when the lookup has none.
+fn clean_label(summary: &LookupSummary) -> String {
+    let label = if summary.year == 0 {
+        summary.title.clone()
+    } else {
+        format!("{} ({})", summary.title, summary.year)
+    };
+    truncate_string(&label, 100)
+}
+
+/// e.g. `TV · Apple TV+ · 2 seasons` or `Movie · A24 · in library`.
+fn clean_description(service: &str, summary: &LookupSummary) -> String {
+    let mut parts = vec![if service == "sonarr" { "TV" } else { "Movie" }.to_string()];
+    if let Some(by) = summary.network.as_ref().or(summary.studio.as_ref()).filter(|s| !s.is_empty()) {
+        parts.push(by.clone());
+    }
+    match summary.season_count {
+        Some(1) => parts.push("1 season".into()),
+        Some(n) if n > 1 => parts.push(format!("{n} seasons")),
+        _ => {}
+    }
+    if summary.in_library() {
+        parts.push("in library".into());
+    }
+    truncate_string(&parts.join(" · "), 100)
+}
+
 #[derive(Debug, Deserialize)]
-#[allow(dead_code)]
 struct QualityProfile {
     id: u32,
     name: String,
 }
 
+/// Choices made so far while adding a search result, carried through the
+/// component custom_ids: the `req_add:<id>:<service>:<index>` button, then
+/// `req_root:<id>:<service>:<index>` (value: root folder id), then
+/// `req_prof:<id>:<service>:<index>:<root folder id>` (value: profile id).
+#[derive(Debug, Clone, PartialEq)]
+struct AddSelection {
+    id: String,
+    service: String,
+    index: usize,
+    root_folder: Option<u32>,
+    profile: Option<u32>,
+}
+
+impl AddSelection {
+    /// Parse a component's custom_id and, for select menus, its chosen value.
+    fn parse(custom_id: &str, value: Option<&str>) -> Option<Self> {
+        let (prefix, rest) = custom_id.split_once(':')?;
+        let parts: Vec<&str> = rest.split(':').collect();
+        let value = || value.and_then(|v| v.parse().ok());
+        let (root_folder, profile) = match (prefix, parts.len()) {
+            ("req_add", 3) => (None, None),
+            ("req_root", 3) => (Some(value()?), None),
+            ("req_prof", 4) => (Some(parts[3].parse().ok()?), Some(value()?)),
+            _ => return None,
+        };
+        Some(Self {
+            id: parts[0].to_string(),
+            service: parts[1].to_string(),
+            index: parts[2].parse().ok()?,
+            root_folder,
+            profile,
+        })
+    }
+
+    fn root_folder_menu_id(&self) -> String {
+        format!("req_root:{}:{}:{}", self.id, self.service, self.index)
+    }
+
+    fn profile_menu_id(&self, root_folder: u32) -> String {
+        format!("req_prof:{}:{}:{}:{root_folder}", self.id, self.service, self.index)
+    }
+}
+
+/// What adding a search result needs next.
+#[derive(Debug)]
+enum AddStep {
+    PickRootFolder(Vec<RootFolder>),
+    /// The root folder id is already settled.
+    PickProfile(u32, Vec<QualityProfile>),
+    /// Finished (added, dry-run, or already in the library); the reply text.
+    Done(String),
+}
+
+/// The option for one step: the user's pick, the only option there is, or
+/// `None` when the user still has to choose. Errors are the reply text.
+fn resolve_choice<'a, T>(
+    chosen: Option<u32>,
+    options: &'a [T],
+    id: impl Fn(&T) -> u32,
+    what: &str,
+    service: &str,
+) -> Result<Option<&'a T>, String> {
+    if options.is_empty() {
+        return Err(format!("No {what} configured in {service}"));
+    }
+    match chosen {
+        Some(chosen) => options
+            .iter()
+            .find(|o| id(o) == chosen)
+            .map(Some)
+            .ok_or_else(|| format!("That {what} no longer exists in {service}")),
+        None if options.len() == 1 => Ok(options.first()),
+        None => Ok(None),
+    }
+}
+
 pub struct RequestPlugin {
     prowlarr: ArrClient,
     sonarr: Option<ArrClient>,
     radarr: Option<ArrClient>,
-    pending: Arc<RwLock<HashMap<String, PendingRequest>>>,
+    /// Search results by interaction id.
+    pending: PendingStore<Vec<PendingItem>>,
+    /// Search Sonarr/Radarr lookups instead of Prowlarr releases.
+    clean_titles: bool,
+    /// Look titles up but skip the add, replying with what would have happened.
+    dry_run: bool,
 }
 
 impl RequestPlugin {
@@ -62,18 +240,92 @@ impl RequestPlugin {
         prowlarr_key: &str,
         sonarr: Option<(&str, &str)>,
         radarr: Option<(&str, &str)>,
+        clean_titles: bool,
+        dry_run: bool,
     ) -> Self {
+        Self::with_clients(
+            ArrClient::with_api_version(prowlarr_url, prowlarr_key, "v1"),
+            sonarr.map(|(url, key)| ArrClient::new(url, key)),
+            radarr.map(|(url, key)| ArrClient::new(url, key)),
+            clean_titles,
+            dry_run,
+        )
+    }
+
+    pub fn with_clients(
+        prowlarr: ArrClient,
+        sonarr: Option<ArrClient>,
+        radarr: Option<ArrClient>,
+        clean_titles: bool,
+        dry_run: bool,
+    ) -> Self {
+        let pending = PendingStore::new(PENDING_TTL);
+        pending.spawn_cleanup(PRUNE_INTERVAL);
+
         Self {
-            prowlarr: ArrClient::with_api_version(prowlarr_url, prowlarr_key, "v1"),
-            sonarr: sonarr.map(|(url, key)| ArrClient::new(url, key)),
-            radarr: radarr.map(|(url, key)| ArrClient::new(url, key)),
-            pending: Arc::new(RwLock::new(HashMap::new())),
+            prowlarr,
+            sonarr,
+            radarr,
+            pending,
+            clean_titles,
+            dry_run,
         }
     }
 
-    async fn cleanup_expired(&self) {
-        let mut pending = self.pending.write().await;
-        pending.retain(|_, req| req.created_at.elapsed().as_secs() < 900);
+    /// The result at `index` of search `id`, if the search hasn't expired.
+    async fn pending_item(&self, id: &str, index: usize) -> Result<PendingItem, PluginError> {
+        let results = self.pending.get(id).await.ok_or_else(|| {
+            PluginError::Other("This request has expired. Please search again.".into())
+        })?;
+
+        results.get(index).cloned().ok_or_else(|| {
+            PluginError::Other("Invalid selection.".into())
+        })
+    }
+
+    /// Up to 25 Prowlarr releases matching `title`.
+    async fn search_releases(&self, title: &str) -> Result<Vec<PendingItem>, PluginError> {
+        let results: Vec<ProwlarrResult> = self
+            .prowlarr
+            .get_with_params("search", &[("query", title)])
+            .await?;
+        Ok(results
+            .into_iter()
+            .take(25)
+            .map(|r| PendingItem {
+                title: r.title,
+                size: r.size,
+                indexer: r.indexer_name.unwrap_or_else(|| "unknown".into()),
+                lookup: None,
+            })
+            .collect())
+    }
+
+    /// Sonarr and Radarr lookups for `title`, splitting the menu's 25 slots
+    /// between the configured services.
+    async fn search_lookups(&self, title: &str) -> Result<Vec<PendingItem>, PluginError> {
+        let services: Vec<(&'static str, &ArrClient, &str)> = [
+            ("sonarr", self.sonarr.as_ref(), "series/lookup"),
+            ("radarr", self.radarr.as_ref(), "movie/lookup"),
+        ]
+        .into_iter()
+        .filter_map(|(service, client, endpoint)| client.map(|c| (service, c, endpoint)))
+        .collect();
+        let per_service = 25 / services.len().max(1);
+
+        let mut items = Vec::new();
+        for (service, client, endpoint) in services {
+            let results: Vec<serde_json::Value> = client
+                .get_with_params(endpoint, &[("term", title)])
+                .await?;
+            items.extend(
+                results
+                    .into_iter()
+                    .take(per_service)
+                    .map(|result| PendingItem::from_lookup(service, result)),
+            );
+        }
+        Ok(items)
     }
 
     async fn handle_search(
@@ -82,56 +334,31 @@ impl RequestPlugin {
         command: &CommandInteraction,
         title: &str,
     ) -> Result<(), PluginError> {
-        self.cleanup_expired().await;
-
-        let results: Vec<ProwlarrResult> = self
-            .prowlarr
-            .get_with_params("search", &[("query", title)])
-            .await
-            .map_err(|e| PluginError::ApiError(e.to_string()))?;
+        let items = if self.clean_titles {
+            self.search_lookups(title).await?
+        } else {
+            self.search_releases(title).await?
+        };
 
-        if results.is_empty() {
-            let data = CreateInteractionResponseMessage::new()
+        if items.is_empty() {
+            let data = response_message()
                 .content(format!("No results found for \"{title}\""));
-            command
-                .create_response(&ctx.http, CreateInteractionResponse::Message(data))
-                .await
-                .map_err(PluginError::DiscordError)?;
+            ack::respond(ctx, command, data).await?;
             return Ok(());
         }
 
         let id = format!("{}", command.id);
-        let items: Vec<PendingItem> = results
-            .iter()
-            .take(25)
-            .map(|r| PendingItem {
-                title: r.title.clone(),
-                size: r.size,
-                indexer: r.indexer_name.clone().unwrap_or_else(|| "unknown".into()),
-            })
-            .collect();
-
         let options: Vec<CreateSelectMenuOption> = items
             .iter()
             .enumerate()
             .map(|(i, item)| {
-                let size_str = item
-                    .size
-                    .map(|s| format!(" ({:.1} MB)", s as f64 / 1_048_576.0))
-                    .unwrap_or_default();
-                let label = truncate_string(&item.title, 100);
-                let desc = format!("{}{}", item.indexer, size_str);
-                CreateSelectMenuOption::new(label, format!("{i}")).description(truncate_string(&desc, 100))
+                let (label, desc) = item.menu_text();
+                CreateSelectMenuOption::new(truncate_string(&label, 100), format!("{i}"))
+                    .description(truncate_string(&desc, 100))
             })
             .collect();
 
-        self.pending.write().await.insert(
-            id.clone(),
-            PendingRequest {
-                results: items,
-                created_at: std::time::Instant::now(),
-            },
-        );
+        self.pending.insert(id.clone(), items).await;
 
         let select = CreateSelectMenu::new(
             format!("req_sel:{id}"),
@@ -139,14 +366,11 @@ impl RequestPlugin {
         )
         .placeholder("Select a result...");
 
-        let data = CreateInteractionResponseMessage::new()
+        let data = response_message()
             .content(format!("**Search results for \"{title}\":**"))
             .components(vec![CreateActionRow::SelectMenu(select)]);
 
-        command
-            .create_response(&ctx.http, CreateInteractionResponse::Message(data))
-            .await
-            .map_err(PluginError::DiscordError)?;
+        ack::respond(ctx, command, data).await?;
         Ok(())
     }
 
@@ -157,14 +381,19 @@ impl RequestPlugin {
         id: &str,
         index: usize,
     ) -> Result<(), PluginError> {
-        let pending = self.pending.read().await;
-        let req = pending.get(id).ok_or_else(|| {
-            PluginError::Other("This request has expired. Please search again.".into())
-        })?;
-
-        let item = req.results.get(index).ok_or_else(|| {
-            PluginError::Other("Invalid selection.".into())
-        })?;
+        let item = self.pending_item(id, index).await?;
+
+        // Clean-title results already know their service
+        if let Some(hit) = &item.lookup {
+            let sel = AddSelection {
+                id: id.to_string(),
+                service: hit.service.to_string(),
+                index,
+                root_folder: None,
+                profile: None,
+            };
+            return self.handle_add(ctx, component, &sel).await;
+        }
 
         let mut buttons = Vec::new();
         if self.sonarr.is_some() {
@@ -175,7 +404,7 @@ impl RequestPlugin {
         }
 
         if buttons.is_empty() {
-            let data = CreateInteractionResponseMessage::new()
+            let data = response_message()
                 .content("No target services configured (Sonarr/Radarr).")
                 .ephemeral(true);
             component
@@ -187,10 +416,10 @@ impl RequestPlugin {
 
         let size_str = item
             .size
-            .map(|s| format!(" ({:.1} MB)", s as f64 / 1_048_576.0))
+            .map(|s| format!(" ({})", format_size(s, size::unit())))
             .unwrap_or_default();
 
-        let data = CreateInteractionResponseMessage::new()
+        let data = response_message()
             .content(format!(
                 "**Selected:** {}{}\nWhere would you like to add it?",
                 item.title, size_str
@@ -208,19 +437,62 @@ impl RequestPlugin {
         &self,
         ctx: &Context,
         component: &ComponentInteraction,
-        id: &str,
-        service: &str,
-        index: usize,
+        sel: &AddSelection,
     ) -> Result<(), PluginError> {
-        let pending = self.pending.read().await;
-        let req = pending.get(id).ok_or_else(|| {
-            PluginError::Other("This request has expired. Please search again.".into())
-        })?;
+        let item = self.pending_item(&sel.id, sel.index).await?;
+
+        let data = match self.add_step(&item, sel).await? {
+            AddStep::PickRootFolder(folders) => {
+                let options = folders
+                    .iter()
+                    .take(25)
+                    .map(|f| CreateSelectMenuOption::new(truncate_string(&f.path, 100), f.id.to_string()))
+                    .collect();
+                let select = CreateSelectMenu::new(
+                    sel.root_folder_menu_id(),
+                    CreateSelectMenuKind::String { options },
+                )
+                .placeholder("Select a root folder...");
+                response_message()
+                    .content(format!("**{}** — which root folder?", item.title))
+                    .components(vec![CreateActionRow::SelectMenu(select)])
+            }
+            AddStep::PickProfile(root_folder, profiles) => {
+                let options = profiles
+                    .iter()
+                    .take(25)
+                    .map(|p| CreateSelectMenuOption::new(truncate_string(&p.name, 100), p.id.to_string()))
+                    .collect();
+                let select = CreateSelectMenu::new(
+                    sel.profile_menu_id(root_folder),
+                    CreateSelectMenuKind::String { options },
+                )
+                .placeholder("Select a quality profile...");
+                response_message()
+                    .content(format!("**{}** — which quality profile?", item.title))
+                    .components(vec![CreateActionRow::SelectMenu(select)])
+            }
+            AddStep::Done(content) => {
+                // Cleanup this pending request
+                self.pending.remove(&sel.id).await;
+                response_message().content(content)
+            }
+        };
 
-        let item = req.results.get(index).ok_or_else(|| {
-            PluginError::Other("Invalid selection.".into())
-        })?;
+        component
+            .create_response(&ctx.http, CreateInteractionResponse::Message(data))
+            .await
+            .map_err(PluginError::DiscordError)?;
+        Ok(())
+    }
 
+    /// Look `item` up (unless it came from a lookup), then work out the root
+    /// folder and quality profile for `sel`, asking only when the service has
+    /// more than one, and add it once both are known. Titles already in the
+    /// library are never re-added.
+    async fn add_step(&self, item: &PendingItem, sel: &AddSelection) -> Result<AddStep, PluginError> {
+        let title = item.title.as_str();
+        let service = sel.service.as_str();
         let client = match service {
             "sonarr" => self.sonarr.as_ref(),
             "radarr" => self.radarr.as_ref(),
@@ -228,45 +500,72 @@ impl RequestPlugin {
         }
         .ok_or_else(|| PluginError::Other(format!("{service} is not configured")))?;
 
-        // Get root folder and quality profile defaults
+        let result = match &item.lookup {
+            Some(hit) => hit.result.clone(),
+            None => {
+                // Search the target service for this title to get proper metadata
+                let search_endpoint = match service {
+                    "sonarr" => "series/lookup",
+                    "radarr" => "movie/lookup",
+                    _ => unreachable!(),
+                };
+
+                let search_results: Vec<serde_json::Value> = client
+                    .get_with_params(search_endpoint, &[("term", title)])
+                    .await?;
+
+                search_results.into_iter().next().ok_or_else(|| {
+                    PluginError::Other(format!("Could not find \"{title}\" in {service}"))
+                })?
+            }
+        };
+
+        if LookupSummary::from_value(&result).in_library() {
+            return Ok(AddStep::Done(format!(
+                "**{title}** is already in {}.",
+                service_display_name(service)
+            )));
+        }
+
         let root_folders: Vec<RootFolder> = client
             .get("rootfolder")
-            .await
-            .map_err(|e| PluginError::ApiError(e.to_string()))?;
-
-        let root_path = root_folders
-            .first()
-            .map(|r| r.path.clone())
-            .ok_or_else(|| PluginError::Other(format!("No root folder configured in {service}")))?;
+            .await?;
+        let Some(root_folder) =
+            resolve_choice(sel.root_folder, &root_folders, |f| f.id, "root folder", service)
+                .map_err(PluginError::Other)?
+        else {
+            return Ok(AddStep::PickRootFolder(root_folders));
+        };
 
         let profiles: Vec<QualityProfile> = client
             .get("qualityprofile")
-            .await
-            .map_err(|e| PluginError::ApiError(e.to_string()))?;
-
-        let profile_id = profiles
-            .first()
-            .map(|p| p.id)
-            .ok_or_else(|| PluginError::Other(format!("No quality profile configured in {service}")))?;
-
-        // Search the target service for this title to get proper metadata
-        let search_endpoint = match service {
-            "sonarr" => "series/lookup",
-            "radarr" => "movie/lookup",
-            _ => unreachable!(),
+            .await?;
+        let Some(profile) =
+            resolve_choice(sel.profile, &profiles, |p| p.id, "quality profile", service)
+                .map_err(PluginError::Other)?
+        else {
+            return Ok(AddStep::PickProfile(root_folder.id, profiles));
         };
 
-        let search_results: Vec<serde_json::Value> = client
-            .get_with_params(search_endpoint, &[("term", item.title.as_str())])
-            .await
-            .map_err(|e| PluginError::ApiError(e.to_string()))?;
-
-        let result = search_results
-            .first()
-            .ok_or_else(|| PluginError::Other(format!("Could not find \"{}\" in {service}", item.title)))?;
+        let content = self
+            .add_to_service(client, title, service, result, &root_folder.path, profile.id)
+            .await?;
+        Ok(AddStep::Done(content))
+    }
 
+    /// Add the lookup `result` for `title` to Sonarr/Radarr under `root_path`
+    /// with `profile_id`. Returns the reply text.
+    async fn add_to_service(
+        &self,
+        client: &ArrClient,
+        title: &str,
+        service: &str,
+        result: serde_json::Value,
+        root_path: &str,
+        profile_id: u32,
+    ) -> Result<String, PluginError> {
         // Build the add request
-        let mut add_body = result.clone();
+        let mut add_body = result;
         if let Some(obj) = add_body.as_object_mut() {
             obj.insert("rootFolderPath".into(), serde_json::json!(root_path));
             obj.insert("qualityProfileId".into(), serde_json::json!(profile_id));
@@ -284,28 +583,17 @@ impl RequestPlugin {
             _ => unreachable!(),
         };
 
-        let _: serde_json::Value = client
-            .post(add_endpoint, &add_body)
-            .await
-            .map_err(|e| PluginError::ApiError(e.to_string()))?;
+        let service_name = service_display_name(service);
 
-        let service_name = match service {
-            "sonarr" => "Sonarr",
-            "radarr" => "Radarr",
-            _ => service,
-        };
+        if self.dry_run {
+            return Ok(dry_run_message(&format!("added **{title}** to {service_name}")));
+        }
 
-        let data = CreateInteractionResponseMessage::new()
-            .content(format!("Added **{}** to {service_name}!", item.title));
-        component
-            .create_response(&ctx.http, CreateInteractionResponse::Message(data))
-            .await
-            .map_err(PluginError::DiscordError)?;
+        let _: serde_json::Value = client
+            .post(add_endpoint, &add_body)
+            .await?;
 
-        // Cleanup this pending request
-        drop(pending);
-        self.pending.write().await.remove(id);
-        Ok(())
+        Ok(format!("Added **{title}** to {service_name}!"))
     }
 }
 
@@ -328,6 +616,17 @@ impl Plugin for RequestPlugin {
             )]
     }
 
+    fn component_command(&self, custom_id: &str) -> Option<&str> {
+        let prefix = custom_id.split_once(':')?.0;
+        matches!(prefix, "req_sel" | "req_add" | "req_root" | "req_prof").then_some("request")
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![
+            ("/request".into(), "Search and add media to Sonarr/Radarr".into()),
+        ]
+    }
+
     async fn handle_command(
         &self,
         ctx: &Context,
@@ -372,23 +671,35 @@ impl Plugin for RequestPlugin {
                 .ok_or_else(|| PluginError::Other("Invalid selection".into()))?;
             self.handle_select(ctx, component, id, index).await?;
             Ok(true)
-        } else if let Some(rest) = custom_id.strip_prefix("req_add:") {
-            // Button: req_add:<id>:<service>:<index>
-            let parts: Vec<&str> = rest.splitn(3, ':').collect();
-            if parts.len() != 3 {
-                return Ok(false);
-            }
-            let id = parts[0];
-            let service = parts[1];
-            let index: usize = parts[2]
-                .parse()
-                .map_err(|_| PluginError::Other("Invalid index".into()))?;
-            self.handle_add(ctx, component, id, service, index).await?;
+        } else if custom_id.starts_with("req_add:")
+            || custom_id.starts_with("req_root:")
+            || custom_id.starts_with("req_prof:")
+        {
+            // Add button, then root folder and quality profile menus
+            let value = match &component.data.kind {
+                ComponentInteractionDataKind::StringSelect { values } => values.first(),
+                _ => None,
+            };
+            let sel = AddSelection::parse(custom_id, value.map(String::as_str))
+                .ok_or_else(|| PluginError::Other("Invalid selection".into()))?;
+            self.handle_add(ctx, component, &sel).await?;
             Ok(true)
         } else {
             Ok(false)
         }
     }
+
+    async fn clear_state(&self) -> usize {
+        self.pending.clear().await
+    }
+}
+
+fn service_display_name(service: &str) -> &str {
+    match service {
+        "sonarr" => "Sonarr",
+        "radarr" => "Radarr",
+        _ => service,
+    }
 }
 
 fn truncate_string(s: &str, max: usize) -> String {
@@ -406,6 +717,248 @@ fn truncate_string(s: &str, max: usize) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// A Sonarr mock that answers the lookups and expects `posts` adds.
+    async fn mock_sonarr(posts: u64) -> MockServer {
+        mock_sonarr_with_folders(posts, serde_json::json!([{"id": 1, "path": "/tv"}])).await
+    }
+
+    /// [`mock_sonarr`] with a custom root folder list.
+    async fn mock_sonarr_with_folders(posts: u64, folders: serde_json::Value) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/rootfolder"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(folders))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/qualityprofile"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([{"id": 1, "name": "Any"}])),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/series/lookup"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([{"title": "Severance", "tvdbId": 371980}])),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v3/series"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({"id": 7})))
+            .expect(posts)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_add_posts_to_service() {
+        let server = mock_sonarr(1).await;
+        let uri = server.uri();
+        let plugin = RequestPlugin::new("http://prowlarr", "key", Some((uri.as_str(), "key")), None, false, false);
+        let step = plugin.add_step(&release("Severance"), &button("sonarr")).await.unwrap();
+        assert!(matches!(step, AddStep::Done(ref msg) if msg == "Added **Severance** to Sonarr!"), "{step:?}");
+    }
+
+    #[tokio::test]
+    async fn test_add_asks_for_root_folder_when_several() {
+        let server = mock_sonarr_with_folders(
+            0,
+            serde_json::json!([{"id": 1, "path": "/tv"}, {"id": 2, "path": "/tv-4k"}]),
+        )
+        .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v3/series"))
+            .and(body_partial_json(serde_json::json!({"rootFolderPath": "/tv-4k", "qualityProfileId": 1})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({"id": 7})))
+            .with_priority(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        let uri = server.uri();
+        let plugin = RequestPlugin::new("http://prowlarr", "key", Some((uri.as_str(), "key")), None, false, false);
+
+        let sel = button("sonarr");
+        let step = plugin.add_step(&release("Severance"), &sel).await.unwrap();
+        assert!(matches!(step, AddStep::PickRootFolder(ref f) if f.len() == 2), "{step:?}");
+
+        // The only quality profile is used without asking.
+        let sel = AddSelection::parse(&sel.root_folder_menu_id(), Some("2")).unwrap();
+        let step = plugin.add_step(&release("Severance"), &sel).await.unwrap();
+        assert!(matches!(step, AddStep::Done(_)), "{step:?}");
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_add_skips_post() {
+        let server = mock_sonarr(0).await;
+        let uri = server.uri();
+        let plugin = RequestPlugin::new("http://prowlarr", "key", Some((uri.as_str(), "key")), None, false, true);
+        let step = plugin.add_step(&release("Severance"), &button("sonarr")).await.unwrap();
+        assert!(
+            matches!(step, AddStep::Done(ref msg) if msg == "[dry-run] would have added **Severance** to Sonarr"),
+            "{step:?}"
+        );
+        // Dropping the server verifies the POST expectation
+    }
+
+    #[tokio::test]
+    async fn test_add_skips_titles_already_in_library() {
+        let server = mock_sonarr(0).await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/series/lookup"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!([{"title": "Severance", "tvdbId": 371980, "id": 5, "monitored": true}]),
+            ))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        let uri = server.uri();
+        let plugin = RequestPlugin::new("http://prowlarr", "key", Some((uri.as_str(), "key")), None, false, false);
+        let step = plugin.add_step(&release("Severance"), &button("sonarr")).await.unwrap();
+        assert!(matches!(step, AddStep::Done(ref msg) if msg == "**Severance** is already in Sonarr."), "{step:?}");
+        // Dropping the server verifies no POST was made
+    }
+
+    #[test]
+    fn test_lookup_in_library() {
+        let check = |v: serde_json::Value| LookupSummary::from_value(&v).in_library();
+        assert!(!check(serde_json::json!({"title": "New", "monitored": true})));
+        assert!(!check(serde_json::json!({"title": "New", "id": 0})));
+        assert!(check(serde_json::json!({"title": "Old", "id": 12})));
+        assert!(check(serde_json::json!({"title": "Old", "hasFile": true})));
+    }
+
+    fn release(title: &str) -> PendingItem {
+        PendingItem { title: title.into(), size: None, indexer: "x".into(), lookup: None }
+    }
+
+    #[test]
+    fn test_clean_title_menu_text() {
+        let series = PendingItem::from_lookup(
+            "sonarr",
+            serde_json::json!({"title": "Severance", "year": 2022, "network": "Apple TV+", "seasonCount": 2}),
+        );
+        assert_eq!(series.title, "Severance");
+        assert_eq!(
+            series.menu_text(),
+            ("Severance (2022)".to_string(), "TV · Apple TV+ · 2 seasons".to_string())
+        );
+
+        let movie = PendingItem::from_lookup(
+            "radarr",
+            serde_json::json!({"title": "Past Lives", "year": 2023, "studio": "A24", "id": 4}),
+        );
+        assert_eq!(
+            movie.menu_text(),
+            ("Past Lives (2023)".to_string(), "Movie · A24 · in library".to_string())
+        );
+
+        // Missing year and network/studio are left out.
+        let bare = PendingItem::from_lookup("sonarr", serde_json::json!({"title": "Untitled", "seasonCount": 1}));
+        assert_eq!(bare.menu_text(), ("Untitled".to_string(), "TV · 1 season".to_string()));
+    }
+
+    #[test]
+    fn test_release_menu_text() {
+        let item = PendingItem {
+            title: "Severance.S01.1080p.WEB-DL".into(),
+            size: Some(1_610_612_736),
+            indexer: "nyaa".into(),
+            lookup: None,
+        };
+        assert_eq!(
+            item.menu_text(),
+            ("Severance.S01.1080p.WEB-DL".to_string(), "nyaa (1.5 GB)".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clean_title_add_uses_lookup_result() {
+        let server = mock_sonarr(1).await;
+        let uri = server.uri();
+        // The stored lookup is added as-is; looking it up again finds nothing.
+        Mock::given(method("GET"))
+            .and(path("/api/v3/series/lookup"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .with_priority(1)
+            .expect(0)
+            .mount(&server)
+            .await;
+        let plugin = RequestPlugin::new("http://prowlarr", "key", Some((uri.as_str(), "key")), None, true, false);
+        let item = PendingItem::from_lookup("sonarr", serde_json::json!({"title": "Severance", "tvdbId": 371980}));
+        let step = plugin.add_step(&item, &button("sonarr")).await.unwrap();
+        assert!(matches!(step, AddStep::Done(ref msg) if msg == "Added **Severance** to Sonarr!"), "{step:?}");
+    }
+
+    fn button(service: &str) -> AddSelection {
+        AddSelection::parse(&format!("req_add:42:{service}:3"), None).unwrap()
+    }
+
+    #[test]
+    fn test_add_selection_threads_choices() {
+        let sel = button("radarr");
+        assert_eq!(
+            sel,
+            AddSelection {
+                id: "42".into(),
+                service: "radarr".into(),
+                index: 3,
+                root_folder: None,
+                profile: None,
+            }
+        );
+
+        let root_id = sel.root_folder_menu_id();
+        assert_eq!(root_id, "req_root:42:radarr:3");
+        let sel = AddSelection::parse(&root_id, Some("7")).unwrap();
+        assert_eq!((sel.root_folder, sel.profile), (Some(7), None));
+
+        let profile_id = sel.profile_menu_id(7);
+        assert_eq!(profile_id, "req_prof:42:radarr:3:7");
+        let sel = AddSelection::parse(&profile_id, Some("4")).unwrap();
+        assert_eq!((sel.id.as_str(), sel.index), ("42", 3));
+        assert_eq!((sel.root_folder, sel.profile), (Some(7), Some(4)));
+
+        // Menus need a numeric value; malformed ids are rejected.
+        assert_eq!(AddSelection::parse(&root_id, None), None);
+        assert_eq!(AddSelection::parse(&root_id, Some("x")), None);
+        assert_eq!(AddSelection::parse("req_add:42:radarr", None), None);
+        assert_eq!(AddSelection::parse("req_sel:42", None), None);
+    }
+
+    #[test]
+    fn test_resolve_choice() {
+        let ids = [3u32, 5];
+        let id = |n: &u32| *n;
+        assert_eq!(resolve_choice(None, &ids, id, "root folder", "radarr").unwrap(), None);
+        assert_eq!(resolve_choice(Some(5), &ids, id, "root folder", "radarr").unwrap(), Some(&5));
+        assert_eq!(resolve_choice(None, &ids[..1], id, "root folder", "radarr").unwrap(), Some(&3));
+
+        let err = resolve_choice(Some(9), &ids, id, "root folder", "radarr").unwrap_err();
+        assert_eq!(err, "That root folder no longer exists in radarr");
+        let err = resolve_choice(None, &[], id, "quality profile", "radarr").unwrap_err();
+        assert_eq!(err, "No quality profile configured in radarr");
+    }
+
+    #[tokio::test]
+    async fn test_expired_request_is_rejected() {
+        let mut plugin = RequestPlugin::new("http://prowlarr", "key", None, None, false, false);
+        plugin.pending.insert("fresh".into(), vec![release("Severance")]).await;
+
+        assert_eq!(plugin.pending_item("fresh", 0).await.unwrap().title, "Severance");
+        let err = plugin.pending_item("fresh", 3).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid selection.");
+
+        plugin.pending = PendingStore::new(Duration::ZERO);
+        plugin.pending.insert("stale".into(), vec![release("Severance")]).await;
+        let err = plugin.pending_item("stale", 0).await.unwrap_err();
+        assert!(err.to_string().contains("Please search again"), "{err}");
+    }
 
     #[test]
     fn test_truncate_string_short() {
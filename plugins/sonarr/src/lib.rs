@@ -1,14 +1,30 @@
 use async_trait::async_trait;
-use discord_assist_arr_common::ArrClient;
-use discord_assist_plugin_api::{Plugin, PluginError};
+use discord_assist_arr_common::{ArrClient, PagedResponse};
+use discord_assist_plugin_api::{
+    Plugin, PluginError, ack, list_embed, respond_chunked, respond_embed, response_message,
+};
+use discord_assist_util::{
+    discord_relative, escape_discord, iso_date_in_days, parse_rfc3339, today_iso,
+};
 use serde::Deserialize;
 use serenity::builder::{
-    CreateCommand, CreateCommandOption, CreateInteractionResponse,
-    CreateInteractionResponseMessage,
+    CreateActionRow, CreateButton, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse,
+};
+use serenity::model::application::{
+    ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, ResolvedValue,
 };
-use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
 use serenity::prelude::Context;
 
+/// Discord message content limit.
+const MAX_MESSAGE_LEN: usize = 2000;
+
+/// Calendar window for `upcoming` when `days` is not given.
+const DEFAULT_UPCOMING_DAYS: u64 = 7;
+
+const SEARCH_MISSING_CONFIRM: &str = "sonarr_searchmissing:confirm";
+const SEARCH_MISSING_CANCEL: &str = "sonarr_searchmissing:cancel";
+
 #[derive(Debug, Deserialize)]
 struct Series {
     title: String,
@@ -19,15 +35,20 @@ struct Series {
 struct Episode {
     #[serde(rename = "seriesTitle")]
     series_title: Option<String>,
+    /// Present when requested with `includeSeries=true`.
+    series: Option<Series>,
     title: Option<String>,
+    #[serde(rename = "seasonNumber")]
+    season_number: Option<u32>,
+    #[serde(rename = "episodeNumber")]
+    episode_number: Option<u32>,
     #[serde(rename = "airDateUtc")]
     air_date_utc: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-struct QueueStatus {
-    #[serde(rename = "totalCount")]
-    total_count: Option<u32>,
+struct CommandResponse {
+    id: u64,
 }
 
 pub struct SonarrPlugin {
@@ -36,9 +57,11 @@ pub struct SonarrPlugin {
 
 impl SonarrPlugin {
     pub fn new(api_url: &str, api_key: &str) -> Self {
-        Self {
-            client: ArrClient::new(api_url, api_key),
-        }
+        Self::with_client(ArrClient::new(api_url, api_key))
+    }
+
+    pub fn with_client(client: ArrClient) -> Self {
+        Self { client }
     }
 }
 
@@ -66,18 +89,57 @@ impl Plugin for SonarrPlugin {
                     .required(true),
                 ),
             )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "upcoming",
+                    "Show upcoming episodes",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "days",
+                        "How many days ahead to look (default 7)",
+                    )
+                    .min_int_value(1)
+                    .max_int_value(90),
+                ),
+            )
             .add_option(CreateCommandOption::new(
                 CommandOptionType::SubCommand,
-                "upcoming",
-                "Show upcoming episodes",
+                "status",
+                "Show queue and system status",
             ))
             .add_option(CreateCommandOption::new(
                 CommandOptionType::SubCommand,
-                "status",
-                "Show queue and system status",
+                "missing",
+                "Show monitored episodes that are missing",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "searchmissing",
+                "Search for every missing episode (asks for confirmation)",
             ))]
     }
 
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        matches!(path, ["sonarr", "search" | "upcoming" | "status" | "missing"])
+    }
+
+    fn component_command(&self, custom_id: &str) -> Option<&str> {
+        custom_id.starts_with("sonarr_searchmissing:").then_some("sonarr")
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![
+            ("/sonarr search".into(), "Search for a TV show".into()),
+            ("/sonarr upcoming".into(), "Show upcoming episodes".into()),
+            ("/sonarr status".into(), "Show queue and system status".into()),
+            ("/sonarr missing".into(), "Show monitored episodes that are missing".into()),
+            ("/sonarr searchmissing".into(), "Search for every missing episode (asks for confirmation)".into()),
+        ]
+    }
+
     async fn handle_command(
         &self,
         ctx: &Context,
@@ -108,16 +170,16 @@ impl Plugin for SonarrPlugin {
                     let results: Vec<Series> = self
                         .client
                         .get_with_params("series/lookup", &[("term", title)])
-                        .await
-                        .map_err(|e| PluginError::ApiError(e.to_string()))?;
+                        .await?;
 
+                    let title = escape_discord(title);
                     if results.is_empty() {
                         format!("No results found for \"{title}\"")
                     } else {
                         let mut msg = format!("**Search results for \"{title}\":**\n");
                         for (i, s) in results.iter().take(10).enumerate() {
                             let year = s.year.map(|y| format!(" ({y})")).unwrap_or_default();
-                            msg.push_str(&format!("{}. **{}**{}\n", i + 1, s.title, year));
+                            msg.push_str(&format!("{}. **{}**{}\n", i + 1, escape_discord(&s.title), year));
                         }
                         msg
                     }
@@ -126,44 +188,258 @@ impl Plugin for SonarrPlugin {
                 }
             }
             "upcoming" => {
+                let days = match &subopt.value {
+                    ResolvedValue::SubCommand(opts) => opts
+                        .iter()
+                        .find(|o| o.name == "days")
+                        .and_then(|o| match o.value {
+                            ResolvedValue::Integer(n) => u64::try_from(n).ok(),
+                            _ => None,
+                        }),
+                    _ => None,
+                }
+                .unwrap_or(DEFAULT_UPCOMING_DAYS);
+                let start = today_iso();
+                let end = iso_date_in_days(days);
                 let episodes: Vec<Episode> = self
                     .client
-                    .get("calendar")
-                    .await
-                    .map_err(|e| PluginError::ApiError(e.to_string()))?;
+                    .get_with_params(
+                        "calendar",
+                        &[("start", start.as_str()), ("end", end.as_str())],
+                    )
+                    .await?;
 
-                if episodes.is_empty() {
-                    "No upcoming episodes.".into()
+                let embed = list_embed("Upcoming Episodes", &upcoming_fields(&episodes));
+                let embed = if episodes.is_empty() {
+                    embed.description("No upcoming episodes.")
                 } else {
-                    let mut msg = String::from("**Upcoming Episodes:**\n");
-                    for ep in episodes.iter().take(10) {
-                        let series = ep.series_title.as_deref().unwrap_or("Unknown");
-                        let title = ep.title.as_deref().unwrap_or("TBA");
-                        let date = ep.air_date_utc.as_deref().unwrap_or("TBA");
-                        msg.push_str(&format!("- **{series}** — {title} ({date})\n"));
+                    embed
+                };
+                respond_embed(ctx, command, embed).await?;
+                return Ok(true);
+            }
+            "status" => {
+                let count = self.client.queue_count().await?;
+                let mut msg = format!("**Sonarr Status**\nQueue: {count} items");
+                let disks = self
+                    .client
+                    .disk_space()
+                    .await?;
+                if !disks.is_empty() {
+                    msg.push_str("\n**Disk Space**");
+                    for disk in &disks {
+                        msg.push('\n');
+                        msg.push_str(&disk.render());
                     }
-                    msg
                 }
+                msg
             }
-            "status" => {
-                let queue: QueueStatus = self
+            "missing" => {
+                let missing: PagedResponse<Episode> = self
+                    .client
+                    .get_with_params(
+                        "wanted/missing",
+                        &[
+                            ("pageSize", "10"),
+                            ("sortKey", "airDateUtc"),
+                            ("sortDirection", "descending"),
+                            ("includeSeries", "true"),
+                        ],
+                    )
+                    .await?;
+                format_missing(&missing)
+            }
+            "searchmissing" => {
+                let buttons = vec![
+                    CreateButton::new(SEARCH_MISSING_CONFIRM)
+                        .label("Search all missing")
+                        .style(ButtonStyle::Danger),
+                    CreateButton::new(SEARCH_MISSING_CANCEL)
+                        .label("Cancel")
+                        .style(ButtonStyle::Secondary),
+                ];
+                let data = response_message()
+                    .content("This searches indexers for every missing episode and can take a while on large libraries. Continue?")
+                    .components(vec![CreateActionRow::Buttons(buttons)]);
+                ack::respond(ctx, command, data).await?;
+                return Ok(true);
+            }
+            _ => return Ok(false),
+        };
+
+        respond_chunked(ctx, command, &content).await?;
+        Ok(true)
+    }
+
+    async fn handle_component(
+        &self,
+        ctx: &Context,
+        component: &ComponentInteraction,
+    ) -> Result<bool, PluginError> {
+        let content = match component.data.custom_id.as_str() {
+            SEARCH_MISSING_CONFIRM => {
+                let started: CommandResponse = self
                     .client
-                    .get("queue/status")
-                    .await
-                    .map_err(|e| PluginError::ApiError(e.to_string()))?;
-                let count = queue.total_count.unwrap_or(0);
-                format!("**Sonarr Status**\nQueue: {count} items")
+                    .post("command", &serde_json::json!({ "name": "MissingEpisodeSearch" }))
+                    .await?;
+                format!(
+                    "Started MissingEpisodeSearch (command {}). It runs in the background; check Sonarr for progress.",
+                    started.id
+                )
             }
+            SEARCH_MISSING_CANCEL => "Search cancelled.".to_string(),
             _ => return Ok(false),
         };
 
-        let data = CreateInteractionResponseMessage::new().content(content);
-        let builder = CreateInteractionResponse::Message(data);
-        command
-            .create_response(&ctx.http, builder)
+        let data = response_message()
+            .content(content)
+            .components(Vec::new());
+        component
+            .create_response(&ctx.http, CreateInteractionResponse::UpdateMessage(data))
             .await
             .map_err(PluginError::DiscordError)?;
         Ok(true)
     }
 }
 
+/// Air dates become Discord timestamps (`<t:UNIX:R>`) so each viewer sees a
+/// localized relative time; unparseable values are shown as-is.
+fn format_air_date(raw: Option<&str>) -> String {
+    match raw {
+        Some(raw) => match parse_rfc3339(raw) {
+            Some(unix) => discord_relative(unix),
+            None => escape_discord(raw),
+        },
+        None => "TBA".into(),
+    }
+}
+
+/// One `(series, "episode (air date)")` embed field per episode.
+fn upcoming_fields(episodes: &[Episode]) -> Vec<(String, String)> {
+    episodes
+        .iter()
+        .take(10)
+        .map(|ep| {
+            let series = ep.series_title.as_deref().unwrap_or("Unknown");
+            let title = ep.title.as_deref().unwrap_or("TBA");
+            let date = format_air_date(ep.air_date_utc.as_deref());
+            (escape_discord(series), format!("{} ({date})", escape_discord(title)))
+        })
+        .collect()
+}
+
+fn format_missing(missing: &PagedResponse<Episode>) -> String {
+    if missing.records.is_empty() {
+        return "No missing episodes.".into();
+    }
+    let mut msg = format!("**Missing Episodes** ({} total)\n", missing.total_records);
+    for (i, ep) in missing.records.iter().enumerate() {
+        let series = ep
+            .series
+            .as_ref()
+            .map(|s| s.title.as_str())
+            .or(ep.series_title.as_deref())
+            .unwrap_or("Unknown");
+        let number = match (ep.season_number, ep.episode_number) {
+            (Some(s), Some(e)) => format!(" S{s:02}E{e:02}"),
+            _ => String::new(),
+        };
+        let title = ep.title.as_deref().unwrap_or("TBA");
+        let date = ep.air_date_utc.as_deref().map_or("TBA", |d| d.get(..10).unwrap_or(d));
+        let (series, title) = (escape_discord(series), escape_discord(title));
+        let line = format!("- **{series}**{number} — {title} ({date})\n");
+        if msg.len() + line.len() > MAX_MESSAGE_LEN - 20 {
+            msg.push_str(&format!("... and {} more\n", missing.records.len() - i));
+            break;
+        }
+        msg.push_str(&line);
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(title: &str) -> Episode {
+        Episode {
+            series_title: None,
+            series: Some(Series {
+                title: "Severance".into(),
+                year: Some(2022),
+            }),
+            title: Some(title.into()),
+            season_number: Some(1),
+            episode_number: Some(2),
+            air_date_utc: Some("2022-02-25T02:00:00Z".into()),
+        }
+    }
+
+    #[test]
+    fn test_format_air_date() {
+        assert_eq!(
+            format_air_date(Some("2024-01-02T20:00:00Z")),
+            "<t:1704225600:R>"
+        );
+        assert_eq!(format_air_date(Some("sometime")), "sometime");
+        assert_eq!(format_air_date(None), "TBA");
+    }
+
+    #[test]
+    fn test_upcoming_fields() {
+        let mut ep = episode("Hello, Ms. Cobel");
+        ep.series_title = Some("Severance".into());
+        let fields = upcoming_fields(&[ep, episode("Half Loop")]);
+        assert_eq!(
+            fields,
+            vec![
+                ("Severance".into(), "Hello, Ms. Cobel (<t:1645754400:R>)".into()),
+                ("Unknown".into(), "Half Loop (<t:1645754400:R>)".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_missing() {
+        let missing = PagedResponse {
+            records: vec![episode("Half Loop")],
+            total_records: 3,
+        };
+        assert_eq!(
+            format_missing(&missing),
+            "**Missing Episodes** (3 total)\n- **Severance** S01E02 — Half Loop (2022-02-25)\n"
+        );
+    }
+
+    #[test]
+    fn test_malicious_title_is_escaped() {
+        let missing = PagedResponse {
+            records: vec![episode("**x** @everyone")],
+            total_records: 1,
+        };
+        assert_eq!(
+            format_missing(&missing),
+            "**Missing Episodes** (1 total)\n- **Severance** S01E02 — \\*\\*x\\*\\* \\@everyone (2022-02-25)\n"
+        );
+    }
+
+    #[test]
+    fn test_format_missing_empty() {
+        let missing = PagedResponse {
+            records: Vec::new(),
+            total_records: 0,
+        };
+        assert_eq!(format_missing(&missing), "No missing episodes.");
+    }
+
+    #[test]
+    fn test_format_missing_stays_under_limit() {
+        let missing = PagedResponse {
+            records: (0..10).map(|_| episode(&"x".repeat(400))).collect(),
+            total_records: 10,
+        };
+        let msg = format_missing(&missing);
+        assert!(msg.len() <= MAX_MESSAGE_LEN);
+        assert!(msg.ends_with("more\n"));
+    }
+}
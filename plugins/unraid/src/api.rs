@@ -1,3 +1,4 @@
+use discord_assist_util::http::{HttpOptions, build_client};
 use reqwest::Client;
 use serde::Deserialize;
 use thiserror::Error;
@@ -8,6 +9,8 @@ pub enum UnraidApiError {
     Http(#[from] reqwest::Error),
     #[error("GraphQL error: {0}")]
     GraphQL(String),
+    #[error("VM '{0}' not found")]
+    VmNotFound(String),
 }
 
 #[derive(Clone)]
@@ -33,6 +36,30 @@ pub struct ArrayStatus {
     pub state: String,
 }
 
+/// Current (or most recent) parity check.
+#[derive(Debug, Deserialize)]
+pub struct ParityCheckStatus {
+    pub status: Option<String>,
+    #[serde(default)]
+    pub running: bool,
+    /// Percent complete, 0-100.
+    pub progress: Option<f64>,
+    /// Human-readable speed as reported by Unraid, e.g. "152.3 MB/s".
+    pub speed: Option<String>,
+    pub errors: Option<u64>,
+    /// Seconds elapsed since the check started.
+    pub duration: Option<u64>,
+}
+
+impl ParityCheckStatus {
+    /// Estimated seconds remaining, extrapolated from elapsed time and progress.
+    pub fn eta_secs(&self) -> Option<u64> {
+        let progress = self.progress.filter(|p| *p > 0.0 && *p < 100.0)?;
+        let elapsed = self.duration? as f64;
+        Some((elapsed * (100.0 - progress) / progress).round() as u64)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SystemStatus {
     pub array: ArrayStatus,
@@ -68,6 +95,13 @@ pub struct DiskInfo {
     pub smart_status: String,
     #[serde(rename = "type")]
     pub disk_type: String,
+    /// Filesystem size/usage (KB). Null for unformatted or unmounted disks.
+    #[serde(rename = "fsSize")]
+    pub fs_size: Option<f64>,
+    #[serde(rename = "fsFree")]
+    pub fs_free: Option<f64>,
+    #[serde(rename = "fsUsed")]
+    pub fs_used: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -91,16 +125,19 @@ impl DockerContainer {
 
 #[derive(Debug, Deserialize)]
 pub struct VmDomain {
+    pub id: String,
     pub name: String,
     pub state: String,
 }
 
 impl UnraidApi {
-    pub fn new(base_url: &str, api_key: &str) -> Self {
-        let client = Client::builder()
-            .danger_accept_invalid_certs(true)
-            .build()
-            .expect("Failed to build HTTP client");
+    pub fn new(base_url: &str, api_key: &str, accept_invalid_certs: bool) -> Self {
+        let client = build_client(&HttpOptions::new(accept_invalid_certs));
+        Self::with_client(client, base_url, api_key)
+    }
+
+    /// Use a shared `client` instead of building one.
+    pub fn with_client(client: Client, base_url: &str, api_key: &str) -> Self {
         Self {
             client,
             base_url: base_url.trim_end_matches('/').to_string(),
@@ -145,11 +182,59 @@ impl UnraidApi {
         Ok(resp.array)
     }
 
+    /// Start or stop the array. `action` is "start" or "stop"; returns the new state.
+    pub async fn array_action(&self, action: &str) -> Result<ArrayStatus, UnraidApiError> {
+        #[derive(Deserialize)]
+        struct Resp {
+            array: SetStateResp,
+        }
+        #[derive(Deserialize)]
+        struct SetStateResp {
+            #[serde(rename = "setState")]
+            set_state: ArrayStatus,
+        }
+        let query = "mutation($state: ArrayStateInputState!) { array { setState(input: { desiredState: $state }) { state } } }";
+        let variables = serde_json::json!({ "state": action.to_uppercase() });
+        let resp: Resp = self.query(query, Some(&variables)).await?;
+        Ok(resp.array.set_state)
+    }
+
+    pub async fn get_parity_status(&self) -> Result<ParityCheckStatus, UnraidApiError> {
+        #[derive(Deserialize)]
+        struct Resp {
+            array: ArrayResp,
+        }
+        #[derive(Deserialize)]
+        struct ArrayResp {
+            #[serde(rename = "parityCheckStatus")]
+            parity_check_status: ParityCheckStatus,
+        }
+        let resp: Resp = self
+            .query(
+                "{ array { parityCheckStatus { status running progress speed errors duration } } }",
+                None,
+            )
+            .await?;
+        Ok(resp.array.parity_check_status)
+    }
+
+    /// Start a (non-correcting) parity check or cancel the running one.
+    /// Uses nested mutation: `mutation { parityCheck { start(correct: false) } }`
+    pub async fn parity_action(&self, action: &str) -> Result<String, UnraidApiError> {
+        let query = match action {
+            "start" => "mutation { parityCheck { start(correct: false) } }",
+            "cancel" => "mutation { parityCheck { cancel } }",
+            _ => return Err(UnraidApiError::GraphQL(format!("unknown parity action '{action}'"))),
+        };
+        let _: serde_json::Value = self.query(query, None).await?;
+        Ok(format!("{action} succeeded"))
+    }
+
     pub async fn get_system_status(&self) -> Result<SystemStatus, UnraidApiError> {
         let query = r#"{
             array { state }
             info { cpu { brand cores threads } os { hostname uptime } }
-            disks { name size temperature smartStatus type }
+            disks { name size temperature smartStatus type fsSize fsFree fsUsed }
         }"#;
         let resp: SystemStatus = self.query(query, None).await?;
         Ok(resp)
@@ -188,14 +273,19 @@ impl UnraidApi {
         Ok(format!("{action} succeeded"))
     }
 
-    /// Start/stop a VM. Uses nested mutation: `mutation { vm { start(id: ...) } }`
+    /// Start/stop a VM by name. The mutation wants the domain id, so the name is
+    /// resolved (case-insensitively) first: `mutation { vm { start(id: ...) } }`
     pub async fn vm_action(&self, name: &str, action: &str) -> Result<String, UnraidApiError> {
-        // VMs use name, not id — we need to look up the domain first
-        // For now, pass the name as-is and see if the API accepts it
+        let vm = self
+            .get_vms()
+            .await?
+            .into_iter()
+            .find(|vm| vm.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnraidApiError::VmNotFound(name.to_string()))?;
         let query = format!(
             "mutation($id: PrefixedID!) {{ vm {{ {action}(id: $id) }} }}"
         );
-        let variables = serde_json::json!({ "id": name });
+        let variables = serde_json::json!({ "id": vm.id });
         let _: serde_json::Value = self.query(&query, Some(&variables)).await?;
         Ok(format!("{action} succeeded"))
     }
@@ -210,7 +300,7 @@ impl UnraidApi {
             domains: Vec<VmDomain>,
         }
         let resp: Resp = self
-            .query("{ vms { domains { name state } } }", None)
+            .query("{ vms { domains { id name state } } }", None)
             .await?;
         Ok(resp.vms.domains)
     }
@@ -219,7 +309,7 @@ impl UnraidApi {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use wiremock::matchers::{method, path};
+    use wiremock::matchers::{body_partial_json, body_string_contains, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     #[tokio::test]
@@ -240,7 +330,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let api = UnraidApi::new(&mock_server.uri(), "test-key");
+        let api = UnraidApi::new(&mock_server.uri(), "test-key", false);
         let containers = api.get_docker_containers().await.unwrap();
         assert_eq!(containers.len(), 2);
         assert_eq!(containers[0].display_name(), "plex");
@@ -258,11 +348,104 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let api = UnraidApi::new(&mock_server.uri(), "test-key");
+        let api = UnraidApi::new(&mock_server.uri(), "test-key", false);
         let status = api.get_array_status().await.unwrap();
         assert_eq!(status.state, "STARTED");
     }
 
+    #[tokio::test]
+    async fn test_array_action() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "variables": { "state": "STOP" } })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": { "array": { "setState": { "state": "STOPPED" } } }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let api = UnraidApi::new(&mock_server.uri(), "test-key", false);
+        let status = api.array_action("stop").await.unwrap();
+        assert_eq!(status.state, "STOPPED");
+    }
+
+    #[tokio::test]
+    async fn test_get_parity_status() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("parityCheckStatus"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": {
+                    "array": {
+                        "parityCheckStatus": {
+                            "status": "RUNNING",
+                            "running": true,
+                            "progress": 25.0,
+                            "speed": "150.2 MB/s",
+                            "errors": 0,
+                            "duration": 3600
+                        }
+                    }
+                }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let api = UnraidApi::new(&mock_server.uri(), "test-key", false);
+        let status = api.get_parity_status().await.unwrap();
+        assert!(status.running);
+        assert_eq!(status.progress, Some(25.0));
+        assert_eq!(status.speed.as_deref(), Some("150.2 MB/s"));
+        assert_eq!(status.eta_secs(), Some(10_800));
+    }
+
+    async fn mock_vms(mock_server: &MockServer) {
+        Mock::given(method("POST"))
+            .and(body_string_contains("domains"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": {
+                    "vms": {
+                        "domains": [
+                            {"id": "vm:1234-abcd", "name": "Windows 11", "state": "SHUTOFF"}
+                        ]
+                    }
+                }
+            })))
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_vm_action_resolves_name_to_id() {
+        let mock_server = MockServer::start().await;
+        mock_vms(&mock_server).await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("mutation"))
+            .and(body_partial_json(serde_json::json!({ "variables": { "id": "vm:1234-abcd" } })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": { "vm": { "start": true } }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let api = UnraidApi::new(&mock_server.uri(), "test-key", false);
+        let result = api.vm_action("windows 11", "start").await.unwrap();
+        assert_eq!(result, "start succeeded");
+    }
+
+    #[tokio::test]
+    async fn test_vm_action_unknown_name() {
+        let mock_server = MockServer::start().await;
+        mock_vms(&mock_server).await;
+
+        let api = UnraidApi::new(&mock_server.uri(), "test-key", false);
+        let err = api.vm_action("ubuntu", "start").await.unwrap_err();
+        assert!(matches!(err, UnraidApiError::VmNotFound(_)));
+        assert_eq!(err.to_string(), "VM 'ubuntu' not found");
+    }
+
     #[tokio::test]
     async fn test_graphql_error_handling() {
         let mock_server = MockServer::start().await;
@@ -275,7 +458,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let api = UnraidApi::new(&mock_server.uri(), "bad-key");
+        let api = UnraidApi::new(&mock_server.uri(), "bad-key", false);
         let result = api.get_array_status().await;
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Unauthorized"));
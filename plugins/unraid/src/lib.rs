@@ -1,27 +1,39 @@
 pub mod api;
 
-use api::UnraidApi;
+use api::{DiskInfo, ParityCheckStatus, UnraidApi};
 use async_trait::async_trait;
 use chrono::Utc;
-use discord_assist_plugin_api::{Plugin, PluginError};
+use discord_assist_plugin_api::{
+    Plugin, PluginError, ack, dry_run_message, list_embed, respond_chunked, respond_embed, response_message,
+};
+use discord_assist_util::size::{self, format_size};
 use serenity::builder::{
-    CreateCommand, CreateCommandOption, CreateInteractionResponse,
-    CreateInteractionResponseMessage,
+    CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse,
 };
 use serenity::model::application::{
-    CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue,
+    ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, ResolvedOption,
+    ResolvedValue,
 };
 use serenity::prelude::Context;
+use tracing::info;
+
+const ARRAY_STOP_CONFIRM: &str = "unraid_array:stop";
+const ARRAY_STOP_CANCEL: &str = "unraid_array:cancel";
 
 pub struct UnraidPlugin {
     api: UnraidApi,
+    /// Reply with what would have happened instead of starting/stopping anything.
+    dry_run: bool,
 }
 
 impl UnraidPlugin {
-    pub fn new(api_url: &str, api_key: &str) -> Self {
-        Self {
-            api: UnraidApi::new(api_url, api_key),
-        }
+    pub fn new(api_url: &str, api_key: &str, accept_invalid_certs: bool, dry_run: bool) -> Self {
+        Self::with_api(UnraidApi::new(api_url, api_key, accept_invalid_certs), dry_run)
+    }
+
+    pub fn with_api(api: UnraidApi, dry_run: bool) -> Self {
+        Self { api, dry_run }
     }
 }
 
@@ -129,9 +141,69 @@ impl Plugin for UnraidPlugin {
                         .required(true),
                     ),
                 ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommandGroup,
+                    "array",
+                    "Array management",
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "start",
+                    "Start the array",
+                ))
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "stop",
+                    "Stop the array (asks for confirmation)",
+                )),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommandGroup,
+                    "parity",
+                    "Parity check management",
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "status",
+                    "Show parity check progress",
+                ))
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "start",
+                    "Start a parity check",
+                ))
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "cancel",
+                    "Cancel the running parity check",
+                )),
             )]
     }
 
+    fn is_read_only(&self, path: &[&str]) -> bool {
+        matches!(
+            path,
+            ["unraid", "status"] | ["unraid", "docker" | "vm", "list"] | ["unraid", "parity", "status"]
+        )
+    }
+
+    fn component_command(&self, custom_id: &str) -> Option<&str> {
+        custom_id.starts_with("unraid_array:").then_some("unraid")
+    }
+
+    fn describe(&self) -> Vec<(String, String)> {
+        vec![
+            ("/unraid status".into(), "Show array and system status".into()),
+            ("/unraid docker list|start|stop".into(), "Docker container management".into()),
+            ("/unraid vm list|start|stop".into(), "Virtual machine management".into()),
+            ("/unraid array start|stop".into(), "Start or stop the array".into()),
+            ("/unraid parity status|start|cancel".into(), "Parity check management".into()),
+        ]
+    }
+
     async fn handle_command(
         &self,
         ctx: &Context,
@@ -148,10 +220,20 @@ impl Plugin for UnraidPlugin {
         };
 
         let content = match &subopt.value {
+            ResolvedValue::SubCommand(_) if subopt.name == "status" => {
+                let embed = self.status_embed().await?;
+                respond_embed(ctx, command, embed).await?;
+                return Ok(true);
+            }
             ResolvedValue::SubCommand(opts) => {
                 self.handle_subcommand("", subopt.name, opts).await?
             }
             ResolvedValue::SubCommandGroup(opts) => {
+                if subopt.name == "array"
+                    && opts.first().is_some_and(|sub| sub.name == "stop")
+                {
+                    return self.confirm_array_stop(ctx, command).await;
+                }
                 if let Some(sub) = opts.first() {
                     if let ResolvedValue::SubCommand(inner) = &sub.value {
                         self.handle_subcommand(subopt.name, sub.name, inner).await?
@@ -165,10 +247,36 @@ impl Plugin for UnraidPlugin {
             _ => return Ok(false),
         };
 
-        let data = CreateInteractionResponseMessage::new().content(content);
-        let builder = CreateInteractionResponse::Message(data);
-        command
-            .create_response(&ctx.http, builder)
+        respond_chunked(ctx, command, &content).await?;
+        Ok(true)
+    }
+
+    async fn handle_component(
+        &self,
+        ctx: &Context,
+        component: &ComponentInteraction,
+    ) -> Result<bool, PluginError> {
+        let content = match component.data.custom_id.as_str() {
+            ARRAY_STOP_CONFIRM if self.dry_run => dry_run_message("stopped the array"),
+            ARRAY_STOP_CONFIRM => {
+                info!("Stopping Unraid array (confirmed by {})", component.user.name);
+                let status = self
+                    .api
+                    .array_action("stop")
+                    .await
+                    .map_err(|e| PluginError::ApiError(e.to_string()))?;
+                format!("Array stop requested. Array: **{}**", status.state)
+            }
+            ARRAY_STOP_CANCEL => "Array stop cancelled.".to_string(),
+            _ => return Ok(false),
+        };
+
+        // Replace the confirmation prompt so the buttons can't be pressed twice
+        let data = response_message()
+            .content(content)
+            .components(Vec::new());
+        component
+            .create_response(&ctx.http, CreateInteractionResponse::UpdateMessage(data))
             .await
             .map_err(PluginError::DiscordError)?;
         Ok(true)
@@ -176,6 +284,66 @@ impl Plugin for UnraidPlugin {
 }
 
 impl UnraidPlugin {
+    async fn confirm_array_stop(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+    ) -> Result<bool, PluginError> {
+        let buttons = vec![
+            CreateButton::new(ARRAY_STOP_CONFIRM)
+                .label("Stop array")
+                .style(ButtonStyle::Danger),
+            CreateButton::new(ARRAY_STOP_CANCEL)
+                .label("Cancel")
+                .style(ButtonStyle::Secondary),
+        ];
+        let data = response_message()
+            .content("Stopping the array takes every share, container, and VM offline. Are you sure?")
+            .components(vec![CreateActionRow::Buttons(buttons)]);
+        ack::respond(ctx, command, data).await?;
+        Ok(true)
+    }
+
+    async fn status_embed(&self) -> Result<CreateEmbed, PluginError> {
+        let status = self
+            .api
+            .get_system_status()
+            .await
+            .map_err(|e| PluginError::ApiError(e.to_string()))?;
+
+        let uptime_str = status.info.os.uptime
+            .as_deref()
+            .and_then(|s| {
+                let boot = s.parse::<chrono::DateTime<Utc>>().ok()?;
+                let dur = Utc::now().signed_duration_since(boot);
+                let days = dur.num_days();
+                let hours = dur.num_hours() % 24;
+                Some(if days > 0 {
+                    format!("{days}d {hours}h")
+                } else {
+                    format!("{hours}h")
+                })
+            })
+            .unwrap_or_else(|| "unknown".into());
+
+        let total_storage: f64 = status.disks.iter().map(|d| d.size).sum();
+        let description = format!(
+            "Array: {}\n\
+             CPU: {} ({} cores / {} threads)\n\
+             Uptime: {}\n\
+             Disks: {} total",
+            status.array.state,
+            status.info.cpu.brand,
+            status.info.cpu.cores,
+            status.info.cpu.threads,
+            uptime_str,
+            format_size(total_storage as u64, size::unit()),
+        );
+
+        let disks: Vec<_> = status.disks.iter().map(|d| (d.name.clone(), format_disk(d))).collect();
+        Ok(list_embed(&status.info.os.hostname, &disks).description(description))
+    }
+
     async fn handle_subcommand(
         &self,
         group: &str,
@@ -183,64 +351,6 @@ impl UnraidPlugin {
         options: &[ResolvedOption<'_>],
     ) -> Result<String, PluginError> {
         match (group, subcommand) {
-            ("", "status") => {
-                let status = self
-                    .api
-                    .get_system_status()
-                    .await
-                    .map_err(|e| PluginError::ApiError(e.to_string()))?;
-
-                let uptime_str = status.info.os.uptime
-                    .as_deref()
-                    .and_then(|s| {
-                        let boot = s.parse::<chrono::DateTime<Utc>>().ok()?;
-                        let dur = Utc::now().signed_duration_since(boot);
-                        let days = dur.num_days();
-                        let hours = dur.num_hours() % 24;
-                        Some(if days > 0 {
-                            format!("{days}d {hours}h")
-                        } else {
-                            format!("{hours}h")
-                        })
-                    })
-                    .unwrap_or_else(|| "unknown".into());
-
-                let total_storage: f64 = status.disks.iter().map(|d| d.size).sum();
-                let total_tb = total_storage / 1_099_511_627_776.0;
-
-                let mut msg = format!(
-                    "**{}**\n\
-                     Array: {}\n\
-                     CPU: {} ({} cores / {} threads)\n\
-                     Uptime: {}\n\
-                     \n**Disks** ({:.1} TB total)\n",
-                    status.info.os.hostname,
-                    status.array.state,
-                    status.info.cpu.brand,
-                    status.info.cpu.cores,
-                    status.info.cpu.threads,
-                    uptime_str,
-                    total_tb,
-                );
-
-                for d in &status.disks {
-                    let temp = d.temperature
-                        .map(|t| format!(" {t:.0}C"))
-                        .unwrap_or_default();
-                    let size_bytes = d.size;
-                    let size_str = if size_bytes < 1_099_511_627_776.0 {
-                        format!("{:.1} GB", size_bytes / 1_073_741_824.0)
-                    } else {
-                        format!("{:.1} TB", size_bytes / 1_099_511_627_776.0)
-                    };
-                    msg.push_str(&format!(
-                        "- {} ({}) {} [{}]{}\n",
-                        d.name, size_str, d.disk_type, d.smart_status, temp
-                    ));
-                }
-
-                Ok(msg)
-            }
             ("docker", "list") => {
                 let containers = self
                     .api
@@ -275,6 +385,9 @@ impl UnraidPlugin {
                     .iter()
                     .find(|c| c.display_name().eq_ignore_ascii_case(name))
                     .ok_or_else(|| PluginError::Other(format!("Container '{name}' not found")))?;
+                if self.dry_run {
+                    return Ok(dry_run_message(&format!("{} container **{name}**", past_tense(action))));
+                }
                 let result = self
                     .api
                     .docker_action(&container.id, action)
@@ -306,14 +419,179 @@ impl UnraidPlugin {
                         _ => None,
                     })
                     .ok_or_else(|| PluginError::Other("Missing VM name".into()))?;
+                if self.dry_run {
+                    return Ok(dry_run_message(&format!("{} VM **{name}**", past_tense(action))));
+                }
+                let result = self.api.vm_action(name, action).await.map_err(|e| match e {
+                    api::UnraidApiError::VmNotFound(_) => PluginError::Other(e.to_string()),
+                    _ => PluginError::ApiError(e.to_string()),
+                })?;
+                Ok(format!("VM **{name}**: {result}"))
+            }
+            ("array", "start") if self.dry_run => Ok(dry_run_message("started the array")),
+            ("array", "start") => {
+                let status = self
+                    .api
+                    .array_action("start")
+                    .await
+                    .map_err(|e| PluginError::ApiError(e.to_string()))?;
+                Ok(format!("Array start requested. Array: **{}**", status.state))
+            }
+            ("parity", "status") => {
+                let status = self
+                    .api
+                    .get_parity_status()
+                    .await
+                    .map_err(|e| PluginError::ApiError(e.to_string()))?;
+                Ok(format_parity_status(&status))
+            }
+            ("parity", action @ ("start" | "cancel")) if self.dry_run => Ok(dry_run_message(
+                &format!("{} the parity check", past_tense(action)),
+            )),
+            ("parity", action @ ("start" | "cancel")) => {
                 let result = self
                     .api
-                    .vm_action(name, action)
+                    .parity_action(action)
                     .await
                     .map_err(|e| PluginError::ApiError(e.to_string()))?;
-                Ok(format!("VM **{name}**: {result}"))
+                Ok(format!("Parity check: {result}"))
             }
             _ => Ok(format!("Unknown unraid command: {group} {subcommand}")),
         }
     }
 }
+
+/// "start" -> "started", for dry-run replies.
+fn past_tense(action: &str) -> &str {
+    match action {
+        "start" => "started",
+        "stop" => "stopped",
+        "cancel" => "cancelled",
+        other => other,
+    }
+}
+
+/// e.g. `4.0 TB DATA [OK] 35C [###-------] 30%`
+fn format_disk(d: &DiskInfo) -> String {
+    let temp = d.temperature
+        .map(|t| format!(" {t:.0}C"))
+        .unwrap_or_default();
+    let usage = match (d.fs_used, d.fs_size) {
+        (Some(used), Some(total)) => format!(" {}", usage_bar(used, total, 10)),
+        _ => String::new(),
+    };
+    format!(
+        "{} {} [{}]{}{}",
+        format_size(d.size as u64, size::unit()),
+        d.disk_type,
+        d.smart_status,
+        temp,
+        usage
+    )
+}
+
+fn format_parity_status(status: &ParityCheckStatus) -> String {
+    if !status.running {
+        return "No parity check is running.".into();
+    }
+    let mut msg = format!(
+        "**Parity Check** {:.1}% complete",
+        status.progress.unwrap_or_default()
+    );
+    if let Some(ref speed) = status.speed {
+        msg.push_str(&format!("\nSpeed: {speed}"));
+    }
+    if let Some(secs) = status.eta_secs() {
+        msg.push_str(&format!("\nEstimated time remaining: {}", format_duration(secs)));
+    }
+    msg.push_str(&format!("\nErrors: {}", status.errors.unwrap_or_default()));
+    msg
+}
+
+/// Text bar like `[####------] 42%`.
+fn usage_bar(used: f64, total: f64, width: usize) -> String {
+    let ratio = if total > 0.0 {
+        (used / total).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let filled = (ratio * width as f64).round() as usize;
+    format!(
+        "[{}{}] {:.0}%",
+        "#".repeat(filled),
+        "-".repeat(width - filled),
+        ratio * 100.0
+    )
+}
+
+fn format_duration(secs: u64) -> String {
+    let hours = secs / 3600;
+    let minutes = (secs % 3600) / 60;
+    if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parity(running: bool) -> ParityCheckStatus {
+        ParityCheckStatus {
+            status: None,
+            running,
+            progress: Some(40.0),
+            speed: Some("150.2 MB/s".into()),
+            errors: Some(0),
+            duration: Some(4 * 3600),
+        }
+    }
+
+    #[test]
+    fn test_format_parity_status_running() {
+        assert_eq!(
+            format_parity_status(&parity(true)),
+            "**Parity Check** 40.0% complete\nSpeed: 150.2 MB/s\nEstimated time remaining: 6h 0m\nErrors: 0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_skips_api() {
+        // Nothing listens here, so any real request would fail
+        let plugin = UnraidPlugin::new("http://127.0.0.1:9", "key", false, true);
+        let msg = plugin.handle_subcommand("array", "start", &[]).await.unwrap();
+        assert_eq!(msg, "[dry-run] would have started the array");
+        let msg = plugin.handle_subcommand("parity", "cancel", &[]).await.unwrap();
+        assert_eq!(msg, "[dry-run] would have cancelled the parity check");
+    }
+
+    #[test]
+    fn test_format_parity_status_idle() {
+        assert_eq!(format_parity_status(&parity(false)), "No parity check is running.");
+    }
+
+    #[test]
+    fn test_usage_bar_empty() {
+        assert_eq!(usage_bar(0.0, 100.0, 10), "[----------] 0%");
+        assert_eq!(usage_bar(0.0, 0.0, 10), "[----------] 0%");
+    }
+
+    #[test]
+    fn test_usage_bar_full() {
+        assert_eq!(usage_bar(100.0, 100.0, 10), "[##########] 100%");
+    }
+
+    #[test]
+    fn test_usage_bar_rounding() {
+        assert_eq!(usage_bar(42.0, 100.0, 10), "[####------] 42%");
+        assert_eq!(usage_bar(46.0, 100.0, 10), "[#####-----] 46%");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(59), "0m");
+        assert_eq!(format_duration(5400), "1h 30m");
+    }
+}